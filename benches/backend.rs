@@ -0,0 +1,43 @@
+//! Throughput of the backend state without a compositor: recording synthetic selections and
+//! answering history queries. Run with `cargo bench`, compare the numbers before and after
+//! performance-related changes.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use cursor_clip::backend::backend_state::BackendState;
+use cursor_clip::backend::simulation::SyntheticSelections;
+use cursor_clip::shared::SortMode;
+
+const ROUNDS: u32 = 10_000;
+
+fn main() {
+    let mut state = BackendState::new();
+    let mut selections = SyntheticSelections::new(0x5eed, 100);
+
+    report("capture", ROUNDS, || {
+        let selection = selections.next().unwrap();
+        black_box(state.add_clipboard_item_from_mime_map(selection, Vec::new(), None));
+    });
+    report("history page", ROUNDS, || {
+        black_box(state.get_history_page(0, 50, SortMode::Recent));
+    });
+    report("search", ROUNDS, || {
+        black_box(state.search_history("select", &[], None, SortMode::Recent, 0, 50));
+    });
+    report("stats", ROUNDS, || {
+        black_box(state.history_stats());
+    });
+}
+
+/// Run `f` `rounds` times and print the mean and the slowest run
+fn report(name: &str, rounds: u32, mut f: impl FnMut()) {
+    let mut slowest = Duration::ZERO;
+    let started = Instant::now();
+    for _ in 0..rounds {
+        let run = Instant::now();
+        f();
+        slowest = slowest.max(run.elapsed());
+    }
+    let mean = started.elapsed() / rounds;
+    println!("{name:<14} {mean:>12.2?} mean {slowest:>12.2?} max ({rounds} runs)");
+}
@@ -1,27 +1,68 @@
-use std::time::{SystemTime, UNIX_EPOCH};
-use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::path::PathBuf;
 use wayland_client::backend::ObjectId;
 use wayland_client::protocol::wl_seat;
 use crate::backend::wayland_clipboard::MutexBackendState;
-use wayland_client::{QueueHandle, Connection};
+use wayland_client::{QueueHandle, Connection, Proxy};
 
 // Import both protocol types
 use wayland_protocols_wlr::data_control::v1::client::{
     zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
     zwlr_data_control_device_v1::ZwlrDataControlDeviceV1,
+    zwlr_data_control_offer_v1::ZwlrDataControlOfferV1,
     zwlr_data_control_source_v1::ZwlrDataControlSourceV1,
 };
 
 use crate::backend::ext_data_control::{
     ExtDataControlManagerV1,
     ExtDataControlDeviceV1,
+    ExtDataControlOfferV1,
     ExtDataControlSourceV1,
 };
 
-use crate::shared::{ClipboardItem, ClipboardItemPreview, ClipboardContentType};
+use crate::shared::{BackendError, BackendMessage, ClipboardItem, HistoryStats, ClipboardItemPreview, ClipboardContentType, Config, ErrorCode, EventStamp, Health, Metrics, MonitorStatus, SortMode, SyncStatus, TextStats, TextTransform, ORIGINAL_MIME_PREFIX};
+use crate::backend::{hooks, kdeconnect, metrics_endpoint, notifications, payload_store, plugins, saved_selection, transforms};
+use crate::backend::saved_selection::SavedSelection;
+use crate::backend::capture_throttle::CaptureThrottle;
+use crate::backend::clipboard_backend::{ClipboardBackend, WaylandClipboard};
+use crate::backend::classification::ContentClassifier;
+use crate::backend::clients::ClientRegistry;
+use crate::backend::foreign_toplevel::{IncognitoMatcher, Toplevel};
+use crate::backend::image_pipeline::ThumbnailCache;
+use crate::backend::selection_state::SeatSelection;
+use crate::shared::config::{self, DeferMode, OwnershipPolicy, SecretsPolicy};
+use crate::shared::protocol;
 use indexmap::IndexMap;
 use bytes::Bytes;
-use log::{debug, info, warn};
+use tracing::{debug, info, warn};
+
+/// MIME type we offer alongside every selection we own, so cooperating clipboard managers
+/// (and other cursor-clip instances) can tell the selection comes from a clipboard manager
+pub const OWNER_MARKER_MIME: &str = "application/x-cursor-clip-owner";
+
+/// Marker MIME types offered by clipboard managers when they own the selection
+const MANAGER_MARKER_MIMES: &[&str] = &[OWNER_MARKER_MIME, "application/x-copyq-owner"];
+
+/// Largest number of items returned for a single history page request
+const MAX_HISTORY_PAGE_SIZE: u32 = 200;
+
+/// Number of most copied items listed in `HistoryStats`
+const MOST_COPIED_IN_STATS: usize = 5;
+
+/// Longest tag accepted from clients, in characters
+const MAX_TAG_CHARS: usize = 64;
+
+/// Losing a selection this soon after taking it over counts as ownership churn
+const CHURN_WINDOW: Duration = Duration::from_secs(1);
+/// This many churn events within `CHURN_PERIOD` mean another manager fights for the selection
+const CHURN_THRESHOLD: usize = 3;
+const CHURN_PERIOD: Duration = Duration::from_secs(60);
+/// How long another manager counts as active after the last sign of it
+const OTHER_MANAGER_TIMEOUT: Duration = Duration::from_secs(300);
+/// How long deleted and cleared items can be restored
+const UNDO_WINDOW: Duration = Duration::from_secs(protocol::UNDO_WINDOW_SECS as u64);
 
 /// Which data control protocol is being used
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -32,47 +73,224 @@ pub enum DataControlProtocol {
     Ext,
 }
 
+/// A deleted item, kept for `UNDO_WINDOW` in case the deletion is undone
+#[derive(Debug)]
+pub struct TrashedItem {
+    pub item: ClipboardItem,
+    pub deleted_at: Instant,
+    /// Generation of the clear that removed the item, None for single deletions
+    pub clear: Option<u64>,
+}
+
+/// Recent items pasted one by one with `PopAndPaste`, in the order they were copied
+#[derive(Debug)]
+pub struct CopyStack {
+    pub ids: Vec<u64>,
+    /// Index of the next item to paste
+    pub cursor: usize,
+}
+
+/// Clipboard objects of one Wayland seat. Every seat has its own selection, recorded into the
+/// shared history with the seat name attached to the items.
+#[derive(Debug)]
+pub struct SeatClipboard {
+    /// Registry name of the wl_seat global, the user data of the seat's Wayland objects
+    pub global: u32,
+    /// Seat name announced by the compositor (e.g. "seat0")
+    pub name: String,
+    pub seat: wl_seat::WlSeat,
+    pub data_control_device: Option<ZwlrDataControlDeviceV1>,
+    pub ext_data_control_device: Option<ExtDataControlDeviceV1>,
+
+    pub current_data_offer: Option<ObjectId>,
+    pub current_source_object: Option<ZwlrDataControlSourceV1>,
+    pub ext_current_source_object: Option<ExtDataControlSourceV1>,
+    pub current_source_entry_id: Option<u64>,
+    /// The source serves the text as copied instead of the cleaned-up one
+    pub current_source_original: bool,
+
+    /// Whether the selection is ours, so its announcement is not read back
+    pub selection: SeatSelection,
+
+    /// Recorded external selection to take over once its source goes away (`on-source-exit`)
+    pub adopt_on_source_exit: Option<u64>,
+
+    /// Selection over `max_captures_per_sec`, read once the limit allows it unless replaced first
+    pub deferred_offer: Option<DeferredOffer>,
+}
+
+/// An offer of an external selection kept for reading later, with its MIME types
+#[derive(Debug)]
+pub enum DeferredOffer {
+    Wlr(ZwlrDataControlOfferV1, Vec<String>),
+    Ext(ExtDataControlOfferV1, Vec<String>),
+}
+
+impl DeferredOffer {
+    pub fn destroy(self) {
+        match self {
+            Self::Wlr(offer, _) => offer.destroy(),
+            Self::Ext(offer, _) => offer.destroy(),
+        }
+    }
+}
+
+impl SeatClipboard {
+    pub fn new(global: u32, seat: wl_seat::WlSeat) -> Self {
+        Self {
+            global,
+            // Replaced by the name event sent right after binding
+            name: format!("seat-{global}"),
+            seat,
+            data_control_device: None,
+            ext_data_control_device: None,
+            current_data_offer: None,
+            current_source_object: None,
+            ext_current_source_object: None,
+            current_source_entry_id: None,
+            current_source_original: false,
+            selection: SeatSelection::default(),
+            adopt_on_source_exit: None,
+            deferred_offer: None,
+        }
+    }
+
+    /// Keep `offer` for reading later, in place of an older deferred one
+    pub fn defer_offer(&mut self, offer: DeferredOffer) {
+        if let Some(stale) = self.deferred_offer.replace(offer) {
+            stale.destroy();
+        }
+    }
+
+    /// Forget the deferred offer, a newer selection replaced it
+    pub fn drop_deferred_offer(&mut self) {
+        if let Some(stale) = self.deferred_offer.take() {
+            stale.destroy();
+        }
+    }
+
+    /// Destroy the seat's data device and the source we serve on it
+    pub fn destroy(mut self) {
+        self.drop_deferred_offer();
+        if let Some(device) = self.data_control_device {
+            device.destroy();
+        }
+        if let Some(source) = self.current_source_object {
+            source.destroy();
+        }
+        if let Some(device) = self.ext_data_control_device {
+            device.destroy();
+        }
+        if let Some(source) = self.ext_current_source_object {
+            source.destroy();
+        }
+        if self.seat.version() >= 5 {
+            self.seat.release();
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct BackendState {
     // Clipboard history and management
     pub history: Vec<ClipboardItem>,
     pub id_for_next_entry: u64,
+    /// When the daemon started, for the uptime reported by `Ping`
+    pub started_at: Instant,
+    /// Capture and IPC counters, see `metrics()`
+    pub metrics: Metrics,
+    // Stamp of the most recently recorded item, keeps new stamps monotonic
+    pub last_stamp: Option<EventStamp>,
 
     // Which protocol is active
     pub active_protocol: Option<DataControlProtocol>,
+    /// Whether selections are being recorded, reported to clients
+    pub monitor_status: MonitorStatus,
 
-    // Wayland objects for clipboard operations - wlroots
+    // Wayland objects for clipboard operations, one manager for all seats
     pub data_control_manager: Option<ZwlrDataControlManagerV1>,
-    pub data_control_device: Option<ZwlrDataControlDeviceV1>,
-
-    // Wayland objects for clipboard operations - ext (standard)
     pub ext_data_control_manager: Option<ExtDataControlManagerV1>,
-    pub ext_data_control_device: Option<ExtDataControlDeviceV1>,
 
     pub qh: Option<QueueHandle<MutexBackendState>>,
-    pub seat: Option<wl_seat::WlSeat>,
+    pub seats: Vec<SeatClipboard>,
     pub connection: Option<Connection>,
 
-    // Current clipboard data - wlroots
+    // MIME types of pending offers (of any seat) - wlroots / ext
     pub mime_type_offers: HashMap<ObjectId, Vec<String>>,
-    pub current_data_offer: Option<ObjectId>,
-    pub current_source_object: Option<ZwlrDataControlSourceV1>,
-    pub current_source_entry_id: Option<u64>,
-
-    // Current clipboard data - ext
     pub ext_mime_type_offers: HashMap<ObjectId, Vec<String>>,
-    pub ext_current_data_offer: Option<ObjectId>,
-    pub ext_current_source_object: Option<ExtDataControlSourceV1>,
-    pub ext_current_source_entry_id: Option<u64>,
 
-    // When we programmatically set the selection, the compositor will echo it
-    // back as a new offer/selection. If we immediately try to read that offer
-    // inside the dispatch callback, we deadlock because the Send event for our
-    // own source cannot be processed until we return to the event loop.
-    pub suppress_next_selection_read: bool,
     /// If true, we only monitor external selections and DO NOT immediately
     /// re-set (take ownership of) the newly received selection.
     pub monitor_only: bool,
+    /// If true, traffic is synthetic (`--simulate`) and there is no compositor to
+    /// hand selections to, so pasting an item only gets logged.
+    pub simulated: bool,
+    /// Source of selections and target of the ones we set (the compositor outside of tests)
+    pub clipboard: Arc<dyn ClipboardBackend>,
+    // While set, new selections are not recorded (see `set_capture_paused`)
+    pub capture_paused: bool,
+    // Open windows and whether a focused one matches an incognito rule, which pauses capture too
+    pub toplevels: HashMap<ObjectId, Toplevel>,
+    /// Whether the compositor tells us about windows (zwlr_foreign_toplevel_manager_v1 is bound)
+    pub toplevel_tracking: bool,
+    pub incognito: IncognitoMatcher,
+    pub incognito_active: bool,
+    // `content_rules` of the config, compiled
+    pub classifier: ContentClassifier,
+
+    pub config: Config,
+    /// Where `SetConfig` saves the preferences, the user's config file outside of tests
+    pub config_path: PathBuf,
+
+    // Connected IPC clients, which get the unsolicited push messages
+    pub clients: ClientRegistry,
+    // While a bulk operation runs, NewItem pushes are collected here and sent as one ItemsAdded
+    pub batch_depth: u32,
+    pub batched_new_items: Vec<ClipboardItemPreview>,
+
+    // Deleted items that can still be restored, and the number of clears so far
+    pub trash: Vec<TrashedItem>,
+    pub clear_generation: u64,
+
+    // Copy stack being pasted from, None until the first pop after a new copy
+    pub copy_stack: Option<CopyStack>,
+
+    // Generated thumbnails keyed by (item id, max_px), bounded in size
+    pub thumbnail_cache: ThumbnailCache,
+
+    // Queue of the plugin worker, None while it is not running
+    pub plugin_jobs: Option<std::sync::mpsc::Sender<plugins::PluginJob>>,
+    // Queue of the OCR worker, None while it is not running
+    #[cfg(feature = "ocr")]
+    pub ocr_jobs: Option<std::sync::mpsc::Sender<super::ocr::OcrJob>>,
+    // Links (item id, URL) waiting for their page title, None unless enabled in the config
+    #[cfg(feature = "link-titles")]
+    pub link_title_jobs: Option<tokio::sync::mpsc::UnboundedSender<(u64, String)>>,
+
+    // History sync (see `sync`)
+    pub sync_status: SyncStatus,
+    /// Phone asked for its clipboard (see `kdeconnect::pull`), the next selection is attributed to it
+    pub pending_source_device: Option<(String, Instant)>,
+
+    // Detection of other clipboard managers (see `should_take_ownership`)
+    pub ownership_taken_at: Option<Instant>,
+    pub ownership_churn: Vec<Instant>,
+    pub other_manager_seen_at: Option<Instant>,
+    /// The selection is to be restored as soon as the history has an item (see `restore_selection`)
+    pub selection_restore_pending: bool,
+    /// Queue of the writer keeping the selection across restarts (see `saved_selection`)
+    pub selection_saves: Option<std::sync::mpsc::Sender<Option<SavedSelection>>>,
+    /// The newest selection to keep from before the writer started (the storage key was still
+    /// loading), `Some(None)` forgets the saved one
+    pub selection_save_pending: Option<Option<SavedSelection>>,
+
+    /// Rate limiting of selections read from apps setting the clipboard in a loop
+    pub capture_throttle: CaptureThrottle,
+    /// Whether a thread waits to read the deferred offers of the seats (see `SeatClipboard::deferred_offer`)
+    pub deferred_read_scheduled: bool,
+
+    /// Time, source app and item id of the last copy, to group rapid copies (see `group_window_ms`)
+    pub last_capture: Option<(Instant, String, u64)>,
 }
 
 impl Default for BackendState {
@@ -88,143 +306,1724 @@ impl BackendState {
             mime_type_offers: HashMap::new(),
             ext_mime_type_offers: HashMap::new(),
             id_for_next_entry: 1,
+            started_at: Instant::now(),
+            metrics: Metrics::default(),
+            last_stamp: None,
             active_protocol: None,
+            monitor_status: MonitorStatus::Starting,
             data_control_manager: None,
-            data_control_device: None,
             ext_data_control_manager: None,
-            ext_data_control_device: None,
-            seat: None,
-            current_data_offer: None,
-            ext_current_data_offer: None,
-            current_source_object: None,
-            ext_current_source_object: None,
-            current_source_entry_id: None,
-            ext_current_source_entry_id: None,
+            seats: Vec::new(),
             qh: None,
-            suppress_next_selection_read: false,
             connection: None,
             monitor_only: false,
+            simulated: false,
+            clipboard: Arc::new(WaylandClipboard),
+            capture_paused: false,
+            toplevels: HashMap::new(),
+            toplevel_tracking: false,
+            incognito: IncognitoMatcher::default(),
+            incognito_active: false,
+            classifier: ContentClassifier::default(),
+            config: Config::default(),
+            config_path: config::config_file_path(),
+            clients: ClientRegistry::default(),
+            batch_depth: 0,
+            batched_new_items: Vec::new(),
+            trash: Vec::new(),
+            clear_generation: 0,
+            copy_stack: None,
+            thumbnail_cache: ThumbnailCache::default(),
+            plugin_jobs: None,
+            #[cfg(feature = "ocr")]
+            ocr_jobs: None,
+            #[cfg(feature = "link-titles")]
+            link_title_jobs: None,
+            sync_status: SyncStatus::default(),
+            pending_source_device: None,
+            ownership_taken_at: None,
+            ownership_churn: Vec::new(),
+            other_manager_seen_at: None,
+            selection_restore_pending: false,
+            selection_saves: None,
+            selection_save_pending: None,
+            capture_throttle: CaptureThrottle::default(),
+            deferred_read_scheduled: false,
+            last_capture: None,
         }
     }
 
-    pub fn add_clipboard_item_from_mime_map(&mut self, mut mime_content: IndexMap<String, Bytes>) -> Option<u64> {
+    /// Add a new history item from the received MIME payloads and queue the configured plugins
+    /// on it (see `plugins::start_worker`). Returns the id of the new item, or None if nothing new was added (empty payload or
+    /// a page title that got merged into the preceding link item).
+    /// `suspect_mime_types` lists payloads that failed the sanity checks (see `payload_check`),
+    /// `seat` is the name of the seat the selection was made on (None if not from a seat)
+    pub fn add_clipboard_item_from_mime_map(&mut self, mut mime_content: IndexMap<String, Bytes>, suspect_mime_types: Vec<String>, seat: Option<String>) -> Option<u64> {
         if mime_content.is_empty() { return None; }
+        if self.config.clean_urls && transforms::clean_url_payloads(&mut mime_content, &self.config.tracking_params) {
+            debug!("Removed tracking parameters from a copied link");
+        }
+        if transforms::normalize_text_payloads(&mut mime_content, &self.config.normalize) {
+            debug!("Cleaned up the text of a copied item, keeping it as copied as well");
+        }
 
-        // If we have image/png, prefer showing mime_type + bytes and set type to Image
-        let (content_preview, content_type) = if let Some(png_bytes) = mime_content.get("image/png") {
-            (format!("<image/png {} bytes>", png_bytes.len()), ClipboardContentType::Image)
-        } else {
-            // Otherwise, if we have text/plain;charset=utf-8, show up to first 200 chars and infer type
-            let preview: String = if let Some(txt_bytes) = mime_content.get("text/plain;charset=utf-8") {
-                match std::str::from_utf8(txt_bytes.as_ref()) {
-                    Ok(s) => s.chars().take(200).collect(),
-                    Err(_) => format!("<text/plain;charset=utf-8 {} bytes>", txt_bytes.len()),
-                }
-            } else {
-                // Fallback: show placeholder using first mime entry
-                let (mime_name, len) = mime_content.iter().next().map(|(k,v)| (k.clone(), v.len())).unwrap();
-                format!("<{mime_name} {len} bytes>")
-            };
-            let content_type = ClipboardContentType::type_from_preview(&preview);
-            (preview, content_type)
-        };
-
-
-        let item = ClipboardItem {
+        if self.config.secrets == SecretsPolicy::Ignore && is_secret_payload(&mime_content) {
+            debug!("Not recording an item marked as secret");
+            return None;
+        }
+
+        let (content_preview, content_type) = preview_for(&mime_content, &self.classifier);
+        let source_device = self
+            .pending_source_device
+            .take()
+            .filter(|(_, requested_at)| requested_at.elapsed() < kdeconnect::PULL_WINDOW)
+            .map(|(device, _)| device);
+        // Selections set by cursor-clip itself (no seat) were not copied in the focused app
+        let source_app = seat.as_ref().and(self.focused_app_id());
+
+        let mut item = ClipboardItem {
             item_id: self.id_for_next_entry,
             content_type,
             content_preview,
-            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            stamp: self.next_stamp(),
+            copy_count: 0,
+            last_pasted: None,
             mime_data: mime_content.drain(..).collect(),
+            suspect_mime_types,
+            ocr_text: None,
+            tags: Vec::new(),
+            source_device,
+            seat,
+            source_app,
+            text_stats: None,
+            group_id: None,
         };
+        item.text_stats = text_stats_of(&item);
 
-        // remove duplicates (todo change to more robust solution -> hashes)
-        self.history.retain(|existing| existing.content_preview != item.content_preview);
-        self.history.insert(0, item);
-        if self.history.len() > 100 { self.history.truncate(100); }
+        let mut removed_ids = Vec::new();
+        if self.config.merge_link_titles && self.merge_link_title(&mut item, &mut removed_ids) {
+            // The link item was retitled in place, clients replace their copy of it
+            if let Some(link) = self.history.first() {
+                let preview = ClipboardItemPreview::from(link);
+                self.broadcast(BackendMessage::NewItem { item: preview });
+            }
+            return None;
+        }
+
+        // remove duplicates (todo change to more robust solution -> hashes), keeping their copy counter
+        let mut duplicate_id = None;
+        if let Some(pos) = self.history.iter().position(|existing| existing.content_preview == item.content_preview) {
+            let duplicate = self.history.remove(pos);
+            item.copy_count = duplicate.copy_count;
+            removed_ids.push(duplicate.item_id);
+            duplicate_id = Some(duplicate.item_id);
+        }
+        self.note_copy_for_stack(duplicate_id, item.item_id);
+        let regrouped = self.group_with_previous_copy(&mut item);
+        let preview = ClipboardItemPreview::from(&item);
+        let secret = is_secret(&item);
+        self.insert_ordered(item);
+        removed_ids.extend(self.evict_over_limit());
+        self.prune_thumbnail_cache();
+        if !removed_ids.is_empty() {
+            self.broadcast(BackendMessage::ItemsRemoved { ids: removed_ids });
+        }
         let new_id = self.id_for_next_entry;
         self.id_for_next_entry += 1;
+        // Secrets never reach external commands
+        if !secret {
+            hooks::run_hook(&self.config.on_copy, hooks::HookEvent::Copy, &preview);
+            if !self.config.plugins.is_empty()
+                && let Some(jobs) = &self.plugin_jobs
+                && let Some(item) = self.history.iter().find(|item| item.item_id == new_id)
+            {
+                plugins::queue(jobs, new_id, self.config.plugins.clone(), item.mime_data.clone());
+            }
+        }
+        notifications::notify_new_item(&self.config, &preview, secret);
+        self.broadcast(BackendMessage::NewItem { item: preview });
+        for member in regrouped {
+            self.broadcast(BackendMessage::ItemUpdated { item: member });
+        }
+
+        #[cfg(feature = "link-titles")]
+        if let Some(jobs) = &self.link_title_jobs
+            && let Some(item) = self.history.iter().find(|item| item.item_id == new_id)
+            && is_plain_link(item)
+        {
+            let _ = jobs.send((new_id, item.content_preview.clone()));
+        }
+
+        #[cfg(feature = "ocr")]
+        if let Some(jobs) = &self.ocr_jobs
+            && let Some(item) = self.history.iter().find(|item| item.item_id == new_id)
+            && let Some(image) = super::image_pipeline::image_payload(&item.mime_data)
+        {
+            super::ocr::queue(jobs, new_id, image.clone());
+        }
         Some(new_id)
     }
 
+    /// Apply what the plugins made of a recorded item: payloads they replaced and the tags they
+    /// added. Nothing happens if the item was removed while they ran.
+    pub fn apply_plugin_results(&mut self, id: u64, payloads: IndexMap<String, Bytes>, tags: Vec<String>) {
+        let Some(item) = self.history.iter_mut().find(|item| item.item_id == id) else { return };
+        let replaced = !payloads.is_empty();
+        if replaced {
+            item.mime_data.extend(payloads);
+            (item.content_preview, item.content_type) = preview_for(&item.mime_data, &self.classifier);
+            item.text_stats = text_stats_of(item);
+        }
+        for tag in tags {
+            if !item.tags.contains(&tag) {
+                item.tags.push(tag);
+            }
+        }
+        let preview = ClipboardItemPreview::from(&*item);
+        if replaced {
+            self.thumbnail_cache.retain_items(|cached| cached != id);
+        }
+        self.broadcast(BackendMessage::ItemUpdated { item: preview });
+    }
+
+    /// Put a new item into one group with the previous copy if both came from the same app
+    /// within `group_window_ms`. The new item becomes the group's head, the other members are
+    /// moved to its id and returned for clients to update.
+    fn group_with_previous_copy(&mut self, item: &mut ClipboardItem) -> Vec<ClipboardItemPreview> {
+        // Items added by cursor-clip itself and copies from an unknown app (no window tracking)
+        // are never grouped, and end the group before them
+        let Some(app_id) = item.source_app.clone() else {
+            self.last_capture = None;
+            return Vec::new();
+        };
+        let now = Instant::now();
+        let previous = self.last_capture.replace((now, app_id.clone(), item.item_id));
+        let window = Duration::from_millis(self.config.group_window_ms);
+        let Some((_, _, previous_id)) = previous
+            .filter(|(at, previous_app, _)| !window.is_zero() && now.duration_since(*at) <= window && *previous_app == app_id)
+        else {
+            return Vec::new();
+        };
+        // Gone as a duplicate of the new item or deleted meanwhile
+        let Some(previous) = self.history.iter().find(|existing| existing.item_id == previous_id) else {
+            return Vec::new();
+        };
+        let old_group = previous.group_id.unwrap_or(previous_id);
+        item.group_id = Some(item.item_id);
+        self.history
+            .iter_mut()
+            .filter(|existing| existing.item_id == old_group || existing.group_id == Some(old_group))
+            .map(|member| {
+                member.group_id = Some(item.item_id);
+                ClipboardItemPreview::from(&*member)
+            })
+            .collect()
+    }
+
+    /// App id of the focused window, if the compositor tells us about toplevels
+    fn focused_app_id(&self) -> Option<String> {
+        self.toplevels
+            .values()
+            .find(|toplevel| toplevel.activated && !toplevel.app_id.is_empty())
+            .map(|toplevel| toplevel.app_id.clone())
+    }
+
+    /// Add an item received through history sync, keeping the stamp it got on the device it was
+    /// copied on. Plugins, hooks and notifications only run for local copies.
+    /// An item with the same content as a newer local one is dropped.
+    pub fn import_synced_item(&mut self, mime_data: IndexMap<String, Bytes>, stamp: EventStamp, source_device: String) -> Option<u64> {
+        self.import_item(mime_data, stamp, Some(source_device))
+    }
+
+    /// Record the selection saved by the previous run of the daemon, made the selection again once
+    /// connected to the compositor (see `restore_selection`)
+    pub fn restore_saved_selection(&mut self, saved: SavedSelection) -> Option<u64> {
+        let mut mime_data = saved.mime_data;
+        payload_store::store_payloads(&mut mime_data);
+        let id = self.import_item(mime_data, saved.stamp, None)?;
+        // Stamps of new copies follow the restored one even if the clock went backwards
+        self.last_stamp = self.last_stamp.max(Some(saved.stamp));
+        Some(id)
+    }
+
+    /// Add an item copied earlier (on another device or before a restart) at the position of its
+    /// stamp, returns its id unless it is older than everything kept
+    fn import_item(&mut self, mime_data: IndexMap<String, Bytes>, stamp: EventStamp, source_device: Option<String>) -> Option<u64> {
+        if mime_data.is_empty() { return None; }
+        if self.expiry_cutoff().is_some_and(|cutoff| stamp.millis < cutoff) {
+            // Would be removed right away
+            return None;
+        }
+        let (content_preview, content_type) = preview_for(&mime_data, &self.classifier);
+        let mut removed_ids = Vec::new();
+        let mut copy_count = 0;
+        if let Some(pos) = self.history.iter().position(|existing| existing.content_preview == content_preview) {
+            if self.history[pos].stamp >= stamp {
+                return None;
+            }
+            let duplicate = self.history.remove(pos);
+            copy_count = duplicate.copy_count;
+            removed_ids.push(duplicate.item_id);
+        }
+
+        let item_id = self.id_for_next_entry;
+        self.id_for_next_entry += 1;
+        let mut item = ClipboardItem {
+            item_id,
+            content_type,
+            content_preview,
+            stamp,
+            copy_count,
+            last_pasted: None,
+            mime_data,
+            suspect_mime_types: Vec::new(),
+            ocr_text: None,
+            tags: Vec::new(),
+            source_device,
+            seat: None,
+            source_app: None,
+            text_stats: None,
+            group_id: None,
+        };
+        item.text_stats = text_stats_of(&item);
+        let preview = ClipboardItemPreview::from(&item);
+        self.insert_ordered(item);
+        removed_ids.extend(self.evict_over_limit());
+        self.prune_thumbnail_cache();
+        if !removed_ids.is_empty() {
+            self.broadcast(BackendMessage::ItemsRemoved { ids: removed_ids.clone() });
+        }
+        if removed_ids.contains(&item_id) {
+            // Older than everything kept
+            return None;
+        }
+        self.broadcast(BackendMessage::NewItem { item: preview });
+        Some(item_id)
+    }
+
+    /// Move an item to the position of a newer stamp, used when the same content was copied
+    /// again on another device
+    pub fn restamp_item(&mut self, id: u64, stamp: EventStamp) {
+        let Some(pos) = self.history.iter().position(|item| item.item_id == id) else { return };
+        if self.history[pos].stamp >= stamp {
+            return;
+        }
+        let mut item = self.history.remove(pos);
+        item.stamp = stamp;
+        let preview = ClipboardItemPreview::from(&item);
+        self.insert_ordered(item);
+        // Clients replace their copy of the item and sort it in again
+        self.broadcast(BackendMessage::NewItem { item: preview });
+    }
+
+    /// Move an item to `position` (0 is the top) of the history, returns its new position. The
+    /// history stays ordered by stamp, so the item gets a stamp between its new neighbours (a
+    /// fresh one at the top) and is shown with their copy time.
+    pub fn move_item(&mut self, id: u64, position: u32) -> Result<u32, BackendError> {
+        let from = self
+            .history
+            .iter()
+            .position(|item| item.item_id == id)
+            .ok_or_else(|| BackendError::new(ErrorCode::NotFound, format!("No clipboard item found with ID: {id}")))?;
+        let mut item = self.history.remove(from);
+        let to = (position as usize).min(self.history.len());
+        item.stamp = match to {
+            0 => self.next_stamp(),
+            _ => stamp_between(self.history[to - 1].stamp, self.history.get(to).map(|below| below.stamp)),
+        };
+        let preview = ClipboardItemPreview::from(&item);
+        self.insert_ordered(item);
+        let position = self.history.iter().position(|item| item.item_id == id).unwrap_or(to) as u32;
+        debug!("Moved clipboard item {id} from position {from} to {position}");
+        // Clients replace their copy of the item and sort it in again
+        self.broadcast(BackendMessage::NewItem { item: preview });
+        Ok(position)
+    }
+
+    /// Attach a tag (a leading `#` is dropped) to an item, returns the item's tags
+    pub fn add_tag(&mut self, id: u64, tag: &str) -> Result<Vec<String>, BackendError> {
+        let tag = tag.trim().trim_start_matches('#').trim();
+        if tag.is_empty() || tag.chars().count() > MAX_TAG_CHARS {
+            return Err(BackendError::new(ErrorCode::InvalidRequest, format!("Tags must have 1 to {MAX_TAG_CHARS} characters")));
+        }
+        let item = self
+            .history
+            .iter_mut()
+            .find(|item| item.item_id == id)
+            .ok_or_else(|| BackendError::new(ErrorCode::NotFound, format!("No clipboard item found with ID: {id}")))?;
+        if !item.tags.iter().any(|existing| existing == tag) {
+            item.tags.push(tag.to_string());
+        }
+        let preview = ClipboardItemPreview::from(&*item);
+        debug!("Tagged clipboard item {id} with {tag}");
+        let tags = preview.tags.clone();
+        self.broadcast(BackendMessage::ItemUpdated { item: preview });
+        Ok(tags)
+    }
+
+    /// Remove a tag from an item (removing a tag it does not carry is no error), returns the item's tags
+    pub fn remove_tag(&mut self, id: u64, tag: &str) -> Result<Vec<String>, BackendError> {
+        let item = self
+            .history
+            .iter_mut()
+            .find(|item| item.item_id == id)
+            .ok_or_else(|| BackendError::new(ErrorCode::NotFound, format!("No clipboard item found with ID: {id}")))?;
+        item.tags.retain(|existing| existing != tag);
+        let preview = ClipboardItemPreview::from(&*item);
+        debug!("Removed tag {tag} from clipboard item {id}");
+        let tags = preview.tags.clone();
+        self.broadcast(BackendMessage::ItemUpdated { item: preview });
+        Ok(tags)
+    }
+
+    /// Every tag used in the history, sorted by name
+    pub fn tag_names(&self) -> Vec<String> {
+        let tags: BTreeSet<&String> = self.history.iter().flat_map(|item| &item.tags).collect();
+        tags.into_iter().cloned().collect()
+    }
+
+    /// Set the next item of the copy stack as the selection and advance the stack's cursor, returns
+    /// the item and the number of items left. The first pop after a new copy puts the `depth` most
+    /// recent items on the stack, which are then pasted in the order they were copied. Items deleted
+    /// meanwhile are skipped.
+    pub fn pop_and_paste(&mut self, depth: u32) -> Result<(ClipboardItemPreview, u32), BackendError> {
+        if depth == 0 {
+            return Err(BackendError::new(ErrorCode::InvalidRequest, "The copy stack needs a depth of at least 1"));
+        }
+        let history = &self.history;
+        let stack = self.copy_stack.get_or_insert_with(|| CopyStack {
+            ids: history.iter().take(depth as usize).rev().map(|item| item.item_id).collect(),
+            cursor: 0,
+        });
+        let previous_cursor = stack.cursor;
+        let in_history = |id: &u64| history.iter().any(|item| item.item_id == *id);
+        let Some(offset) = stack.ids[stack.cursor..].iter().position(in_history) else {
+            stack.cursor = stack.ids.len();
+            return Err(BackendError::new(ErrorCode::NotFound, "The copy stack is empty, copy something to fill it again"));
+        };
+        let id = stack.ids[stack.cursor + offset];
+        stack.cursor += offset + 1;
+        let remaining = stack.ids[stack.cursor..].iter().filter(|id| in_history(id)).count() as u32;
+
+        if let Err(e) = self.copy_item_by_id(id) {
+            // Nothing was pasted, the next pop tries the same item again
+            if let Some(stack) = &mut self.copy_stack {
+                stack.cursor = previous_cursor;
+            }
+            return Err(e);
+        }
+        debug!("Pasted clipboard item {id} from the copy stack, {remaining} left");
+        let item = self
+            .history
+            .iter()
+            .find(|item| item.item_id == id)
+            .map(ClipboardItemPreview::from)
+            .ok_or_else(|| BackendError::new(ErrorCode::Internal, "Pasted item left the history"))?;
+        Ok((item, remaining))
+    }
+
+    /// Keep the copy stack when a copy duplicates one of its items (e.g. another clipboard manager
+    /// re-serving what was just pasted), any other new copy starts a new stack with the next pop
+    fn note_copy_for_stack(&mut self, duplicate_id: Option<u64>, new_id: u64) {
+        let Some(stack) = &mut self.copy_stack else { return };
+        match duplicate_id.and_then(|old| stack.ids.iter_mut().find(|id| **id == old)) {
+            Some(id) => *id = new_id,
+            None => self.copy_stack = None,
+        }
+    }
+
+    /// Stamp for a newly recorded item: never earlier than the previous one, even if the wall
+    /// clock went backwards, and with a sequence number breaking ties within a millisecond
+    fn next_stamp(&mut self) -> EventStamp {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+        let stamp = match self.last_stamp {
+            Some(last) => EventStamp { millis: now.max(last.millis), seq: last.seq + 1 },
+            None => EventStamp { millis: now, seq: 0 },
+        };
+        self.last_stamp = Some(stamp);
+        stamp
+    }
+
+    /// Insert an item keeping the history sorted newest first by stamp
+    fn insert_ordered(&mut self, item: ClipboardItem) {
+        let pos = self.history.partition_point(|existing| existing.stamp > item.stamp);
+        self.history.insert(pos, item);
+    }
+
+    /// Browsers copy a link and its page title as two separate selections. If the other half of
+    /// such a pair is the most recent history item and was copied within the configured window,
+    /// combine both into a single "Title — domain" item carrying the URL as payload.
+    /// Returns true if the new item was a title that got folded into the existing link item.
+    /// The id of a replaced title item is added to `removed_ids`.
+    fn merge_link_title(&mut self, item: &mut ClipboardItem, removed_ids: &mut Vec<u64>) -> bool {
+        let Some(previous) = self.history.first_mut() else { return false };
+        if item.stamp.millis.saturating_sub(previous.stamp.millis) > self.config.link_merge_window_secs * 1000 {
+            return false;
+        }
+
+        if is_plain_link(item) && is_page_title(previous) {
+            // URL copied after its title: the new item takes over the title, drop the old one
+            item.content_preview = format!("{} — {}", previous.content_preview.trim(), link_domain(&item.content_preview));
+            removed_ids.push(self.history.remove(0).item_id);
+            debug!("Merged page title into new link item");
+            false
+        } else if is_page_title(item) && is_plain_link(previous) {
+            // Title copied after its URL: keep the URL item as payload, just retitle it
+            previous.content_preview = format!("{} — {}", item.content_preview.trim(), link_domain(&previous.content_preview));
+            previous.stamp = item.stamp;
+            debug!("Merged page title into existing link item (id {})", previous.item_id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Send a push message to every connected client, dropping clients that went away.
+    /// Inside `batch_pushes`, NewItem pushes are coalesced instead of sent one by one.
+    pub fn broadcast(&mut self, message: BackendMessage) {
+        if self.batch_depth > 0
+            && let BackendMessage::NewItem { item } = message
+        {
+            self.batched_new_items.insert(0, item);
+            return;
+        }
+        self.clients.broadcast(&message);
+    }
+
+    /// Run a bulk operation (import, restore, replay, ...) and notify clients once with a
+    /// single ItemsAdded push instead of one NewItem per added item
+    pub fn batch_pushes<R>(&mut self, operation: impl FnOnce(&mut Self) -> R) -> R {
+        self.batch_depth += 1;
+        let result = operation(self);
+        self.batch_depth -= 1;
+
+        if self.batch_depth == 0 && !self.batched_new_items.is_empty() {
+            let mut items = std::mem::take(&mut self.batched_new_items);
+            // Drop items that were replaced or evicted again during the same batch
+            items.retain(|item| self.history.iter().any(|existing| existing.item_id == item.item_id));
+            match items.len() {
+                0 => {}
+                1 => self.broadcast(BackendMessage::NewItem { item: items.remove(0) }),
+                _ => self.broadcast(BackendMessage::ItemsAdded { items }),
+            }
+        }
+        result
+    }
+
     pub fn get_history(&self) -> Vec<ClipboardItemPreview> {
     self.history.iter().map(ClipboardItemPreview::from).collect()
     }
 
+    pub fn history_stats(&self) -> HistoryStats {
+        let mut stats = HistoryStats {
+            item_count: self.history.len() as u32,
+            total_bytes: self.history.iter().flat_map(|item| item.mime_data.values()).map(|data| data.len() as u64).sum(),
+            oldest: self.history.iter().map(|item| item.stamp).min(),
+            newest: self.history.iter().map(|item| item.stamp).max(),
+            captures_coalesced: self.metrics.captures_coalesced,
+            captures_throttled: self.metrics.captures_throttled,
+            ..HistoryStats::default()
+        };
+        for item in &self.history {
+            stats.count_type(item.content_type);
+            stats.total_copies += item.copy_count as u64;
+        }
+        let mut copied: Vec<&ClipboardItem> = self.history.iter().filter(|item| item.copy_count > 0).collect();
+        // Stable, so equally often copied items stay newest first
+        copied.sort_by_key(|item| std::cmp::Reverse(item.copy_count));
+        stats.most_copied = copied.into_iter().take(MOST_COPIED_IN_STATS).map(ClipboardItemPreview::from).collect();
+        stats
+    }
+
+    /// Answer to `Ping`: uptime, monitoring state and history size
+    pub fn health(&self) -> Health {
+        Health {
+            uptime_secs: self.started_at.elapsed().as_secs(),
+            status: self.monitor_status.clone(),
+            item_count: self.history.len() as u32,
+            capture_paused: self.is_capture_paused(),
+        }
+    }
+
+    /// Counters and latencies recorded so far, with the current history size and memory use
+    pub fn metrics(&self) -> Metrics {
+        Metrics {
+            history_bytes: self.history.iter().flat_map(|item| item.mime_data.values()).map(|data| data.len() as u64).sum(),
+            resident_bytes: metrics_endpoint::resident_memory(),
+            ..self.metrics.clone()
+        }
+    }
+
+    /// Previews of `limit` items starting at `offset` (newest first) and the total history length.
+    /// `limit` is capped at `MAX_HISTORY_PAGE_SIZE` to keep single responses small.
+    pub fn get_history_page(&self, offset: u32, limit: u32, sort: SortMode) -> (Vec<ClipboardItemPreview>, u32) {
+        if sort != SortMode::Recent {
+            return self.search_history("", &[], None, sort, offset, limit);
+        }
+        let items = self.history
+            .iter()
+            .skip(offset as usize)
+            .take(limit.min(MAX_HISTORY_PAGE_SIZE) as usize)
+            .map(ClipboardItemPreview::from)
+            .collect();
+        (items, self.history.len() as u32)
+    }
+
+    /// Like `get_history_page`, but only over the items matching `query` and `content_types`
+    /// (and carrying `tag`, if given)
+    pub fn search_history(&self, query: &str, content_types: &[ClipboardContentType], tag: Option<&str>, sort: SortMode, offset: u32, limit: u32) -> (Vec<ClipboardItemPreview>, u32) {
+        let mut matching: Vec<ClipboardItemPreview> = self.history
+            .iter()
+            .map(ClipboardItemPreview::from)
+            .filter(|item| item.matches(query, content_types) && item.has_tag(tag))
+            .collect();
+        // The history is kept newest first, other orders are sorted per request
+        if sort != SortMode::Recent {
+            matching.sort_by(|a, b| sort.compare(a, b));
+        }
+        let total = matching.len() as u32;
+        let items = matching.into_iter().skip(offset as usize).take(limit.min(MAX_HISTORY_PAGE_SIZE) as usize).collect();
+        (items, total)
+    }
+
+    /// Add the text recognized in an image item as a new text item and set it as the clipboard
+    pub fn copy_ocr_text(&mut self, id: u64) -> Result<(), BackendError> {
+        let item = self
+            .get_item_by_id(id)
+            .ok_or_else(|| BackendError::new(ErrorCode::NotFound, format!("No clipboard item found with ID: {id}")))?;
+        let text = item
+            .ocr_text
+            .ok_or_else(|| BackendError::new(ErrorCode::InvalidRequest, format!("No text was recognized in item {id}")))?;
+        self.copy_new_text_item(text)
+    }
+
+    /// Add the text of an item run through a transform as a new item and set it as the clipboard
+    pub fn transform_item(&mut self, id: u64, op: TextTransform) -> Result<(), BackendError> {
+        let item = self
+            .get_item_by_id(id)
+            .ok_or_else(|| BackendError::new(ErrorCode::NotFound, format!("No clipboard item found with ID: {id}")))?;
+        let text = transforms::item_text(&item)
+            .ok_or_else(|| BackendError::new(ErrorCode::InvalidRequest, format!("Item {id} has no text")))?;
+        let transformed = transforms::apply(op, text, &self.config).map_err(|e| {
+            BackendError::new(ErrorCode::InvalidRequest, format!("Cannot apply {} to item {id}", op.label())).with_details(e)
+        })?;
+        self.copy_new_text_item(transformed)
+    }
+
+    /// Add plain text as a new history item and set it as the clipboard
+    fn copy_new_text_item(&mut self, text: String) -> Result<(), BackendError> {
+        let mut mime_map = IndexMap::new();
+        mime_map.insert("text/plain;charset=utf-8".to_string(), Bytes::from(text.clone()));
+        mime_map.insert("text/plain".to_string(), Bytes::from(text));
+        // A duplicate of an existing item replaces it, the new item is always the newest
+        let new_id = self
+            .add_clipboard_item_from_mime_map(mime_map, Vec::new(), None)
+            .ok_or_else(|| BackendError::new(ErrorCode::InvalidRequest, "The text was not recorded (a secret, or merged into a link)"))?;
+        self.copy_item_by_id(new_id)
+    }
+
+    /// Previews of the items copied from `from` until before `to` (Unix time in milliseconds),
+    /// newest first whatever the order of the history
+    pub fn history_range(&self, from: u64, to: u64) -> Vec<ClipboardItemPreview> {
+        let mut items: Vec<_> = self
+            .history
+            .iter()
+            .filter(|item| (from..to).contains(&item.stamp.millis))
+            .map(ClipboardItemPreview::from)
+            .collect();
+        items.sort_by_key(|item| std::cmp::Reverse(item.stamp));
+        items
+    }
+
+    /// Add an item sent by a client (e.g. a screenshot) as if it had been copied, optionally
+    /// setting it as the clipboard. Returns the id of the new item.
+    pub fn add_item(&mut self, mime_data: IndexMap<String, Bytes>, set_clipboard: bool) -> Result<u64, BackendError> {
+        if mime_data.is_empty() {
+            return Err(BackendError::new(ErrorCode::InvalidRequest, "The item has no payloads"));
+        }
+        let id = self
+            .add_clipboard_item_from_mime_map(mime_data, Vec::new(), None)
+            .ok_or_else(|| BackendError::new(ErrorCode::InvalidRequest, "The item was not recorded (a secret, or merged into a link)"))?;
+        if set_clipboard {
+            self.copy_item_by_id(id)?;
+        }
+        Ok(id)
+    }
+
     pub fn get_item_by_id(&self, id: u64) -> Option<ClipboardItem> {
         self.history.iter().find(|i| i.item_id == id).cloned()
     }
 
+    /// Set the clipboard on behalf of a user request and count it as a re-copy of that item
+    pub fn copy_item_by_id(&mut self, entry_id: u64) -> Result<(), BackendError> {
+        self.copy_item_to_seat(entry_id, None)
+    }
+
+    /// Like `copy_item_by_id`, on the seat with the given name (see `set_clipboard_on_seat`)
+    pub fn copy_item_to_seat(&mut self, entry_id: u64, seat: Option<&str>) -> Result<(), BackendError> {
+        self.copy_item(entry_id, seat, None, false)
+    }
+
+    /// Like `copy_item_to_seat`, serving the text as copied if it was cleaned up
+    pub fn copy_original_to_seat(&mut self, entry_id: u64, seat: Option<&str>) -> Result<(), BackendError> {
+        self.copy_item(entry_id, seat, None, true)
+    }
+
+    /// Like `copy_item_by_id`, offering only the item's payload in one MIME type
+    pub fn copy_item_mime(&mut self, entry_id: u64, mime: &str) -> Result<(), BackendError> {
+        let item = self
+            .history
+            .iter()
+            .find(|item| item.item_id == entry_id)
+            .ok_or_else(|| BackendError::new(ErrorCode::NotFound, format!("No clipboard item found with ID: {entry_id}")))?;
+        if !item.mime_data.contains_key(mime) {
+            return Err(BackendError::new(ErrorCode::NotFound, format!("Clipboard item {entry_id} has no {mime} data")));
+        }
+        self.copy_item(entry_id, None, Some(mime), false)
+    }
+
+    fn copy_item(&mut self, entry_id: u64, seat: Option<&str>, mime: Option<&str>, original: bool) -> Result<(), BackendError> {
+        self.set_clipboard_offering(entry_id, seat, mime, original)?;
+        // A user-requested copy losing the selection is not ownership churn
+        self.ownership_taken_at = None;
+        let Some(item) = self.history.iter_mut().find(|i| i.item_id == entry_id) else {
+            return Ok(());
+        };
+        item.copy_count += 1;
+        item.last_pasted = Some(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64);
+        let preview = ClipboardItemPreview::from(&*item);
+        if !is_secret(item) {
+            hooks::run_hook(&self.config.on_paste, hooks::HookEvent::Paste, &preview);
+        }
+        self.save_selection(entry_id);
+        // Clients sorting by use move the item up
+        self.broadcast(BackendMessage::ItemUpdated { item: preview });
+        Ok(())
+    }
+
+    /// Whether new selections are currently not recorded, paused by a client or by an incognito rule
+    pub const fn is_capture_paused(&self) -> bool {
+        self.capture_paused || self.incognito_active
+    }
+
+    /// Whether a new selection may be read, false while over `max_captures_per_sec`
+    pub fn admit_capture(&mut self) -> bool {
+        let admitted = self.capture_throttle.admit(Instant::now(), self.config.max_captures_per_sec);
+        if !admitted {
+            self.metrics.captures_throttled += 1;
+        }
+        admitted
+    }
+
+    /// When `admit_capture` admits a selection again after refusing one
+    pub fn capture_reopens_at(&self) -> Option<Instant> {
+        self.capture_throttle.reopens_at(self.config.max_captures_per_sec)
+    }
+
+    /// Whether a selection read repeats the previous one within `coalesce_window_ms`
+    pub fn is_repeated_capture(&mut self, mime_data: &IndexMap<String, Bytes>) -> bool {
+        let window = Duration::from_millis(self.config.coalesce_window_ms);
+        let repeat = self.capture_throttle.is_repeat(Instant::now(), mime_data, window);
+        if repeat {
+            self.metrics.captures_coalesced += 1;
+        }
+        repeat
+    }
+
+    /// Pause or resume recording of new selections on behalf of a client
+    pub fn set_capture_paused(&mut self, paused: bool) {
+        let was_paused = self.is_capture_paused();
+        self.capture_paused = paused;
+        self.notify_capture_state(was_paused);
+    }
+
+    /// Pause capture while the focused window matches an incognito rule, called whenever
+    /// the known windows change
+    pub fn update_incognito(&mut self) {
+        let active = self.toplevels.values().any(|toplevel| toplevel.activated && self.incognito.matches(toplevel));
+        if active == self.incognito_active {
+            return;
+        }
+        let was_paused = self.is_capture_paused();
+        self.incognito_active = active;
+        info!("Incognito window {}", if active { "focused, pausing capture" } else { "left, capture no longer paused by it" });
+        self.notify_capture_state(was_paused);
+    }
+
+    /// Switch to a changed config file (see `config_watch`), returns false if nothing changed.
+    /// Incognito and content rules, hooks, plugins, retention and the history size apply right away; the metrics
+    /// port and fetching link titles only with the next daemon start.
+    pub fn apply_config(&mut self, config: Config) -> bool {
+        if config == self.config {
+            return false;
+        }
+        if config.metrics_port != self.config.metrics_port {
+            warn!("metrics_port changes take effect when the daemon is restarted");
+        }
+        if config.fetch_link_titles != self.config.fetch_link_titles || config.link_title_timeout_secs != self.config.link_title_timeout_secs {
+            warn!("fetch_link_titles and link_title_timeout_secs changes take effect when the daemon is restarted");
+        }
+        // Windows are tracked whenever the compositor can tell about them, see `toplevel_tracking`
+        if config.incognito != self.config.incognito && !config.incognito.is_empty() && self.active_protocol.is_some() && !self.toplevel_tracking {
+            warn!("Incognito rules are configured, but the compositor does not support zwlr_foreign_toplevel_manager_v1");
+        }
+        self.incognito = IncognitoMatcher::new(&config.incognito);
+        self.classifier = ContentClassifier::new(&config.content_rules);
+        self.config = config;
+        // The focused window may match the new rules, a shorter retention or a smaller history
+        // removes items now
+        self.update_incognito();
+        self.expire_items();
+        let evicted = self.evict_over_limit();
+        if !evicted.is_empty() {
+            self.prune_thumbnail_cache();
+            self.broadcast(BackendMessage::ItemsRemoved { ids: evicted });
+        }
+        self.broadcast(BackendMessage::ConfigReloaded);
+        true
+    }
+
+    /// Remove the items beyond `max_history_items` (the oldest), returns their ids
+    fn evict_over_limit(&mut self) -> Vec<u64> {
+        let limit = (self.config.max_history_items as usize).max(1);
+        if self.history.len() <= limit {
+            return Vec::new();
+        }
+        self.history.drain(limit..).map(|evicted| evicted.item_id).collect()
+    }
+
+    /// Update the monitoring state, clients are told about changes
+    pub fn set_monitor_status(&mut self, status: MonitorStatus) {
+        if self.monitor_status == status {
+            return;
+        }
+        self.monitor_status = status.clone();
+        self.broadcast(BackendMessage::StatusChanged { status });
+    }
+
+    fn notify_capture_state(&mut self, was_paused: bool) {
+        let paused = self.is_capture_paused();
+        if paused != was_paused {
+            info!("Clipboard capture {}", if paused { "paused" } else { "resumed" });
+            self.broadcast(BackendMessage::CaptureStateChanged { paused });
+        }
+    }
+
+    /// Remove the items older than `max_item_age_days`. The item currently served as the
+    /// clipboard content is kept, pasting it must keep working until something else is copied.
+    pub fn expire_items(&mut self) {
+        self.purge_trash();
+        let Some(cutoff) = self.expiry_cutoff() else { return };
+        let served: Vec<u64> = self.seats.iter().filter_map(|seat| seat.current_source_entry_id).collect();
+        let mut expired = Vec::new();
+        self.history.retain(|item| {
+            let keep = item.stamp.millis >= cutoff || served.contains(&item.item_id);
+            if !keep {
+                expired.push(item.item_id);
+            }
+            keep
+        });
+        if !expired.is_empty() {
+            info!("Removed {} clipboard items past the retention window", expired.len());
+            self.prune_thumbnail_cache();
+            self.broadcast(BackendMessage::ItemsRemoved { ids: expired });
+        }
+    }
+
+    /// Items stamped before this Unix time in milliseconds are expired, None if they are kept forever
+    fn expiry_cutoff(&self) -> Option<u64> {
+        let days = self.config.max_item_age_days?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+        Some(now.saturating_sub(days.saturating_mul(24 * 3600 * 1000)))
+    }
+
+    /// Move the whole history to the trash, `undo_clear` brings it back within `UNDO_WINDOW`
     pub fn clear_history(&mut self) {
-        self.history.clear();
+        self.purge_trash();
+        self.clear_generation += 1;
+        let deleted_at = Instant::now();
+        let clear = Some(self.clear_generation);
+        let ids: Vec<u64> = self.history.iter().map(|item| item.item_id).collect();
+        self.trash.extend(self.history.drain(..).map(|item| TrashedItem { item, deleted_at, clear }));
+        self.thumbnail_cache.clear();
+        // A cleared history does not come back as the selection after a restart
+        self.queue_selection_save(None);
+        if !ids.is_empty() {
+            self.broadcast(BackendMessage::ItemsRemoved { ids });
+        }
+    }
+
+    /// Move one item to the trash, `undo_delete` brings it back within `UNDO_WINDOW`
+    pub fn delete_item(&mut self, id: u64) -> Result<(), BackendError> {
+        self.purge_trash();
+        let pos = self
+            .history
+            .iter()
+            .position(|item| item.item_id == id)
+            .ok_or_else(|| BackendError::new(ErrorCode::NotFound, format!("No clipboard item found with ID: {id}")))?;
+        let item = self.history.remove(pos);
+        self.trash.push(TrashedItem { item, deleted_at: Instant::now(), clear: None });
+        self.prune_thumbnail_cache();
+        self.broadcast(BackendMessage::ItemsRemoved { ids: vec![id] });
+        Ok(())
+    }
+
+    /// Restore the items removed by the most recent clear, returns their ids
+    pub fn undo_clear(&mut self) -> Result<Vec<u64>, BackendError> {
+        let generation = self.clear_generation;
+        self.restore_from_trash(|trashed| trashed.clear == Some(generation))
+    }
+
+    /// Restore a deleted item
+    pub fn undo_delete(&mut self, id: u64) -> Result<Vec<u64>, BackendError> {
+        self.restore_from_trash(|trashed| trashed.clear.is_none() && trashed.item.item_id == id)
+    }
+
+    fn restore_from_trash(&mut self, selected: impl Fn(&TrashedItem) -> bool) -> Result<Vec<u64>, BackendError> {
+        self.purge_trash();
+        let (restore, keep): (Vec<_>, Vec<_>) = std::mem::take(&mut self.trash).into_iter().partition(|trashed| selected(trashed));
+        self.trash = keep;
+        if restore.is_empty() {
+            return Err(BackendError::new(
+                ErrorCode::NotFound,
+                format!("Nothing to undo, deleted items can be restored for {} seconds", protocol::UNDO_WINDOW_SECS),
+            ));
+        }
+        let mut restored = Vec::new();
+        for TrashedItem { item, .. } in restore {
+            // Copied again since the deletion, the newer copy stays
+            if self.history.iter().any(|existing| existing.content_preview == item.content_preview) {
+                continue;
+            }
+            restored.push(ClipboardItemPreview::from(&item));
+            self.insert_ordered(item);
+        }
+        let mut removed_ids = self.evict_over_limit();
+        // Restored items pushed out right away were never shown again
+        let evicted_restored: Vec<u64> = restored.iter().map(|item| item.item_id).filter(|id| removed_ids.contains(id)).collect();
+        restored.retain(|item| !evicted_restored.contains(&item.item_id));
+        removed_ids.retain(|id| !evicted_restored.contains(id));
+        if !removed_ids.is_empty() {
+            self.broadcast(BackendMessage::ItemsRemoved { ids: removed_ids });
+        }
+        let ids = restored.iter().map(|item| item.item_id).collect();
+        if !restored.is_empty() {
+            info!("Restored {} deleted clipboard items", restored.len());
+            self.broadcast(BackendMessage::ItemsAdded { items: restored });
+        }
+        Ok(ids)
+    }
+
+    /// Forget deleted items whose undo window has passed
+    pub fn purge_trash(&mut self) {
+        self.trash.retain(|trashed| trashed.deleted_at.elapsed() < UNDO_WINDOW);
+    }
+
+    /// Drop cached thumbnails of items that are no longer in the history
+    fn prune_thumbnail_cache(&mut self) {
+        let history = &self.history;
+        self.thumbnail_cache.retain_items(|id| history.iter().any(|item| item.item_id == id));
+    }
+
+    /// Whether a newly recorded external selection should be re-served by us.
+    /// In the default "auto" defer mode we stop taking ownership while another clipboard
+    /// manager appears to be active, to avoid ownership wars between managers.
+    pub fn should_take_ownership(&self) -> bool {
+        if self.monitor_only {
+            return false;
+        }
+        match self.config.defer_mode {
+            DeferMode::Never => true,
+            DeferMode::Always => false,
+            DeferMode::Auto => !self.other_manager_active(),
+        }
+    }
+
+    fn other_manager_active(&self) -> bool {
+        self.other_manager_seen_at.is_some_and(|seen| seen.elapsed() < OTHER_MANAGER_TIMEOUT)
+    }
+
+    /// Remove clipboard manager marker MIME types from an external offer, noting another
+    /// manager as active if one was present. Returns the remaining MIME types.
+    pub fn filter_manager_markers(&mut self, mime_types: Vec<String>) -> Vec<String> {
+        let (markers, payload_types): (Vec<String>, Vec<String>) =
+            mime_types.into_iter().partition(|mime| MANAGER_MARKER_MIMES.contains(&mime.as_str()));
+        if !markers.is_empty() {
+            if !self.other_manager_active() {
+                info!("Selection owned by another clipboard manager ({}), deferring to it", markers.join(", "));
+            }
+            self.other_manager_seen_at = Some(Instant::now());
+        }
+        payload_types
+    }
+
+    /// Record a selection read from an app on the seat `seat_global`, reading it started at
+    /// `started`. Returns the id of the new item.
+    pub fn record_external_selection(&mut self, seat_global: u32, mime_data: IndexMap<String, Bytes>, suspect_mime_types: Vec<String>, started: Instant) -> Option<u64> {
+        if mime_data.is_empty() {
+            return None;
+        }
+        if self.is_repeated_capture(&mime_data) {
+            debug!("Selection repeats the previous one, not recording it again");
+            return None;
+        }
+        let seat_name = self.seat_mut(seat_global).map(|seat| seat.name.clone());
+        let new_id = self.add_clipboard_item_from_mime_map(mime_data, suspect_mime_types, seat_name)?;
+        self.save_selection(new_id);
+        self.metrics.record_capture(started.elapsed());
+        self.claim_external_selection(seat_global, new_id);
+        Some(new_id)
+    }
+
+    /// Apply the ownership policy to an external selection just recorded as `item_id`
+    pub fn claim_external_selection(&mut self, seat_global: u32, item_id: u64) {
+        if !self.should_take_ownership() {
+            return;
+        }
+        let policy = self.config.ownership;
+        let Some(seat) = self.seat_mut(seat_global) else { return };
+        if seat.selection.is_ours() {
+            return;
+        }
+        match policy {
+            OwnershipPolicy::Always => self.take_over_selection(item_id),
+            OwnershipPolicy::OnSourceExit => {
+                seat.adopt_on_source_exit = Some(item_id);
+                debug!("Leaving selection id {item_id} to its source until it exits");
+            }
+            OwnershipPolicy::Never => {}
+        }
+    }
+
+    /// The selection of a seat was cleared, which is what happens when its source exits.
+    /// Takes over the selection kept for this moment, if any.
+    pub fn selection_source_exited(&mut self, seat_global: u32) {
+        let Some(item_id) = self.seat_mut(seat_global).and_then(|seat| seat.adopt_on_source_exit.take()) else { return };
+        if self.should_take_ownership() && self.get_item_by_id(item_id).is_some() {
+            info!("Source of selection id {item_id} exited, taking the selection over");
+            self.take_over_selection(item_id);
+        }
+    }
+
+    fn take_over_selection(&mut self, item_id: u64) {
+        if let Err(e) = self.set_clipboard_by_id(item_id) {
+            warn!("Failed to take ownership of selection id {item_id}: {e}");
+        } else {
+            self.note_ownership_taken();
+            debug!("Took ownership of external selection (id {item_id})");
+        }
+    }
+
+    /// Serve the most recent item on every seat without a selection, which is the state after
+    /// the daemon or the compositor restarted. While the history is still empty (synced items
+    /// arrive shortly after startup), the restore stays pending until it has an item.
+    pub fn restore_selection(&mut self) {
+        self.selection_restore_pending = false;
+        if self.monitor_only || self.config.ownership == OwnershipPolicy::Never || !self.should_take_ownership() {
+            return;
+        }
+        let Some(latest) = self.history.first().map(|item| item.item_id) else {
+            self.selection_restore_pending = true;
+            return;
+        };
+        let empty_seats: Vec<String> = self
+            .seats
+            .iter()
+            .filter(|seat| seat.current_data_offer.is_none() && !seat.selection.is_ours())
+            .map(|seat| seat.name.clone())
+            .collect();
+        for seat in empty_seats {
+            match self.set_clipboard_on_seat(latest, Some(&seat)) {
+                Ok(()) => info!("Restored the selection on {seat} (id {latest})"),
+                Err(e) => warn!("Could not restore the selection on {seat}: {e}"),
+            }
+        }
+    }
+
+    /// Keep the item that just became the selection for the next start of the daemon. Secrets,
+    /// passwords and items over `MAX_SAVED_BYTES` make it forget the saved selection instead.
+    fn save_selection(&mut self, id: u64) {
+        let Some(item) = self.history.iter().find(|item| item.item_id == id) else { return };
+        let size: usize = item.mime_data.values().map(Bytes::len).sum();
+        let keep = !is_secret(item) && item.content_type != ClipboardContentType::Password && size <= saved_selection::MAX_SAVED_BYTES;
+        let selection = keep.then(|| SavedSelection { stamp: item.stamp, mime_data: item.mime_data.clone() });
+        self.queue_selection_save(selection);
+    }
+
+    /// Hand a selection to keep to the writer, or hold it until the writer starts
+    fn queue_selection_save(&mut self, selection: Option<SavedSelection>) {
+        match &self.selection_saves {
+            Some(saves) => saved_selection::queue(saves, selection),
+            // Synthetic traffic is never kept
+            None if !self.simulated => self.selection_save_pending = Some(selection),
+            None => {}
+        }
+    }
+
+    /// Hand selections to keep to the writer of `saved_selection` from now on, starting with
+    /// one made before it ran
+    pub fn set_selection_writer(&mut self, saves: std::sync::mpsc::Sender<Option<SavedSelection>>) {
+        if let Some(selection) = self.selection_save_pending.take() {
+            saved_selection::queue(&saves, selection);
+        }
+        self.selection_saves = Some(saves);
+    }
+
+    /// Restore the selection if that waited for the history to fill
+    pub fn restore_pending_selection(&mut self) {
+        if self.selection_restore_pending {
+            self.restore_selection();
+        }
+    }
+
+    /// Record that we took over an external selection
+    pub fn note_ownership_taken(&mut self) {
+        self.ownership_taken_at = Some(Instant::now());
+    }
+
+    /// Record that an external client took the selection from us. Repeatedly losing it right
+    /// after taking it over is the pattern of another manager re-serving the same selection.
+    pub fn note_ownership_lost(&mut self) {
+        let Some(taken_at) = self.ownership_taken_at.take() else { return };
+        if taken_at.elapsed() > CHURN_WINDOW {
+            return;
+        }
+
+        let now = Instant::now();
+        self.ownership_churn.retain(|at| now.duration_since(*at) < CHURN_PERIOD);
+        self.ownership_churn.push(now);
+        if self.ownership_churn.len() >= CHURN_THRESHOLD {
+            if !self.other_manager_active() {
+                info!("Selection ownership keeps being taken over, assuming another clipboard manager is active");
+            }
+            self.other_manager_seen_at = Some(now);
+            self.ownership_churn.clear();
+        }
+    }
+
+    /// Set the selection to an item on the seat it was copied on (or the first seat)
+    pub fn set_clipboard_by_id(&mut self, entry_id: u64) -> Result<(), BackendError> {
+        self.set_clipboard_on_seat(entry_id, None)
+    }
+
+    /// Set the selection to an item on the seat with the given name. Without a name, the seat
+    /// the item was copied on is used if it still exists, otherwise the first seat.
+    pub fn set_clipboard_on_seat(&mut self, entry_id: u64, seat: Option<&str>) -> Result<(), BackendError> {
+        self.set_clipboard_offering(entry_id, seat, None, false)
     }
 
-    pub fn set_clipboard_by_id(&mut self, entry_id: u64) -> Result<(), String> {
-        let item = self.get_item_by_id(entry_id).ok_or_else(|| format!("No clipboard item found with ID: {entry_id}"))?;
+    /// Like `set_clipboard_on_seat`, offering only `mime` if given and serving the text as
+    /// copied if `original`
+    fn set_clipboard_offering(&mut self, entry_id: u64, seat: Option<&str>, mime: Option<&str>, original: bool) -> Result<(), BackendError> {
+        let mut item = self
+            .get_item_by_id(entry_id)
+            .ok_or_else(|| BackendError::new(ErrorCode::NotFound, format!("No clipboard item found with ID: {entry_id}")))?;
 
         info!("Setting clipboard content by ID {entry_id}");
         debug!("Setting clipboard content by ID {entry_id}: {}", item.content_preview);
+        match mime {
+            Some(mime) => item.mime_data.retain(|offered, _| offered == mime),
+            None => item.mime_data.retain(|offered, _| !offered.starts_with(ORIGINAL_MIME_PREFIX)),
+        }
+
+        if self.simulated {
+            info!("Simulation mode: not setting the selection for item {entry_id}");
+            return Ok(());
+        }
 
-        match self.active_protocol {
-            Some(DataControlProtocol::Wlr) => self.set_clipboard_wlr(entry_id, &item),
-            Some(DataControlProtocol::Ext) => self.set_clipboard_ext(entry_id, &item),
-            None => Err("No data control protocol available".into()),
+        let seat_index = match seat {
+            Some(name) => self
+                .seats
+                .iter()
+                .position(|seat| seat.name == name)
+                .ok_or_else(|| BackendError::new(ErrorCode::NotFound, format!("No seat named {name}")))?,
+            None => item
+                .seat
+                .as_ref()
+                .and_then(|name| self.seats.iter().position(|seat| &seat.name == name))
+                .unwrap_or(0),
+        };
+        let clipboard = self.clipboard.clone();
+        clipboard.set_selection(self, seat_index, &item).map_err(|message| BackendError::new(ErrorCode::WaylandUnavailable, message))?;
+        if let Some(seat) = self.seats.get_mut(seat_index) {
+            seat.current_source_original = original;
         }
+        Ok(())
     }
 
-    fn set_clipboard_wlr(&mut self, entry_id: u64, item: &ClipboardItem) -> Result<(), String> {
-        let (Some(manager), Some(device), Some(qh)) = (
-            &self.data_control_manager,
-            &self.data_control_device,
-            &self.qh,
-        ) else {
+    pub fn set_clipboard_wlr(&mut self, seat_index: usize, entry_id: u64, item: &ClipboardItem) -> Result<(), String> {
+        let (Some(manager), Some(qh)) = (&self.data_control_manager, &self.qh) else {
             return Err("Wayland wlroots clipboard objects not available yet".into());
         };
+        let Some(seat) = self.seats.get_mut(seat_index) else {
+            return Err("No Wayland seat available".into());
+        };
+        let Some(device) = &seat.data_control_device else {
+            return Err(format!("No wlroots data device for {} yet", seat.name));
+        };
 
         // Clean up any previously set source that we own
-        if let Some(prev) = self.current_source_object.take() {
+        if let Some(prev) = seat.current_source_object.take() {
             prev.destroy();
         }
 
-        let source = manager.create_data_source(qh, ());
+        let source = manager.create_data_source(qh, seat.global);
         for (mime, _data) in &item.mime_data { source.offer(mime.clone()); }
+        source.offer(OWNER_MARKER_MIME.to_string());
         device.set_selection(Some(&source));
-        self.current_source_object = Some(source);
-        self.current_source_entry_id = Some(entry_id);
-        self.suppress_next_selection_read = true;
+        seat.current_source_object = Some(source);
+        seat.current_source_entry_id = Some(entry_id);
+        let serial = seat.selection.source_set(entry_id);
+        seat.adopt_on_source_exit = None;
 
-        if let Some(conn) = &self.connection {
-            if let Err(e) = conn.flush() { warn!("Failed to flush Wayland connection after setting selection: {e}"); }
-        }
-        debug!("Created wlroots clipboard source and set selection (id {entry_id})");
+        if let Some(conn) = &self.connection
+            && let Err(e) = conn.flush() { warn!("Failed to flush Wayland connection after setting selection: {e}"); }
+        debug!("Created wlroots clipboard source {serial} and set selection (id {entry_id})");
         Ok(())
     }
 
-    fn set_clipboard_ext(&mut self, entry_id: u64, item: &ClipboardItem) -> Result<(), String> {
-        let (Some(manager), Some(device), Some(qh)) = (
-            &self.ext_data_control_manager,
-            &self.ext_data_control_device,
-            &self.qh,
-        ) else {
+    pub fn set_clipboard_ext(&mut self, seat_index: usize, entry_id: u64, item: &ClipboardItem) -> Result<(), String> {
+        let (Some(manager), Some(qh)) = (&self.ext_data_control_manager, &self.qh) else {
             return Err("Wayland ext clipboard objects not available yet".into());
         };
+        let Some(seat) = self.seats.get_mut(seat_index) else {
+            return Err("No Wayland seat available".into());
+        };
+        let Some(device) = &seat.ext_data_control_device else {
+            return Err(format!("No ext data device for {} yet", seat.name));
+        };
 
         // Clean up any previously set source that we own
-        if let Some(prev) = self.ext_current_source_object.take() {
+        if let Some(prev) = seat.ext_current_source_object.take() {
             prev.destroy();
         }
 
-        let source = manager.create_data_source(qh, ());
+        let source = manager.create_data_source(qh, seat.global);
         for (mime, _data) in &item.mime_data { source.offer(mime.clone()); }
+        source.offer(OWNER_MARKER_MIME.to_string());
         device.set_selection(Some(&source));
-        self.ext_current_source_object = Some(source);
-        self.ext_current_source_entry_id = Some(entry_id);
-        self.suppress_next_selection_read = true;
+        seat.ext_current_source_object = Some(source);
+        seat.current_source_entry_id = Some(entry_id);
+        let serial = seat.selection.source_set(entry_id);
+        seat.adopt_on_source_exit = None;
 
-        if let Some(conn) = &self.connection {
-            if let Err(e) = conn.flush() { warn!("Failed to flush Wayland connection after setting selection: {e}"); }
-        }
-        debug!("Created ext clipboard source and set selection (id {entry_id})");
+        if let Some(conn) = &self.connection
+            && let Err(e) = conn.flush() { warn!("Failed to flush Wayland connection after setting selection: {e}"); }
+        debug!("Created ext clipboard source {serial} and set selection (id {entry_id})");
         Ok(())
     }
+
+    /// Seat with the registry name `global`
+    pub fn seat_mut(&mut self, global: u32) -> Option<&mut SeatClipboard> {
+        self.seats.iter_mut().find(|seat| seat.global == global)
+    }
+
+    /// Track a newly bound seat, creating its data device if a data control manager is bound
+    pub fn add_seat(&mut self, mut seat: SeatClipboard) {
+        if let Some(qh) = &self.qh {
+            if let Some(manager) = &self.data_control_manager {
+                seat.data_control_device = Some(manager.get_data_device(&seat.seat, qh, seat.global));
+            }
+            if let Some(manager) = &self.ext_data_control_manager {
+                seat.ext_data_control_device = Some(manager.get_data_device(&seat.seat, qh, seat.global));
+            }
+        }
+        self.seats.push(seat);
+    }
+
+    /// Forget a seat removed by the compositor
+    pub fn remove_seat(&mut self, global: u32) {
+        if let Some(pos) = self.seats.iter().position(|seat| seat.global == global) {
+            let seat = self.seats.remove(pos);
+            info!("Seat {} removed", seat.name);
+            seat.destroy();
+        }
+    }
+}
+
+/// A stamp older than `above` and newer than `below` (or equal to `below` if there is none in
+/// between, which still sorts in right above it)
+fn stamp_between(above: EventStamp, below: Option<EventStamp>) -> EventStamp {
+    let Some(below) = below else {
+        return EventStamp { millis: above.millis.saturating_sub(1), seq: above.seq };
+    };
+    if above.millis > below.millis + 1 {
+        EventStamp { millis: below.millis + (above.millis - below.millis) / 2, seq: below.seq }
+    } else if above.millis > below.millis || above.seq > below.seq + 1 {
+        EventStamp { millis: below.millis, seq: below.seq + 1 }
+    } else {
+        below
+    }
+}
+
+/// Preview text and content type of an item with the given payloads: image/png items are shown
+/// as placeholder, text items by their first 200 characters
+fn preview_for(mime_content: &IndexMap<String, Bytes>, classifier: &ContentClassifier) -> (String, ClipboardContentType) {
+    if let Some(png_bytes) = mime_content.get("image/png") {
+        return (format!("<image/png {} bytes>", png_bytes.len()), ClipboardContentType::Image);
+    }
+    let preview: String = if let Some(txt_bytes) = mime_content.get("text/plain;charset=utf-8") {
+        match std::str::from_utf8(txt_bytes.as_ref()) {
+            Ok(s) => s.chars().take(200).collect(),
+            Err(_) => format!("<text/plain;charset=utf-8 {} bytes>", txt_bytes.len()),
+        }
+    } else {
+        // Fallback: show placeholder using first mime entry
+        let (mime_name, len) = mime_content.iter().next().map(|(k,v)| (k.clone(), v.len())).unwrap();
+        format!("<{mime_name} {len} bytes>")
+    };
+    let content_type = classifier.classify(&preview);
+    (preview, content_type)
+}
+
+fn text_stats_of(item: &ClipboardItem) -> Option<TextStats> {
+    transforms::item_text(item).and_then(|text| TextStats::of(text, item.content_type))
+}
+
+/// A URL item that has not been merged with a page title yet
+fn is_plain_link(item: &ClipboardItem) -> bool {
+    matches!(item.content_type, ClipboardContentType::Url) && item.content_preview.starts_with("http")
+}
+
+/// Marked as a secret by the source (KDE's password manager hint, also used by KeePassXC)
+pub fn is_secret(item: &ClipboardItem) -> bool {
+    is_secret_payload(&item.mime_data)
+}
+
+pub fn is_secret_payload(mime_data: &IndexMap<String, Bytes>) -> bool {
+    mime_data.get("x-kde-passwordManagerHint").is_some_and(|hint| hint.as_ref() == b"secret")
+}
+
+/// Single-line plain text short enough to be a page title
+fn is_page_title(item: &ClipboardItem) -> bool {
+    let title = item.content_preview.trim();
+    matches!(item.content_type, ClipboardContentType::Text)
+        && !title.is_empty()
+        && !title.contains('\n')
+        && title.chars().count() <= 150
+}
+
+/// Host part of a URL without scheme and leading "www."
+pub fn link_domain(url: &str) -> &str {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let host = without_scheme.split(['/', '?', '#']).next().unwrap_or(without_scheme);
+    host.strip_prefix("www.").unwrap_or(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(content: &str) -> IndexMap<String, Bytes> {
+        IndexMap::from([
+            ("text/plain;charset=utf-8".to_string(), Bytes::copy_from_slice(content.as_bytes())),
+            ("text/plain".to_string(), Bytes::copy_from_slice(content.as_bytes())),
+        ])
+    }
+
+    fn state_merging_link_titles(merge: bool) -> BackendState {
+        let mut state = BackendState::new();
+        state.config.merge_link_titles = merge;
+        state
+    }
+
+    fn previews(state: &BackendState) -> Vec<&str> {
+        state.history.iter().map(|item| item.content_preview.as_str()).collect()
+    }
+
+    #[test]
+    fn link_titles_are_not_merged_by_default() {
+        let mut state = BackendState::new();
+        assert!(!state.config.merge_link_titles);
+        assert!(state.add_clipboard_item_from_mime_map(text("Hello, world Cursor-Clip!"), Vec::new(), None).is_some());
+        assert!(state.add_clipboard_item_from_mime_map(text("https://github.com/rust-lang/rust"), Vec::new(), None).is_some());
+        assert!(state.add_clipboard_item_from_mime_map(text("Sample clipboard content"), Vec::new(), None).is_some());
+        assert_eq!(previews(&state), ["Sample clipboard content", "https://github.com/rust-lang/rust", "Hello, world Cursor-Clip!"]);
+    }
+
+    #[test]
+    fn title_copied_before_its_link_is_merged_into_the_link() {
+        let mut state = state_merging_link_titles(true);
+        state.add_clipboard_item_from_mime_map(text("Rust Programming Language"), Vec::new(), None);
+        let link = state.add_clipboard_item_from_mime_map(text("https://www.rust-lang.org/learn"), Vec::new(), None);
+        assert!(link.is_some());
+        assert_eq!(previews(&state), ["Rust Programming Language — rust-lang.org"]);
+        assert_eq!(state.history[0].mime_data["text/plain"], "https://www.rust-lang.org/learn");
+    }
+
+    #[test]
+    fn title_copied_after_its_link_retitles_the_link() {
+        let mut state = state_merging_link_titles(true);
+        let link = state.add_clipboard_item_from_mime_map(text("https://www.rust-lang.org/learn"), Vec::new(), None);
+        assert_eq!(state.add_clipboard_item_from_mime_map(text("Rust Programming Language"), Vec::new(), None), None);
+        assert_eq!(previews(&state), ["Rust Programming Language — rust-lang.org"]);
+        assert_eq!(Some(state.history[0].item_id), link);
+    }
+
+    #[test]
+    fn multi_line_text_is_no_page_title() {
+        let mut state = state_merging_link_titles(true);
+        state.add_clipboard_item_from_mime_map(text("https://www.rust-lang.org/learn"), Vec::new(), None);
+        assert!(state.add_clipboard_item_from_mime_map(text("first line\nsecond line"), Vec::new(), None).is_some());
+        assert_eq!(state.history.len(), 2);
+    }
+
+    fn state_with_plugin_queue() -> (BackendState, std::sync::mpsc::Receiver<plugins::PluginJob>) {
+        let mut state = BackendState::new();
+        state.config.plugins = vec![crate::shared::config::PluginConfig::default()];
+        let (tx, rx) = std::sync::mpsc::channel();
+        state.plugin_jobs = Some(tx);
+        (state, rx)
+    }
+
+    #[test]
+    fn secret_items_are_not_passed_to_plugins() {
+        let (mut state, jobs) = state_with_plugin_queue();
+        let mut secret = text("hunter2");
+        secret.insert("x-kde-passwordManagerHint".to_string(), Bytes::from_static(b"secret"));
+        assert!(state.add_clipboard_item_from_mime_map(secret, Vec::new(), None).is_some());
+        assert!(jobs.try_recv().is_err());
+
+        let id = state.add_clipboard_item_from_mime_map(text("public"), Vec::new(), None).unwrap();
+        let job = jobs.try_recv().unwrap();
+        assert_eq!(job.item_id, id);
+        assert_eq!(job.mime_data["text/plain"], "public");
+    }
+
+    #[test]
+    fn plugin_results_update_the_recorded_item() {
+        let mut state = BackendState::new();
+        let id = state.add_clipboard_item_from_mime_map(text("token ghp_abc"), Vec::new(), None).unwrap();
+        state.history[0].tags.push("mine".to_string());
+        let mut redacted = text("token [redacted]");
+        redacted.shift_remove("text/plain");
+        state.apply_plugin_results(id, redacted, vec!["work".to_string(), "mine".to_string()]);
+
+        let item = &state.history[0];
+        assert_eq!(item.content_preview, "token [redacted]");
+        assert_eq!(item.mime_data["text/plain"], "token ghp_abc");
+        assert_eq!(item.tags, ["mine", "work"]);
+        // Removed before the plugins finished
+        state.apply_plugin_results(id + 1, IndexMap::new(), vec!["lost".to_string()]);
+    }
+
+    #[test]
+    fn hooks_do_not_run_for_secret_items() {
+        let marker = std::env::temp_dir().join(format!("cursor-clip-hook-test-{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        let mut state = BackendState::new();
+        state.config.on_copy = vec!["touch".to_string(), marker.display().to_string()];
+        let mut secret = text("hunter2");
+        secret.insert("x-kde-passwordManagerHint".to_string(), Bytes::from_static(b"secret"));
+        state.add_clipboard_item_from_mime_map(secret, Vec::new(), None);
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(!marker.exists());
+
+        state.add_clipboard_item_from_mime_map(text("public"), Vec::new(), None);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !marker.exists() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(marker.exists());
+        let _ = std::fs::remove_file(&marker);
+    }
+
+    #[test]
+    fn selections_are_saved_unless_secret() {
+        let mut state = BackendState::new();
+        state.simulated = true;
+        let (tx, saves) = std::sync::mpsc::channel();
+        state.selection_saves = Some(tx);
+
+        let id = state.add_clipboard_item_from_mime_map(text("keep me"), Vec::new(), None).unwrap();
+        state.copy_item_by_id(id).unwrap();
+        let saved = saves.try_recv().unwrap().unwrap();
+        assert_eq!(saved.mime_data, text("keep me"));
+        assert_eq!(saved.stamp, state.history[0].stamp);
+
+        let mut secret = text("hunter2");
+        secret.insert("x-kde-passwordManagerHint".to_string(), Bytes::from_static(b"secret"));
+        let id = state.add_clipboard_item_from_mime_map(secret, Vec::new(), None).unwrap();
+        state.copy_item_by_id(id).unwrap();
+        assert!(saves.try_recv().unwrap().is_none());
+
+        state.clear_history();
+        assert!(saves.try_recv().unwrap().is_none());
+    }
+
+    #[test]
+    fn saved_selection_is_restored_as_the_newest_item() {
+        let mut state = BackendState::new();
+        let saved = SavedSelection { stamp: EventStamp { millis: 1_000, seq: 4 }, mime_data: text("from the last run") };
+        let id = state.restore_saved_selection(saved).unwrap();
+        assert_eq!(state.history[0].item_id, id);
+        assert_eq!(previews(&state), ["from the last run"]);
+        assert_eq!(state.history[0].source_device, None);
+
+        // Copies after the restart come first
+        state.add_clipboard_item_from_mime_map(text("new copy"), Vec::new(), None);
+        assert_eq!(previews(&state), ["new copy", "from the last run"]);
+    }
+
+    fn add(state: &mut BackendState, content: &str) -> u64 {
+        state.add_clipboard_item_from_mime_map(text(content), Vec::new(), None).unwrap()
+    }
+
+    fn now_millis() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+    }
+
+    const DAY_MILLIS: u64 = 24 * 3600 * 1000;
+
+    #[test]
+    fn items_past_the_age_limit_expire() {
+        let mut state = BackendState::new();
+        add(&mut state, "last week");
+        add(&mut state, "yesterday");
+        add(&mut state, "today");
+        state.history[2].stamp.millis = now_millis() - 7 * DAY_MILLIS;
+        state.history[1].stamp.millis = now_millis() - DAY_MILLIS + 60_000;
+
+        // Kept forever without a limit
+        state.expire_items();
+        assert_eq!(state.history.len(), 3);
+
+        state.config.max_item_age_days = Some(1);
+        state.expire_items();
+        assert_eq!(previews(&state), ["today", "yesterday"]);
+    }
+
+    #[test]
+    fn imports_past_the_age_limit_are_dropped() {
+        let mut state = BackendState::new();
+        state.config.max_item_age_days = Some(2);
+        let old = EventStamp { millis: now_millis() - 3 * DAY_MILLIS, seq: 0 };
+        assert_eq!(state.import_synced_item(text("old"), old, "phone".to_string()), None);
+        let recent = EventStamp { millis: now_millis() - DAY_MILLIS, seq: 0 };
+        assert!(state.import_synced_item(text("recent"), recent, "phone".to_string()).is_some());
+        assert_eq!(previews(&state), ["recent"]);
+    }
+
+    fn error_code<T>(result: Result<T, BackendError>) -> Option<ErrorCode> {
+        result.err().map(|error| error.code)
+    }
+
+    #[test]
+    fn deleted_items_can_be_restored_once() {
+        let mut state = BackendState::new();
+        let first = add(&mut state, "first");
+        add(&mut state, "second");
+        state.delete_item(first).unwrap();
+        assert_eq!(previews(&state), ["second"]);
+        assert_eq!(error_code(state.delete_item(first)), Some(ErrorCode::NotFound));
+
+        assert_eq!(state.undo_delete(first).unwrap(), [first]);
+        assert_eq!(previews(&state), ["second", "first"]);
+        assert_eq!(error_code(state.undo_delete(first)), Some(ErrorCode::NotFound));
+    }
+
+    #[test]
+    fn undoing_a_clear_restores_the_whole_history() {
+        let mut state = BackendState::new();
+        let ids = [add(&mut state, "one"), add(&mut state, "two")];
+        state.clear_history();
+        assert!(state.history.is_empty());
+        // A deleted item does not come back with the clear
+        assert_eq!(error_code(state.undo_delete(ids[0])), Some(ErrorCode::NotFound));
+
+        let mut restored = state.undo_clear().unwrap();
+        restored.sort_unstable();
+        assert_eq!(restored, ids);
+        assert_eq!(previews(&state), ["two", "one"]);
+        assert_eq!(error_code(state.undo_clear()), Some(ErrorCode::NotFound));
+    }
+
+    #[test]
+    fn undo_keeps_newer_copies_and_ends_with_its_window() {
+        let mut state = BackendState::new();
+        let copied = add(&mut state, "copied again");
+        let expired = add(&mut state, "expired");
+        state.delete_item(copied).unwrap();
+        state.delete_item(expired).unwrap();
+        add(&mut state, "copied again");
+        assert_eq!(state.undo_delete(copied).unwrap(), Vec::<u64>::new());
+        assert_eq!(previews(&state), ["copied again"]);
+
+        for trashed in &mut state.trash {
+            trashed.deleted_at -= UNDO_WINDOW;
+        }
+        assert_eq!(error_code(state.undo_delete(expired)), Some(ErrorCode::NotFound));
+        assert!(state.trash.is_empty());
+    }
+
+    #[test]
+    fn moved_items_take_a_stamp_between_their_neighbours() {
+        let mut state = BackendState::new();
+        let a = add(&mut state, "a");
+        add(&mut state, "b");
+        add(&mut state, "c");
+        assert_eq!(state.move_item(a, 1).unwrap(), 1);
+        assert_eq!(previews(&state), ["c", "a", "b"]);
+        // Copies within one millisecond leave no room, the moved item then ties with the one below
+        assert!(state.history[0].stamp > state.history[1].stamp && state.history[1].stamp >= state.history[2].stamp);
+
+        // The top gets a fresh stamp, positions past the end move to the bottom
+        assert_eq!(state.move_item(a, 0).unwrap(), 0);
+        assert_eq!(previews(&state), ["a", "c", "b"]);
+        assert_eq!(state.move_item(a, 99).unwrap(), 2);
+        assert_eq!(previews(&state), ["c", "b", "a"]);
+        assert_eq!(error_code(state.move_item(a + 100, 0)), Some(ErrorCode::NotFound));
+    }
+
+    #[test]
+    fn stamp_between_sorts_in_between_or_right_above() {
+        let stamp = |millis, seq| EventStamp { millis, seq };
+        assert_eq!(stamp_between(stamp(100, 0), Some(stamp(50, 3))), stamp(75, 3));
+        assert_eq!(stamp_between(stamp(51, 0), Some(stamp(50, 3))), stamp(50, 4));
+        assert_eq!(stamp_between(stamp(50, 9), Some(stamp(50, 3))), stamp(50, 4));
+        // No stamp in between, the moved item ties with the one below
+        assert_eq!(stamp_between(stamp(50, 4), Some(stamp(50, 3))), stamp(50, 3));
+        assert_eq!(stamp_between(stamp(100, 2), None), stamp(99, 2));
+    }
+
+    /// Pops the copy stack until it runs out, returning the pasted previews and the counts left
+    fn pop_all(state: &mut BackendState, depth: u32) -> Vec<(String, u32)> {
+        std::iter::from_fn(|| state.pop_and_paste(depth).ok().map(|(item, left)| (item.content_preview, left))).collect()
+    }
+
+    #[test]
+    fn the_copy_stack_pastes_in_copy_order() {
+        let mut state = BackendState::new();
+        state.simulated = true;
+        for content in ["a", "b", "c", "d"] {
+            add(&mut state, content);
+        }
+        assert_eq!(error_code(state.pop_and_paste(0)), Some(ErrorCode::InvalidRequest));
+        let popped = pop_all(&mut state, 3);
+        assert_eq!(popped, [("b".to_string(), 2), ("c".to_string(), 1), ("d".to_string(), 0)]);
+        assert_eq!(error_code(state.pop_and_paste(3)), Some(ErrorCode::NotFound));
+
+        // A new copy fills the stack again
+        add(&mut state, "e");
+        assert_eq!(state.pop_and_paste(2).unwrap().0.content_preview, "d");
+    }
+
+    #[test]
+    fn the_copy_stack_skips_deleted_items() {
+        let mut state = BackendState::new();
+        state.simulated = true;
+        add(&mut state, "a");
+        let b = add(&mut state, "b");
+        add(&mut state, "c");
+        let (first, left) = state.pop_and_paste(3).unwrap();
+        assert_eq!((first.content_preview.as_str(), left), ("a", 2));
+        state.delete_item(b).unwrap();
+        assert_eq!(pop_all(&mut state, 3), [("c".to_string(), 0)]);
+    }
+
+    #[test]
+    fn stats_count_copies_and_list_the_most_copied_items() {
+        let mut state = BackendState::new();
+        state.simulated = true;
+        let a = add(&mut state, "a");
+        let b = add(&mut state, "b");
+        add(&mut state, "never copied");
+        for id in [a, b, a, a] {
+            state.copy_item_by_id(id).unwrap();
+        }
+        let stats = state.history_stats();
+        assert_eq!(stats.total_copies, 4);
+        let most_copied: Vec<_> = stats.most_copied.iter().map(|item| (item.content_preview.as_str(), item.copy_count)).collect();
+        assert_eq!(most_copied, [("a", 3), ("b", 1)]);
+
+        // Deleted items no longer count
+        state.delete_item(a).unwrap();
+        let stats = state.history_stats();
+        assert_eq!(stats.total_copies, 1);
+        assert_eq!(stats.most_copied.len(), 1);
+    }
+
+    #[test]
+    fn selections_made_before_the_writer_starts_are_saved_once_it_does() {
+        let mut state = BackendState::new();
+        let id = add(&mut state, "early");
+        state.save_selection(id);
+        let (tx, saves) = std::sync::mpsc::channel();
+        state.set_selection_writer(tx);
+        assert_eq!(saves.try_recv().unwrap().unwrap().mime_data, text("early"));
+
+        state.clear_history();
+        assert!(saves.try_recv().unwrap().is_none());
+        assert!(saves.try_recv().is_err());
+    }
+
+    /// Pretend the compositor reported `app_id` as the focused window
+    fn focus(state: &mut BackendState, app_id: &str) {
+        let toplevel = Toplevel { app_id: app_id.to_string(), title: String::new(), activated: true };
+        state.toplevels.insert(ObjectId::null(), toplevel);
+    }
+
+    #[test]
+    fn copies_record_the_focused_app() {
+        let mut state = BackendState::new();
+        focus(&mut state, "org.gnome.TextEditor");
+        let copied = state.add_clipboard_item_from_mime_map(text("copied"), Vec::new(), Some("seat0".to_string())).unwrap();
+        // Items cursor-clip adds itself have no seat and were not copied in that app
+        let added = add(&mut state, "added");
+        let source_app = |id| state.history.iter().find(|item| item.item_id == id).unwrap().source_app.clone();
+        assert_eq!(source_app(copied).as_deref(), Some("org.gnome.TextEditor"));
+        assert_eq!(source_app(added), None);
+    }
+
+    fn copy_in(state: &mut BackendState, app_id: &str, content: &str) -> u64 {
+        focus(state, app_id);
+        state.add_clipboard_item_from_mime_map(text(content), Vec::new(), Some("seat0".to_string())).unwrap()
+    }
+
+    fn group_of(state: &BackendState, id: u64) -> Option<u64> {
+        state.history.iter().find(|item| item.item_id == id).unwrap().group_id
+    }
+
+    #[test]
+    fn rapid_copies_from_one_app_are_grouped() {
+        let mut state = BackendState::new();
+        let a = copy_in(&mut state, "kitty", "a");
+        let b = copy_in(&mut state, "kitty", "b");
+        let c = copy_in(&mut state, "kitty", "c");
+        assert_eq!([a, b, c].map(|id| group_of(&state, id)), [Some(c); 3]);
+
+        // Another app starts a new group
+        let d = copy_in(&mut state, "firefox", "d");
+        assert_eq!(group_of(&state, d), None);
+        assert_eq!(group_of(&state, c), Some(c));
+    }
+
+    #[test]
+    fn copies_outside_the_window_or_of_unknown_apps_are_not_grouped() {
+        let mut state = BackendState::new();
+        let a = copy_in(&mut state, "kitty", "a");
+        state.last_capture.as_mut().unwrap().0 -= Duration::from_millis(state.config.group_window_ms + 1);
+        let b = copy_in(&mut state, "kitty", "b");
+        assert_eq!((group_of(&state, a), group_of(&state, b)), (None, None));
+
+        // Added by cursor-clip, then copied without window tracking
+        let added = add(&mut state, "added");
+        let c = copy_in(&mut state, "kitty", "c");
+        assert_eq!((group_of(&state, added), group_of(&state, c)), (None, None));
+        state.toplevels.clear();
+        let unknown = state.add_clipboard_item_from_mime_map(text("unknown"), Vec::new(), Some("seat0".to_string())).unwrap();
+        let e = copy_in(&mut state, "kitty", "e");
+        assert_eq!((group_of(&state, unknown), group_of(&state, e)), (None, None));
+    }
+
+    #[test]
+    fn incognito_rules_added_by_a_reload_apply_to_the_focused_window() {
+        let mut state = BackendState::new();
+        focus(&mut state, "org.keepassxc.KeePassXC");
+        assert!(!state.is_capture_paused());
+        let mut config = state.config.clone();
+        config.incognito = vec![crate::shared::config::IncognitoRule { app_id: Some("keepassxc".to_string()), title: None }];
+        assert!(state.apply_config(config));
+        assert!(state.is_capture_paused());
+    }
 }
@@ -0,0 +1,107 @@
+// Rate limiting of captures: some apps (terminals, IDEs) set the selection many times per
+// second, often to the same content. Repeats within a short window are coalesced and the
+// number of selections read per second is capped, so they cannot flood the history. The newest
+// selection over the cap is read once the cap allows it again, so the last copy of a burst is
+// still recorded.
+
+use std::collections::VecDeque;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+use bytes::Bytes;
+use indexmap::IndexMap;
+
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default)]
+pub struct CaptureThrottle {
+    /// Selections admitted within the last `RATE_WINDOW`
+    admitted: VecDeque<Instant>,
+    /// Time and content hash of the last selection read
+    last: Option<(Instant, u64)>,
+}
+
+impl CaptureThrottle {
+    /// Whether another selection may be read now, at most `max_per_sec` are (0: no limit)
+    pub fn admit(&mut self, now: Instant, max_per_sec: u32) -> bool {
+        if max_per_sec == 0 {
+            return true;
+        }
+        while self.admitted.front().is_some_and(|at| now.duration_since(*at) >= RATE_WINDOW) {
+            self.admitted.pop_front();
+        }
+        if self.admitted.len() >= max_per_sec as usize {
+            return false;
+        }
+        self.admitted.push_back(now);
+        true
+    }
+
+    /// When the next selection is admitted again after `admit` refused one
+    pub fn reopens_at(&self, max_per_sec: u32) -> Option<Instant> {
+        if max_per_sec == 0 || self.admitted.len() < max_per_sec as usize {
+            return None;
+        }
+        self.admitted.front().map(|at| *at + RATE_WINDOW)
+    }
+
+    /// Whether a selection read has the content of the previous one, read less than `window` ago
+    pub fn is_repeat(&mut self, now: Instant, mime_data: &IndexMap<String, Bytes>, window: Duration) -> bool {
+        let hash = content_hash(mime_data);
+        let repeat = self.last.is_some_and(|(at, last_hash)| last_hash == hash && now.duration_since(at) < window);
+        self.last = Some((now, hash));
+        repeat
+    }
+}
+
+fn content_hash(mime_data: &IndexMap<String, Bytes>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for (mime, data) in mime_data {
+        mime.hash(&mut hasher);
+        data.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(content: &'static str) -> IndexMap<String, Bytes> {
+        IndexMap::from([("text/plain".to_string(), Bytes::from_static(content.as_bytes()))])
+    }
+
+    #[test]
+    fn captures_per_second_are_capped() {
+        let mut throttle = CaptureThrottle::default();
+        let start = Instant::now();
+        assert!((0..3).all(|i| throttle.admit(start + Duration::from_millis(i * 10), 3)));
+        assert!(!throttle.admit(start + Duration::from_millis(500), 3));
+        assert!(throttle.admit(start + Duration::from_millis(1001), 3));
+        assert!((0..100).all(|_| throttle.admit(start, 0)));
+    }
+
+    #[test]
+    fn refused_captures_know_when_the_cap_reopens() {
+        let mut throttle = CaptureThrottle::default();
+        let start = Instant::now();
+        assert_eq!(throttle.reopens_at(2), None);
+        assert!(throttle.admit(start, 2) && throttle.admit(start + Duration::from_millis(300), 2));
+        assert!(!throttle.admit(start + Duration::from_millis(400), 2));
+        let reopens_at = throttle.reopens_at(2).unwrap();
+        assert_eq!(reopens_at, start + RATE_WINDOW);
+        assert!(throttle.admit(reopens_at, 2));
+        assert_eq!(throttle.reopens_at(0), None);
+    }
+
+    #[test]
+    fn identical_selections_within_the_window_are_repeats() {
+        let mut throttle = CaptureThrottle::default();
+        let start = Instant::now();
+        let window = Duration::from_millis(500);
+        assert!(!throttle.is_repeat(start, &text("a"), window));
+        assert!(throttle.is_repeat(start + Duration::from_millis(100), &text("a"), window));
+        assert!(!throttle.is_repeat(start + Duration::from_millis(200), &text("b"), window));
+        assert!(!throttle.is_repeat(start + Duration::from_millis(900), &text("b"), window));
+    }
+}
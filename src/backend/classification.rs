@@ -0,0 +1,84 @@
+// Content type of new items: the user's `[[content_rules]]` first, then the built-in heuristics
+
+use regex::Regex;
+use tracing::warn;
+use crate::shared::ClipboardContentType;
+use crate::shared::config::ContentRule;
+
+/// The configured content rules with their patterns compiled, in config order
+#[derive(Debug, Default)]
+pub struct ContentClassifier {
+    rules: Vec<(Regex, ClipboardContentType)>,
+}
+
+impl ContentClassifier {
+    /// Rules with an invalid pattern are skipped (and logged)
+    pub fn new(rules: &[ContentRule]) -> Self {
+        let rules = rules
+            .iter()
+            .filter_map(|rule| match Regex::new(&rule.pattern) {
+                Ok(re) => Some((re, rule.content_type)),
+                Err(e) => {
+                    warn!("Ignoring content rule {rule:?}: {e}");
+                    None
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Type of the first rule whose pattern is found in `preview`, otherwise the built-in guess
+    pub fn classify(&self, preview: &str) -> ClipboardContentType {
+        self.rules
+            .iter()
+            .find(|(re, _)| re.is_match(preview))
+            .map_or_else(|| ClipboardContentType::type_from_preview(preview), |(_, content_type)| *content_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, content_type: ClipboardContentType) -> ContentRule {
+        ContentRule { pattern: pattern.to_string(), content_type }
+    }
+
+    #[test]
+    fn without_rules_the_heuristics_apply() {
+        let classifier = ContentClassifier::default();
+        assert_eq!(classifier.classify("https://example.com"), ClipboardContentType::Url);
+        assert_eq!(classifier.classify("fn main() {}"), ClipboardContentType::Code);
+        assert_eq!(classifier.classify("hello world"), ClipboardContentType::Text);
+    }
+
+    #[test]
+    fn rules_take_precedence_over_the_heuristics() {
+        let classifier = ContentClassifier::new(&[rule(r"^\d{2}/\d{2}/\d{4}$", ClipboardContentType::Text)]);
+        // A date would otherwise be taken for a path
+        assert_eq!(ClipboardContentType::type_from_preview("12/05/2024"), ClipboardContentType::File);
+        assert_eq!(classifier.classify("12/05/2024"), ClipboardContentType::Text);
+        assert_eq!(classifier.classify("/etc/fstab"), ClipboardContentType::File);
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let classifier = ContentClassifier::new(&[
+            rule("^SELECT ", ClipboardContentType::Code),
+            rule("SELECT", ClipboardContentType::Other),
+        ]);
+        assert_eq!(classifier.classify("SELECT * FROM items"), ClipboardContentType::Code);
+        assert_eq!(classifier.classify("please SELECT one"), ClipboardContentType::Other);
+    }
+
+    #[test]
+    fn invalid_patterns_are_skipped() {
+        let classifier = ContentClassifier::new(&[
+            rule("(unclosed", ClipboardContentType::Code),
+            rule("^ghp_", ClipboardContentType::Password),
+        ]);
+        assert_eq!(classifier.rules.len(), 1);
+        assert_eq!(classifier.classify("ghp_abc123"), ClipboardContentType::Password);
+        assert_eq!(classifier.classify("some plain words"), ClipboardContentType::Text);
+    }
+}
@@ -0,0 +1,216 @@
+// Connected IPC clients: every connection is registered with an id when it opens and removed
+// when it closes, so pushes only go to live connections and `ListClients` can tell them apart.
+//
+// The queue of each client is bounded. A client that does not keep up reading (a frozen
+// overlay, a stopped script) gets no more pushes once its queue is full. All pushes it misses
+// are coalesced into a single `PushesDropped`, queued by the connection's writer once it has
+// written everything queued before (see `flush_dropped`), after which the client reloads
+// instead of replaying every change.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::mpsc::error::TrySendError;
+use tracing::warn;
+use crate::shared::{BackendMessage, ClientInfo, PushEvent, PushFilter};
+
+#[derive(Debug)]
+struct Client {
+    info: ClientInfo,
+    sender: Sender<BackendMessage>,
+    /// Pushes dropped since the client last got a `PushesDropped`
+    missed: u64,
+    /// Set while `missed` is not 0, read by the writer without taking the state lock
+    owes_dropped: Arc<AtomicBool>,
+}
+
+impl Client {
+    /// Queue a message, false once the connection is gone
+    fn send(&mut self, message: BackendMessage) -> bool {
+        match self.send_dropped() {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.drop_push();
+                return true;
+            }
+            Err(TrySendError::Closed(_)) => return false,
+        }
+        match self.sender.try_send(message) {
+            Ok(()) => {
+                self.info.pushes_sent += 1;
+                true
+            }
+            Err(TrySendError::Full(_)) => {
+                self.drop_push();
+                true
+            }
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+
+    /// Queue the `PushesDropped` the client is owed, if any. Clients subscribed to some events
+    /// only get it if they subscribed to it.
+    fn send_dropped(&mut self) -> Result<(), TrySendError<()>> {
+        if self.missed == 0 {
+            return Ok(());
+        }
+        if self.subscribed(Some(PushEvent::PushesDropped)) {
+            self.sender.try_send(BackendMessage::PushesDropped { count: self.missed }).map_err(|e| match e {
+                TrySendError::Full(_) => TrySendError::Full(()),
+                TrySendError::Closed(_) => TrySendError::Closed(()),
+            })?;
+        }
+        self.missed = 0;
+        self.owes_dropped.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn drop_push(&mut self) {
+        if self.missed == 0 {
+            warn!("Client {} does not keep up reading, dropping pushes to it", self.info.id);
+        }
+        self.missed += 1;
+        self.info.pushes_dropped += 1;
+        self.owes_dropped.store(true, Ordering::Relaxed);
+    }
+
+    fn subscribed(&self, event: Option<PushEvent>) -> bool {
+        self.info.events.as_ref().is_none_or(|events| event.is_some_and(|event| events.contains(&event)))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ClientRegistry {
+    clients: Vec<Client>,
+    next_id: u64,
+}
+
+impl ClientRegistry {
+    /// Add a new connection of the process `pid`, pushes go to `sender`. Returns its id and a
+    /// flag telling the connection's writer to call `flush_dropped` once its queue is empty.
+    pub fn register(&mut self, sender: Sender<BackendMessage>, pid: Option<u32>) -> (u64, Arc<AtomicBool>) {
+        self.next_id += 1;
+        let info = ClientInfo {
+            id: self.next_id,
+            name: None,
+            pid,
+            process: pid.and_then(process_name),
+            connected_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64,
+            filter: PushFilter::default(),
+            events: None,
+            pushes_sent: 0,
+            pushes_dropped: 0,
+        };
+        let owes_dropped = Arc::new(AtomicBool::new(false));
+        self.clients.push(Client { info, sender, missed: 0, owes_dropped: owes_dropped.clone() });
+        (self.next_id, owes_dropped)
+    }
+
+    /// Forget a closed connection
+    pub fn unregister(&mut self, id: u64) {
+        self.clients.retain(|client| client.info.id != id);
+    }
+
+    /// Apply what a client sent with `Identify`
+    pub fn identify(&mut self, id: u64, name: String, filter: PushFilter) {
+        if let Some(client) = self.clients.iter_mut().find(|client| client.info.id == id) {
+            client.info.name = Some(name);
+            client.info.filter = filter;
+        }
+    }
+
+    /// Only send pushes of the kinds in `events` to a client
+    pub fn subscribe(&mut self, id: u64, events: Vec<PushEvent>) {
+        if let Some(client) = self.clients.iter_mut().find(|client| client.info.id == id) {
+            client.info.events = Some(events);
+        }
+    }
+
+    /// Send a push to every client subscribed to it whose filter lets it through, dropping
+    /// clients that went away
+    pub fn broadcast(&mut self, message: &BackendMessage) {
+        self.clients.retain_mut(|client| {
+            if !client.subscribed(message.push_event()) {
+                return true;
+            }
+            let Some(message) = client.info.filter.apply(message) else { return true };
+            client.send(message)
+        });
+    }
+
+    /// Queue the `PushesDropped` a client is owed, called by its writer once the queue has room
+    pub fn flush_dropped(&mut self, id: u64) {
+        if let Some(client) = self.clients.iter_mut().find(|client| client.info.id == id) {
+            let _ = client.send_dropped();
+        }
+    }
+
+    pub fn list(&self) -> Vec<ClientInfo> {
+        self.clients.iter().map(|client| client.info.clone()).collect()
+    }
+}
+
+/// Command name of a process, from procfs
+fn process_name(pid: u32) -> Option<String> {
+    let comm = std::fs::read_to_string(format!("/proc/{pid}/comm")).ok()?;
+    Some(comm.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::{self, Receiver};
+    use super::*;
+
+    fn removed(id: u64) -> BackendMessage {
+        BackendMessage::ItemsRemoved { ids: vec![id] }
+    }
+
+    fn queued(rx: &mut Receiver<BackendMessage>) -> Vec<String> {
+        std::iter::from_fn(|| rx.try_recv().ok()).map(|message| format!("{message:?}")).collect()
+    }
+
+    #[test]
+    fn missed_pushes_are_reported_once_the_queue_drained() {
+        let mut clients = ClientRegistry::default();
+        let (tx, mut rx) = mpsc::channel(2);
+        let (id, owes_dropped) = clients.register(tx, None);
+        for item in 1..=5 {
+            clients.broadcast(&removed(item));
+        }
+        assert!(owes_dropped.load(Ordering::Relaxed));
+        assert_eq!(clients.list()[0].pushes_dropped, 3);
+        assert_eq!(queued(&mut rx), [format!("{:?}", removed(1)), format!("{:?}", removed(2))]);
+
+        // Sent without waiting for another push
+        clients.flush_dropped(id);
+        assert!(!owes_dropped.load(Ordering::Relaxed));
+        assert_eq!(queued(&mut rx), [format!("{:?}", BackendMessage::PushesDropped { count: 3 })]);
+        clients.broadcast(&removed(6));
+        assert_eq!(queued(&mut rx), [format!("{:?}", removed(6))]);
+    }
+
+    #[test]
+    fn subscriptions_decide_what_counts_as_missed() {
+        let mut clients = ClientRegistry::default();
+        let (tx, mut rx) = mpsc::channel(1);
+        let (id, _) = clients.register(tx, None);
+        clients.subscribe(id, vec![PushEvent::ItemsRemoved]);
+        clients.broadcast(&removed(1));
+        clients.broadcast(&BackendMessage::ConfigReloaded);
+        clients.broadcast(&removed(2));
+        assert_eq!(clients.list()[0].pushes_dropped, 1);
+
+        // Not subscribed to the notice, so only the subscribed pushes arrive
+        assert_eq!(queued(&mut rx), [format!("{:?}", removed(1))]);
+        clients.flush_dropped(id);
+        assert!(queued(&mut rx).is_empty());
+
+        clients.subscribe(id, vec![PushEvent::ItemsRemoved, PushEvent::PushesDropped]);
+        clients.broadcast(&removed(3));
+        clients.broadcast(&removed(4));
+        assert_eq!(queued(&mut rx), [format!("{:?}", removed(3))]);
+        clients.flush_dropped(id);
+        assert_eq!(queued(&mut rx), [format!("{:?}", BackendMessage::PushesDropped { count: 1 })]);
+    }
+}
@@ -0,0 +1,89 @@
+// Where selections come from and where the ones cursor-clip sets go. The daemon talks to the
+// compositor (`WaylandClipboard`), tests use `MockClipboard` to run the history and IPC logic
+// without one.
+
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use bytes::Bytes;
+use indexmap::IndexMap;
+use crate::backend::backend_state::{BackendState, DataControlProtocol};
+use crate::backend::wayland_clipboard::{MonitorError, WaylandClipboardMonitor};
+use crate::shared::{ClipboardItem, MonitorStatus};
+
+pub trait ClipboardBackend: Send + Sync + Debug {
+    /// Record selections into `state` until the connection is lost, blocking the calling thread
+    fn start(&self, state: Arc<Mutex<BackendState>>) -> Result<(), MonitorError>;
+
+    /// Make `item` (reduced to the payloads to offer) the selection of the seat at `seat_index`
+    /// in `state.seats`
+    fn set_selection(&self, state: &mut BackendState, seat_index: usize, item: &ClipboardItem) -> Result<(), String>;
+}
+
+/// The compositor, through the wlroots or ext data control protocol
+#[derive(Debug, Default)]
+pub struct WaylandClipboard;
+
+impl ClipboardBackend for WaylandClipboard {
+    fn start(&self, state: Arc<Mutex<BackendState>>) -> Result<(), MonitorError> {
+        WaylandClipboardMonitor::new(state).start_monitoring()
+    }
+
+    fn set_selection(&self, state: &mut BackendState, seat_index: usize, item: &ClipboardItem) -> Result<(), String> {
+        match state.active_protocol {
+            Some(DataControlProtocol::Wlr) => state.set_clipboard_wlr(seat_index, item.item_id, item),
+            Some(DataControlProtocol::Ext) => state.set_clipboard_ext(seat_index, item.item_id, item),
+            None => Err("No data control protocol available".into()),
+        }
+    }
+}
+
+/// A selection set through `MockClipboard`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockSelection {
+    pub item_id: u64,
+    pub mime_types: Vec<String>,
+}
+
+/// In-memory clipboard for tests: apps copying are simulated with `copy`, the selections
+/// cursor-clip sets are kept in order
+#[derive(Debug, Default)]
+pub struct MockClipboard {
+    selections: Mutex<Vec<MockSelection>>,
+}
+
+impl MockClipboard {
+    /// An app copied `mime_data`, recorded like a selection read from the compositor.
+    /// Returns the id of the new item.
+    pub fn copy(state: &mut BackendState, mime_data: IndexMap<String, Bytes>) -> Option<u64> {
+        if state.is_capture_paused() || !state.admit_capture() {
+            return None;
+        }
+        state.record_external_selection(0, mime_data, Vec::new(), Instant::now())
+    }
+
+    /// Every selection set so far, the oldest first
+    pub fn selections(&self) -> Vec<MockSelection> {
+        self.selections.lock().unwrap().clone()
+    }
+
+    /// The selection set last
+    pub fn current(&self) -> Option<MockSelection> {
+        self.selections.lock().unwrap().last().cloned()
+    }
+}
+
+impl ClipboardBackend for MockClipboard {
+    fn start(&self, state: Arc<Mutex<BackendState>>) -> Result<(), MonitorError> {
+        state.lock().unwrap().set_monitor_status(MonitorStatus::Active);
+        Ok(())
+    }
+
+    fn set_selection(&self, _state: &mut BackendState, _seat_index: usize, item: &ClipboardItem) -> Result<(), String> {
+        self.selections.lock().unwrap().push(MockSelection {
+            item_id: item.item_id,
+            mime_types: item.mime_data.keys().cloned().collect(),
+        });
+        Ok(())
+    }
+}
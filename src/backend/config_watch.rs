@@ -0,0 +1,99 @@
+// Config hot reload: the config directory is watched with notify (editors usually replace the
+// file instead of writing it in place) and changes are applied by `BackendState::apply_config`
+
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use notify::event::{AccessKind, AccessMode};
+use tracing::{debug, info, warn};
+use crate::shared::Config;
+use crate::shared::config::{config_dir, config_file_path};
+use super::backend_state::BackendState;
+
+/// Editors save in several steps, the file is read once they are done
+const SETTLE_DELAY: Duration = Duration::from_millis(200);
+
+/// Reload the config whenever its file changes, on a thread of its own (waiting for events blocks)
+pub fn start_watcher(state: Arc<Mutex<BackendState>>) {
+    let dir = config_dir();
+    // The directory must exist to be watched, the file may be created later
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        warn!("Cannot watch the config file, {} is not available: {e}", dir.display());
+        return;
+    }
+    let (tx, events) = std::sync::mpsc::channel();
+    let watcher = notify::recommended_watcher(tx).and_then(|mut watcher| {
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    });
+    let watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!("Cannot watch {}: {e}", dir.display());
+            return;
+        }
+    };
+    debug!("Watching {} for changes", config_file_path().display());
+    let spawned = std::thread::Builder::new().name("config-watch".into()).spawn(move || watch(watcher, &events, &state));
+    if let Err(e) = spawned {
+        warn!("Cannot watch the config file: {e}");
+    }
+}
+
+/// Keeps `_watcher` alive while reading its events
+fn watch(_watcher: RecommendedWatcher, events: &Receiver<notify::Result<Event>>, state: &Mutex<BackendState>) {
+    let config_path = config_file_path();
+    while let Ok(event) = events.recv() {
+        match event {
+            Ok(event) if changes_file(&event, &config_path) => {}
+            Ok(_) => continue,
+            Err(e) => {
+                warn!("Error watching the config file: {e}");
+                continue;
+            }
+        }
+        std::thread::sleep(SETTLE_DELAY);
+        // The events of the rest of the save
+        while events.try_recv().is_ok() {}
+        match Config::try_load() {
+            Ok(config) => {
+                if state.lock().unwrap().apply_config(config) {
+                    info!("Config reloaded from {}", config_path.display());
+                }
+            }
+            Err(e) => warn!("{e}; keeping the current config"),
+        }
+    }
+    warn!("Stopped watching the config file");
+}
+
+/// Whether `event` created, wrote, replaced or removed the file at `path` (reading it does not count)
+fn changes_file(event: &Event, path: &Path) -> bool {
+    let changes = matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) | EventKind::Access(AccessKind::Close(AccessMode::Write))
+    );
+    changes && event.paths.iter().any(|changed| changed == path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use notify::event::{CreateKind, ModifyKind, RenameMode};
+    use super::*;
+
+    #[test]
+    fn only_changes_of_the_config_file_count() {
+        let path = PathBuf::from("/home/user/.config/cursor-clip/config.toml");
+        let event = |kind, file: &str| Event::new(kind).add_path(path.with_file_name(file));
+        assert!(changes_file(&event(EventKind::Create(CreateKind::File), "config.toml"), &path));
+        assert!(changes_file(&event(EventKind::Modify(ModifyKind::Name(RenameMode::To)), "config.toml"), &path));
+        assert!(changes_file(&event(EventKind::Access(AccessKind::Close(AccessMode::Write)), "config.toml"), &path));
+        // Reads, the editor's swap file and the sync settings next to it
+        assert!(!changes_file(&event(EventKind::Access(AccessKind::Close(AccessMode::Read)), "config.toml"), &path));
+        assert!(!changes_file(&event(EventKind::Modify(ModifyKind::Any), ".config.toml.swp"), &path));
+        assert!(!changes_file(&event(EventKind::Create(CreateKind::File), "sync.toml"), &path));
+    }
+}
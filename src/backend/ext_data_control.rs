@@ -1,6 +1,7 @@
 // Generated protocol bindings for ext-data-control-v1
 // This is the standard protocol supported by KDE Plasma 6
 
+#[allow(clippy::module_inception)]
 pub mod ext_data_control {
     use wayland_client;
     use wayland_client::protocol::*;
@@ -24,11 +25,14 @@ pub use ext_data_control::ext_data_control_offer_v1::ExtDataControlOfferV1;
 use wayland_client::{Connection, Dispatch, Proxy, QueueHandle};
 use std::os::fd::AsFd;
 use std::io::Read;
+use std::time::Instant;
 use indexmap::IndexMap;
 use bytes::Bytes;
-use log::{debug, warn, error};
-use crate::backend::backend_state::BackendState;
-use crate::backend::wayland_clipboard::MutexBackendState;
+use tracing::{debug, debug_span, warn};
+use crate::backend::backend_state::{BackendState, DeferredOffer, OWNER_MARKER_MIME};
+use crate::backend::{payload_check, payload_store, selection_send};
+use crate::backend::wayland_clipboard::{schedule_deferred_reads, MutexBackendState};
+use crate::shared::BackendMessage;
 
 // Helper function for creating pipes
 fn create_pipes() -> Result<(std::os::fd::OwnedFd, std::os::fd::OwnedFd), Box<dyn std::error::Error>> {
@@ -61,16 +65,18 @@ impl Dispatch<ExtDataControlManagerV1, ()> for MutexBackendState {
     }
 }
 
-impl Dispatch<ExtDataControlDeviceV1, ()> for MutexBackendState {
+/// Devices carry the registry name of their seat
+impl Dispatch<ExtDataControlDeviceV1, u32> for MutexBackendState {
     fn event(
         wrapper: &mut Self,
         _: &ExtDataControlDeviceV1,
         event: <ExtDataControlDeviceV1 as Proxy>::Event,
-        (): &(),
+        seat_global: &u32,
         conn: &Connection,
         _qh: &QueueHandle<Self>,
     ) {
         use crate::backend::backend_state::DataControlProtocol;
+        let _span = debug_span!("wayland_event", protocol = "ext", seat = *seat_global).entered();
         let mut state = wrapper.backend_state.lock().unwrap();
 
         // Only process if we're using ext protocol
@@ -89,23 +95,38 @@ impl Dispatch<ExtDataControlDeviceV1, ()> for MutexBackendState {
                     let offer_key = offer_id.id();
                     debug!("[EXT] Selection changed to offer ID: {:?}", offer_key);
 
-                    let already_current = state.ext_current_data_offer.as_ref().is_some_and(|o| o == &offer_key);
-                    if let Some(mime_list) = state.ext_mime_type_offers.get(&offer_key).cloned() {
-                        debug!("[EXT] New clipboard content available with {} MIME types", mime_list.len());
-                        if state.suppress_next_selection_read {
-                            state.ext_current_data_offer = Some(offer_key);
-                            debug!("[EXT] Suppressed reading our own just-set selection");
+                    let mime_list = state.ext_mime_type_offers.get(&offer_key).cloned();
+                    let Some(seat) = state.seat_mut(*seat_global) else { return };
+                    let already_current = seat.current_data_offer.as_ref().is_some_and(|o| o == &offer_key);
+                    if let Some(mime_list) = mime_list {
+                        debug!("[EXT] New clipboard content available on {} with {} MIME types", seat.name, mime_list.len());
+                        let marked_ours = mime_list.iter().any(|mime| mime == OWNER_MARKER_MIME);
+                        if !seat.selection.offered(marked_ours) {
+                            seat.current_data_offer = Some(offer_key);
+                            seat.drop_deferred_offer();
+                            debug!("[EXT] Not reading our own selection ({:?})", seat.selection.state());
                             offer_id.destroy();
                         } else if !already_current {
-                            state.ext_current_data_offer = Some(offer_key);
-                            process_all_data_formats_ext(&offer_id, mime_list, conn, &mut state);
+                            seat.current_data_offer = Some(offer_key);
+                            seat.adopt_on_source_exit = None;
+                            seat.drop_deferred_offer();
+                            let read = process_all_data_formats_ext(&offer_id, mime_list, conn, &mut state, *seat_global);
                             state.ext_mime_type_offers.clear();
-                            offer_id.destroy();
+                            if read {
+                                offer_id.destroy();
+                            } else {
+                                schedule_deferred_reads(&wrapper.backend_state, &mut state);
+                            }
                         }
                     }
                 } else {
                     debug!("[EXT] Selection cleared");
-                    state.ext_current_data_offer = None;
+                    if let Some(seat) = state.seat_mut(*seat_global) {
+                        seat.current_data_offer = None;
+                        seat.drop_deferred_offer();
+                        seat.selection.lost();
+                    }
+                    state.selection_source_exited(*seat_global);
                 }
             }
             ext_data_control_device_v1::Event::PrimarySelection { .. } => {
@@ -145,41 +166,40 @@ impl Dispatch<ExtDataControlOfferV1, ()> for MutexBackendState {
         let object_id = offer.id();
         debug!("[EXT] Offer event: MIME type offered: {}", mime_type);
         let mut state = wrapper.backend_state.lock().unwrap();
-        if let Some(mime_list) = state.ext_mime_type_offers.get_mut(&object_id) {
-            if !mime_type.starts_with("video") {
-                mime_list.push(mime_type);
-            }
+        if let Some(mime_list) = state.ext_mime_type_offers.get_mut(&object_id)
+            && !mime_type.starts_with("video")
+        {
+            mime_list.push(mime_type);
         }
     }
 }
 
-impl Dispatch<ExtDataControlSourceV1, ()> for MutexBackendState {
+/// Sources carry the registry name of the seat they were set on
+impl Dispatch<ExtDataControlSourceV1, u32> for MutexBackendState {
     fn event(
         wrapper: &mut Self,
         event_source: &ExtDataControlSourceV1,
         event: <ExtDataControlSourceV1 as Proxy>::Event,
-        (): &(),
+        seat_global: &u32,
         _: &Connection,
         _: &QueueHandle<Self>,
     ) {
+        let _span = debug_span!("wayland_event", protocol = "ext", seat = *seat_global).entered();
         let mut state = wrapper.backend_state.lock().unwrap();
 
         match event {
             ext_data_control_source_v1::Event::Send { mime_type, fd } => {
                 debug!("[EXT] Data source Send event for MIME type: {}", mime_type);
-                if let Some(item_id) = state.ext_current_source_entry_id {
+                let source = state.seat_mut(*seat_global).and_then(|seat| Some((seat.current_source_entry_id?, seat.current_source_original)));
+                if let Some((item_id, original)) = source {
                     if let Some(item) = state.get_item_by_id(item_id) {
-                        use std::io::Write;
-                        let mut file: std::fs::File = fd.into();
-                        if let Some(bytes) = item.mime_data.get(&mime_type) {
-                            if let Err(e) = file.write_all(bytes.as_ref()) {
-                                error!(
-                                    "[EXT] Failed writing selection data (id {}, mime {}): {}",
-                                    item_id, mime_type, e
-                                );
-                            } else {
-                                debug!("[EXT] Wrote {} bytes for id {} (mime {})", bytes.len(), item_id, mime_type);
-                            }
+                        let payload = if mime_type == OWNER_MARKER_MIME {
+                            Some(Bytes::from_static(b"cursor-clip"))
+                        } else {
+                            item.payload(&mime_type, original).cloned()
+                        };
+                        if let Some(bytes) = payload {
+                            selection_send::send_payload(fd, bytes, item_id, mime_type);
                         } else {
                             warn!("[EXT] No data stored for MIME {} (id {})", mime_type, item_id);
                         }
@@ -192,10 +212,18 @@ impl Dispatch<ExtDataControlSourceV1, ()> for MutexBackendState {
             }
             ext_data_control_source_v1::Event::Cancelled => {
                 debug!("[EXT] Data source cancelled");
-                if state.ext_current_source_object.as_ref().map(Proxy::id) == Some(event_source.id()) {
-                    state.suppress_next_selection_read = false;
-                    state.ext_current_source_object = None;
+                let ours = state.seat_mut(*seat_global).is_some_and(|seat| {
+                    let current = seat.ext_current_source_object.as_ref().map(Proxy::id) == Some(event_source.id());
+                    if current {
+                        seat.selection.lost();
+                        seat.ext_current_source_object = None;
+                    }
+                    current
+                });
+                if ours {
+                    state.note_ownership_lost();
                     debug!("[EXT] Re-enabled selection reading");
+                    state.broadcast(BackendMessage::SelectionTakenByExternal);
                 }
                 drop(state);
                 event_source.destroy();
@@ -204,45 +232,52 @@ impl Dispatch<ExtDataControlSourceV1, ()> for MutexBackendState {
     }
 }
 
-fn process_all_data_formats_ext(
+/// Record the selection of `data_offer`, returns false if it was deferred by the capture limit
+/// (the seat then keeps the offer)
+pub fn process_all_data_formats_ext(
     data_offer: &ExtDataControlOfferV1,
     mime_types: Vec<String>,
     conn: &Connection,
     backend_state: &mut BackendState,
-) {
-    if mime_types.is_empty() { return; }
+    seat_global: u32,
+) -> bool {
+    if backend_state.is_capture_paused() {
+        debug!("[EXT] Capture paused, not recording the new selection");
+        return true;
+    }
+    if !backend_state.admit_capture() {
+        let Some(seat) = backend_state.seat_mut(seat_global) else { return true };
+        debug!("[EXT] Too many selections per second, reading this one once the limit allows");
+        seat.defer_offer(DeferredOffer::Ext(data_offer.clone(), mime_types));
+        return false;
+    }
+    let started = Instant::now();
+    let mime_types = backend_state.filter_manager_markers(mime_types);
+    if mime_types.is_empty() { return true; }
 
     let mut mime_map: IndexMap<String, Bytes> = IndexMap::new();
+    let mut suspect_mime_types = Vec::new();
 
     for mime in mime_types {
-        let (reader_fd, writer_fd) = match create_pipes() {
-            Ok(pair) => pair,
-            Err(err) => { warn!("[EXT] Could not open pipe to read data for {}: {:?}", mime, err); continue; }
+        let read = || -> Result<Vec<u8>, String> {
+            let (reader_fd, writer_fd) = create_pipes().map_err(|e| format!("could not open pipe: {e}"))?;
+            debug!("[EXT] Requesting {mime} content...");
+            data_offer.receive(mime.clone(), writer_fd.as_fd());
+            drop(writer_fd);
+            if let Err(e) = conn.flush() { warn!("[EXT] Flush failed: {e}"); }
+
+            let mut buf = Vec::new();
+            std::fs::File::from(reader_fd).read_to_end(&mut buf).map_err(|e| e.to_string())?;
+            Ok(buf)
         };
-        debug!("[EXT] Requesting {} content...", mime);
-        data_offer.receive(mime.clone(), writer_fd.as_fd());
-        drop(writer_fd);
-        if let Err(e) = conn.flush() { warn!("[EXT] Flush failed: {}", e); }
-
-        let mut reader_file = std::fs::File::from(reader_fd);
-        let mut buf = Vec::new();
-        match reader_file.read_to_end(&mut buf) {
-            Ok(_) => {
-                if !buf.is_empty() { mime_map.insert(mime, Bytes::from(buf)); }
-            }
-            Err(e) => warn!("[EXT] Failed reading data for mime: {}", e),
+        if let Some(payload) = payload_check::read_with_retry(&mime, read) {
+            if payload.suspicious { suspect_mime_types.push(mime.clone()); }
+            backend_state.metrics.record_read(&mime, payload.data.len());
+            mime_map.insert(mime, Bytes::from(payload.data));
         }
     }
+    payload_store::store_payloads(&mut mime_map);
 
-    if !mime_map.is_empty() {
-        if let Some(new_id) = backend_state.add_clipboard_item_from_mime_map(mime_map) {
-            if !backend_state.monitor_only && !backend_state.suppress_next_selection_read {
-                if let Err(e) = backend_state.set_clipboard_by_id(new_id) {
-                    warn!("[EXT] Failed to take ownership of selection id {}: {}", new_id, e);
-                } else {
-                    debug!("[EXT] Took ownership of external selection (id {})", new_id);
-                }
-            }
-        }
-    }
+    backend_state.record_external_selection(seat_global, mime_map, suspect_mime_types, started);
+    true
 }
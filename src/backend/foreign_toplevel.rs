@@ -0,0 +1,131 @@
+// Tracking of open windows through zwlr_foreign_toplevel_management_v1, whenever the compositor
+// offers it: the focused window is recorded as the app an item was copied in, and capture pauses
+// while it matches an incognito rule (e.g. a password manager)
+
+use std::sync::Arc as StdArc;
+use regex::Regex;
+use wayland_client::{Connection, Dispatch, Proxy, QueueHandle};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+    zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+};
+use tracing::{debug, warn};
+use crate::backend::wayland_clipboard::MutexBackendState;
+use crate::shared::config::IncognitoRule;
+
+/// What is known about an open window
+#[derive(Debug, Clone, Default)]
+pub struct Toplevel {
+    pub app_id: String,
+    pub title: String,
+    pub activated: bool,
+}
+
+/// The configured incognito rules with their patterns compiled
+#[derive(Debug, Default)]
+pub struct IncognitoMatcher {
+    rules: Vec<(Option<Regex>, Option<Regex>)>,
+}
+
+impl IncognitoMatcher {
+    /// Rules with an invalid pattern are skipped (and logged)
+    pub fn new(rules: &[IncognitoRule]) -> Self {
+        let compile = |pattern: &Option<String>| -> Result<Option<Regex>, regex::Error> {
+            pattern.as_deref().map(Regex::new).transpose()
+        };
+        let rules = rules
+            .iter()
+            .filter(|rule| rule.app_id.is_some() || rule.title.is_some())
+            .filter_map(|rule| match (compile(&rule.app_id), compile(&rule.title)) {
+                (Ok(app_id), Ok(title)) => Some((app_id, title)),
+                (Err(e), _) | (_, Err(e)) => {
+                    warn!("Ignoring incognito rule {rule:?}: {e}");
+                    None
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// A window matches a rule if all of the rule's patterns are found in its app id and title
+    pub fn matches(&self, toplevel: &Toplevel) -> bool {
+        self.rules.iter().any(|(app_id, title)| {
+            app_id.as_ref().is_none_or(|re| re.is_match(&toplevel.app_id))
+                && title.as_ref().is_none_or(|re| re.is_match(&toplevel.title))
+        })
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for MutexBackendState {
+    fn event(
+        wrapper: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        (): &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } => {
+                let mut state = wrapper.backend_state.lock().unwrap();
+                state.toplevels.insert(toplevel.id(), Toplevel::default());
+            }
+            zwlr_foreign_toplevel_manager_v1::Event::Finished => {
+                debug!("Foreign toplevel manager finished, windows are no longer tracked");
+                let mut state = wrapper.backend_state.lock().unwrap();
+                state.toplevels.clear();
+                state.toplevel_tracking = false;
+                state.update_incognito();
+            }
+            _ => {}
+        }
+    }
+
+    fn event_created_child(opcode: u16, qhandle: &QueueHandle<Self>) -> StdArc<dyn wayland_client::backend::ObjectData> {
+        match opcode {
+            0 => qhandle.make_data::<ZwlrForeignToplevelHandleV1, ()>(()),
+            _ => panic!("Unknown child object for opcode {opcode}"),
+        }
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for MutexBackendState {
+    fn event(
+        wrapper: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        (): &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let mut state = wrapper.backend_state.lock().unwrap();
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::Title { title } => {
+                state.toplevels.entry(handle.id()).or_default().title = title;
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                state.toplevels.entry(handle.id()).or_default().app_id = app_id;
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::State { state: states } => {
+                let activated = states
+                    .chunks_exact(4)
+                    .map(|raw| u32::from_ne_bytes([raw[0], raw[1], raw[2], raw[3]]))
+                    .any(|value| value == zwlr_foreign_toplevel_handle_v1::State::Activated as u32);
+                state.toplevels.entry(handle.id()).or_default().activated = activated;
+            }
+            // Title, app id and state changes are applied atomically with `done`
+            zwlr_foreign_toplevel_handle_v1::Event::Done => state.update_incognito(),
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+                state.toplevels.remove(&handle.id());
+                state.update_incognito();
+                drop(state);
+                handle.destroy();
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,125 @@
+// Hook commands from the config (`on_copy`, `on_paste`), run when an item is recorded or
+// pasted through cursor-clip (never for secret items). They get the item metadata as JSON on stdin.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use tracing::{debug, warn};
+use crate::shared::ClipboardItemPreview;
+
+/// What happened to the item, passed as `$CURSOR_CLIP_EVENT`
+#[derive(Debug, Clone, Copy)]
+pub enum HookEvent {
+    Copy,
+    Paste,
+}
+
+impl HookEvent {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Copy => "copy",
+            Self::Paste => "paste",
+        }
+    }
+}
+
+/// Start `command` (program and arguments) for an item without waiting for it. The hook gets
+/// the item preview as JSON on stdin and the event, id and content type in the environment.
+pub fn run_hook(command: &[String], event: HookEvent, item: &ClipboardItemPreview) {
+    let Some((program, args)) = command.split_first() else { return };
+    let json = match serde_json::to_vec(item) {
+        Ok(json) => json,
+        Err(e) => {
+            warn!("Cannot serialize item {} for the {} hook: {e}", item.item_id, event.as_str());
+            return;
+        }
+    };
+    let child = Command::new(program)
+        .args(args)
+        .env("CURSOR_CLIP_EVENT", event.as_str())
+        .env("CURSOR_CLIP_ITEM_ID", item.item_id.to_string())
+        .env("CURSOR_CLIP_CONTENT_TYPE", item.content_type.as_str())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Could not start the {} hook {program}: {e}", event.as_str());
+            return;
+        }
+    };
+    debug!("Started the {} hook for item {}", event.as_str(), item.item_id);
+
+    // Hooks may be slow (notifications, network), feed and reap them off the clipboard thread
+    let program = program.clone();
+    std::thread::spawn(move || {
+        if let Some(mut stdin) = child.stdin.take() {
+            // A hook may not read its input
+            let _ = stdin.write_all(&json);
+        }
+        match child.wait() {
+            Ok(status) if !status.success() => warn!("Hook {program} exited with {status}"),
+            Ok(_) => {}
+            Err(e) => warn!("Waiting for hook {program} failed: {e}"),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::{Duration, Instant};
+    use bytes::Bytes;
+    use indexmap::IndexMap;
+    use super::*;
+    use crate::backend::backend_state::BackendState;
+    use crate::shared::ClipboardContentType;
+
+    fn preview(content: &str) -> ClipboardItemPreview {
+        let mut state = BackendState::new();
+        let mime_data = IndexMap::from([("text/plain;charset=utf-8".to_string(), Bytes::copy_from_slice(content.as_bytes()))]);
+        state.add_clipboard_item_from_mime_map(mime_data, Vec::new(), None);
+        ClipboardItemPreview::from(&state.history[0])
+    }
+
+    fn wait_for(path: &Path) -> String {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            // The hook writes the file once it has read all of stdin
+            if let Ok(content) = std::fs::read_to_string(path)
+                && content.ends_with("done")
+            {
+                return content;
+            }
+            assert!(Instant::now() < deadline, "hook did not run");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn hooks_get_the_item_as_json_and_the_event_in_the_environment() {
+        let out = std::env::temp_dir().join(format!("cursor-clip-hooks-test-{}", std::process::id()));
+        let _ = std::fs::remove_file(&out);
+        let script = format!(
+            "input=$(cat); printf '%s\\n%s %s %s\\ndone' \"$input\" \"$CURSOR_CLIP_EVENT\" \"$CURSOR_CLIP_ITEM_ID\" \"$CURSOR_CLIP_CONTENT_TYPE\" > {}",
+            out.display()
+        );
+        let item = preview("hello");
+        run_hook(&["sh".to_string(), "-c".to_string(), script], HookEvent::Paste, &item);
+
+        let content = wait_for(&out);
+        let (json, env) = content.rsplit_once('\n').unwrap().0.split_once('\n').unwrap();
+        let sent: ClipboardItemPreview = serde_json::from_str(json).unwrap();
+        assert_eq!(sent.item_id, item.item_id);
+        assert_eq!(sent.content_preview, "hello");
+        assert_eq!(env, format!("paste {} {}", item.item_id, ClipboardContentType::Text.as_str()));
+        let _ = std::fs::remove_file(&out);
+    }
+
+    #[test]
+    fn missing_commands_are_ignored() {
+        let item = preview("hello");
+        run_hook(&[], HookEvent::Copy, &item);
+        run_hook(&["/nonexistent/cursor-clip-hook".to_string()], HookEvent::Copy, &item);
+    }
+}
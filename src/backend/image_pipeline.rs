@@ -0,0 +1,141 @@
+// Image handling for image clipboard items (decoding and thumbnail generation)
+
+use bytes::Bytes;
+use image::ImageFormat;
+use indexmap::IndexMap;
+use std::io::Cursor;
+
+/// Requested thumbnail sizes are clamped to this range
+pub const MIN_THUMBNAIL_PX: u32 = 16;
+pub const MAX_THUMBNAIL_PX: u32 = 1024;
+
+/// Total size of the cached thumbnails, the least recently served ones are dropped beyond it
+pub const MAX_THUMBNAIL_CACHE_BYTES: usize = 32 * 1024 * 1024;
+
+/// Generated thumbnails keyed by (item id, max_px), bounded by `MAX_THUMBNAIL_CACHE_BYTES`.
+/// Clients may ask for any size, so the number of entries alone says nothing about memory.
+#[derive(Debug, Default)]
+pub struct ThumbnailCache {
+    /// Least recently served first
+    entries: IndexMap<(u64, u32), Bytes>,
+    bytes: usize,
+}
+
+impl ThumbnailCache {
+    /// Cached thumbnail, which becomes the most recently served one
+    pub fn get(&mut self, id: u64, max_px: u32) -> Option<Bytes> {
+        let png = self.entries.shift_remove(&(id, max_px))?;
+        self.entries.insert((id, max_px), png.clone());
+        Some(png)
+    }
+
+    pub fn insert(&mut self, id: u64, max_px: u32, png: Bytes) {
+        if let Some(replaced) = self.entries.shift_remove(&(id, max_px)) {
+            self.bytes -= replaced.len();
+        }
+        self.bytes += png.len();
+        self.entries.insert((id, max_px), png);
+        while self.bytes > MAX_THUMBNAIL_CACHE_BYTES {
+            let Some((_, evicted)) = self.entries.shift_remove_index(0) else { break };
+            self.bytes -= evicted.len();
+        }
+    }
+
+    /// Keep only the thumbnails of the items `keep` returns true for
+    pub fn retain_items(&mut self, keep: impl Fn(u64) -> bool) {
+        self.entries.retain(|(id, _), _| keep(*id));
+        self.bytes = self.entries.values().map(Bytes::len).sum();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.bytes = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Total size of the cached thumbnails
+    pub const fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+/// Image payload of an item, preferring PNG over other image formats
+pub fn image_payload(mime_data: &IndexMap<String, Bytes>) -> Option<&Bytes> {
+    mime_data
+        .get("image/png")
+        .or_else(|| mime_data.iter().find(|(mime, _)| mime.starts_with("image/")).map(|(_, data)| data))
+}
+
+/// Decode an image and scale it down to fit into a `max_px` square, encoded as PNG.
+/// Images already smaller than the requested size are only re-encoded, never upscaled.
+pub fn generate_thumbnail(data: &[u8], max_px: u32) -> Result<Bytes, String> {
+    let max_px = max_px.clamp(MIN_THUMBNAIL_PX, MAX_THUMBNAIL_PX);
+    let image = image::load_from_memory(data).map_err(|e| format!("Failed to decode image: {e}"))?;
+    let thumbnail = if image.width() <= max_px && image.height() <= max_px {
+        image
+    } else {
+        image.thumbnail(max_px, max_px)
+    };
+
+    let mut png = Cursor::new(Vec::new());
+    thumbnail
+        .write_to(&mut png, ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode thumbnail: {e}"))?;
+    Ok(Bytes::from(png.into_inner()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(len: usize) -> Bytes {
+        Bytes::from(vec![0; len])
+    }
+
+    #[test]
+    fn cache_stays_within_its_byte_limit() {
+        let mut cache = ThumbnailCache::default();
+        let chunk = MAX_THUMBNAIL_CACHE_BYTES / 4;
+        for max_px in MIN_THUMBNAIL_PX..MIN_THUMBNAIL_PX + 10 {
+            cache.insert(1, max_px, png(chunk));
+        }
+        assert_eq!(cache.len(), 4);
+        assert!(cache.bytes() <= MAX_THUMBNAIL_CACHE_BYTES);
+        // The oldest sizes went first
+        assert!(cache.get(1, MIN_THUMBNAIL_PX).is_none());
+        assert!(cache.get(1, MIN_THUMBNAIL_PX + 9).is_some());
+    }
+
+    #[test]
+    fn served_thumbnails_are_evicted_last() {
+        let mut cache = ThumbnailCache::default();
+        let chunk = MAX_THUMBNAIL_CACHE_BYTES / 2;
+        cache.insert(1, 64, png(chunk));
+        cache.insert(2, 64, png(chunk));
+        assert!(cache.get(1, 64).is_some());
+        cache.insert(3, 64, png(chunk));
+        assert!(cache.get(1, 64).is_some());
+        assert!(cache.get(2, 64).is_none());
+    }
+
+    #[test]
+    fn replacing_and_retaining_keep_the_byte_count() {
+        let mut cache = ThumbnailCache::default();
+        cache.insert(1, 64, png(100));
+        cache.insert(1, 64, png(40));
+        cache.insert(2, 64, png(10));
+        assert_eq!(cache.bytes(), 50);
+        cache.retain_items(|id| id == 2);
+        assert_eq!((cache.len(), cache.bytes()), (1, 10));
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.bytes(), 0);
+    }
+}
@@ -1,63 +1,111 @@
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
 use tokio::net::{UnixListener, UnixStream};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
 
-use crate::shared::{BackendMessage, FrontendMessage};
-use super::wayland_clipboard::WaylandClipboardMonitor;
+use crate::shared::{BackendError, BackendMessage, Config, ErrorCode, FrontendMessage, MonitorStatus};
+use crate::shared::config::Preferences;
+use crate::shared::logging;
+use crate::shared::protocol::{self, PeerInfo};
+use crate::shared::wire::{self, WireFormat};
+use super::wayland_clipboard::MonitorError;
 use super::backend_state::BackendState;
-use log::{info, error};
+use super::classification::ContentClassifier;
+use super::foreign_toplevel::IncognitoMatcher;
+use super::image_pipeline;
+use super::{saved_selection, storage_key};
+use super::simulation;
+use super::sync;
+use super::config_watch;
+use super::kdeconnect;
+use super::metrics_endpoint;
+use tracing::{Instrument, debug, debug_span, info, warn, error};
 
-pub async fn run_backend(monitor_only: bool) -> Result<(), Box<dyn std::error::Error>> {
-    // Remove existing socket if it exists
-    let socket_path = "/tmp/cursor-clip.sock";
-    let _ = std::fs::remove_file(socket_path);
-
-    // Create Unix socket for IPC
-    let listener = UnixListener::bind(socket_path)?;
-
-    // Set socket permissions to allow all users to connect (666)
-    // This is needed because the daemon may run with different privileges than the frontend
-    let perms = std::fs::Permissions::from_mode(0o666);
-    std::fs::set_permissions(socket_path, perms)?;
+/// Delays between attempts to set up the Wayland clipboard monitor
+const MONITOR_RETRY_INITIAL: Duration = Duration::from_secs(2);
+const MONITOR_RETRY_MAX: Duration = Duration::from_secs(60);
+/// How long the keyring may take to hand out the storage key, e.g. waiting to be unlocked
+const STORAGE_KEY_TIMEOUT: Duration = Duration::from_secs(120);
+/// How often items past `max_item_age_days` are removed
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+/// Messages waiting to be written to a client. Pushes to a client with a full queue are
+/// dropped (see `ClientRegistry::broadcast`), so a stuck client cannot grow the daemon's memory.
+const CLIENT_QUEUE_LEN: usize = 256;
 
-    info!("Clipboard backend listening on {socket_path}");
+pub async fn run_backend(monitor_only: bool, simulate: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = bind_socket(Path::new(protocol::SOCKET_PATH))?;
 
     let state = Arc::new(Mutex::new(BackendState::new()));
     {
         let mut s = state.lock().unwrap();
         s.monitor_only = monitor_only;
+        s.simulated = simulate;
+        s.config = Config::load();
+        s.incognito = IncognitoMatcher::new(&s.config.incognito);
+        s.classifier = ContentClassifier::new(&s.config.content_rules);
+    }
+    // Synthetic traffic starts from scratch
+    if !simulate {
+        tokio::spawn(keep_selection_across_restarts(state.clone()));
     }
 
-    // Start Wayland clipboard monitoring in a separate task
-    let wayland_state = state.clone();
-    tokio::spawn(async move {
-        let monitor = WaylandClipboardMonitor::new(wayland_state);
-        if let Err(e) = monitor.start_monitoring() {
-            error!("Wayland clipboard monitoring error: {e}");
-        }
-    });
+    tokio::spawn(sync::run_sync(state.clone()));
+    tokio::spawn(run_expiry(state.clone()));
+    config_watch::start_watcher(state.clone());
+    let metrics_port = state.lock().unwrap().config.metrics_port;
+    if let Some(port) = metrics_port {
+        tokio::spawn(metrics_endpoint::serve(state.clone(), port));
+    }
+    super::plugins::start_worker(state.clone());
+    #[cfg(feature = "ocr")]
+    super::ocr::start_worker(state.clone());
+    #[cfg(feature = "link-titles")]
+    super::link_titles::start_worker(state.clone());
 
-    // Add some sample data only in debug builds (helps during development without polluting release)
-    #[cfg(debug_assertions)]
-    {
-        let mut state_lock = state.lock().unwrap();
-        for sample in [
-            "Hello, world Cursor-Clip!",
-            "https://github.com/rust-lang/rust",
-            "Sample clipboard content for testing the clipboard manager",
-            "impl Display for MyStruct {\n    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {\n        write!(f, \"MyStruct\")\n    }\n}",
-            "Password4234!Cursor-Clip",
-        ] {
-            let mut map = indexmap::IndexMap::new();
-            map.insert("text/plain;charset=utf-8".to_string(), Bytes::from_static(sample.as_bytes()));
-            let _ = state_lock.add_clipboard_item_from_mime_map(map);
-        }
+    if simulate {
+        // Synthetic traffic instead of a compositor connection
+        state.lock().unwrap().monitor_status = MonitorStatus::Simulated;
+        tokio::spawn(simulation::run_simulation(state.clone()));
+    } else {
+        // Wayland event dispatching blocks, it gets a thread of its own
+        let wayland_state = state.clone();
+        std::thread::spawn(move || run_monitor(&wayland_state));
     }
 
-    // Handle IPC connections
+    serve(listener, state).await?;
+    Ok(())
+}
+
+/// Create the IPC socket at `socket_path`, replacing a stale one
+pub fn bind_socket(socket_path: &Path) -> std::io::Result<UnixListener> {
+    let _ = std::fs::remove_file(socket_path);
+    let listener = UnixListener::bind(socket_path)?;
+
+    // Set socket permissions to allow all users to connect (666)
+    // This is needed because the daemon may run with different privileges than the frontend,
+    // which clients are actually served is decided by their peer credentials (see `client_allowed`)
+    let perms = std::fs::Permissions::from_mode(0o666);
+    std::fs::set_permissions(socket_path, perms)?;
+
+    info!("Clipboard backend listening on {}", socket_path.display());
+    Ok(listener)
+}
+
+/// Answer clients connecting to `listener` until accepting fails
+pub async fn serve(listener: UnixListener, state: Arc<Mutex<BackendState>>) -> std::io::Result<()> {
     loop {
         let (stream, _addr) = listener.accept().await?;
+        let allowed = {
+            let state = state.lock().unwrap();
+            client_allowed(&stream, &state.config)
+        };
+        if !allowed {
+            continue;
+        }
         let state_clone = state.clone();
         
         tokio::spawn(async move {
@@ -68,39 +116,487 @@ pub async fn run_backend(monitor_only: bool) -> Result<(), Box<dyn std::error::E
     }
 }
 
+/// Remove expired items periodically, the first check right at startup
+async fn run_expiry(state: Arc<Mutex<BackendState>>) {
+    let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+    loop {
+        interval.tick().await;
+        state.lock().unwrap().expire_items();
+    }
+}
+
+/// Restore the clipboard content of the previous run, then keep the selection for the next one.
+/// Both wait for the storage key, which may take until the user unlocks the keyring, so clients
+/// are served and selections recorded meanwhile.
+async fn keep_selection_across_restarts(state: Arc<Mutex<BackendState>>) {
+    let Some(key) = load_storage_key().await else { return };
+    let saved = tokio::task::spawn_blocking(move || {
+        let saved = saved_selection::path().and_then(|path| saved_selection::read(&path, &key));
+        (key, saved)
+    })
+    .await;
+    let Ok((key, saved)) = saved else { return };
+    if let Some(saved) = saved {
+        let mut s = state.lock().unwrap();
+        s.restore_saved_selection(saved);
+        // Served once the monitor is up, or now if it waited for an item
+        s.restore_pending_selection();
+    }
+    saved_selection::start_writer(&state, key);
+}
+
+/// The key of the files kept between runs, from the keyring. Without it nothing is kept.
+async fn load_storage_key() -> Option<chacha20poly1305::Key> {
+    let loading = tokio::task::spawn_blocking(storage_key::load_or_create);
+    match tokio::time::timeout(STORAGE_KEY_TIMEOUT, loading).await {
+        Ok(Ok(Ok(key))) => Some(key),
+        Ok(Ok(Err(e))) => {
+            warn!("The selection is not kept across restarts, no storage key: {e}");
+            None
+        }
+        Ok(Err(e)) => {
+            error!("Loading the storage key failed: {e}");
+            None
+        }
+        Err(_) => {
+            warn!("The selection is not kept across restarts, the keyring did not answer within {}s", STORAGE_KEY_TIMEOUT.as_secs());
+            None
+        }
+    }
+}
+
+/// Run the Wayland clipboard monitor. Failing to set it up (no compositor yet, or one without
+/// the needed protocols) is reported to clients and retried with growing delays, so a daemon
+/// started before the compositor or outliving a compositor switch starts working on its own.
+/// When the connection breaks (e.g. the compositor restarted), the monitor reconnects and
+/// serves the most recent item again, since the selection died with the old compositor.
+fn run_monitor(state: &Arc<Mutex<BackendState>>) {
+    let mut delay = MONITOR_RETRY_INITIAL;
+    loop {
+        let clipboard = state.lock().unwrap().clipboard.clone();
+        let error = match clipboard.start(state.clone()) {
+            Ok(()) => return,
+            Err(e) => e,
+        };
+        state.lock().unwrap().set_monitor_status(error.status());
+        if let MonitorError::Dispatch(_) = error {
+            warn!("Lost the Wayland connection ({error}), reconnecting");
+            // The connection worked until now, start over with short delays
+            delay = MONITOR_RETRY_INITIAL;
+        } else {
+            error!("Wayland clipboard monitoring unavailable: {error}. Retrying in {}s", delay.as_secs());
+        }
+        std::thread::sleep(delay);
+        delay = (delay * 2).min(MONITOR_RETRY_MAX);
+    }
+}
+
+/// Check the peer credentials of a new connection: only processes of the daemon's own user
+/// (or allowlisted users) may talk to the backend, the history may contain passwords
+fn client_allowed(stream: &UnixStream, config: &Config) -> bool {
+    let cred = match stream.peer_cred() {
+        Ok(cred) => cred,
+        Err(e) => {
+            warn!("Rejecting client, peer credentials unavailable: {e}");
+            return false;
+        }
+    };
+    let uid = cred.uid();
+    let pid = cred.pid().map_or_else(|| "?".to_string(), |pid| pid.to_string());
+    if uid == 0 && config.reject_root_clients {
+        warn!("Rejecting root client (pid {pid})");
+        return false;
+    }
+    let own_uid = unsafe { libc::geteuid() };
+    if uid != own_uid && !config.allowed_client_uids.contains(&uid) {
+        warn!("Rejecting client of user {uid} (pid {pid}), only user {own_uid} and allowed_client_uids may connect");
+        return false;
+    }
+    true
+}
+
+/// Removes a client from the registry once its connection is closed, however it ends
+struct ClientRegistration {
+    state: Arc<Mutex<BackendState>>,
+    client_id: u64,
+}
+
+impl Drop for ClientRegistration {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.clients.unregister(self.client_id);
+        }
+    }
+}
+
 async fn handle_client(
     stream: UnixStream,
     state: Arc<Mutex<BackendState>>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let stream_cred = stream.peer_cred().ok();
+    let stream_pid = stream_cred.and_then(|cred| cred.pid());
+    let peer_uid = stream_cred.map(|cred| cred.uid());
     let (reader, mut writer) = stream.into_split();
-    let mut lines = BufReader::new(reader).lines();
+    let mut reader = BufReader::new(reader);
+    // Both directions switch the wire format right after the Hello exchange
+    let mut format = WireFormat::Json;
 
-    while let Some(line) = lines.next_line().await? {
-        let message: FrontendMessage = serde_json::from_str(&line)?;
-        
-        let response = match message {
-            FrontendMessage::GetHistory => {
-                let state = state.lock().unwrap();
-                BackendMessage::History { items: state.get_history() }
+    // What the client announced in its Hello, clients without a Hello get every push
+    let peer: Arc<Mutex<Option<PeerInfo>>> = Arc::default();
+
+    // Responses and pushes share one outgoing channel so they are written in order
+    let (tx, mut rx) = mpsc::channel::<BackendMessage>(CLIENT_QUEUE_LEN);
+    let pid = stream_pid.and_then(|pid| u32::try_from(pid).ok());
+    let (client_id, owes_dropped) = state.lock().unwrap().clients.register(tx.clone(), pid);
+    let _registration = ClientRegistration { state: state.clone(), client_id };
+    let peer_for_writer = peer.clone();
+    let state_for_writer = state.clone();
+    tokio::spawn(async move {
+        let mut writer_format = WireFormat::Json;
+        loop {
+            // Pushes were dropped while the queue was full, it has room again
+            if rx.is_empty() && owes_dropped.load(Ordering::Relaxed) {
+                state_for_writer.lock().unwrap().clients.flush_dropped(client_id);
             }
-            FrontendMessage::SetClipboardById { id } => {
-                let mut state = state.lock().unwrap();
-                match state.set_clipboard_by_id(id) {
-                    Ok(()) => BackendMessage::ClipboardSet,
-                    Err(e) => BackendMessage::Error { message: e },
+            let Some(message) = rx.recv().await else { break };
+            // Leave out pushes the client would not understand
+            if message.is_push()
+                && let Some(capability) = message.required_capability()
+                && peer_for_writer.lock().unwrap().as_ref().is_some_and(|peer| !peer.supports(capability))
+            {
+                continue;
+            }
+            let frame = match wire::encode(writer_format, &message) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    error!("Failed to encode message for client: {e}");
+                    continue;
                 }
+            };
+            if writer.write_all(&frame).await.is_err() {
+                break;
+            }
+            if let BackendMessage::Hello { wire_format, .. } = message {
+                writer_format = wire_format;
             }
-            FrontendMessage::ClearHistory => {
-                let mut state = state.lock().unwrap();
-                state.clear_history();
-                BackendMessage::HistoryCleared
+        }
+    });
+
+    while let Some(body) = wire::read_frame(&mut reader, format).await? {
+        // Answer unknown or malformed requests instead of dropping the connection
+        let message: FrontendMessage = match wire::decode(format, &body) {
+            Ok(message) => message,
+            Err(e) => {
+                let error = BackendError::new(ErrorCode::InvalidRequest, "Could not parse request").with_details(e.to_string());
+                if tx.send(error.into()).await.is_err() {
+                    break;
+                }
+                continue;
             }
         };
 
-        let response_json = serde_json::to_string(&response)?;
-        writer.write_all(response_json.as_bytes()).await?;
-        writer.write_all(b"\n").await?;
+        // Everything logged while answering is tagged with the request
+        let request = message.name();
+        let span = debug_span!("ipc_request", request);
+        let started = Instant::now();
+        let response = async {
+            match message {
+                FrontendMessage::Hello { protocol_version, capabilities, wire_format } => {
+                    if protocol_version != protocol::PROTOCOL_VERSION {
+                        info!("Client speaks protocol version {protocol_version}, backend {}", protocol::PROTOCOL_VERSION);
+                    }
+                    debug!("Client capabilities: {capabilities:?}, requested wire format {wire_format:?}");
+                    *peer.lock().unwrap() = Some(PeerInfo { protocol_version, capabilities });
+                    // The writer switches after sending this answer, requests follow once the client got it
+                    format = wire_format;
+                    BackendMessage::Hello {
+                        protocol_version: protocol::PROTOCOL_VERSION,
+                        capabilities: protocol::own_capabilities(),
+                        wire_format,
+                    }
+                }
+                FrontendMessage::GetHistory => {
+                    let state = state.lock().unwrap();
+                    BackendMessage::History { items: state.get_history() }
+                }
+                FrontendMessage::GetHistoryPage { offset, limit, sort } => {
+                    let state = state.lock().unwrap();
+                    let (items, total) = state.get_history_page(offset, limit, sort);
+                    BackendMessage::HistoryPage { offset, items, total }
+                }
+                FrontendMessage::SearchHistory { query, content_types, offset, limit, sort, tag } => {
+                    let state = state.lock().unwrap();
+                    let (items, total) = state.search_history(&query, &content_types, tag.as_deref(), sort, offset, limit);
+                    BackendMessage::HistoryPage { offset, items, total }
+                }
+                FrontendMessage::SetClipboardById { id, seat, original } => {
+                    let mut state = state.lock().unwrap();
+                    let result = if original {
+                        state.copy_original_to_seat(id, seat.as_deref())
+                    } else {
+                        state.copy_item_to_seat(id, seat.as_deref())
+                    };
+                    match result {
+                        Ok(()) => BackendMessage::ClipboardSet,
+                        Err(e) => e.into(),
+                    }
+                }
+                FrontendMessage::SetClipboardByIdMime { id, mime } => {
+                    let mut state = state.lock().unwrap();
+                    match state.copy_item_mime(id, &mime) {
+                        Ok(()) => BackendMessage::ClipboardSet,
+                        Err(e) => e.into(),
+                    }
+                }
+                FrontendMessage::CopyOcrText { id } => {
+                    let mut state = state.lock().unwrap();
+                    match state.copy_ocr_text(id) {
+                        Ok(()) => BackendMessage::ClipboardSet,
+                        Err(e) => e.into(),
+                    }
+                }
+                FrontendMessage::TransformItem { id, op } => {
+                    let mut state = state.lock().unwrap();
+                    match state.transform_item(id, op) {
+                        Ok(()) => BackendMessage::ClipboardSet,
+                        Err(e) => e.into(),
+                    }
+                }
+                FrontendMessage::PauseCapture | FrontendMessage::ResumeCapture => {
+                    let mut state = state.lock().unwrap();
+                    state.set_capture_paused(matches!(message, FrontendMessage::PauseCapture));
+                    BackendMessage::CaptureState { paused: state.is_capture_paused() }
+                }
+                FrontendMessage::GetCaptureState => {
+                    let state = state.lock().unwrap();
+                    BackendMessage::CaptureState { paused: state.is_capture_paused() }
+                }
+                FrontendMessage::ListPhones => {
+                    let devices = tokio::task::spawn_blocking(kdeconnect::list_devices).await.unwrap_or_default();
+                    BackendMessage::PhoneList { devices }
+                }
+                FrontendMessage::SendToPhone { id, device } => {
+                    let state = state.clone();
+                    let result = tokio::task::spawn_blocking(move || kdeconnect::send_item(&state, id, device.as_deref())).await;
+                    phones_reached(result)
+                }
+                FrontendMessage::PullFromPhone { device } => {
+                    let state = state.clone();
+                    let result = tokio::task::spawn_blocking(move || kdeconnect::pull(&state, device.as_deref())).await;
+                    phones_reached(result)
+                }
+                FrontendMessage::Ping => {
+                    let state = state.lock().unwrap();
+                    BackendMessage::Pong { health: state.health() }
+                }
+                FrontendMessage::GetVersion => BackendMessage::Version {
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    protocol_version: protocol::PROTOCOL_VERSION,
+                },
+                FrontendMessage::SetLogLevel { filter } => match logging::set_filter(&filter) {
+                    Ok(filter) => {
+                        info!("Log filter set to {filter}");
+                        BackendMessage::LogLevel { filter }
+                    }
+                    Err(e) => BackendError::new(ErrorCode::InvalidRequest, "Invalid log filter").with_details(e).into(),
+                },
+                FrontendMessage::GetMetrics => {
+                    let state = state.lock().unwrap();
+                    BackendMessage::Metrics { metrics: state.metrics() }
+                }
+                FrontendMessage::GetStatus => {
+                    let state = state.lock().unwrap();
+                    BackendMessage::Status { status: state.monitor_status.clone() }
+                }
+                FrontendMessage::GetSyncStatus => {
+                    let state = state.lock().unwrap();
+                    BackendMessage::SyncStatus { status: state.sync_status.clone() }
+                }
+                FrontendMessage::ClearHistory => {
+                    let mut state = state.lock().unwrap();
+                    state.clear_history();
+                    BackendMessage::HistoryCleared
+                }
+                FrontendMessage::DeleteItem { id } => {
+                    let mut state = state.lock().unwrap();
+                    match state.delete_item(id) {
+                        Ok(()) => BackendMessage::ItemDeleted { id },
+                        Err(e) => e.into(),
+                    }
+                }
+                FrontendMessage::UndoDelete { id } => {
+                    let mut state = state.lock().unwrap();
+                    match state.undo_delete(id) {
+                        Ok(ids) => BackendMessage::Restored { ids },
+                        Err(e) => e.into(),
+                    }
+                }
+                FrontendMessage::MoveItem { id, position } => {
+                    let mut state = state.lock().unwrap();
+                    match state.move_item(id, position) {
+                        Ok(position) => BackendMessage::ItemMoved { id, position },
+                        Err(e) => e.into(),
+                    }
+                }
+                FrontendMessage::AddTag { id, tag } => {
+                    let mut state = state.lock().unwrap();
+                    match state.add_tag(id, &tag) {
+                        Ok(tags) => BackendMessage::ItemTags { id, tags },
+                        Err(e) => e.into(),
+                    }
+                }
+                FrontendMessage::RemoveTag { id, tag } => {
+                    let mut state = state.lock().unwrap();
+                    match state.remove_tag(id, &tag) {
+                        Ok(tags) => BackendMessage::ItemTags { id, tags },
+                        Err(e) => e.into(),
+                    }
+                }
+                FrontendMessage::ListTags => {
+                    let state = state.lock().unwrap();
+                    BackendMessage::TagList { tags: state.tag_names() }
+                }
+                FrontendMessage::PopAndPaste { depth } => {
+                    let mut state = state.lock().unwrap();
+                    let depth = depth.unwrap_or(state.config.copy_stack_depth);
+                    match state.pop_and_paste(depth) {
+                        Ok((item, remaining)) => BackendMessage::StackPopped { item, remaining },
+                        Err(e) => e.into(),
+                    }
+                }
+                FrontendMessage::SetConfig { preferences } => set_config_response(&state, peer_uid, preferences).await,
+                FrontendMessage::GetItemData { id } => {
+                    let state = state.lock().unwrap();
+                    item_data_response(&state, id, format)
+                }
+                FrontendMessage::GetHistoryRange { from, to } => {
+                    let state = state.lock().unwrap();
+                    BackendMessage::HistoryRange { from, to, items: state.history_range(from, to) }
+                }
+                FrontendMessage::AddItem { mime_data, set_clipboard } => {
+                    let mut state = state.lock().unwrap();
+                    match state.add_item(mime_data, set_clipboard) {
+                        Ok(id) => BackendMessage::ItemAdded { id },
+                        Err(e) => e.into(),
+                    }
+                }
+                FrontendMessage::UndoClear => {
+                    let mut state = state.lock().unwrap();
+                    match state.undo_clear() {
+                        Ok(ids) => BackendMessage::Restored { ids },
+                        Err(e) => e.into(),
+                    }
+                }
+                FrontendMessage::GetThumbnail { id, max_px } => thumbnail_response(&state, id, max_px).await,
+                FrontendMessage::GetStats => {
+                    let state = state.lock().unwrap();
+                    BackendMessage::Stats { stats: state.history_stats() }
+                }
+                FrontendMessage::Identify { name, filter } => {
+                    debug!("Client {client_id} is {name} ({filter:?})");
+                    state.lock().unwrap().clients.identify(client_id, name, filter);
+                    BackendMessage::Identified { client_id }
+                }
+                FrontendMessage::Subscribe { events } => {
+                    debug!("Client {client_id} subscribed to {events:?}");
+                    state.lock().unwrap().clients.subscribe(client_id, events.clone());
+                    BackendMessage::Subscribed { events }
+                }
+                FrontendMessage::ListClients => BackendMessage::ClientList { clients: state.lock().unwrap().clients.list() },
+            }
+        }
+        .instrument(span)
+        .await;
+        state.lock().unwrap().metrics.record_request(request, started.elapsed());
+
+        if tx.send(response).await.is_err() {
+            break;
+        }
     }
 
     Ok(())
 }
+
+/// All payloads of an item, as long as they fit into one frame of the connection's wire format
+fn item_data_response(state: &BackendState, id: u64, format: WireFormat) -> BackendMessage {
+    let Some(item) = state.history.iter().find(|item| item.item_id == id) else {
+        return BackendError::new(ErrorCode::NotFound, format!("No clipboard item found with ID: {id}")).into();
+    };
+    // JSON spells every byte out as a number
+    let limit = match format {
+        WireFormat::Json => wire::MAX_FRAME_LEN / 4,
+        WireFormat::MsgPack => wire::MAX_FRAME_LEN - 1024 * 1024,
+    };
+    let size: usize = item.mime_data.values().map(bytes::Bytes::len).sum();
+    if size > limit as usize {
+        return BackendError::new(ErrorCode::TooLarge, format!("Clipboard item {id} is too large to transfer ({size} bytes)")).into();
+    }
+    BackendMessage::ItemData { id, mime_data: item.mime_data.clone() }
+}
+
+/// Write changed preferences to the config file and apply them. Only the daemon's own user may
+/// change them (`allowed_client_uids` only grants access to the history); the file is written
+/// outside the state lock.
+async fn set_config_response(state: &Arc<Mutex<BackendState>>, peer_uid: Option<u32>, preferences: Preferences) -> BackendMessage {
+    let own_uid = unsafe { libc::geteuid() };
+    if peer_uid != Some(own_uid) {
+        warn!("Rejecting config change by user {peer_uid:?}, only user {own_uid} may change the config");
+        return BackendError::new(ErrorCode::InvalidRequest, "Only the daemon's own user may change its config").into();
+    }
+    let path = state.lock().unwrap().config_path.clone();
+    let saved = tokio::task::spawn_blocking(move || preferences.save_to(&path).map(|()| preferences)).await;
+    match saved {
+        Ok(Ok(preferences)) => {
+            let mut state = state.lock().unwrap();
+            let mut config = state.config.clone();
+            preferences.apply_to(&mut config);
+            if state.apply_config(config) {
+                info!("Config changed by a client");
+            }
+            BackendMessage::ConfigApplied { config: state.config.clone() }
+        }
+        Ok(Err(e)) => BackendError::new(ErrorCode::Internal, "Could not write the config file").with_details(e.to_string()).into(),
+        Err(e) => BackendError::new(ErrorCode::Internal, "Could not write the config file").with_details(e.to_string()).into(),
+    }
+}
+
+/// Reply to a request sent to phones on a blocking task: the devices reached, or why it failed
+fn phones_reached(result: Result<Result<Vec<String>, BackendError>, tokio::task::JoinError>) -> BackendMessage {
+    match result {
+        Ok(Ok(devices)) => BackendMessage::PhonesReached { devices },
+        Ok(Err(e)) => e.into(),
+        Err(e) => BackendError::new(ErrorCode::Internal, "Phone request failed").with_details(e.to_string()).into(),
+    }
+}
+
+/// Serve a thumbnail from the cache or generate it outside the state lock
+async fn thumbnail_response(state: &Arc<Mutex<BackendState>>, id: u64, max_px: u32) -> BackendMessage {
+    let max_px = max_px.clamp(image_pipeline::MIN_THUMBNAIL_PX, image_pipeline::MAX_THUMBNAIL_PX);
+    let source = {
+        let mut state = state.lock().unwrap();
+        if let Some(png) = state.thumbnail_cache.get(id, max_px) {
+            return BackendMessage::Thumbnail { id, max_px, png };
+        }
+        let Some(item) = state.history.iter().find(|item| item.item_id == id) else {
+            return BackendError::new(ErrorCode::NotFound, format!("No clipboard item found with ID: {id}")).into();
+        };
+        match image_pipeline::image_payload(&item.mime_data) {
+            Some(data) => data.clone(),
+            None => return BackendError::new(ErrorCode::InvalidRequest, format!("Clipboard item {id} is not an image")).into(),
+        }
+    };
+
+    let generated = tokio::task::spawn_blocking(move || image_pipeline::generate_thumbnail(&source, max_px)).await;
+    match generated {
+        Ok(Ok(png)) => {
+            let mut state = state.lock().unwrap();
+            // The item may have been removed while the thumbnail was generated
+            if state.history.iter().any(|item| item.item_id == id) {
+                state.thumbnail_cache.insert(id, max_px, png.clone());
+            }
+            BackendMessage::Thumbnail { id, max_px, png }
+        }
+        Ok(Err(message)) => BackendError::new(ErrorCode::Internal, format!("No thumbnail for item {id}")).with_details(message).into(),
+        Err(e) => BackendError::new(ErrorCode::Internal, "Thumbnail generation failed").with_details(e.to_string()).into(),
+    }
+}
@@ -0,0 +1,187 @@
+// Bridge to phones paired with KDE Connect or GSConnect, over their session bus APIs.
+//
+// Text copied on a phone reaches the desktop clipboard through KDE Connect itself and is recorded
+// like any other selection. Items pulled on request (`PullFromPhone`, GSConnect only) are tagged
+// with the phone as `source_device`. Sending works with both: KDE Connect takes the text directly,
+// GSConnect pushes the current clipboard, so the item is made the clipboard content first.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use gio::prelude::*;
+use tracing::{debug, info};
+use crate::shared::{BackendError, ErrorCode, PhoneDevice, PhoneService};
+use super::backend_state::BackendState;
+use super::transforms;
+
+const KDECONNECT_BUS_NAME: &str = "org.kde.kdeconnect";
+const KDECONNECT_PATH: &str = "/modules/kdeconnect";
+const GSCONNECT_BUS_NAME: &str = "org.gnome.Shell.Extensions.GSConnect";
+const GSCONNECT_PATH: &str = "/org/gnome/Shell/Extensions/GSConnect";
+const GSCONNECT_DEVICE_INTERFACE: &str = "org.gnome.Shell.Extensions.GSConnect.Device";
+const CALL_TIMEOUT_MS: i32 = 2000;
+/// Time for GSConnect to notice a clipboard change before pushing it
+const CLIPBOARD_SETTLE: Duration = Duration::from_millis(300);
+/// A selection arriving this soon after a pull is attributed to the phone
+pub const PULL_WINDOW: Duration = Duration::from_secs(3);
+
+/// Paired phones that are currently reachable, from both services (whichever are running)
+pub fn list_devices() -> Vec<PhoneDevice> {
+    let bus = match gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE) {
+        Ok(bus) => bus,
+        Err(e) => {
+            debug!("No session bus: {e}");
+            return Vec::new();
+        }
+    };
+    let mut devices = kdeconnect_devices(&bus).unwrap_or_else(|e| {
+        debug!("KDE Connect not available: {e}");
+        Vec::new()
+    });
+    devices.extend(gsconnect_devices(&bus).unwrap_or_else(|e| {
+        debug!("GSConnect not available: {e}");
+        Vec::new()
+    }));
+    devices
+}
+
+/// Send the text of an item to the phones matching `device` (id or name, all reachable ones if
+/// None). Returns the names of the phones it was sent to.
+pub fn send_item(state: &Arc<Mutex<BackendState>>, id: u64, device: Option<&str>) -> Result<Vec<String>, BackendError> {
+    let text = {
+        let state = state.lock().unwrap();
+        let item = state
+            .get_item_by_id(id)
+            .ok_or_else(|| BackendError::new(ErrorCode::NotFound, format!("No clipboard item found with ID: {id}")))?;
+        transforms::item_text(&item)
+            .map(str::to_string)
+            .ok_or_else(|| BackendError::new(ErrorCode::InvalidRequest, "Only text items can be sent to a phone"))?
+    };
+    let devices = select_devices(list_devices(), device)?;
+    let bus = session_bus()?;
+
+    if devices.iter().any(|device| device.service == PhoneService::GSConnect) {
+        state.lock().unwrap().set_clipboard_by_id(id)?;
+        std::thread::sleep(CLIPBOARD_SETTLE);
+    }
+    for device in &devices {
+        let result = match device.service {
+            PhoneService::KdeConnect => call(
+                &bus,
+                KDECONNECT_BUS_NAME,
+                &format!("{KDECONNECT_PATH}/devices/{}/clipboard", device.id),
+                "org.kde.kdeconnect.device.clipboard",
+                "sendClipboard",
+                &(text.as_str(),).to_variant(),
+            ),
+            PhoneService::GSConnect => activate_gsconnect_action(&bus, device, "clipboardPush"),
+        };
+        result.map_err(|e| phone_error(device, e))?;
+        info!("Sent clipboard item {id} to {}", device.name);
+    }
+    Ok(devices.into_iter().map(|device| device.name).collect())
+}
+
+/// Ask a phone for its clipboard, which then arrives as a new selection tagged with the phone.
+/// Only GSConnect can do this, KDE Connect phones share their clipboard on their own.
+pub fn pull(state: &Arc<Mutex<BackendState>>, device: Option<&str>) -> Result<Vec<String>, BackendError> {
+    let devices: Vec<_> = select_devices(list_devices(), device)?
+        .into_iter()
+        .filter(|device| device.service == PhoneService::GSConnect)
+        .collect();
+    let Some(device) = devices.into_iter().next() else {
+        return Err(BackendError::new(
+            ErrorCode::InvalidRequest,
+            "Only GSConnect phones can be asked for their clipboard; with KDE Connect, copy on the phone",
+        ));
+    };
+    let bus = session_bus()?;
+    state.lock().unwrap().pending_source_device = Some((device.name.clone(), Instant::now()));
+    activate_gsconnect_action(&bus, &device, "clipboardPull").map_err(|e| phone_error(&device, e))?;
+    Ok(vec![device.name])
+}
+
+fn select_devices(devices: Vec<PhoneDevice>, selector: Option<&str>) -> Result<Vec<PhoneDevice>, BackendError> {
+    let selected: Vec<_> = devices
+        .into_iter()
+        .filter(|device| selector.is_none_or(|selector| device.id == selector || device.name.eq_ignore_ascii_case(selector)))
+        .collect();
+    if selected.is_empty() {
+        let message = match selector {
+            Some(selector) => format!("No reachable phone named {selector}"),
+            None => "No reachable phone paired with KDE Connect or GSConnect".to_string(),
+        };
+        return Err(BackendError::new(ErrorCode::NotFound, message));
+    }
+    Ok(selected)
+}
+
+fn kdeconnect_devices(bus: &gio::DBusConnection) -> Result<Vec<PhoneDevice>, glib::Error> {
+    // devices(onlyReachable, onlyPaired)
+    let reply = call(bus, KDECONNECT_BUS_NAME, KDECONNECT_PATH, "org.kde.kdeconnect.daemon", "devices", &(true, true).to_variant())?;
+    let Some((ids,)) = reply.get::<(Vec<String>,)>() else { return Ok(Vec::new()) };
+    let mut devices = Vec::new();
+    for id in ids {
+        let path = format!("{KDECONNECT_PATH}/devices/{id}");
+        let has_clipboard = call(bus, KDECONNECT_BUS_NAME, &path, "org.kde.kdeconnect.device", "hasPlugin", &("kdeconnect_clipboard",).to_variant())?
+            .get::<(bool,)>()
+            .is_some_and(|(enabled,)| enabled);
+        if !has_clipboard {
+            continue;
+        }
+        let name = call(bus, KDECONNECT_BUS_NAME, &path, "org.freedesktop.DBus.Properties", "Get", &("org.kde.kdeconnect.device", "name").to_variant())?
+            .child_value(0)
+            .as_variant()
+            .and_then(|name| name.get::<String>())
+            .unwrap_or_else(|| id.clone());
+        devices.push(PhoneDevice { id, name, service: PhoneService::KdeConnect });
+    }
+    Ok(devices)
+}
+
+fn gsconnect_devices(bus: &gio::DBusConnection) -> Result<Vec<PhoneDevice>, glib::Error> {
+    let reply = call(bus, GSCONNECT_BUS_NAME, GSCONNECT_PATH, "org.freedesktop.DBus.ObjectManager", "GetManagedObjects", &().to_variant())?;
+    let objects = reply.child_value(0);
+    let mut devices = Vec::new();
+    for object in objects.iter() {
+        // a{sa{sv}}: interface name -> properties
+        let Some(properties) = object
+            .child_value(1)
+            .iter()
+            .find(|entry| entry.child_value(0).str() == Some(GSCONNECT_DEVICE_INTERFACE))
+            .map(|entry| glib::VariantDict::new(Some(&entry.child_value(1))))
+        else {
+            continue;
+        };
+        let property = |name: &str| properties.lookup_value(name, None);
+        let connected = property("Connected").and_then(|value| value.get::<bool>()).unwrap_or(false);
+        let paired = property("Paired").and_then(|value| value.get::<bool>()).unwrap_or(false);
+        let (Some(id), Some(name)) = (property("Id").and_then(|v| v.get::<String>()), property("Name").and_then(|v| v.get::<String>())) else {
+            continue;
+        };
+        if connected && paired {
+            devices.push(PhoneDevice { id, name, service: PhoneService::GSConnect });
+        }
+    }
+    Ok(devices)
+}
+
+/// GSConnect exposes device features as GActions on the device object
+fn activate_gsconnect_action(bus: &gio::DBusConnection, device: &PhoneDevice, action: &str) -> Result<glib::Variant, glib::Error> {
+    let path = format!("{GSCONNECT_PATH}/Device/{}", device.id.replace(|c: char| !c.is_ascii_alphanumeric(), "_"));
+    let parameters: Vec<glib::Variant> = Vec::new();
+    let platform_data = glib::VariantDict::new(None).end();
+    call(bus, GSCONNECT_BUS_NAME, &path, "org.gtk.Actions", "Activate", &(action, parameters, platform_data).to_variant())
+}
+
+fn call(bus: &gio::DBusConnection, name: &str, path: &str, interface: &str, method: &str, parameters: &glib::Variant) -> Result<glib::Variant, glib::Error> {
+    bus.call_sync(Some(name), path, interface, method, Some(parameters), None, gio::DBusCallFlags::NONE, CALL_TIMEOUT_MS, gio::Cancellable::NONE)
+}
+
+fn session_bus() -> Result<gio::DBusConnection, BackendError> {
+    gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE)
+        .map_err(|e| BackendError::new(ErrorCode::Internal, "No session bus").with_details(e.to_string()))
+}
+
+fn phone_error(device: &PhoneDevice, error: glib::Error) -> BackendError {
+    BackendError::new(ErrorCode::Busy, format!("Could not reach {}", device.name)).with_details(error.to_string())
+}
@@ -0,0 +1,109 @@
+// Page titles of copied links (cargo feature "link-titles", enabled with `fetch_link_titles`):
+// the page is fetched in the background and the item shown as "Title — domain" once it arrives
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+use tokio::sync::mpsc::UnboundedReceiver;
+use crate::shared::{BackendMessage, ClipboardItemPreview};
+use super::backend_state::{BackendState, link_domain};
+
+/// Only the start of a page is read, the title is in the head
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+/// Start fetching titles if enabled in the config, links are queued by
+/// `BackendState::add_clipboard_item_from_mime_map`
+pub fn start_worker(state: Arc<Mutex<BackendState>>) {
+    let timeout = {
+        let state = state.lock().unwrap();
+        if !state.config.fetch_link_titles {
+            return;
+        }
+        Duration::from_secs(state.config.link_title_timeout_secs)
+    };
+    let client = match reqwest::Client::builder().timeout(timeout).build() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Cannot fetch link titles: {e}");
+            return;
+        }
+    };
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    state.lock().unwrap().link_title_jobs = Some(tx);
+    info!("Fetching page titles of copied links");
+    tokio::spawn(run_worker(state, client, rx));
+}
+
+async fn run_worker(state: Arc<Mutex<BackendState>>, client: reqwest::Client, mut jobs: UnboundedReceiver<(u64, String)>) {
+    while let Some((item_id, url)) = jobs.recv().await {
+        let state = state.clone();
+        let client = client.clone();
+        // Fetch concurrently, a slow page must not hold up the following links
+        tokio::spawn(async move {
+            match fetch_title(&client, &url).await {
+                Ok(Some(title)) => set_title(&state, item_id, &url, &title),
+                Ok(None) => debug!("No title found for {url}"),
+                Err(e) => debug!("Fetching the title of {url} failed: {e}"),
+            }
+        });
+    }
+}
+
+async fn fetch_title(client: &reqwest::Client, url: &str) -> Result<Option<String>, reqwest::Error> {
+    let mut response = client.get(url).send().await?.error_for_status()?;
+    let is_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    if !is_html {
+        return Ok(None);
+    }
+
+    let mut head = Vec::new();
+    while head.len() < MAX_HEAD_BYTES
+        && let Some(chunk) = response.chunk().await?
+    {
+        head.extend_from_slice(&chunk);
+        if find_ignore_case(&head, b"</title>").is_some() {
+            break;
+        }
+    }
+    Ok(extract_title(&String::from_utf8_lossy(&head)))
+}
+
+/// Text of the first `<title>` element with entities decoded and whitespace collapsed
+fn extract_title(html: &str) -> Option<String> {
+    let start = find_ignore_case(html.as_bytes(), b"<title")?;
+    let content_start = start + html[start..].find('>')? + 1;
+    let content_len = find_ignore_case(&html.as_bytes()[content_start..], b"</title>")?;
+    let title = decode_entities(&html[content_start..content_start + content_len]);
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+fn find_ignore_case(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window.eq_ignore_ascii_case(needle))
+}
+
+/// The entities commonly found in page titles
+fn decode_entities(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+fn set_title(state: &Arc<Mutex<BackendState>>, item_id: u64, url: &str, title: &str) {
+    let mut state = state.lock().unwrap();
+    // The item may have been removed or merged with a copied title in the meantime
+    let Some(item) = state.history.iter_mut().find(|item| item.item_id == item_id && item.content_preview == url) else {
+        return;
+    };
+    item.content_preview = format!("{title} — {}", link_domain(url));
+    let preview = ClipboardItemPreview::from(&*item);
+    state.broadcast(BackendMessage::ItemUpdated { item: preview });
+}
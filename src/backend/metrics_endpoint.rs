@@ -0,0 +1,86 @@
+// Prometheus scrape endpoint, a minimal HTTP server on localhost enabled by `metrics_port`
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+use super::backend_state::BackendState;
+
+/// Largest request head read before answering
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+/// Connections that send no request within this time are closed
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Pause after a failed accept (e.g. out of file descriptors), retrying at once would spin
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Serve `GET /metrics` on 127.0.0.1:`port` until the daemon exits
+pub async fn serve(state: Arc<Mutex<BackendState>>, port: u16) {
+    let listener = match TcpListener::bind(("127.0.0.1", port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Could not open the metrics endpoint on port {port}: {e}");
+            return;
+        }
+    };
+    info!("Serving metrics on http://127.0.0.1:{port}/metrics");
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                debug!("Accepting a metrics connection failed: {e}");
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = answer(stream, &state).await {
+                debug!("Metrics request failed: {e}");
+            }
+        });
+    }
+}
+
+async fn answer(mut stream: TcpStream, state: &Arc<Mutex<BackendState>>) -> std::io::Result<()> {
+    let request = tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream))
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "no request received"))??;
+    let request_line = String::from_utf8_lossy(&request);
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let metrics = state.lock().unwrap().metrics();
+            ("200 OK", metrics.to_prometheus())
+        }
+        _ => ("404 Not Found", "Not found, metrics are served at /metrics\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// The request head, or what arrived of it before the client stopped sending
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Vec<u8>> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < MAX_REQUEST_BYTES {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+    Ok(request)
+}
+
+/// Resident memory of this process from /proc/self/statm
+pub fn resident_memory() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * u64::try_from(page_size).ok()?)
+}
@@ -2,5 +2,30 @@ pub mod ipc_server;
 pub mod backend_state;
 pub mod wayland_clipboard;
 pub mod ext_data_control;
+pub mod foreign_toplevel;
+pub mod image_pipeline;
+pub mod payload_check;
+pub mod capture_throttle;
+pub mod clipboard_backend;
+pub mod clients;
+pub mod payload_store;
+pub mod saved_selection;
+pub mod storage_key;
+pub mod selection_send;
+pub mod selection_state;
+pub mod simulation;
+pub mod sync;
+pub mod plugins;
+pub mod hooks;
+pub mod kdeconnect;
+pub mod metrics_endpoint;
+pub mod notifications;
+pub mod transforms;
+pub mod classification;
+pub mod config_watch;
+#[cfg(feature = "ocr")]
+pub mod ocr;
+#[cfg(feature = "link-titles")]
+pub mod link_titles;
 
 pub use ipc_server::*;
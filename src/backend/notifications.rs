@@ -0,0 +1,79 @@
+// Desktop notifications for newly captured items, sent to `org.freedesktop.Notifications`
+// on the session bus
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use gio::prelude::*;
+use tracing::{debug, warn};
+use crate::shared::{ClipboardContentType, ClipboardItemPreview, Config};
+use crate::shared::i18n::trf;
+
+/// How long a notification stays visible
+const EXPIRE_TIMEOUT_MS: i32 = 3000;
+/// Characters of the item preview shown in the notification body
+const BODY_CHARS: usize = 120;
+/// Shown instead of the content of secret items
+const MASK: &str = "••••••••";
+
+/// Id of the last notification, replaced by the next one so bursts of copies don't pile up
+static LAST_NOTIFICATION_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Notify about a new item if enabled for its content type. `secret` items (passwords, or
+/// marked by a password manager) are skipped unless `notify_secrets` is set, and never show content.
+pub fn notify_new_item(config: &Config, item: &ClipboardItemPreview, secret: bool) {
+    if !config.notifications || !config.notification_content_types.contains(&item.content_type) {
+        return;
+    }
+    let secret = secret || item.content_type == ClipboardContentType::Password;
+    if secret && !config.notify_secrets {
+        return;
+    }
+
+    let summary = format!("{} {}", item.content_type.icon(), trf("{} copied", &item.content_type.label()));
+    let body = if secret {
+        MASK.to_string()
+    } else {
+        let preview: String = item.content_preview.chars().take(BODY_CHARS).collect();
+        glib::markup_escape_text(&preview).to_string()
+    };
+
+    // The D-Bus round trip must not hold up clipboard handling
+    std::thread::spawn(move || {
+        if let Err(e) = send_notification(&summary, &body) {
+            warn!("Could not show a notification: {e}");
+        }
+    });
+}
+
+fn send_notification(summary: &str, body: &str) -> Result<(), glib::Error> {
+    let bus = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE)?;
+    let actions: Vec<String> = Vec::new();
+    let hints = glib::VariantDict::new(None);
+    hints.insert("transient", true);
+    let parameters = (
+        "Cursor Clip",
+        LAST_NOTIFICATION_ID.load(Ordering::Relaxed),
+        "edit-paste",
+        summary,
+        body,
+        actions,
+        hints.end(),
+        EXPIRE_TIMEOUT_MS,
+    )
+        .to_variant();
+    let reply = bus.call_sync(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        "org.freedesktop.Notifications",
+        "Notify",
+        Some(&parameters),
+        Some(glib::VariantTy::new("(u)").unwrap()),
+        gio::DBusCallFlags::NONE,
+        1000,
+        gio::Cancellable::NONE,
+    )?;
+    if let Some((id,)) = reply.get::<(u32,)>() {
+        debug!("Shown notification {id}");
+        LAST_NOTIFICATION_ID.store(id, Ordering::Relaxed);
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+// OCR of copied images (cargo feature "ocr"): text is recognized by the tesseract CLI on a
+// worker thread and stored on the item as searchable metadata
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use bytes::Bytes;
+use tracing::{debug, info, warn};
+use crate::shared::{BackendMessage, ClipboardItemPreview};
+use super::backend_state::BackendState;
+
+/// Image item waiting for OCR
+pub struct OcrJob {
+    pub item_id: u64,
+    pub image: Bytes,
+}
+
+/// Start the OCR worker, jobs are queued by `BackendState::add_clipboard_item_from_mime_map`
+pub fn start_worker(state: Arc<Mutex<BackendState>>) {
+    let (tx, rx) = std::sync::mpsc::channel();
+    state.lock().unwrap().ocr_jobs = Some(tx);
+    std::thread::spawn(move || run_worker(&state, &rx));
+}
+
+fn run_worker(state: &Arc<Mutex<BackendState>>, jobs: &Receiver<OcrJob>) {
+    for job in jobs {
+        let text = match recognize_text(&job.image) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("OCR of item {} failed: {e}", job.item_id);
+                continue;
+            }
+        };
+        if text.is_empty() {
+            debug!("No text recognized in item {}", job.item_id);
+            continue;
+        }
+
+        let mut state = state.lock().unwrap();
+        // The item may have been removed while the text was recognized
+        let Some(item) = state.history.iter_mut().find(|item| item.item_id == job.item_id) else { continue };
+        debug!("Recognized {} characters of text in item {}", text.len(), job.item_id);
+        item.ocr_text = Some(text);
+        let preview = ClipboardItemPreview::from(&*item);
+        state.broadcast(BackendMessage::ItemUpdated { item: preview });
+    }
+}
+
+/// Run `tesseract stdin stdout` on the image data
+fn recognize_text(image: &[u8]) -> Result<String, String> {
+    let mut child = Command::new("tesseract")
+        .args(["stdin", "stdout"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run tesseract: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(image).map_err(|e| format!("Failed to pass image to tesseract: {e}"))?;
+    }
+    let output = child.wait_with_output().map_err(|e| format!("tesseract failed: {e}"))?;
+    if !output.status.success() {
+        return Err(format!("tesseract exited with {}", output.status));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Queue OCR of a new image item
+pub fn queue(jobs: &Sender<OcrJob>, item_id: u64, image: Bytes) {
+    if jobs.send(OcrJob { item_id, image }).is_err() {
+        info!("OCR worker stopped, not recognizing text of item {item_id}");
+    }
+}
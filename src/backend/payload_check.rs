@@ -0,0 +1,209 @@
+// Sanity checks for MIME payloads read from a data offer (empty and short reads)
+
+use tracing::{debug, warn};
+
+/// Why a payload looks broken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadIssue {
+    /// The source announced the MIME type but wrote nothing
+    Empty,
+    /// The data ends early or lacks the format's signature
+    Truncated(&'static str),
+}
+
+impl std::fmt::Display for PayloadIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty payload"),
+            Self::Truncated(reason) => write!(f, "looks truncated ({reason})"),
+        }
+    }
+}
+
+/// A payload that passed the checks, or the best attempt flagged as suspicious
+pub struct CheckedPayload {
+    pub data: Vec<u8>,
+    pub suspicious: bool,
+}
+
+/// Read a payload with `read`, retrying once if it is empty or fails the sanity checks.
+/// Returns None if nothing usable was received; a payload failing both attempts is kept
+/// (the longer one) but flagged as suspicious.
+pub fn read_with_retry(
+    mime: &str,
+    mut read: impl FnMut() -> Result<Vec<u8>, String>,
+) -> Option<CheckedPayload> {
+    let first = match read() {
+        Ok(data) => match check_payload(mime, &data) {
+            Ok(()) => return Some(CheckedPayload { data, suspicious: false }),
+            Err(issue) => {
+                debug!("Payload for {mime} ({} bytes): {issue}, retrying once", data.len());
+                data
+            }
+        },
+        Err(e) => {
+            debug!("Failed reading {mime}: {e}, retrying once");
+            Vec::new()
+        }
+    };
+
+    let second = match read() {
+        Ok(data) => data,
+        Err(e) => {
+            warn!("Failed reading {mime} on retry: {e}");
+            Vec::new()
+        }
+    };
+    if check_payload(mime, &second).is_ok() {
+        return Some(CheckedPayload { data: second, suspicious: false });
+    }
+
+    let data = if second.len() >= first.len() { second } else { first };
+    match check_payload(mime, &data) {
+        Err(PayloadIssue::Empty) => {
+            warn!("Source offered {mime} but sent no data, dropping it");
+            None
+        }
+        Err(issue) => {
+            warn!("Payload for {mime} ({} bytes) {issue}, keeping it flagged as suspicious", data.len());
+            Some(CheckedPayload { data, suspicious: true })
+        }
+        Ok(()) => Some(CheckedPayload { data, suspicious: false }),
+    }
+}
+
+/// Minimum sanity checks per MIME class: image signatures and end markers, complete UTF-8 text
+pub fn check_payload(mime: &str, data: &[u8]) -> Result<(), PayloadIssue> {
+    if data.is_empty() {
+        return Err(PayloadIssue::Empty);
+    }
+
+    match mime {
+        "image/png" => {
+            if !data.starts_with(b"\x89PNG\r\n\x1a\n") {
+                return Err(PayloadIssue::Truncated("missing PNG signature"));
+            }
+            // The IEND chunk (type + CRC) closes every complete PNG
+            if data.len() < 20 || !data[data.len() - 8..].starts_with(b"IEND") {
+                return Err(PayloadIssue::Truncated("missing PNG IEND chunk"));
+            }
+        }
+        "image/jpeg" | "image/jpg" => {
+            if !data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+                return Err(PayloadIssue::Truncated("missing JPEG signature"));
+            }
+            if !data.ends_with(&[0xFF, 0xD9]) {
+                return Err(PayloadIssue::Truncated("missing JPEG end marker"));
+            }
+        }
+        "image/gif" => {
+            if !data.starts_with(b"GIF87a") && !data.starts_with(b"GIF89a") {
+                return Err(PayloadIssue::Truncated("missing GIF signature"));
+            }
+            if data.last() != Some(&0x3B) {
+                return Err(PayloadIssue::Truncated("missing GIF trailer"));
+            }
+        }
+        "image/webp" => {
+            if data.len() < 12 || !data.starts_with(b"RIFF") || &data[8..12] != b"WEBP" {
+                return Err(PayloadIssue::Truncated("missing WebP signature"));
+            }
+            // The RIFF size counts everything after the size field itself
+            let riff_size = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+            if data.len() < riff_size + 8 {
+                return Err(PayloadIssue::Truncated("shorter than its RIFF size"));
+            }
+        }
+        "image/bmp" => {
+            if data.len() < 6 || !data.starts_with(b"BM") {
+                return Err(PayloadIssue::Truncated("missing BMP signature"));
+            }
+            let file_size = u32::from_le_bytes([data[2], data[3], data[4], data[5]]) as usize;
+            if data.len() < file_size {
+                return Err(PayloadIssue::Truncated("shorter than its BMP file size"));
+            }
+        }
+        "image/tiff" if !data.starts_with(b"II*\0") && !data.starts_with(b"MM\0*") => {
+            return Err(PayloadIssue::Truncated("missing TIFF signature"));
+        }
+        "text/plain;charset=utf-8" | "UTF8_STRING" | "text/html" | "text/uri-list" => {
+            // A UTF-8 sequence cut off at the very end indicates a short read
+            if let Err(e) = std::str::from_utf8(data)
+                && e.error_len().is_none()
+            {
+                return Err(PayloadIssue::Truncated("incomplete UTF-8 sequence at the end"));
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(format: image::ImageFormat) -> Vec<u8> {
+        let mut data = Vec::new();
+        image::RgbImage::from_pixel(8, 8, image::Rgb([200, 40, 90]))
+            .write_to(&mut std::io::Cursor::new(&mut data), format)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn complete_images_pass() {
+        for (mime, format) in [("image/png", image::ImageFormat::Png), ("image/jpeg", image::ImageFormat::Jpeg), ("image/gif", image::ImageFormat::Gif)] {
+            assert_eq!(check_payload(mime, &encode(format)), Ok(()), "{mime}");
+        }
+    }
+
+    #[test]
+    fn cut_off_images_are_truncated() {
+        for (mime, format, reason) in [
+            ("image/png", image::ImageFormat::Png, "missing PNG IEND chunk"),
+            ("image/jpeg", image::ImageFormat::Jpeg, "missing JPEG end marker"),
+            ("image/gif", image::ImageFormat::Gif, "missing GIF trailer"),
+        ] {
+            let data = encode(format);
+            assert_eq!(check_payload(mime, &data[..data.len() / 2]), Err(PayloadIssue::Truncated(reason)), "{mime}");
+        }
+        assert_eq!(check_payload("image/png", b"\x89PNG\r\n\x1a\n"), Err(PayloadIssue::Truncated("missing PNG IEND chunk")));
+    }
+
+    #[test]
+    fn images_without_their_signature_are_truncated() {
+        let png = encode(image::ImageFormat::Png);
+        assert_eq!(check_payload("image/png", &png[1..]), Err(PayloadIssue::Truncated("missing PNG signature")));
+        assert_eq!(check_payload("image/jpeg", &png), Err(PayloadIssue::Truncated("missing JPEG signature")));
+        assert_eq!(check_payload("image/gif", b"GIF90a;"), Err(PayloadIssue::Truncated("missing GIF signature")));
+        assert_eq!(check_payload("image/gif", b""), Err(PayloadIssue::Empty));
+    }
+
+    #[test]
+    fn text_cut_inside_a_character_is_truncated() {
+        assert_eq!(check_payload("text/plain;charset=utf-8", "café".as_bytes()), Ok(()));
+        let cut = &"café".as_bytes()[..4];
+        assert_eq!(check_payload("text/plain;charset=utf-8", cut), Err(PayloadIssue::Truncated("incomplete UTF-8 sequence at the end")));
+        // Other types are not checked
+        assert_eq!(check_payload("application/octet-stream", cut), Ok(()));
+    }
+
+    #[test]
+    fn a_short_read_is_retried_once() {
+        let png = encode(image::ImageFormat::Png);
+        let mut reads = vec![Ok(png.clone()), Ok(png[..10].to_vec())];
+        let checked = read_with_retry("image/png", || reads.pop().unwrap()).unwrap();
+        assert_eq!(checked.data, png);
+        assert!(!checked.suspicious);
+
+        // The longer of two broken reads is kept, flagged
+        let mut reads = vec![Ok(png[..30].to_vec()), Ok(png[..10].to_vec())];
+        let checked = read_with_retry("image/png", || reads.pop().unwrap()).unwrap();
+        assert_eq!(checked.data.len(), 30);
+        assert!(checked.suspicious);
+
+        let mut reads = vec![Err("gone".to_string()), Ok(Vec::new())];
+        assert!(read_with_retry("image/png", || reads.pop().unwrap()).is_none());
+    }
+}
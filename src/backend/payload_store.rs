@@ -0,0 +1,130 @@
+// Tiered payload storage: large payloads are written to unlinked files in the private runtime
+// directory and memory-mapped, so huge items (big TIFFs, archives, ...) live in shared memory the
+// kernel can swap out instead of the daemon's heap. They stay ordinary `Bytes` for the rest of the
+// backend, serving a selection reads them from the mapping. Payloads of secret items stay in the heap.
+//
+// The runtime directory is a tmpfs, so clipboard content never reaches the disk in plaintext.
+// Encrypted spill files would not do: every read of a payload would need its plaintext in the
+// heap again.
+
+use bytes::Bytes;
+use indexmap::IndexMap;
+use memmap2::Mmap;
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{debug, warn};
+use crate::shared::format::format_bytes;
+use super::backend_state::is_secret_payload;
+
+/// Payloads from this size on are spilled
+pub const SPILL_THRESHOLD: usize = 4 * 1024 * 1024;
+
+/// Names of spill files on filesystems without `O_TMPFILE`, they are removed right away
+static SPILL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Keep the payloads read from a data offer, spilling the large ones unless they belong to a
+/// secret item
+pub fn store_payloads(mime_data: &mut IndexMap<String, Bytes>) {
+    if is_secret_payload(mime_data) {
+        return;
+    }
+    for payload in mime_data.values_mut() {
+        if payload.len() >= SPILL_THRESHOLD {
+            *payload = store_bytes(std::mem::take(payload));
+        }
+    }
+}
+
+/// Keep a payload of an item that is not secret, spilled if it is large
+pub fn store(data: Vec<u8>) -> Bytes {
+    store_bytes(Bytes::from(data))
+}
+
+fn store_bytes(data: Bytes) -> Bytes {
+    if data.len() < SPILL_THRESHOLD {
+        return data;
+    }
+    let Some(dir) = spill_dir() else {
+        debug!("Keeping a {} payload in memory, there is no runtime directory to spill it to", format_bytes(data.len() as u64));
+        return data;
+    };
+    match spill(&dir, &data) {
+        Ok(bytes) => {
+            debug!("Spilled a {} payload", format_bytes(data.len() as u64));
+            bytes
+        }
+        Err(e) => {
+            warn!("Keeping a {} payload in memory, spilling it to {} failed: {e}", format_bytes(data.len() as u64), dir.display());
+            data
+        }
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/cursor-clip/payloads`, private to the user and not on disk. None without a
+/// runtime directory, /tmp and the cache directory may be on disk.
+fn spill_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from).filter(|p| p.is_absolute())?;
+    Some(base.join("cursor-clip").join("payloads"))
+}
+
+/// Write `data` to a file without a name in `dir` (created accessible only to the user) and map
+/// it, the file is gone with the last mapping
+fn spill(dir: &Path, data: &[u8]) -> std::io::Result<Bytes> {
+    std::fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    // Also for a directory created before (or by an older version)
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+    let mut file = match std::fs::OpenOptions::new().read(true).write(true).mode(0o600).custom_flags(libc::O_TMPFILE).open(dir) {
+        Ok(file) => file,
+        Err(e) => {
+            debug!("No O_TMPFILE support in {} ({e}), using a named file", dir.display());
+            let path = dir.join(format!("spill-{}-{}", std::process::id(), SPILL_COUNTER.fetch_add(1, Ordering::Relaxed)));
+            let file = std::fs::OpenOptions::new().read(true).write(true).create_new(true).mode(0o600).open(&path)?;
+            std::fs::remove_file(&path)?;
+            file
+        }
+    };
+    file.write_all(data)?;
+    // Only this process has the (unlinked) file and never writes it again
+    let mmap = unsafe { Mmap::map(&file)? };
+    Ok(Bytes::from_owner(mmap))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn large_payload() -> Vec<u8> {
+        (0..SPILL_THRESHOLD + 17).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn spilled_payloads_read_back_and_leave_no_files() {
+        let dir = std::env::temp_dir().join(format!("cursor-clip-spill-test-{}", std::process::id()));
+        let data = large_payload();
+        let spilled = spill(&dir, &data).unwrap();
+        assert_eq!(spilled, data);
+        assert_ne!(spilled.as_ptr(), data.as_ptr());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        assert_eq!(std::fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+        drop(spilled);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn small_and_secret_payloads_stay_in_memory() {
+        let small = Bytes::from(vec![1u8; 1024]);
+        let large = Bytes::from(large_payload());
+        let (small_at, large_at) = (small.as_ptr(), large.as_ptr());
+        let mut mime_data = IndexMap::from([
+            ("text/plain".to_string(), small),
+            ("image/tiff".to_string(), large),
+            ("x-kde-passwordManagerHint".to_string(), Bytes::from_static(b"secret")),
+        ]);
+        store_payloads(&mut mime_data);
+        assert_eq!(mime_data["text/plain"].as_ptr(), small_at);
+        assert_eq!(mime_data["image/tiff"].as_ptr(), large_at);
+        assert_eq!(store(vec![7; 16]), [7; 16][..]);
+    }
+}
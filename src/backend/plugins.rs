@@ -0,0 +1,243 @@
+// User plugins: executables from the config run on every new clipboard item once it is
+// recorded (never on secret items). They run on a worker thread outside the state lock, the
+// payload is passed on stdin, stdout replaces the payload or adds tags.
+
+use std::io::{Read, Write};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use bytes::Bytes;
+use indexmap::IndexMap;
+use tracing::{debug, info, warn};
+use crate::shared::config::{PluginConfig, PluginOutput};
+use super::backend_state::BackendState;
+use super::payload_store;
+
+/// Larger plugin output is an error, the item is left as is
+const MAX_OUTPUT_BYTES: usize = 64 * 1024 * 1024;
+
+/// Recorded item waiting for the plugins, with the plugins configured when it was recorded
+pub struct PluginJob {
+    pub item_id: u64,
+    pub plugins: Vec<PluginConfig>,
+    pub mime_data: IndexMap<String, Bytes>,
+}
+
+/// Start the plugin worker, jobs are queued by `BackendState::add_clipboard_item_from_mime_map`
+pub fn start_worker(state: Arc<Mutex<BackendState>>) {
+    let (tx, rx) = std::sync::mpsc::channel();
+    state.lock().unwrap().plugin_jobs = Some(tx);
+    std::thread::spawn(move || run_worker(&state, &rx));
+}
+
+fn run_worker(state: &Arc<Mutex<BackendState>>, jobs: &Receiver<PluginJob>) {
+    for job in jobs {
+        let mut mime_data = job.mime_data.clone();
+        let tags = run_plugins(&job.plugins, &mut mime_data);
+        let replaced: IndexMap<String, Bytes> = mime_data
+            .into_iter()
+            .filter(|(mime, payload)| job.mime_data.get(mime) != Some(payload))
+            .collect();
+        if replaced.is_empty() && tags.is_empty() {
+            continue;
+        }
+        state.lock().unwrap().apply_plugin_results(job.item_id, replaced, tags);
+    }
+}
+
+/// Queue the plugins for a new item
+pub fn queue(jobs: &Sender<PluginJob>, item_id: u64, plugins: Vec<PluginConfig>, mime_data: IndexMap<String, Bytes>) {
+    if jobs.send(PluginJob { item_id, plugins, mime_data }).is_err() {
+        info!("Plugin worker stopped, not running plugins on item {item_id}");
+    }
+}
+
+/// Run every plugin whose MIME filter matches the item, in config order. Payloads are
+/// replaced in place, the collected tags are returned. A failing plugin leaves the item as is.
+pub fn run_plugins(plugins: &[PluginConfig], mime_data: &mut IndexMap<String, Bytes>) -> Vec<String> {
+    let mut tags = Vec::new();
+    for plugin in plugins {
+        let matching: Vec<String> = mime_data.keys().filter(|mime| plugin.accepts(mime)).cloned().collect();
+        match plugin.output {
+            // Every matching representation, so e.g. a redaction covers all text MIME types
+            PluginOutput::Payload => {
+                for mime in matching {
+                    match run_plugin(plugin, &mime, &mime_data[&mime]) {
+                        Ok(output) if !output.is_empty() => {
+                            mime_data.insert(mime, payload_store::store(output));
+                        }
+                        Ok(_) => {}
+                        Err(e) => warn!("Plugin {} failed on {mime}: {e}", plugin.name),
+                    }
+                }
+            }
+            // The first matching representation is enough to describe the item
+            PluginOutput::Tags | PluginOutput::Ignore => {
+                let Some(mime) = matching.first() else { continue };
+                match run_plugin(plugin, mime, &mime_data[mime]) {
+                    Ok(output) if plugin.output == PluginOutput::Tags => {
+                        let new_tags = String::from_utf8_lossy(&output)
+                            .lines()
+                            .map(str::trim)
+                            .filter(|tag| !tag.is_empty() && !tags.iter().any(|t| t == tag))
+                            .map(str::to_string)
+                            .collect::<Vec<_>>();
+                        tags.extend(new_tags);
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Plugin {} failed on {mime}: {e}", plugin.name),
+                }
+            }
+        }
+    }
+    tags
+}
+
+/// Run one plugin on a payload and return its stdout, killing it once the timeout passes
+fn run_plugin(plugin: &PluginConfig, mime: &str, payload: &Bytes) -> Result<Vec<u8>, String> {
+    let (program, args) = plugin.command.split_first().ok_or("no command configured")?;
+    debug!("Running plugin {} on {mime} ({} bytes)", plugin.name, payload.len());
+    // In a process group of its own, so processes it starts (e.g. by `sh -c`) are killed with it
+    let mut child = Command::new(program)
+        .args(args)
+        .env("CURSOR_CLIP_MIME_TYPE", mime)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .process_group(0)
+        .spawn()
+        .map_err(|e| format!("could not start {program}: {e}"))?;
+
+    // Feed stdin and drain stdout on their own threads, large payloads would otherwise
+    // deadlock with a plugin that writes before it has read everything
+    let stdin = child.stdin.take();
+    let input = payload.clone();
+    let writer = std::thread::spawn(move || {
+        if let Some(mut stdin) = stdin {
+            // A plugin may exit without reading its input
+            let _ = stdin.write_all(&input);
+        }
+    });
+    let stdout = child.stdout.take();
+    let reader = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        if let Some(stdout) = stdout {
+            // Closing the pipe after the limit stops a plugin writing endlessly
+            let _ = stdout.take(MAX_OUTPUT_BYTES as u64 + 1).read_to_end(&mut output);
+        }
+        output
+    });
+
+    let deadline = Instant::now() + Duration::from_millis(plugin.timeout_ms);
+    let status = loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break Ok(status),
+            None if Instant::now() >= deadline => {
+                kill_process_group(&child);
+                let _ = child.wait();
+                break Err(format!("timed out after {} ms", plugin.timeout_ms));
+            }
+            None => std::thread::sleep(Duration::from_millis(5)),
+        }
+    };
+    // Processes left behind would keep the pipes open
+    kill_process_group(&child);
+    let _ = writer.join();
+    let output = reader.join().map_err(|_| "reading the output failed")?;
+    let status = status?;
+    if output.len() > MAX_OUTPUT_BYTES {
+        return Err(format!("output larger than {MAX_OUTPUT_BYTES} bytes"));
+    }
+    if !status.success() {
+        return Err(format!("exited with {status}"));
+    }
+    Ok(output)
+}
+
+fn kill_process_group(child: &Child) {
+    // The group id is the plugin's pid, see `process_group(0)`
+    unsafe {
+        libc::killpg(child.id() as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugin(output: PluginOutput, script: &str) -> PluginConfig {
+        PluginConfig {
+            name: "test".to_string(),
+            command: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+            mime_types: vec!["text/*".to_string()],
+            output,
+            timeout_ms: 2000,
+        }
+    }
+
+    fn text(content: &str) -> IndexMap<String, Bytes> {
+        IndexMap::from([
+            ("text/plain".to_string(), Bytes::copy_from_slice(content.as_bytes())),
+            ("image/png".to_string(), Bytes::from_static(b"\x89PNG")),
+        ])
+    }
+
+    #[test]
+    fn payload_plugins_replace_matching_payloads_in_order() {
+        let mut mime_data = text("token ghp_abc");
+        let plugins = [plugin(PluginOutput::Payload, "sed s/ghp_abc/redacted/"), plugin(PluginOutput::Payload, "tr a-z A-Z")];
+        assert!(run_plugins(&plugins, &mut mime_data).is_empty());
+        assert_eq!(mime_data["text/plain"], "TOKEN REDACTED");
+        assert_eq!(mime_data["image/png"], b"\x89PNG"[..]);
+    }
+
+    #[test]
+    fn tag_plugins_add_each_line_once() {
+        let mut mime_data = text("hello");
+        let plugins = [plugin(PluginOutput::Tags, "printf 'work\\n\\n todo \\n'"), plugin(PluginOutput::Tags, "echo work")];
+        assert_eq!(run_plugins(&plugins, &mut mime_data), ["work", "todo"]);
+        assert_eq!(mime_data["text/plain"], "hello");
+    }
+
+    #[test]
+    fn failing_and_slow_plugins_leave_the_item_unchanged() {
+        let mut mime_data = text("hello");
+        let mut slow = plugin(PluginOutput::Payload, "sleep 5; echo late");
+        slow.timeout_ms = 100;
+        let plugins = [plugin(PluginOutput::Payload, "echo broken; exit 1"), slow, plugin(PluginOutput::Payload, "true")];
+        let started = Instant::now();
+        assert!(run_plugins(&plugins, &mut mime_data).is_empty());
+        assert!(started.elapsed() < Duration::from_secs(4));
+        assert_eq!(mime_data["text/plain"], "hello");
+    }
+
+    #[test]
+    fn processes_started_by_plugins_do_not_outlive_them() {
+        let mut mime_data = text("hello");
+        let mut slow = plugin(PluginOutput::Payload, "sleep 5 & sleep 5; echo late");
+        slow.timeout_ms = 100;
+        let plugins = [plugin(PluginOutput::Tags, "sleep 5 & echo done"), slow];
+        let started = Instant::now();
+        assert_eq!(run_plugins(&plugins, &mut mime_data), ["done"]);
+        assert!(started.elapsed() < Duration::from_secs(4));
+        assert_eq!(mime_data["text/plain"], "hello");
+    }
+
+    #[test]
+    fn endless_output_is_cut_off() {
+        let mut mime_data = text("hello");
+        let started = Instant::now();
+        assert!(run_plugins(&[plugin(PluginOutput::Payload, "yes")], &mut mime_data).is_empty());
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(mime_data["text/plain"], "hello");
+    }
+
+    #[test]
+    fn plugins_get_the_mime_type_in_the_environment() {
+        let mut mime_data = text("hello");
+        let plugins = [plugin(PluginOutput::Tags, "echo \"$CURSOR_CLIP_MIME_TYPE\"")];
+        assert_eq!(run_plugins(&plugins, &mut mime_data), ["text/plain"]);
+    }
+}
@@ -0,0 +1,151 @@
+// The clipboard content across daemon restarts: the payloads of the item last set as the selection
+// are kept in a file in the state directory and recorded again when the daemon starts, from where
+// `BackendState::restore_selection` serves them. Secret items and very large ones are not kept.
+// The file is encrypted with the storage key from the keyring (see `storage_key`).
+
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use bytes::Bytes;
+use chacha20poly1305::Key;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use crate::shared::EventStamp;
+use super::backend_state::BackendState;
+use super::storage_key;
+
+/// Items with more payload bytes than this are not kept, a restart loses them
+pub const MAX_SAVED_BYTES: usize = 16 * 1024 * 1024;
+
+/// Content of the selection file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSelection {
+    pub stamp: EventStamp,
+    pub mime_data: IndexMap<String, Bytes>,
+}
+
+/// `$XDG_STATE_HOME/cursor-clip/selection` or `~/.local/state/cursor-clip/selection`
+pub fn path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state")))?;
+    Some(base.join("cursor-clip").join("selection"))
+}
+
+/// Start the writer of the selection file, selections are queued by `BackendState::save_selection`
+/// (None forgets the saved one)
+pub fn start_writer(state: &Arc<Mutex<BackendState>>, key: Key) {
+    let Some(path) = path() else {
+        info!("No home directory, the selection is not kept across restarts");
+        return;
+    };
+    let (tx, rx) = std::sync::mpsc::channel();
+    state.lock().unwrap().set_selection_writer(tx);
+    std::thread::spawn(move || run_writer(&path, &key, &rx));
+}
+
+fn run_writer(path: &Path, key: &Key, saves: &Receiver<Option<SavedSelection>>) {
+    while let Ok(mut save) = saves.recv() {
+        // Only the newest of quickly following selections matters
+        while let Ok(newer) = saves.try_recv() {
+            save = newer;
+        }
+        let result = match save {
+            Some(selection) => write(path, key, &selection),
+            None => remove(path),
+        };
+        if let Err(e) = result {
+            warn!("Could not update the saved selection {}: {e}", path.display());
+        }
+    }
+}
+
+/// Queue a selection for the writer
+pub fn queue(saves: &Sender<Option<SavedSelection>>, selection: Option<SavedSelection>) {
+    if saves.send(selection).is_err() {
+        debug!("Selection writer stopped, not saving the selection");
+    }
+}
+
+/// Write the selection file encrypted and readable only by the current user, replacing it in one step
+pub fn write(path: &Path, key: &Key, selection: &SavedSelection) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let plaintext = rmp_serde::to_vec(selection).map_err(std::io::Error::other)?;
+    let content = storage_key::seal(key, &plaintext).map_err(std::io::Error::other)?;
+    let tmp = path.with_extension("tmp");
+    let mut file = std::fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(&tmp)?;
+    file.write_all(&content)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// The saved selection, None if there is none or the file cannot be read with `key`
+pub fn read(path: &Path, key: &Key) -> Option<SavedSelection> {
+    let content = std::fs::read(path).ok()?;
+    storage_key::open(key, &content)
+        .and_then(|plaintext| rmp_serde::from_slice(&plaintext).map_err(|e| e.to_string()))
+        .map_err(|e| warn!("Ignoring the saved selection {}: {e}", path.display()))
+        .ok()
+}
+
+/// Forget the saved selection
+fn remove(path: &Path) -> std::io::Result<()> {
+    std::fs::remove_file(path).or_else(|e| if e.kind() == std::io::ErrorKind::NotFound { Ok(()) } else { Err(e) })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+    use super::*;
+
+    fn test_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("cursor-clip-selection-test-{}-{name}", std::process::id())).join("selection")
+    }
+
+    fn key(byte: u8) -> Key {
+        *Key::from_slice(&[byte; 32])
+    }
+
+    fn selection() -> SavedSelection {
+        SavedSelection {
+            stamp: EventStamp { millis: 1_700_000_000_000, seq: 3 },
+            mime_data: IndexMap::from([
+                ("text/plain;charset=utf-8".to_string(), Bytes::from_static(b"kept")),
+                ("text/html".to_string(), Bytes::from_static(b"<b>kept</b>")),
+            ]),
+        }
+    }
+
+    #[test]
+    fn selections_round_trip_through_a_private_encrypted_file() {
+        let path = test_path("round-trip");
+        write(&path, &key(1), &selection()).unwrap();
+        assert_eq!(read(&path, &key(1)), Some(selection()));
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        let content = std::fs::read(&path).unwrap();
+        assert!(!content.windows(4).any(|window| window == b"kept"));
+        remove(&path).unwrap();
+        remove(&path).unwrap();
+        assert_eq!(read(&path, &key(1)), None);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn missing_damaged_and_foreign_files_restore_nothing() {
+        let path = test_path("damaged");
+        assert_eq!(read(&path, &key(1)), None);
+        write(&path, &key(2), &selection()).unwrap();
+        assert_eq!(read(&path, &key(1)), None);
+        // Unencrypted files are not trusted either
+        std::fs::write(&path, rmp_serde::to_vec(&selection()).unwrap()).unwrap();
+        assert_eq!(read(&path, &key(1)), None);
+        std::fs::write(&path, storage_key::seal(&key(1), b"\x93not msgpack").unwrap()).unwrap();
+        assert_eq!(read(&path, &key(1)), None);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,67 @@
+// Writing selection data to requesting clients. Each Send event is served on a thread of its
+// own holding only a reference to the payload, so a slow (or stuck) reader neither blocks the
+// Wayland dispatch thread nor keeps the backend state locked.
+
+use std::io::Write;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::time::Duration;
+use bytes::Bytes;
+use tracing::{debug, error};
+
+/// Bytes written per write call
+const SEND_CHUNK: usize = 64 * 1024;
+/// A reader that does not take any data for this long is given up on
+const SEND_STALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Write `payload` to the pipe of a Send event in the background
+pub fn send_payload(fd: OwnedFd, payload: Bytes, item_id: u64, mime_type: String) {
+    let spawned = std::thread::Builder::new().name("selection-send".into()).spawn(move || {
+        match write_chunked(fd, &payload) {
+            Ok(()) => debug!("Wrote {} bytes for id {item_id} (mime {mime_type})", payload.len()),
+            Err(e) => error!("Failed writing selection data (id {item_id}, mime {mime_type}): {e}"),
+        }
+    });
+    if let Err(e) = spawned {
+        error!("Cannot serve selection data (id {item_id}): {e}");
+    }
+}
+
+/// Write in chunks to the non-blocking `fd`, waiting for the reader whenever the pipe is full
+fn write_chunked(fd: OwnedFd, payload: &[u8]) -> std::io::Result<()> {
+    set_nonblocking(&fd)?;
+    let mut pipe = std::fs::File::from(fd);
+    let mut written = 0;
+    while written < payload.len() {
+        let end = (written + SEND_CHUNK).min(payload.len());
+        match pipe.write(&payload[written..end]) {
+            Ok(0) => return Err(std::io::ErrorKind::WriteZero.into()),
+            Ok(len) => written += len,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => wait_writable(&pipe)?,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+fn set_nonblocking(fd: &OwnedFd) -> std::io::Result<()> {
+    let flags = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GETFL) };
+    if flags < 0 || unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Wait until the reader made room in the pipe, failing after `SEND_STALL_TIMEOUT`
+fn wait_writable(pipe: &std::fs::File) -> std::io::Result<()> {
+    let mut poll_fd = libc::pollfd { fd: pipe.as_raw_fd(), events: libc::POLLOUT, revents: 0 };
+    let ready = unsafe { libc::poll(&mut poll_fd, 1, SEND_STALL_TIMEOUT.as_millis() as libc::c_int) };
+    if ready < 0 {
+        let e = std::io::Error::last_os_error();
+        return if e.kind() == std::io::ErrorKind::Interrupted { Ok(()) } else { Err(e) };
+    }
+    if ready == 0 {
+        return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "the reader stopped reading"));
+    }
+    Ok(())
+}
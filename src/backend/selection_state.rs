@@ -0,0 +1,120 @@
+// Who owns a seat's selection. When we set the selection, the compositor announces it back to
+// us as a new offer. Reading that offer inside the dispatch callback would deadlock (the Send
+// event for our own source cannot be processed until we return to the event loop), so our own
+// offers are recognized by the owner marker MIME type and skipped. Every other offer is read,
+// also one arriving while we still wait for our echo.
+
+/// Selection of a seat as seen by the backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelectionState {
+    /// Owned by another client (or nobody)
+    #[default]
+    Idle,
+    /// We set the selection to item `id` with our `serial`-th source on the seat and wait for
+    /// the compositor to announce it
+    AwaitingEcho { serial: u64, id: u64 },
+    /// The announced selection is our source serving item `id`
+    Owned { id: u64 },
+}
+
+/// Selection state of a seat along with the number of sources we set on it
+#[derive(Debug, Default)]
+pub struct SeatSelection {
+    state: SelectionState,
+    sources_set: u64,
+}
+
+impl SeatSelection {
+    pub fn state(&self) -> SelectionState {
+        self.state
+    }
+
+    /// Whether the selection is (about to be) served by us
+    pub fn is_ours(&self) -> bool {
+        self.state != SelectionState::Idle
+    }
+
+    /// We set the selection to item `id`, returns the serial of the new source
+    pub fn source_set(&mut self, id: u64) -> u64 {
+        self.sources_set += 1;
+        self.state = SelectionState::AwaitingEcho { serial: self.sources_set, id };
+        self.sources_set
+    }
+
+    /// The compositor announced a new selection, `marked_ours` if it carries our owner marker.
+    /// Returns whether it has to be read.
+    pub fn offered(&mut self, marked_ours: bool) -> bool {
+        match (self.state, marked_ours) {
+            (SelectionState::AwaitingEcho { id, .. } | SelectionState::Owned { id }, true) => {
+                self.state = SelectionState::Owned { id };
+                false
+            }
+            // Without a source of ours the marker comes from another cursor-clip instance
+            (SelectionState::Idle, true) => true,
+            (_, false) => {
+                self.state = SelectionState::Idle;
+                true
+            }
+        }
+    }
+
+    /// Our current source was cancelled (replaced by another client) or the selection cleared
+    pub fn lost(&mut self) {
+        self.state = SelectionState::Idle;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn our_echo_is_skipped() {
+        let mut selection = SeatSelection::default();
+        assert_eq!(selection.source_set(7), 1);
+        assert_eq!(selection.state(), SelectionState::AwaitingEcho { serial: 1, id: 7 });
+        assert!(!selection.offered(true));
+        assert_eq!(selection.state(), SelectionState::Owned { id: 7 });
+        // Repeated announcements of our selection are skipped as well
+        assert!(!selection.offered(true));
+        assert_eq!(selection.state(), SelectionState::Owned { id: 7 });
+    }
+
+    #[test]
+    fn external_copy_during_the_echo_window_is_read() {
+        let mut selection = SeatSelection::default();
+        selection.source_set(3);
+        assert!(selection.offered(false));
+        assert_eq!(selection.state(), SelectionState::Idle);
+        // A late echo of the replaced source is not taken for ours
+        assert!(selection.offered(true));
+    }
+
+    #[test]
+    fn external_copy_after_owning_is_read() {
+        let mut selection = SeatSelection::default();
+        selection.source_set(3);
+        selection.offered(true);
+        assert!(selection.offered(false));
+        assert!(!selection.is_ours());
+    }
+
+    #[test]
+    fn losing_the_selection_returns_to_idle() {
+        let mut selection = SeatSelection::default();
+        selection.source_set(1);
+        selection.offered(true);
+        selection.lost();
+        assert_eq!(selection.state(), SelectionState::Idle);
+        assert_eq!(selection.source_set(2), 2);
+        selection.lost();
+        assert!(selection.offered(true));
+    }
+
+    #[test]
+    fn marked_offers_without_a_source_of_ours_are_read() {
+        let mut selection = SeatSelection::default();
+        assert!(selection.offered(true));
+        assert_eq!(selection.state(), SelectionState::Idle);
+    }
+}
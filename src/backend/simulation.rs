@@ -0,0 +1,155 @@
+// Simulation mode: synthetic clipboard traffic instead of a Wayland compositor (development, demos, CI)
+
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use bytes::Bytes;
+use image::{ImageFormat, Rgb, RgbImage};
+use indexmap::IndexMap;
+use tracing::{debug, info, warn};
+use super::backend_state::BackendState;
+
+/// Pause between simulated copies (bursts come in faster)
+const COPY_INTERVAL: Duration = Duration::from_secs(8);
+const BURST_INTERVAL: Duration = Duration::from_millis(150);
+
+const TEXT_SAMPLES: &[&str] = &[
+    "Hello, world Cursor-Clip!",
+    "Sample clipboard content for testing the clipboard manager",
+    "Meeting moved to Thursday 14:00, room 3.12",
+    "The quick brown fox jumps over the lazy dog",
+    "Password4234!Cursor-Clip",
+    "~/.config/cursor-clip/config.toml",
+];
+
+const CODE_SAMPLES: &[&str] = &[
+    "impl Display for MyStruct {\n    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {\n        write!(f, \"MyStruct\")\n    }\n}",
+    "fn main() {\n    println!(\"Hello from the simulator\");\n}",
+    "SELECT id, name FROM users WHERE active = 1;",
+];
+
+const URL_SAMPLES: &[&str] = &[
+    "https://github.com/rust-lang/rust",
+    "https://gtk-rs.org/gtk4-rs/stable/latest/book/",
+    "https://wayland.app/protocols/ext-data-control-v1",
+];
+
+/// Seed the history with a few items of each kind, then keep copying on a timer
+pub async fn run_simulation(state: Arc<Mutex<BackendState>>) {
+    info!("Simulation mode: generating synthetic clipboard traffic");
+    let mut rng = XorShift::from_time();
+
+    {
+        let mut state = state.lock().unwrap();
+        state.batch_pushes(|state| {
+            // A short text right before a link, as when copying a link after its page title
+            for sample in [TEXT_SAMPLES[0], URL_SAMPLES[0], TEXT_SAMPLES[1], CODE_SAMPLES[0], TEXT_SAMPLES[4]] {
+                let _ = state.add_clipboard_item_from_mime_map(text_payload(sample), Vec::new(), None);
+            }
+            let _ = state.add_clipboard_item_from_mime_map(image_payload(&mut rng), Vec::new(), None);
+        });
+    }
+
+    loop {
+        tokio::time::sleep(COPY_INTERVAL).await;
+
+        // Every few copies, simulate a burst (e.g. copying several lines in quick succession)
+        let copies = if rng.below(5) == 0 { 2 + rng.below(4) } else { 1 };
+        for i in 0..copies {
+            if i > 0 {
+                tokio::time::sleep(BURST_INTERVAL).await;
+            }
+            let payload = match rng.below(10) {
+                0..=4 => text_payload(TEXT_SAMPLES[rng.below(TEXT_SAMPLES.len())]),
+                5..=6 => text_payload(CODE_SAMPLES[rng.below(CODE_SAMPLES.len())]),
+                7..=8 => text_payload(URL_SAMPLES[rng.below(URL_SAMPLES.len())]),
+                _ => image_payload(&mut rng),
+            };
+            let mut state = state.lock().unwrap();
+            if state.is_capture_paused() {
+                continue;
+            }
+            if let Some(id) = state.add_clipboard_item_from_mime_map(payload, Vec::new(), None) {
+                debug!("Simulated copy (id {id})");
+            }
+        }
+    }
+}
+
+fn text_payload(text: &str) -> IndexMap<String, Bytes> {
+    let mut map = IndexMap::new();
+    map.insert("text/plain;charset=utf-8".to_string(), Bytes::copy_from_slice(text.as_bytes()));
+    map.insert("text/plain".to_string(), Bytes::copy_from_slice(text.as_bytes()));
+    map
+}
+
+/// A small PNG with a random color gradient, so image items are distinguishable
+fn image_payload(rng: &mut XorShift) -> IndexMap<String, Bytes> {
+    let (width, height) = (160 + rng.below(480) as u32, 120 + rng.below(360) as u32);
+    let from = [rng.below(256) as f32, rng.below(256) as f32, rng.below(256) as f32];
+    let to = [rng.below(256) as f32, rng.below(256) as f32, rng.below(256) as f32];
+    let image = RgbImage::from_fn(width, height, |x, y| {
+        let t = (x + y) as f32 / (width + height) as f32;
+        Rgb(std::array::from_fn(|c| (from[c] + (to[c] - from[c]) * t) as u8))
+    });
+
+    let mut map = IndexMap::new();
+    let mut png = Cursor::new(Vec::new());
+    match image.write_to(&mut png, ImageFormat::Png) {
+        Ok(()) => {
+            map.insert("image/png".to_string(), Bytes::from(png.into_inner()));
+        }
+        Err(e) => warn!("Failed to encode simulated image: {e}"),
+    }
+    map
+}
+
+/// Tiny xorshift generator, good enough for picking samples
+struct XorShift(u64);
+
+impl XorShift {
+    fn from_time() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+        Self(nanos | 1)
+    }
+
+    /// Number in `0..bound`
+    fn below(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound as u64) as usize
+    }
+}
+
+/// Endless stream of synthetic selections, each with unique content so none is merged into an
+/// earlier item. Drives the benchmarks and stress tests; every `image_every`-th one is an image.
+pub struct SyntheticSelections {
+    rng: XorShift,
+    count: u64,
+    image_every: u64,
+}
+
+impl SyntheticSelections {
+    pub fn new(seed: u64, image_every: u64) -> Self {
+        Self { rng: XorShift(seed | 1), count: 0, image_every }
+    }
+}
+
+impl Iterator for SyntheticSelections {
+    type Item = IndexMap<String, Bytes>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.count += 1;
+        if self.image_every > 0 && self.count.is_multiple_of(self.image_every) {
+            return Some(image_payload(&mut self.rng));
+        }
+        let samples = match self.rng.below(3) {
+            0 => TEXT_SAMPLES,
+            1 => CODE_SAMPLES,
+            _ => URL_SAMPLES,
+        };
+        let sample = samples[self.rng.below(samples.len())];
+        Some(text_payload(&format!("#{} {sample}", self.count)))
+    }
+}
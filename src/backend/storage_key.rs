@@ -0,0 +1,180 @@
+// Encryption of what the daemon keeps on disk between runs (the saved selection), so clipboard
+// content (often credentials) never lies on disk in plaintext. The key lives in the user's keyring
+// and is reached through the Secret Service API with the `secret-tool` CLI of libsecret (GNOME
+// Keyring, KWallet and KeePassXC provide the service); the first start creates it. Without a
+// keyring nothing is kept between runs.
+//
+// Sync files are encrypted with the sync key instead (see `sync`), spilled payloads never reach
+// the disk (see `payload_store`).
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use tracing::info;
+use super::sync;
+
+const SECRET_TOOL: &str = "secret-tool";
+/// Attributes the key is stored under in the keyring
+const ATTRIBUTES: [&str; 4] = ["application", "cursor-clip", "purpose", "storage"];
+const LABEL: &str = "cursor-clip storage key";
+/// Start of every encrypted file, followed by the nonce and the ciphertext
+const MAGIC: &[u8; 4] = b"CCE1";
+const NONCE_LEN: usize = 24;
+
+/// The storage key from the keyring, created there on first use. Asking may block while the
+/// keyring prompts to be unlocked.
+pub fn load_or_create() -> Result<Key, String> {
+    load_or_create_with(&[SECRET_TOOL])
+}
+
+fn load_or_create_with(command: &[&str]) -> Result<Key, String> {
+    if let Some(key) = lookup(command)? {
+        return Ok(key);
+    }
+    let key = sync::generate_key();
+    store(command, &key)?;
+    info!("Created the storage key in the keyring");
+    parse(&key)
+}
+
+/// The stored key, None if the keyring has none yet
+fn lookup(command: &[&str]) -> Result<Option<Key>, String> {
+    let output = secret_tool(command, "lookup")
+        .args(ATTRIBUTES)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Cannot run {} (libsecret): {e}", command[0]))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if output.status.success() && !stdout.trim().is_empty() {
+        return parse(&stdout).map(Some);
+    }
+    // A missing key only shows in the exit status, keyring errors are also reported on stderr
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.trim().is_empty() { Ok(None) } else { Err(format!("Keyring lookup failed: {}", stderr.trim())) }
+}
+
+fn store(command: &[&str], key: &str) -> Result<(), String> {
+    let mut child = secret_tool(command, "store")
+        .args(["--label", LABEL])
+        .args(ATTRIBUTES)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Cannot run {} (libsecret): {e}", command[0]))?;
+    // secret-tool reads the secret from a pipe until it is closed
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(key.as_bytes()).map_err(|e| format!("Cannot pass the key to {}: {e}", command[0]))?;
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!("Keyring store failed: {}", String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+/// `secret-tool <action>`, the command is replaced in tests
+fn secret_tool(command: &[&str], action: &str) -> Command {
+    let mut secret_tool = Command::new(command[0]);
+    secret_tool.args(&command[1..]).arg(action);
+    secret_tool
+}
+
+fn parse(text: &str) -> Result<Key, String> {
+    sync::parse_key(text).map_err(|_| "The storage key in the keyring is damaged".to_string())
+}
+
+/// `plaintext` encrypted with XChaCha20-Poly1305 under a fresh nonce
+pub fn seal(key: &Key, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = XChaCha20Poly1305::new(key).encrypt(&nonce, plaintext).map_err(|_| "Encryption failed".to_string())?;
+    let mut blob = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+    blob.extend_from_slice(MAGIC);
+    blob.extend_from_slice(&nonce);
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+}
+
+/// The plaintext of a `seal`ed blob, an error for another key or a damaged blob
+pub fn open(key: &Key, blob: &[u8]) -> Result<Vec<u8>, String> {
+    let body = blob.strip_prefix(MAGIC).ok_or("Not an encrypted file")?;
+    if body.len() < NONCE_LEN {
+        return Err("Truncated file".to_string());
+    }
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    XChaCha20Poly1305::new(key)
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Wrong key or corrupted file".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use super::*;
+
+    /// A stand-in for secret-tool keeping the secret in a file next to the script
+    struct FakeKeyring(PathBuf);
+
+    impl FakeKeyring {
+        fn new(name: &str, script: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("cursor-clip-keyring-test-{}-{name}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("secret-tool"), script).unwrap();
+            Self(dir)
+        }
+
+        fn script(&self) -> String {
+            self.0.join("secret-tool").to_string_lossy().into_owned()
+        }
+    }
+
+    impl Drop for FakeKeyring {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const KEYRING: &str = r#"
+        secret="$(dirname "$0")/secret"
+        case "$1" in
+            lookup) [ -f "$secret" ] && cat "$secret" || exit 1 ;;
+            store) cat > "$secret" ;;
+        esac
+    "#;
+
+    #[test]
+    fn the_key_is_created_once_and_found_again() {
+        let keyring = FakeKeyring::new("create", KEYRING);
+        let script = keyring.script();
+        let created = load_or_create_with(&["sh", &script]).unwrap();
+        assert_eq!(std::fs::read_to_string(keyring.0.join("secret")).unwrap().len(), 64);
+        assert_eq!(load_or_create_with(&["sh", &script]).unwrap(), created);
+    }
+
+    #[test]
+    fn keyring_errors_are_reported() {
+        let keyring = FakeKeyring::new("locked", "echo 'Cannot unlock the collection' >&2; exit 1");
+        let script = keyring.script();
+        let error = load_or_create_with(&["sh", &script]).unwrap_err();
+        assert!(error.contains("Cannot unlock the collection"), "{error}");
+        assert!(load_or_create_with(&["cursor-clip-no-such-secret-tool"]).is_err());
+    }
+
+    #[test]
+    fn sealed_data_opens_only_with_its_key() {
+        let key = parse(&sync::generate_key()).unwrap();
+        let blob = seal(&key, b"hunter2").unwrap();
+        assert!(!blob.windows(7).any(|window| window == b"hunter2"));
+        assert_eq!(open(&key, &blob).unwrap(), b"hunter2");
+
+        let other = parse(&sync::generate_key()).unwrap();
+        assert!(open(&other, &blob).is_err());
+        let mut tampered = blob.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(open(&key, &tampered).is_err());
+        assert!(open(&key, &blob[..MAGIC.len() + 3]).is_err());
+        assert!(open(&key, b"plaintext").is_err());
+    }
+}
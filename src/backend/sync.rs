@@ -0,0 +1,475 @@
+// History sync between devices (enabled with `cursor-clip sync enable`): every device writes its
+// items as encrypted files into a shared folder (kept in sync by e.g. Syncthing or a network
+// share) and imports the files written by the others.
+//
+// Files are named by a keyed hash (HMAC with the sync key) of the item content, so the same content
+// copied on two devices ends up in a single file without the name revealing the content to anyone
+// who can list the folder. When both wrote it, the copy with the newer stamp wins, which makes merging
+// independent of the order in which devices see each other's files.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use bytes::Bytes;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use indexmap::IndexMap;
+use tracing::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::shared::config::SyncConfig;
+use crate::shared::{ClipboardContentType, EventStamp};
+use super::backend_state::{BackendState, is_secret};
+use super::payload_store;
+
+const SYNC_INTERVAL: Duration = Duration::from_secs(15);
+const FILE_EXTENSION: &str = "ccsync";
+/// Start of every sync file, followed by the nonce and the encrypted `SyncRecord`
+const MAGIC: &[u8; 4] = b"CCS1";
+const NONCE_LEN: usize = 24;
+/// Files beyond this many (newest first) are deleted, matching the history length
+const MAX_FILES: usize = 100;
+
+pub type ContentHash = [u8; 32];
+
+/// Content of a sync file
+#[derive(Serialize, Deserialize)]
+struct SyncRecord {
+    hash: ContentHash,
+    stamp: EventStamp,
+    /// Device the item was copied on
+    device: String,
+    mime_data: IndexMap<String, Bytes>,
+}
+
+/// What a device knows about the sync folder, kept between runs
+#[derive(Default)]
+struct SyncMemory {
+    /// Newest stamp seen (written or read) per content hash
+    known: HashMap<ContentHash, EventStamp>,
+    /// Modification time of each file when it was last read, unchanged files are not read again.
+    /// Files gone from the folder are forgotten on the next run.
+    read: HashMap<PathBuf, SystemTime>,
+    /// Content hash per history item id
+    hashes: HashMap<u64, ContentHash>,
+}
+
+/// Work of a single run, prepared while holding the state lock and done without it
+struct Snapshot {
+    config: SyncConfig,
+    /// Items to write: hash, stamp and payloads
+    exports: Vec<(ContentHash, EventStamp, IndexMap<String, Bytes>)>,
+    /// Stamp and id of the local items by content hash
+    local: HashMap<ContentHash, (EventStamp, u64)>,
+}
+
+enum Import {
+    New { stamp: EventStamp, device: String, mime_data: IndexMap<String, Bytes> },
+    Restamp { id: u64, stamp: EventStamp },
+}
+
+/// Sync periodically while enabled. The settings are read again before every run, so
+/// `cursor-clip sync enable/disable` take effect without restarting the daemon.
+pub async fn run_sync(state: Arc<Mutex<BackendState>>) {
+    let memory = Arc::new(Mutex::new(SyncMemory::default()));
+    let mut interval = tokio::time::interval(SYNC_INTERVAL);
+    let mut active_dir: Option<PathBuf> = None;
+    loop {
+        interval.tick().await;
+        let Some(config) = SyncConfig::load() else {
+            if active_dir.take().is_some() {
+                info!("History sync disabled");
+                *memory.lock().unwrap() = SyncMemory::default();
+            }
+            state.lock().unwrap().sync_status.enabled = false;
+            continue;
+        };
+        let key = match parse_key(&config.key) {
+            Ok(key) => key,
+            Err(e) => {
+                set_error(&state, &config, e);
+                continue;
+            }
+        };
+        if active_dir.as_ref() != Some(&config.dir) {
+            info!("Syncing history through {}", config.dir.display());
+            *memory.lock().unwrap() = SyncMemory::default();
+            active_dir = Some(config.dir.clone());
+        }
+
+        let snapshot = take_snapshot(&state, &mut memory.lock().unwrap(), config);
+        let run_memory = memory.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let mut memory = run_memory.lock().unwrap();
+            let imports = import_files(&snapshot, &key, &mut memory)?;
+            let exported = export_items(&snapshot, &key, &mut memory)?;
+            prune_files(&snapshot.config.dir);
+            Ok::<_, String>((snapshot.config, imports, exported))
+        })
+        .await;
+
+        match result {
+            Ok(Ok((config, imports, exported))) => apply_imports(&state, &memory, config, imports, exported),
+            Ok(Err(e)) => {
+                if let Some(config) = SyncConfig::load() {
+                    set_error(&state, &config, e);
+                }
+            }
+            Err(e) => warn!("History sync run failed: {e}"),
+        }
+    }
+}
+
+fn take_snapshot(state: &Arc<Mutex<BackendState>>, memory: &mut SyncMemory, config: SyncConfig) -> Snapshot {
+    let state = state.lock().unwrap();
+    memory.hashes.retain(|id, _| state.history.iter().any(|item| item.item_id == *id));
+    let mut exports = Vec::new();
+    let mut local = HashMap::new();
+    for item in &state.history {
+        let hash = *memory.hashes.entry(item.item_id).or_insert_with(|| content_hash(&item.mime_data));
+        local.insert(hash, (item.stamp, item.item_id));
+        // Passwords never leave the device
+        if item.content_type == ClipboardContentType::Password || is_secret(item) {
+            continue;
+        }
+        if memory.known.get(&hash).is_none_or(|known| *known < item.stamp) {
+            exports.push((hash, item.stamp, item.mime_data.clone()));
+        }
+    }
+    Snapshot { config, exports, local }
+}
+
+/// Read new and changed files of other devices
+fn import_files(snapshot: &Snapshot, key: &Key, memory: &mut SyncMemory) -> Result<Vec<Import>, String> {
+    let entries = std::fs::read_dir(&snapshot.config.dir)
+        .map_err(|e| format!("Cannot read {}: {e}", snapshot.config.dir.display()))?;
+    let cipher = XChaCha20Poly1305::new(key);
+    let mut imports = Vec::new();
+    let paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == FILE_EXTENSION))
+        .collect();
+    // Pruned or deleted files, the folder is bounded so the memory of it is as well
+    memory.read.retain(|path, _| paths.contains(path));
+    for path in paths {
+        let Ok(modified) = std::fs::metadata(&path).and_then(|meta| meta.modified()) else { continue };
+        if memory.read.get(&path) == Some(&modified) {
+            continue;
+        }
+        memory.read.insert(path.clone(), modified);
+
+        let record = match std::fs::read(&path).map_err(|e| e.to_string()).and_then(|blob| decrypt(&cipher, &blob)) {
+            Ok(record) => record,
+            Err(e) => {
+                debug!("Skipping sync file {}: {e}", path.display());
+                continue;
+            }
+        };
+        if memory.known.get(&record.hash).is_some_and(|known| *known >= record.stamp) {
+            continue;
+        }
+        memory.known.insert(record.hash, record.stamp);
+        match snapshot.local.get(&record.hash) {
+            Some((stamp, _)) if *stamp >= record.stamp => {}
+            Some((_, id)) => imports.push(Import::Restamp { id: *id, stamp: record.stamp }),
+            None => {
+                debug!("Importing item copied on {}", record.device);
+                imports.push(Import::New { stamp: record.stamp, device: record.device, mime_data: record.mime_data });
+            }
+        }
+    }
+    Ok(imports)
+}
+
+/// Write local items that are new or newer than their file, returns how many were written
+fn export_items(snapshot: &Snapshot, key: &Key, memory: &mut SyncMemory) -> Result<u32, String> {
+    let cipher = XChaCha20Poly1305::new(key);
+    let mut exported = 0;
+    for (hash, stamp, mime_data) in &snapshot.exports {
+        // A newer copy may just have been imported
+        if memory.known.get(hash).is_some_and(|known| known >= stamp) {
+            continue;
+        }
+        let record = SyncRecord { hash: *hash, stamp: *stamp, device: snapshot.config.device.clone(), mime_data: mime_data.clone() };
+        let path = snapshot.config.dir.join(format!("{}.{FILE_EXTENSION}", file_name(key, hash)));
+        write_atomic(&path, &encrypt(&cipher, &record)?)
+            .map_err(|e| format!("Cannot write {}: {e}", path.display()))?;
+        if let Ok(modified) = std::fs::metadata(&path).and_then(|meta| meta.modified()) {
+            memory.read.insert(path, modified);
+        }
+        memory.known.insert(*hash, *stamp);
+        exported += 1;
+    }
+    Ok(exported)
+}
+
+fn apply_imports(state: &Arc<Mutex<BackendState>>, memory: &Arc<Mutex<SyncMemory>>, config: SyncConfig, imports: Vec<Import>, exported: u32) {
+    let mut state = state.lock().unwrap();
+    let mut imported = 0;
+    state.batch_pushes(|state| {
+        for import in imports {
+            match import {
+                Import::New { stamp, device, mime_data } => {
+                    let hash = content_hash(&mime_data);
+                    let mime_data = mime_data.into_iter().map(|(mime, data)| (mime, payload_store::store(data.into()))).collect();
+                    if let Some(id) = state.import_synced_item(mime_data, stamp, device) {
+                        memory.lock().unwrap().hashes.insert(id, hash);
+                        imported += 1;
+                    }
+                }
+                Import::Restamp { id, stamp } => state.restamp_item(id, stamp),
+            }
+        }
+    });
+    // The first items of a daemon started with an empty history
+    state.restore_pending_selection();
+    let status = &mut state.sync_status;
+    status.enabled = true;
+    status.dir = Some(config.dir.display().to_string());
+    status.device = Some(config.device);
+    status.exported += exported;
+    status.imported += imported;
+    status.last_sync = Some(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64);
+    status.last_error = None;
+}
+
+fn set_error(state: &Arc<Mutex<BackendState>>, config: &SyncConfig, error: String) {
+    warn!("History sync failed: {error}");
+    let status = &mut state.lock().unwrap().sync_status;
+    status.enabled = true;
+    status.dir = Some(config.dir.display().to_string());
+    status.device = Some(config.device.clone());
+    status.last_error = Some(error);
+}
+
+/// Delete the oldest files beyond `MAX_FILES`. Every device prunes, so the folder stays bounded
+/// even while some of them are offline.
+fn prune_files(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    let mut files: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == FILE_EXTENSION))
+        .filter_map(|path| Some((std::fs::metadata(&path).and_then(|meta| meta.modified()).ok()?, path)))
+        .collect();
+    if files.len() <= MAX_FILES {
+        return;
+    }
+    files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    for (_, path) in files.drain(MAX_FILES..) {
+        if let Err(e) = std::fs::remove_file(&path) {
+            debug!("Cannot remove old sync file {}: {e}", path.display());
+        }
+    }
+}
+
+/// Hash of the payloads independent of the order in which the MIME types were offered
+pub fn content_hash(mime_data: &IndexMap<String, Bytes>) -> ContentHash {
+    let mut entries: Vec<_> = mime_data.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    let mut hasher = Sha256::new();
+    for (mime, data) in entries {
+        hasher.update((mime.len() as u64).to_le_bytes());
+        hasher.update(mime.as_bytes());
+        hasher.update((data.len() as u64).to_le_bytes());
+        hasher.update(data);
+    }
+    hasher.finalize().into()
+}
+
+/// Name of the sync file of some content: HMAC-SHA256 of its hash keyed with the sync key, so
+/// only devices with the key can tell which content a file holds
+fn file_name(key: &Key, hash: &ContentHash) -> String {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(hash);
+    hex(&mac.finalize().into_bytes())
+}
+
+fn encrypt(cipher: &XChaCha20Poly1305, record: &SyncRecord) -> Result<Vec<u8>, String> {
+    let plaintext = rmp_serde::to_vec(record).map_err(|e| e.to_string())?;
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, plaintext.as_slice()).map_err(|_| "Encryption failed".to_string())?;
+    let mut blob = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+    blob.extend_from_slice(MAGIC);
+    blob.extend_from_slice(&nonce);
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+}
+
+fn decrypt(cipher: &XChaCha20Poly1305, blob: &[u8]) -> Result<SyncRecord, String> {
+    let body = blob.strip_prefix(MAGIC).ok_or("Not a sync file")?;
+    if body.len() < NONCE_LEN {
+        return Err("Truncated sync file".to_string());
+    }
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    let plaintext = cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Wrong key or corrupted file".to_string())?;
+    rmp_serde::from_slice(&plaintext).map_err(|e| e.to_string())
+}
+
+/// Write to a temporary file first, so other devices never pick up half written files
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// New random key, hex encoded
+pub fn generate_key() -> String {
+    hex(&XChaCha20Poly1305::generate_key(&mut OsRng))
+}
+
+pub fn parse_key(text: &str) -> Result<Key, String> {
+    let text = text.trim();
+    if text.len() != 64 || !text.is_ascii() {
+        return Err("Sync key must be 64 hex digits".to_string());
+    }
+    let bytes = (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| "Sync key must be 64 hex digits".to_string())?;
+    Ok(*Key::from_slice(&bytes))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    fn text(content: &str) -> IndexMap<String, Bytes> {
+        IndexMap::from([("text/plain;charset=utf-8".to_string(), Bytes::copy_from_slice(content.as_bytes()))])
+    }
+
+    fn stamp(millis: u64) -> EventStamp {
+        EventStamp { millis, seq: 0 }
+    }
+
+    fn record(content: &str, millis: u64) -> SyncRecord {
+        let mime_data = text(content);
+        SyncRecord { hash: content_hash(&mime_data), stamp: stamp(millis), device: "laptop".to_string(), mime_data }
+    }
+
+    /// Empty sync folder of a test, removed again when dropped
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("cursor-clip-sync-test-{}-{name}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn snapshot(&self, local: HashMap<ContentHash, (EventStamp, u64)>) -> Snapshot {
+            let config = SyncConfig { dir: self.0.clone(), device: "desktop".to_string(), key: KEY.to_string() };
+            Snapshot { config, exports: Vec::new(), local }
+        }
+
+        fn write(&self, key: &Key, record: &SyncRecord) -> PathBuf {
+            let path = self.0.join(format!("{}.{FILE_EXTENSION}", file_name(key, &record.hash)));
+            write_atomic(&path, &encrypt(&XChaCha20Poly1305::new(key), record).unwrap()).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn records_round_trip_only_with_their_key() {
+        let cipher = XChaCha20Poly1305::new(&parse_key(KEY).unwrap());
+        let blob = encrypt(&cipher, &record("hello", 5)).unwrap();
+        assert!(blob.starts_with(MAGIC));
+        assert!(!blob.windows(5).any(|window| window == b"hello"));
+        let decrypted = decrypt(&cipher, &blob).unwrap();
+        assert_eq!(decrypted.mime_data, text("hello"));
+        assert_eq!(decrypted.stamp, stamp(5));
+        assert_eq!(decrypted.device, "laptop");
+
+        let other = XChaCha20Poly1305::new(&parse_key(&generate_key()).unwrap());
+        assert!(decrypt(&other, &blob).is_err());
+        let mut tampered = blob.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(decrypt(&cipher, &tampered).is_err());
+        assert!(decrypt(&cipher, &blob[..MAGIC.len() + NONCE_LEN - 1]).is_err());
+        assert!(decrypt(&cipher, b"not a sync file").is_err());
+    }
+
+    #[test]
+    fn keys_are_64_hex_digits() {
+        assert_eq!(parse_key(&format!("  {KEY}\n")).unwrap().as_slice(), (0..32).collect::<Vec<u8>>());
+        assert_eq!(parse_key(&KEY.to_uppercase()).unwrap(), parse_key(KEY).unwrap());
+        assert!(parse_key(&generate_key()).is_ok());
+        assert!(parse_key(&KEY[2..]).is_err());
+        assert!(parse_key(&format!("{KEY}00")).is_err());
+        assert!(parse_key(&format!("zz{}", &KEY[2..])).is_err());
+        assert!(parse_key(&format!("é{}", &KEY[3..])).is_err());
+    }
+
+    #[test]
+    fn file_names_do_not_reveal_the_content_hash() {
+        let key = parse_key(KEY).unwrap();
+        let hash = content_hash(&text("hello"));
+        let name = file_name(&key, &hash);
+        assert_eq!(name.len(), 64);
+        assert_ne!(name, hex(&hash));
+        assert_eq!(name, file_name(&key, &hash));
+        assert_ne!(name, file_name(&parse_key(&generate_key()).unwrap(), &hash));
+    }
+
+    #[test]
+    fn import_reads_new_and_changed_files_once() {
+        let dir = TestDir::new("import");
+        let key = parse_key(KEY).unwrap();
+        let newer = record("copied on the laptop", 20);
+        let older = record("also copied here", 10);
+        dir.write(&key, &newer);
+        let older_path = dir.write(&key, &older);
+        std::fs::write(dir.0.join(format!("junk.{FILE_EXTENSION}")), b"junk").unwrap();
+        std::fs::write(dir.0.join("notes.txt"), b"ignored").unwrap();
+        // The second item is in the local history already, copied later than on the laptop
+        let snapshot = dir.snapshot(HashMap::from([(older.hash, (stamp(15), 7))]));
+
+        let mut memory = SyncMemory::default();
+        let imports = import_files(&snapshot, &key, &mut memory).unwrap();
+        assert!(matches!(imports.as_slice(), [Import::New { stamp: s, device, mime_data }] if *s == stamp(20) && device == "laptop" && *mime_data == newer.mime_data));
+        assert_eq!(memory.read.len(), 3);
+
+        // Unchanged files are not read again
+        assert!(import_files(&snapshot, &key, &mut memory).unwrap().is_empty());
+
+        // Copied again on the laptop, after the local copy
+        dir.write(&key, &record("also copied here", 30));
+        let imports = import_files(&snapshot, &key, &mut memory).unwrap();
+        assert!(matches!(imports.as_slice(), [Import::Restamp { id: 7, stamp: s }] if *s == stamp(30)));
+
+        // Files removed from the folder are forgotten
+        std::fs::remove_file(&older_path).unwrap();
+        import_files(&snapshot, &key, &mut memory).unwrap();
+        assert_eq!(memory.read.len(), 2);
+        assert!(!memory.read.contains_key(&older_path));
+    }
+
+    #[test]
+    fn files_of_another_key_are_skipped() {
+        let dir = TestDir::new("other-key");
+        let other = parse_key(&generate_key()).unwrap();
+        dir.write(&other, &record("secret of another folder", 1));
+        let mut memory = SyncMemory::default();
+        let imports = import_files(&dir.snapshot(HashMap::new()), &parse_key(KEY).unwrap(), &mut memory).unwrap();
+        assert!(imports.is_empty());
+    }
+}
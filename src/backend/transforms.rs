@@ -0,0 +1,407 @@
+// Built-in text transforms applied to history items (see `FrontendMessage::TransformItem`)
+
+use bytes::Bytes;
+use indexmap::IndexMap;
+use crate::shared::{ClipboardItem, Config, TextTransform, ORIGINAL_MIME_PREFIX};
+use crate::shared::config::NormalizeConfig;
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// MIME types of a text payload, in order of preference
+const TEXT_MIME_TYPES: [&str; 3] = ["text/plain;charset=utf-8", "UTF8_STRING", "text/plain"];
+
+/// Text of an item, None for items without a text representation (e.g. images)
+pub fn item_text(item: &ClipboardItem) -> Option<&str> {
+    TEXT_MIME_TYPES
+        .iter()
+        .filter_map(|mime| item.mime_data.get(*mime))
+        .find_map(|bytes| std::str::from_utf8(bytes).ok())
+}
+
+/// Run `text` through `op`, errors describe why the text does not fit the transform
+pub fn apply(op: TextTransform, text: &str, config: &Config) -> Result<String, String> {
+    match op {
+        TextTransform::Uppercase => Ok(text.to_uppercase()),
+        TextTransform::Lowercase => Ok(text.to_lowercase()),
+        TextTransform::Trim => Ok(text.trim().to_string()),
+        TextTransform::Base64Encode => Ok(base64_encode(text.as_bytes())),
+        TextTransform::Base64Decode => {
+            let bytes = base64_decode(text.trim())?;
+            String::from_utf8(bytes).map_err(|_| "Decoded data is not UTF-8 text".to_string())
+        }
+        TextTransform::JsonPretty => {
+            let value: serde_json::Value = serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {e}"))?;
+            serde_json::to_string_pretty(&value).map_err(|e| e.to_string())
+        }
+        TextTransform::XmlPretty => xml_pretty(text),
+        TextTransform::UrlDecode => url_decode(text),
+        TextTransform::CleanUrl => clean_url(text.trim(), &config.tracking_params).ok_or_else(|| "Text is not a link".to_string()),
+    }
+}
+
+/// Remove the query parameters matching `tracking_params` from an http(s) link, None if `url`
+/// is not a single link
+pub fn clean_url(url: &str, tracking_params: &[String]) -> Option<String> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) || url.contains(char::is_whitespace) {
+        return None;
+    }
+    let (without_fragment, fragment) = url.split_once('#').map_or((url, None), |(url, fragment)| (url, Some(fragment)));
+    let Some((base, query)) = without_fragment.split_once('?') else { return Some(url.to_string()) };
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|pair| {
+            let key = pair.split('=').next().unwrap_or_default();
+            !pair.is_empty() && !tracking_params.iter().any(|param| param_matches(param, key))
+        })
+        .collect();
+
+    let mut cleaned = base.to_string();
+    if !kept.is_empty() {
+        cleaned.push('?');
+        cleaned.push_str(&kept.join("&"));
+    }
+    if let Some(fragment) = fragment {
+        cleaned.push('#');
+        cleaned.push_str(fragment);
+    }
+    Some(cleaned)
+}
+
+fn param_matches(param: &str, key: &str) -> bool {
+    match param.strip_suffix('*') {
+        Some(prefix) => key.to_ascii_lowercase().starts_with(&prefix.to_ascii_lowercase()),
+        None => key.eq_ignore_ascii_case(param),
+    }
+}
+
+/// Clean the text payloads of a new item that are a single link, returns whether any changed
+pub fn clean_url_payloads(mime_data: &mut IndexMap<String, Bytes>, tracking_params: &[String]) -> bool {
+    let mut changed = false;
+    for mime in TEXT_MIME_TYPES {
+        let Some(payload) = mime_data.get_mut(mime) else { continue };
+        let Ok(text) = std::str::from_utf8(payload) else { continue };
+        if let Some(cleaned) = clean_url(text.trim(), tracking_params)
+            && cleaned != text.trim()
+        {
+            *payload = Bytes::from(cleaned);
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Apply the `[normalize]` clean-ups to the text payloads of a new item, keeping every changed
+/// payload as copied under `original/<mime>`. Returns whether any changed.
+pub fn normalize_text_payloads(mime_data: &mut IndexMap<String, Bytes>, normalize: &NormalizeConfig) -> bool {
+    let mut originals = Vec::new();
+    for mime in TEXT_MIME_TYPES {
+        let Some(payload) = mime_data.get_mut(mime) else { continue };
+        let Ok(text) = std::str::from_utf8(payload) else { continue };
+        let normalized = normalize_text(text, normalize);
+        if normalized != text {
+            let original = std::mem::replace(payload, Bytes::from(normalized));
+            originals.push((format!("{ORIGINAL_MIME_PREFIX}{mime}"), original));
+        }
+    }
+    let changed = !originals.is_empty();
+    mime_data.extend(originals);
+    changed
+}
+
+fn normalize_text(text: &str, normalize: &NormalizeConfig) -> String {
+    let mut text = if normalize.crlf { text.replace("\r\n", "\n") } else { text.to_string() };
+    if let Some(width) = normalize.tab_width {
+        text = text.replace('\t', &" ".repeat(width as usize));
+    }
+    if normalize.trim_trailing {
+        text.truncate(text.trim_end().len());
+    }
+    text
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &b)| bits | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode standard or URL-safe base64, padding and whitespace are optional
+fn base64_decode(text: &str) -> Result<Vec<u8>, String> {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let data = text.trim_end_matches('=');
+    let padding = text.len() - data.len();
+    if padding > 2 || (padding > 0 && !text.len().is_multiple_of(4)) {
+        return Err("Invalid base64 padding".to_string());
+    }
+    if data.len() % 4 == 1 {
+        return Err("Truncated base64".to_string());
+    }
+
+    let mut out = Vec::with_capacity(data.len() / 4 * 3);
+    let (mut bits, mut bit_count) = (0u32, 0);
+    for c in data.chars() {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            '+' | '-' => 62,
+            '/' | '_' => 63,
+            _ => return Err(format!("Invalid base64 character '{c}'")),
+        };
+        bits = bits << 6 | value;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            out.push((bits >> bit_count) as u8);
+            bits &= (1 << bit_count) - 1;
+        }
+    }
+    // Canonical base64 leaves the bits after the last byte zero
+    if bits != 0 {
+        return Err("Invalid trailing bits in base64".to_string());
+    }
+    Ok(out)
+}
+
+/// Decode %XX escapes, and '+' as space in the query part of a link
+fn url_decode(text: &str) -> Result<String, String> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut in_query = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = bytes.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+                let byte = hex
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| format!("Invalid escape at position {i}"))?;
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+            b'+' if in_query => out.push(b' '),
+            b => {
+                match b {
+                    b'?' => in_query = true,
+                    b'#' => in_query = false,
+                    _ => {}
+                }
+                out.push(b);
+            }
+        }
+        i += 1;
+    }
+    String::from_utf8(out).map_err(|_| "Decoded text is not UTF-8".to_string())
+}
+
+/// Put every tag on its own line, indented by nesting depth. Text between tags stays on the
+/// line of its opening tag; comments, declarations and CDATA are kept as they are.
+fn xml_pretty(text: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len() * 2);
+    let mut depth = 0usize;
+    let mut rest = text.trim();
+    if !rest.starts_with('<') {
+        return Err("Text does not start with a tag".to_string());
+    }
+
+    while let Some(start) = rest.find('<') {
+        let content = rest[..start].trim();
+        if !content.is_empty() {
+            out.push_str(content);
+        }
+        let tag_len = rest[start..].find('>').ok_or("Unclosed tag")? + 1;
+        let tag = &rest[start..start + tag_len];
+        rest = &rest[start + tag_len..];
+
+        if tag.starts_with("</") {
+            depth = depth.checked_sub(1).ok_or_else(|| format!("Unexpected closing tag {tag}"))?;
+            // Keep <a>text</a> on one line
+            if content.is_empty() {
+                push_line(&mut out, depth);
+            }
+            out.push_str(tag);
+        } else {
+            push_line(&mut out, depth);
+            out.push_str(tag);
+            let opens_element = !tag.ends_with("/>") && !tag.starts_with("<?") && !tag.starts_with("<!");
+            if opens_element {
+                depth += 1;
+            }
+        }
+    }
+    if !rest.trim().is_empty() {
+        return Err("Trailing text after the last tag".to_string());
+    }
+    Ok(out.trim_start().to_string())
+}
+
+fn push_line(out: &mut String, depth: usize) {
+    out.push('\n');
+    out.push_str(&"  ".repeat(depth));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::backend_state::BackendState;
+
+    #[test]
+    fn base64_round_trips() {
+        for data in [&b""[..], b"f", b"fo", b"foo", b"foob", b"fooba", b"foobar", &[0, 0xff, 0x80, 0x7f]] {
+            let encoded = base64_encode(data);
+            assert_eq!(encoded.len() % 4, 0);
+            assert_eq!(base64_decode(&encoded).unwrap(), data, "{encoded}");
+        }
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+    }
+
+    #[test]
+    fn base64_decode_accepts_url_safe_unpadded_and_wrapped() {
+        assert_eq!(base64_decode("Zm8").unwrap(), b"fo");
+        assert_eq!(base64_decode("Zm9v\nYmFy\n").unwrap(), b"foobar");
+        assert_eq!(base64_decode("-_8=").unwrap(), [0xfb, 0xff]);
+    }
+
+    #[test]
+    fn base64_decode_rejects_malformed_input() {
+        // One character holds only 6 bits, not a byte
+        assert!(base64_decode("Zm9vY").is_err());
+        // "Zm9=" sets bits after the last byte
+        assert!(base64_decode("Zm9=").is_err());
+        assert!(base64_decode("Zg==").is_ok());
+        assert!(base64_decode("Zh==").is_err());
+        assert!(base64_decode("Zg===").is_err());
+        assert!(base64_decode("Z=g=").is_err());
+        assert!(base64_decode("Zm9v!").is_err());
+    }
+
+    #[test]
+    fn url_decode_turns_plus_into_space_only_in_the_query() {
+        assert_eq!(url_decode("https://example.com/a+b?q=c+d%26e#f+g").unwrap(), "https://example.com/a+b?q=c d&e#f+g");
+        assert_eq!(url_decode("1+1%3D2").unwrap(), "1+1=2");
+        assert_eq!(url_decode("caf%C3%A9").unwrap(), "café");
+    }
+
+    #[test]
+    fn url_decode_rejects_bad_escapes() {
+        assert!(url_decode("100%").is_err());
+        assert!(url_decode("%zz").is_err());
+        assert!(url_decode("%ff").is_err());
+    }
+
+    #[test]
+    fn xml_pretty_indents_by_depth() {
+        let pretty = xml_pretty("<?xml version=\"1.0\"?><a><b>text</b><c/><!-- note --></a>").unwrap();
+        assert_eq!(pretty, "<?xml version=\"1.0\"?>\n<a>\n  <b>text</b>\n  <c/>\n  <!-- note -->\n</a>");
+        // Already pretty input comes out the same
+        assert_eq!(xml_pretty(&pretty).unwrap(), pretty);
+    }
+
+    #[test]
+    fn xml_pretty_rejects_what_is_not_xml() {
+        assert!(xml_pretty("plain text").is_err());
+        assert!(xml_pretty("<a></a></a>").is_err());
+        assert!(xml_pretty("<a").is_err());
+        assert!(xml_pretty("<a></a> trailing").is_err());
+    }
+
+    fn params(params: &[&str]) -> Vec<String> {
+        params.iter().map(|param| param.to_string()).collect()
+    }
+
+    #[test]
+    fn clean_url_strips_tracking_parameters_by_name_and_prefix() {
+        let tracking = params(&["utm_*", "fbclid"]);
+        assert_eq!(
+            clean_url("https://example.com/a?id=7&utm_source=news&utm_medium=mail&fbclid=x", &tracking).unwrap(),
+            "https://example.com/a?id=7"
+        );
+        // Keys match without regard to case, also for prefixes
+        assert_eq!(clean_url("https://example.com/?UTM_Source=a&FBCLID=b&q=c", &tracking).unwrap(), "https://example.com/?q=c");
+        assert_eq!(clean_url("https://example.com/?utmost=1", &tracking).unwrap(), "https://example.com/?utmost=1");
+    }
+
+    #[test]
+    fn clean_url_keeps_the_fragment_and_drops_an_empty_query() {
+        let tracking = params(&["utm_*"]);
+        assert_eq!(clean_url("https://example.com/page?utm_source=a#section", &tracking).unwrap(), "https://example.com/page#section");
+        assert_eq!(clean_url("http://example.com/?utm_source=a&", &tracking).unwrap(), "http://example.com/");
+        assert_eq!(clean_url("https://example.com/page#top", &tracking).unwrap(), "https://example.com/page#top");
+    }
+
+    #[test]
+    fn clean_url_leaves_what_is_not_a_link() {
+        let tracking = params(&["utm_*"]);
+        assert_eq!(clean_url("example.com/?utm_source=a", &tracking), None);
+        assert_eq!(clean_url("see https://example.com/?utm_source=a", &tracking), None);
+        assert_eq!(clean_url("ftp://example.com/?utm_source=a", &tracking), None);
+
+        let mut mime_data = IndexMap::from([
+            ("text/plain;charset=utf-8".to_string(), Bytes::from_static(b"https://example.com/?utm_source=a&id=1\n")),
+            ("text/html".to_string(), Bytes::from_static(b"<a href=\"https://example.com/?utm_source=a\">link</a>")),
+        ]);
+        assert!(clean_url_payloads(&mut mime_data, &tracking));
+        assert_eq!(mime_data["text/plain;charset=utf-8"], "https://example.com/?id=1");
+        assert_eq!(mime_data["text/html"], "<a href=\"https://example.com/?utm_source=a\">link</a>");
+        assert!(!clean_url_payloads(&mut mime_data, &tracking));
+    }
+
+    fn normalize(trim_trailing: bool, crlf: bool, tab_width: Option<u32>) -> NormalizeConfig {
+        NormalizeConfig { trim_trailing, crlf, tab_width }
+    }
+
+    #[test]
+    fn normalize_text_applies_only_the_enabled_clean_ups() {
+        let text = "a\tb  \r\nc \r\n\n";
+        assert_eq!(normalize_text(text, &NormalizeConfig::default()), text);
+        assert_eq!(normalize_text(text, &normalize(false, true, None)), "a\tb  \nc \n\n");
+        assert_eq!(normalize_text(text, &normalize(false, false, Some(4))), "a    b  \r\nc \r\n\n");
+        assert_eq!(normalize_text(text, &normalize(true, false, None)), "a\tb  \r\nc");
+        assert_eq!(normalize_text(text, &normalize(true, true, Some(2))), "a  b  \nc");
+        // Whitespace inside lines stays
+        assert_eq!(normalize_text("  indented\n", &normalize(true, true, None)), "  indented");
+    }
+
+    #[test]
+    fn normalized_payloads_keep_the_text_as_copied() {
+        let config = normalize(true, true, None);
+        let mut mime_data = IndexMap::from([
+            ("text/plain;charset=utf-8".to_string(), Bytes::from_static(b"line\r\n")),
+            ("text/html".to_string(), Bytes::from_static(b"<p>line</p>\r\n")),
+        ]);
+        assert!(normalize_text_payloads(&mut mime_data, &config));
+        assert_eq!(mime_data["text/plain;charset=utf-8"], "line");
+        assert_eq!(mime_data["original/text/plain;charset=utf-8"], "line\r\n");
+        assert_eq!(mime_data["text/html"], "<p>line</p>\r\n");
+        assert!(!mime_data.contains_key("original/text/html"));
+
+        let mut clean = IndexMap::from([("text/plain".to_string(), Bytes::from_static(b"line"))]);
+        assert!(!normalize_text_payloads(&mut clean, &config));
+        assert_eq!(clean.len(), 1);
+    }
+
+    #[test]
+    fn items_serve_the_original_text_on_request() {
+        let mut state = BackendState::new();
+        state.config.normalize = normalize(true, false, None);
+        let mime_data = IndexMap::from([("text/plain;charset=utf-8".to_string(), Bytes::from_static(b"copied  \n"))]);
+        state.add_clipboard_item_from_mime_map(mime_data, Vec::new(), None).unwrap();
+
+        let item = &state.history[0];
+        assert!(item.has_original());
+        assert_eq!(item.payload("text/plain;charset=utf-8", false).unwrap(), "copied");
+        assert_eq!(item.payload("text/plain;charset=utf-8", true).unwrap(), "copied  \n");
+        assert_eq!(item.payload("text/html", true), None);
+    }
+}
@@ -1,5 +1,5 @@
 use std::sync::{Arc, Mutex};
-use wayland_client::protocol::wl_seat::WlSeat;
+use wayland_client::protocol::wl_seat::{self, WlSeat};
 use wayland_client::{delegate_noop, Connection, Dispatch, EventQueue, Proxy, QueueHandle};
 use wayland_client::globals::{GlobalList, GlobalListContents, registry_queue_init};
 use wayland_client::protocol::{wl_registry};
@@ -10,12 +10,64 @@ use wayland_protocols_wlr::data_control::v1::client::{
     zwlr_data_control_source_v1::{self, ZwlrDataControlSourceV1},
 };
 use std::sync::Arc as StdArc;
+use std::time::Instant;
 
-use crate::backend::backend_state::{BackendState, DataControlProtocol};
+use crate::backend::backend_state::{BackendState, DataControlProtocol, DeferredOffer, SeatClipboard, OWNER_MARKER_MIME};
 use crate::backend::ext_data_control;
+use wayland_protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1;
+use crate::backend::{payload_check, payload_store, selection_send};
+use crate::shared::{BackendMessage, MonitorStatus};
 use indexmap::IndexMap;
 use bytes::Bytes;
-use log::{info, debug, warn, error};
+use tracing::{info, debug, debug_span, warn};
+
+/// Why clipboard monitoring could not start or stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MonitorError {
+    /// No Wayland compositor to connect to (e.g. WAYLAND_DISPLAY unset, compositor not running)
+    Connect(String),
+    /// The global registry could not be read
+    Registry(String),
+    /// The compositor exposes no wl_seat
+    NoSeat,
+    /// Neither zwlr_data_control_manager_v1 nor ext_data_control_manager_v1 is available
+    NoDataControl,
+    /// The connection broke while waiting for events
+    Dispatch(String),
+}
+
+impl MonitorError {
+    /// The compositor is reachable but cannot be used by cursor-clip
+    pub const fn is_unsupported(&self) -> bool {
+        matches!(self, Self::NoSeat | Self::NoDataControl)
+    }
+
+    /// Status reported to clients for this error
+    pub fn status(&self) -> MonitorStatus {
+        if self.is_unsupported() {
+            MonitorStatus::Unsupported { reason: self.to_string() }
+        } else {
+            MonitorStatus::Disconnected { reason: self.to_string() }
+        }
+    }
+}
+
+impl std::fmt::Display for MonitorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connect(e) => write!(f, "failed to connect to Wayland: {e}"),
+            Self::Registry(e) => write!(f, "failed to init registry: {e}"),
+            Self::NoSeat => f.write_str("the compositor exposes no input seat (wl_seat), which is required to create a data device for clipboard access"),
+            Self::NoDataControl => f.write_str(
+                "no data control protocol available (tried 'zwlr_data_control_manager_v1' and 'ext_data_control_manager_v1'), \
+                 the compositor likely does not support clipboard access via these protocols",
+            ),
+            Self::Dispatch(e) => write!(f, "failed to dispatch events: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for MonitorError {}
 
 // Wrapper struct that holds the shared backend state for dispatch implementations
 pub struct MutexBackendState {
@@ -31,13 +83,16 @@ impl WaylandClipboardMonitor {
         Self { backend_state }
     }
 
-    pub fn start_monitoring(&self) -> Result<(), String> {
+    /// Connect to the compositor and record selections until the connection fails.
+    /// Once connected, the most recent item becomes the selection of seats that have none
+    /// (see `BackendState::restore_selection`), so it survives daemon and compositor restarts.
+    pub fn start_monitoring(&self) -> Result<(), MonitorError> {
         // Establish Wayland connection
         let connection = Connection::connect_to_env()
-            .map_err(|e| format!("Failed to connect to Wayland: {e}"))?;
+            .map_err(|e| MonitorError::Connect(e.to_string()))?;
         let (globals, mut event_queue): (GlobalList, EventQueue<MutexBackendState>) =
             registry_queue_init::<MutexBackendState>(&connection)
-                .map_err(|e| format!("Failed to init registry: {e}"))?;
+                .map_err(|e| MonitorError::Registry(e.to_string()))?;
 
         // Create wrapper for shared state
         let mut shared_state_wrapper = MutexBackendState { backend_state: self.backend_state.clone() };
@@ -51,16 +106,20 @@ impl WaylandClipboardMonitor {
             state.connection = Some(connection);
         }
 
-        // Bind seat
-        if let Ok(seat) = globals.bind::<wayland_client::protocol::wl_seat::WlSeat, _, _>(&qh, 1..=9, ()) {
+        // Bind every seat, each has its own selection. Seats appearing later are bound by the
+        // registry handler below.
+        let seats: Vec<(u32, u32)> = globals.contents().with_list(|list| {
+            list.iter().filter(|global| global.interface == WlSeat::interface().name).map(|global| (global.name, global.version)).collect()
+        });
+        if seats.is_empty() {
+            return Err(MonitorError::NoSeat);
+        }
+        {
             let mut state = self.backend_state.lock().unwrap();
-            state.seat = Some(seat);
-        } else {
-            let msg = "Critical Wayland interface 'wl_seat' is not available. \
-            Your current compositor/session did not expose an input seat, which is required to create a data device for clipboard access. \
-            Clipboard monitoring cannot start, exiting.";
-            error!("{msg}");
-            std::process::exit(1);
+            for (name, version) in seats {
+                let seat = globals.registry().bind::<WlSeat, _, _>(name, version.min(9), &qh, name);
+                state.add_seat(SeatClipboard::new(name, seat));
+            }
         }
 
         // Try wlroots data control manager first
@@ -78,55 +137,69 @@ impl WaylandClipboardMonitor {
             // Use ext (standard) protocol
             self.bind_ext_protocol(&globals, &qh)?;
         } else {
-            let msg = "No supported data control protocol available. \
-            Tried both 'zwlr_data_control_manager_v1' (wlroots) and 'ext_data_control_manager_v1' (standard). \
-            Your compositor likely does not support clipboard access via these protocols. \
-            Clipboard monitoring cannot function without it, exiting.";
-            error!("{msg}");
-            std::process::exit(1);
+            return Err(MonitorError::NoDataControl);
+        }
+
+        // The focused window tells which app a copy came from, incognito rules match it too
+        {
+            let mut state = self.backend_state.lock().unwrap();
+            state.toplevel_tracking = globals.bind::<ZwlrForeignToplevelManagerV1, _, _>(&qh, 1..=3, ()).is_ok();
+            if state.toplevel_tracking {
+                info!("Tracking focused windows for source apps and incognito rules");
+            } else if !state.incognito.is_empty() {
+                warn!("Incognito rules are configured, but the compositor does not support zwlr_foreign_toplevel_manager_v1");
+            } else {
+                info!("The compositor does not support zwlr_foreign_toplevel_manager_v1, the app a copy came from is unknown");
+            }
         }
 
         info!("Wayland clipboard monitor initialized, monitoring changes...");
+        // Seat names and current selections arrive with the first events, a seat that already
+        // has a selection keeps it
+        event_queue.roundtrip(&mut shared_state_wrapper).map_err(|e| MonitorError::Dispatch(e.to_string()))?;
+        {
+            let mut state = self.backend_state.lock().unwrap();
+            state.restore_selection();
+            state.set_monitor_status(MonitorStatus::Active);
+        }
 
         loop {
             // Dispatch pending events, then block waiting for new ones
             event_queue.blocking_dispatch(&mut shared_state_wrapper)
-                .map_err(|e| format!("Failed to dispatch events: {e}"))?;
+                .map_err(|e| MonitorError::Dispatch(e.to_string()))?;
         }
     }
 
-    fn bind_wlr_protocol(&self, globals: &GlobalList, qh: &QueueHandle<MutexBackendState>) -> Result<(), String> {
+    fn bind_wlr_protocol(&self, globals: &GlobalList, qh: &QueueHandle<MutexBackendState>) -> Result<(), MonitorError> {
         let data_control_manager = globals
             .bind::<ZwlrDataControlManagerV1, _, _>(qh, 2..=2, ())
-            .map_err(|_| "Failed to bind wlroots data control manager".to_string())?;
+            .map_err(|_| MonitorError::NoDataControl)?;
 
         let mut state = self.backend_state.lock().unwrap();
         state.active_protocol = Some(DataControlProtocol::Wlr);
         state.data_control_manager = Some(data_control_manager.clone());
 
-        // Create device now that we have seat
-        if let Some(seat) = &state.seat {
-            let device = data_control_manager.get_data_device(seat, qh, ());
-            state.data_control_device = Some(device);
+        // Create a device per seat now that we have the manager
+        for seat in &mut state.seats {
+            seat.data_control_device = Some(data_control_manager.get_data_device(&seat.seat, qh, seat.global));
         }
 
         info!("Using wlroots data control protocol (zwlr_data_control_manager_v1)");
         Ok(())
     }
 
-    fn bind_ext_protocol(&self, globals: &GlobalList, qh: &QueueHandle<MutexBackendState>) -> Result<(), String> {
+    fn bind_ext_protocol(&self, globals: &GlobalList, qh: &QueueHandle<MutexBackendState>) -> Result<(), MonitorError> {
         let data_control_manager = globals
             .bind::<ext_data_control::ExtDataControlManagerV1, _, _>(qh, 1..=1, ())
-            .map_err(|_| "Failed to bind ext data control manager".to_string())?;
+            .map_err(|_| MonitorError::NoDataControl)?;
 
         let mut state = self.backend_state.lock().unwrap();
         state.active_protocol = Some(DataControlProtocol::Ext);
         state.ext_data_control_manager = Some(data_control_manager.clone());
 
-        // Create device now that we have seat
-        if let Some(seat) = &state.seat {
-            let device = data_control_manager.get_data_device(seat, qh, ());
-            state.ext_data_control_device = Some(device);
+        // Create a device per seat now that we have the manager
+        for seat in &mut state.seats {
+            seat.ext_data_control_device = Some(data_control_manager.get_data_device(&seat.seat, qh, seat.global));
         }
 
         info!("Using standard data control protocol (ext_data_control_manager_v1)");
@@ -137,47 +210,45 @@ impl WaylandClipboardMonitor {
 impl Drop for WaylandClipboardMonitor {
     fn drop(&mut self) {
         if let Ok(mut state) = self.backend_state.lock() {
-            // Clean up wlroots objects
-            if let Some(dev) = state.data_control_device.take() {
-                dev.destroy();
-            }
-            if let Some(src) = state.current_source_object.take() {
-                src.destroy();
+            // Clean up the devices and sources of every seat, then the managers
+            for seat in state.seats.drain(..) {
+                seat.destroy();
             }
             if let Some(mgr) = state.data_control_manager.take() {
                 mgr.destroy();
             }
-
-            // Clean up ext objects
-            if let Some(dev) = state.ext_data_control_device.take() {
-                dev.destroy();
-            }
-            if let Some(src) = state.ext_current_source_object.take() {
-                src.destroy();
-            }
             if let Some(mgr) = state.ext_data_control_manager.take() {
                 mgr.destroy();
             }
-
-            state.seat.take();
-            if let Some(conn) = &state.connection {
+            if let Some(conn) = state.connection.take() {
                 let _ = conn.flush();
             }
+
+            // Nothing of the old connection may be used by a new monitor
+            state.active_protocol = None;
+            state.qh = None;
+            state.mime_type_offers.clear();
+            state.ext_mime_type_offers.clear();
+            state.toplevels.clear();
+            state.toplevel_tracking = false;
+            state.update_incognito();
         }
     }
 }
 
 // ================= Dispatch Implementations for wlroots =================
 
-impl Dispatch<ZwlrDataControlDeviceV1, ()> for MutexBackendState {
+/// Devices carry the registry name of their seat
+impl Dispatch<ZwlrDataControlDeviceV1, u32> for MutexBackendState {
     fn event(
         wrapper: &mut Self,
         _: &ZwlrDataControlDeviceV1,
         event: zwlr_data_control_device_v1::Event,
-        (): &(),
+        seat_global: &u32,
         conn: &Connection,
         _qh: &QueueHandle<Self>,
     ) {
+        let _span = debug_span!("wayland_event", protocol = "wlr", seat = *seat_global).entered();
         let mut state = wrapper.backend_state.lock().unwrap();
 
         // Only process if we're using wlroots protocol
@@ -196,23 +267,38 @@ impl Dispatch<ZwlrDataControlDeviceV1, ()> for MutexBackendState {
                     let offer_key = offer_id.id();
                     debug!("Selection changed to offer ID: {:?}", offer_key);
 
-                    let already_current = state.current_data_offer.as_ref().is_some_and(|o| o == &offer_key);
-                    if let Some(mime_list) = state.mime_type_offers.get(&offer_key).cloned() {
-                        debug!("New clipboard content available with {} MIME types", mime_list.len());
-                        if state.suppress_next_selection_read {
-                            state.current_data_offer = Some(offer_key);
-                            debug!("Suppressed reading our own just-set selection; waiting for Cancelled to re-enable reads");
+                    let mime_list = state.mime_type_offers.get(&offer_key).cloned();
+                    let Some(seat) = state.seat_mut(*seat_global) else { return };
+                    let already_current = seat.current_data_offer.as_ref().is_some_and(|o| o == &offer_key);
+                    if let Some(mime_list) = mime_list {
+                        debug!("New clipboard content available on {} with {} MIME types", seat.name, mime_list.len());
+                        let marked_ours = mime_list.iter().any(|mime| mime == OWNER_MARKER_MIME);
+                        if !seat.selection.offered(marked_ours) {
+                            seat.current_data_offer = Some(offer_key);
+                            seat.drop_deferred_offer();
+                            debug!("Not reading our own selection ({:?})", seat.selection.state());
                             offer_id.destroy();
                         } else if !already_current {
-                            state.current_data_offer = Some(offer_key);
-                            process_all_data_formats_wlr(&offer_id, mime_list, conn, &mut state);
+                            seat.current_data_offer = Some(offer_key);
+                            seat.adopt_on_source_exit = None;
+                            seat.drop_deferred_offer();
+                            let read = process_all_data_formats_wlr(&offer_id, mime_list, conn, &mut state, *seat_global);
                             state.mime_type_offers.clear();
-                            offer_id.destroy();
+                            if read {
+                                offer_id.destroy();
+                            } else {
+                                schedule_deferred_reads(&wrapper.backend_state, &mut state);
+                            }
                         }
                     }
                 } else {
                     debug!("Selection cleared");
-                    state.current_data_offer = None;
+                    if let Some(seat) = state.seat_mut(*seat_global) {
+                        seat.current_data_offer = None;
+                        seat.drop_deferred_offer();
+                        seat.selection.lost();
+                    }
+                    state.selection_source_exited(*seat_global);
                 }
             }
             zwlr_data_control_device_v1::Event::PrimarySelection { .. } => {
@@ -249,39 +335,38 @@ impl Dispatch<ZwlrDataControlOfferV1, ()> for MutexBackendState {
             let object_id = offer.id();
             debug!("Offer event: MIME type offered: {mime_type}");
             let mut state = wrapper.backend_state.lock().unwrap();
-            if let Some(mime_list) = state.mime_type_offers.get_mut(&object_id) {
-                if !mime_type.starts_with("video") { mime_list.push(mime_type); }
-            }
+            if let Some(mime_list) = state.mime_type_offers.get_mut(&object_id)
+                && !mime_type.starts_with("video") { mime_list.push(mime_type); }
         }
     }
 }
 
-impl Dispatch<ZwlrDataControlSourceV1, ()> for MutexBackendState {
+/// Sources carry the registry name of the seat they were set on
+impl Dispatch<ZwlrDataControlSourceV1, u32> for MutexBackendState {
     fn event(
         wrapper: &mut Self,
         event_source: &ZwlrDataControlSourceV1,
         event: zwlr_data_control_source_v1::Event,
-        (): &(),
+        seat_global: &u32,
         _: &Connection,
         _: &QueueHandle<Self>,
     ) {
+        let _span = debug_span!("wayland_event", protocol = "wlr", seat = *seat_global).entered();
         let mut state = wrapper.backend_state.lock().unwrap();
 
         match event {
             zwlr_data_control_source_v1::Event::Send { mime_type, fd } => {
                 debug!("Data source Send event for MIME type: {mime_type}");
-                if let Some(item_id) = state.current_source_entry_id {
+                let source = state.seat_mut(*seat_global).and_then(|seat| Some((seat.current_source_entry_id?, seat.current_source_original)));
+                if let Some((item_id, original)) = source {
                     if let Some(item) = state.get_item_by_id(item_id) {
-                        use std::io::Write;
-                        let mut file: std::fs::File = fd.into();
-                        if let Some(bytes) = item.mime_data.get(&mime_type) {
-                            if let Err(e) = file.write_all(bytes.as_ref()) {
-                                error!(
-                                    "Failed writing selection data (id {item_id}, mime {mime_type}): {e}",
-                                );
-                            } else {
-                                debug!("Wrote {} bytes for id {item_id} (mime {mime_type})", bytes.len());
-                            }
+                        let payload = if mime_type == OWNER_MARKER_MIME {
+                            Some(Bytes::from_static(b"cursor-clip"))
+                        } else {
+                            item.payload(&mime_type, original).cloned()
+                        };
+                        if let Some(bytes) = payload {
+                            selection_send::send_payload(fd, bytes, item_id, mime_type);
                         } else {
                             warn!("No data stored for MIME {mime_type} (id {item_id}), nothing written");
                         }
@@ -294,10 +379,18 @@ impl Dispatch<ZwlrDataControlSourceV1, ()> for MutexBackendState {
             }
             zwlr_data_control_source_v1::Event::Cancelled => {
                 debug!("Data source cancelled. Last offered content (object id {:?})", event_source.id());
-                if state.current_source_object.as_ref().map(Proxy::id) == Some(event_source.id()) {
-                    state.suppress_next_selection_read = false;
-                    state.current_source_object = None;
+                let ours = state.seat_mut(*seat_global).is_some_and(|seat| {
+                    let current = seat.current_source_object.as_ref().map(Proxy::id) == Some(event_source.id());
+                    if current {
+                        seat.selection.lost();
+                        seat.current_source_object = None;
+                    }
+                    current
+                });
+                if ours {
+                    state.note_ownership_lost();
                     debug!("Re-enabled selection reading (external client took over)");
+                    state.broadcast(BackendMessage::SelectionTakenByExternal);
                 }
                 drop(state);
                 event_source.destroy();
@@ -310,23 +403,93 @@ impl Dispatch<ZwlrDataControlSourceV1, ()> for MutexBackendState {
 // ================== No-op Dispatch Implementations =================
 
 delegate_noop!(MutexBackendState: ignore ZwlrDataControlManagerV1);
-delegate_noop!(MutexBackendState: ignore WlSeat);
 
+/// Seats carry their registry name, the name event labels their items
+impl Dispatch<WlSeat, u32> for MutexBackendState {
+    fn event(
+        wrapper: &mut Self,
+        _: &WlSeat,
+        event: wl_seat::Event,
+        seat_global: &u32,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_seat::Event::Name { name } = event {
+            let mut state = wrapper.backend_state.lock().unwrap();
+            if let Some(seat) = state.seat_mut(*seat_global) {
+                info!("Monitoring the clipboard of {name}");
+                seat.name = name;
+            }
+        }
+    }
+}
+
+/// Follows seats added and removed after startup
 impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for MutexBackendState {
     fn event(
-        _state: &mut Self,
-        _proxy: &wl_registry::WlRegistry,
-        _event: wl_registry::Event,
+        wrapper: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
         _data: &GlobalListContents,
         _conn: &Connection,
-        _qhandle: &QueueHandle<MutexBackendState>,
+        qhandle: &QueueHandle<MutexBackendState>,
     ) {
-        // No-op
+        match event {
+            wl_registry::Event::Global { name, interface, version } if interface == WlSeat::interface().name => {
+                let seat = registry.bind::<WlSeat, _, _>(name, version.min(9), qhandle, name);
+                wrapper.backend_state.lock().unwrap().add_seat(SeatClipboard::new(name, seat));
+            }
+            wl_registry::Event::GlobalRemove { name } => {
+                wrapper.backend_state.lock().unwrap().remove_seat(name);
+            }
+            _ => {}
+        }
     }
 }
 
 // ================= Helper functions =================
 
+/// Read the deferred offers of the seats (see `SeatClipboard::deferred_offer`) once
+/// `max_captures_per_sec` admits selections again
+pub fn schedule_deferred_reads(shared: &Arc<Mutex<BackendState>>, state: &mut BackendState) {
+    if state.deferred_read_scheduled || state.seats.iter().all(|seat| seat.deferred_offer.is_none()) {
+        return;
+    }
+    // Without a limit (e.g. raised by a config reload meanwhile) they are read right away
+    let reopens_at = state.capture_reopens_at().unwrap_or_else(Instant::now);
+    state.deferred_read_scheduled = true;
+    let shared = shared.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(reopens_at.saturating_duration_since(Instant::now()));
+        let mut state = shared.lock().unwrap();
+        state.deferred_read_scheduled = false;
+        read_deferred_offers(&shared, &mut state);
+    });
+}
+
+fn read_deferred_offers(shared: &Arc<Mutex<BackendState>>, state: &mut BackendState) {
+    let Some(conn) = state.connection.clone() else { return };
+    let deferred: Vec<(u32, DeferredOffer)> =
+        state.seats.iter_mut().filter_map(|seat| Some((seat.global, seat.deferred_offer.take()?))).collect();
+    for (seat_global, offer) in deferred {
+        debug!("Reading the selection deferred by the capture limit");
+        let read = match &offer {
+            DeferredOffer::Wlr(data_offer, mime_types) => {
+                process_all_data_formats_wlr(data_offer, mime_types.clone(), &conn, state, seat_global)
+            }
+            DeferredOffer::Ext(data_offer, mime_types) => {
+                ext_data_control::process_all_data_formats_ext(data_offer, mime_types.clone(), &conn, state, seat_global)
+            }
+        };
+        // Otherwise deferred again, the seat keeps the offer
+        if read {
+            offer.destroy();
+        }
+    }
+    if let Err(e) = conn.flush() { warn!("Flush failed: {e}"); }
+    schedule_deferred_reads(shared, state);
+}
+
 fn create_pipes() -> Result<(std::os::fd::OwnedFd, std::os::fd::OwnedFd), Box<dyn std::error::Error>> {
     use std::os::fd::FromRawFd;
     let mut fds = [0; 2];
@@ -338,48 +501,55 @@ fn create_pipes() -> Result<(std::os::fd::OwnedFd, std::os::fd::OwnedFd), Box<dy
     Ok((reader, writer))
 }
 
+/// Record the selection of `data_offer`, returns false if it was deferred by the capture limit
+/// (the seat then keeps the offer)
 fn process_all_data_formats_wlr(
     data_offer: &ZwlrDataControlOfferV1,
     mime_types: Vec<String>,
     conn: &Connection,
     backend_state: &mut BackendState,
-) {
+    seat_global: u32,
+) -> bool {
     use std::os::fd::AsFd;
     use std::io::Read;
 
-    if mime_types.is_empty() { return; }
+    if backend_state.is_capture_paused() {
+        debug!("Capture paused, not recording the new selection");
+        return true;
+    }
+    if !backend_state.admit_capture() {
+        let Some(seat) = backend_state.seat_mut(seat_global) else { return true };
+        debug!("Too many selections per second, reading this one once the limit allows");
+        seat.defer_offer(DeferredOffer::Wlr(data_offer.clone(), mime_types));
+        return false;
+    }
+    let started = Instant::now();
+    let mime_types = backend_state.filter_manager_markers(mime_types);
+    if mime_types.is_empty() { return true; }
 
     let mut mime_map: IndexMap<String, Bytes> = IndexMap::new();
+    let mut suspect_mime_types = Vec::new();
 
     for mime in mime_types {
-        let (reader_fd, writer_fd) = match create_pipes() {
-            Ok(pair) => pair,
-            Err(err) => { warn!("Could not open pipe to read data for {mime}: {err:?}"); continue; }
+        let read = || -> Result<Vec<u8>, String> {
+            let (reader_fd, writer_fd) = create_pipes().map_err(|e| format!("could not open pipe: {e}"))?;
+            debug!("Requesting {mime} content...");
+            data_offer.receive(mime.clone(), writer_fd.as_fd());
+            drop(writer_fd);
+            if let Err(e) = conn.flush() { warn!("Flush failed: {e}"); }
+
+            let mut buf = Vec::new();
+            std::fs::File::from(reader_fd).read_to_end(&mut buf).map_err(|e| e.to_string())?;
+            Ok(buf)
         };
-        debug!("Requesting {mime} content...");
-        data_offer.receive(mime.clone(), writer_fd.as_fd());
-        drop(writer_fd);
-        if let Err(e) = conn.flush() { warn!("Flush failed: {e}"); }
-
-        let mut reader_file = std::fs::File::from(reader_fd);
-        let mut buf = Vec::new();
-        match reader_file.read_to_end(&mut buf) {
-            Ok(_) => {
-                if !buf.is_empty() { mime_map.insert(mime, Bytes::from(buf)); }
-            }
-            Err(e) => warn!("Failed reading data for mime: {e}"),
+        if let Some(payload) = payload_check::read_with_retry(&mime, read) {
+            if payload.suspicious { suspect_mime_types.push(mime.clone()); }
+            backend_state.metrics.record_read(&mime, payload.data.len());
+            mime_map.insert(mime, Bytes::from(payload.data));
         }
     }
+    payload_store::store_payloads(&mut mime_map);
 
-    if !mime_map.is_empty() {
-        if let Some(new_id) = backend_state.add_clipboard_item_from_mime_map(mime_map) {
-            if !backend_state.monitor_only && !backend_state.suppress_next_selection_read {
-                if let Err(e) = backend_state.set_clipboard_by_id(new_id) {
-                    warn!("Failed to take ownership of selection id {new_id}: {e}");
-                } else {
-                    debug!("Took ownership of external selection (id {new_id})");
-                }
-            }
-        }
-    }
+    backend_state.record_external_selection(seat_global, mime_map, suspect_mime_types, started);
+    true
 }
@@ -0,0 +1,211 @@
+//! Command line of the `cursor-clip` binary
+
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{Args, Parser, Subcommand, ValueEnum, ValueHint};
+use cursor_clip::shared::config::Placement;
+use cursor_clip::shared::{format, ClipboardContentType};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(name = "cursor-clip", version, about = "Clipboard manager with GUI overlay")]
+pub struct Cli {
+    /// Run as background daemon (same as `cursor-clip daemon`)
+    #[arg(long)]
+    pub daemon: bool,
+    #[command(flatten)]
+    pub daemon_options: DaemonOptions,
+    #[command(flatten)]
+    pub overlay_options: OverlayOptions,
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(Debug, Args)]
+pub struct DaemonOptions {
+    /// Do not take ownership of a newly received external selection; just record it. This does not automatically ensure clipboard persistence if the original application is closed. You can still paste the selection by choosing it in the GUI. If unsure, you probably want to keep the default behaviour and don't use this flag.
+    #[arg(long)]
+    pub monitor_only: bool,
+    /// Generate synthetic clipboard traffic (text, code, URLs, images, bursts) instead of monitoring the Wayland clipboard. Useful for development and demos without a compositor.
+    #[arg(long)]
+    pub simulate: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct OverlayOptions {
+    /// Show the overlay with representative fake items instead of your clipboard history, e.g. for sharing screenshots or screencasts. Pasting and clearing only affect the fake items.
+    #[arg(long)]
+    pub demo: bool,
+    /// Keep the overlay loaded in the background with its window hidden. `cursor-clip` then only asks it to show up at the cursor, which is much quicker than starting GTK for every invocation.
+    #[arg(long, conflicts_with = "placement")]
+    pub resident: bool,
+    /// Where the overlay opens: at the cursor, centered on the screen, docked to the bottom or sliding in from the right edge [default: `placement` of the config, or cursor]
+    #[arg(long, value_name = "MODE", value_parser = placement_parser())]
+    pub placement: Option<Placement>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Run the clipboard daemon recording the history (start it with your compositor)
+    Daemon(DaemonOptions),
+    /// Show the history overlay at the cursor (what `cursor-clip` without a command does)
+    Overlay(OverlayOptions),
+    /// Set an item of the history as the clipboard content
+    Copy {
+        /// Id of the item, as printed by `cursor-clip history`
+        id: u64,
+        /// Set it on the Wayland seat with this name [default: the seat it was copied on]
+        #[arg(long, value_name = "NAME")]
+        seat: Option<String>,
+    },
+    /// Write the content of an item to stdout, e.g. `cursor-clip paste > file.png`
+    Paste {
+        /// Id of the item, as printed by `cursor-clip history` [default: the newest item]
+        id: Option<u64>,
+        /// MIME type to write [default: plain text if the item has it, otherwise its first type]
+        #[arg(long, value_name = "TYPE")]
+        mime: Option<String>,
+    },
+    /// Print the clipboard history kept by the running daemon, newest first (passwords are masked)
+    History {
+        /// Print at most N items
+        #[arg(long, short = 'n', value_name = "N", default_value_t = 20, value_parser = clap::value_parser!(u32).range(1..))]
+        limit: u32,
+        /// Only items of this content type (repeatable)
+        #[arg(long = "type", short = 't', value_name = "TYPE", ignore_case = true, value_parser = content_type_parser())]
+        content_types: Vec<ClipboardContentType>,
+        /// Only items copied within this time, e.g. 30m, 2h or 7d
+        #[arg(long, value_name = "DURATION", value_parser = duration_parser)]
+        since: Option<u64>,
+        /// Only items containing TEXT (like type-to-filter in the overlay)
+        #[arg(long, short = 's', value_name = "TEXT")]
+        search: Option<String>,
+        /// Print a JSON array of the items (the same fields hooks get) instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Paste an item chosen in a dmenu-style picker: `cursor-clip pick --dmenu | fuzzel --dmenu | cursor-clip pick`
+    Pick {
+        /// Print one line per item (id, tab, preview) for rofi, fuzzel, wofi or fzf; without it, the item of the line read from stdin is pasted
+        #[arg(long)]
+        dmenu: bool,
+    },
+    /// Browse the history in the terminal with fuzzy search and paste the selected item, e.g. over SSH with waypipe
+    Tui,
+    /// Open a window with the history grouped by day and a calendar to jump to a day
+    Timeline,
+    /// Take a screenshot through the desktop portal, add it to the history and set it as the clipboard
+    Shot {
+        /// Let the user pick what to capture (a window, a region, ...) instead of the whole screen
+        #[arg(long, short = 'i')]
+        interactive: bool,
+        /// Open the overlay afterwards, with the screenshot as its newest item
+        #[arg(long)]
+        show: bool,
+    },
+    /// Paste the next item of the copy stack: the first pop after copying takes the most recent items, each pop sets the next of them (oldest first) as the clipboard
+    Pop {
+        /// Number of recent items a new stack takes [default: `copy_stack_depth` of the config, or 5]
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        depth: Option<u32>,
+    },
+    /// Print a summary of the clipboard history kept by the running daemon
+    Stats,
+    /// Print capture and IPC latencies, bytes read per MIME type and memory use of the running daemon
+    Metrics {
+        /// Print in the Prometheus text format
+        #[arg(long)]
+        prometheus: bool,
+    },
+    /// Change what the running daemon logs, e.g. `debug` or `cursor_clip::backend::wayland_clipboard=trace` (RUST_LOG syntax)
+    LogLevel {
+        #[arg(value_name = "FILTER")]
+        filter: String,
+    },
+    /// List the clients connected to the daemon (overlays, tray, scripts) with their process and the pushes they got
+    Clients,
+    /// Check that the daemon is running and recording; exits with 1 if it is unreachable, 2 if it does not record
+    Ping,
+    /// Print the version of cursor-clip and of the running daemon, which differ after an update until the daemon is restarted
+    Version,
+    /// Stop recording new clipboard selections until `cursor-clip resume`
+    Pause,
+    /// Resume recording clipboard selections
+    Resume,
+    /// Sync the clipboard history with other devices through a shared folder
+    #[command(subcommand)]
+    Sync(SyncCommand),
+    /// Exchange clipboard items with phones paired through KDE Connect or GSConnect
+    #[command(subcommand)]
+    Phone(PhoneCommand),
+    /// Show a tray icon (StatusNotifierItem) to open the history, pause capture and clear the history
+    Tray,
+    /// Serve clipboard history searches to the GNOME Shell overview (org.gnome.Shell.SearchProvider2), usually started by D-Bus activation
+    SearchProvider,
+    /// Inspect the config file
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Print a completion script for a shell, e.g. `cursor-clip completions fish > ~/.config/fish/completions/cursor-clip.fish`
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SyncCommand {
+    /// Start syncing through DIR (e.g. a Syncthing folder); items are stored end-to-end encrypted
+    Enable {
+        #[arg(value_name = "DIR", value_hint = ValueHint::DirPath)]
+        dir: PathBuf,
+        /// Key printed by `sync enable` on the first device; a new key is generated if omitted
+        #[arg(long, value_name = "HEX")]
+        key: Option<String>,
+        /// Name of this device [default: host name]
+        #[arg(long, value_name = "NAME")]
+        device: Option<String>,
+    },
+    /// Stop syncing (the shared folder is left as it is)
+    Disable,
+    /// Print the sync settings and what the running daemon synced
+    Status,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PhoneCommand {
+    /// List the reachable phones
+    List,
+    /// Add the phone's clipboard to the history (GSConnect only; KDE Connect phones share their clipboard on their own)
+    Pull {
+        /// Name or id of the phone [default: the first reachable one]
+        #[arg(value_name = "DEVICE")]
+        device: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print where the config file is read from
+    Path,
+    /// Print the effective config (the file merged with the defaults) as TOML
+    Show,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+fn placement_parser() -> impl TypedValueParser<Value = Placement> {
+    PossibleValuesParser::new(Placement::ALL.map(Placement::name)).map(|name| Placement::from_name(&name).expect("possible value"))
+}
+
+fn content_type_parser() -> impl TypedValueParser<Value = ClipboardContentType> {
+    PossibleValuesParser::new(ClipboardContentType::ALL.map(ClipboardContentType::as_str))
+        .map(|name| ClipboardContentType::from_name(&name).expect("possible value"))
+}
+
+fn duration_parser(text: &str) -> Result<u64, &'static str> {
+    format::parse_duration(text).ok_or("expected a number with an optional unit s, m, h, d or w")
+}
@@ -0,0 +1,169 @@
+//! Shell completion scripts generated from the clap command tree, so they follow the CLI as it changes
+
+use clap::{Arg, Command, ValueHint};
+use std::fmt::Write;
+use crate::cli::Shell;
+
+/// Completion script of `shell` for `command` (the whole CLI)
+pub fn generate(shell: Shell, command: &Command) -> String {
+    let mut command = command.clone();
+    command.build();
+    let mut commands = Vec::new();
+    collect_commands(&command, Vec::new(), &mut commands);
+    match shell {
+        Shell::Bash => bash(command.get_name(), &commands),
+        // zsh runs the bash completion through its compatibility layer
+        Shell::Zsh => format!(
+            "#compdef {name}\n\nautoload -U +X bashcompinit && bashcompinit\n\n{}",
+            bash(command.get_name(), &commands),
+            name = command.get_name()
+        ),
+        Shell::Fish => fish(command.get_name(), &commands),
+    }
+}
+
+/// Every command with the subcommand names leading to it (empty for the binary itself)
+fn collect_commands<'a>(command: &'a Command, path: Vec<&'a str>, commands: &mut Vec<(Vec<&'a str>, &'a Command)>) {
+    // `help <COMMAND>` mirrors the whole tree, its own completion is just the command names
+    for subcommand in command.get_subcommands().filter(|subcommand| !subcommand.is_hide_set() && subcommand.get_name() != "help") {
+        let mut sub_path = path.clone();
+        sub_path.push(subcommand.get_name());
+        collect_commands(subcommand, sub_path, commands);
+    }
+    commands.insert(0, (path, command));
+}
+
+fn visible_args(command: &Command) -> impl Iterator<Item = &Arg> {
+    command.get_arguments().filter(|arg| !arg.is_hide_set())
+}
+
+fn possible_values(arg: &Arg) -> Vec<String> {
+    arg.get_possible_values().iter().filter(|value| !value.is_hide_set()).map(|value| value.get_name().to_string()).collect()
+}
+
+/// First line of a help text, for the one-line descriptions of fish
+fn summary(help: Option<&clap::builder::StyledStr>) -> String {
+    help.map(|help| help.to_string().lines().next().unwrap_or_default().to_string()).unwrap_or_default()
+}
+
+fn bash(name: &str, commands: &[(Vec<&str>, &Command)]) -> String {
+    let function = format!("_{}", name.replace('-', "_"));
+    let path_of = |path: &[&str]| path.iter().map(|name| format!("/{name}")).collect::<String>();
+    let mut script = String::new();
+    let _ = writeln!(script, "{function}() {{");
+    let _ = writeln!(script, "    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\" prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\" path=\"\" i");
+    let _ = writeln!(script, "    for ((i = 1; i < COMP_CWORD; i++)); do");
+    let _ = writeln!(script, "        case \"$path/${{COMP_WORDS[i]}}\" in");
+    let subcommand_paths: Vec<String> = commands.iter().filter(|(path, _)| !path.is_empty()).map(|(path, _)| path_of(path)).collect();
+    let _ = writeln!(script, "            {}) path=\"$path/${{COMP_WORDS[i]}}\" ;;", subcommand_paths.join("|"));
+    let _ = writeln!(script, "        esac");
+    let _ = writeln!(script, "    done");
+
+    // Values of the option just typed
+    let _ = writeln!(script, "    case \"$path $prev\" in");
+    for (path, command) in commands {
+        for arg in visible_args(command).filter(|arg| !arg.is_positional() && arg.get_action().takes_values()) {
+            let flags: Vec<String> = arg
+                .get_long()
+                .map(|long| format!("--{long}"))
+                .into_iter()
+                .chain(arg.get_short().map(|short| format!("-{short}")))
+                .map(|flag| format!("\"{} {flag}\"", path_of(path)))
+                .collect();
+            let _ = writeln!(script, "        {}) {}; return ;;", flags.join("|"), bash_values(arg));
+        }
+    }
+    let _ = writeln!(script, "    esac");
+
+    // Subcommands, options and positional values of the current command
+    let _ = writeln!(script, "    case \"$path\" in");
+    for (path, command) in commands {
+        let mut words: Vec<String> = command.get_subcommands().filter(|subcommand| !subcommand.is_hide_set()).map(|subcommand| subcommand.get_name().to_string()).collect();
+        let mut positional_completion = None;
+        for arg in visible_args(command) {
+            if arg.is_positional() {
+                words.extend(possible_values(arg));
+                positional_completion = positional_completion.or_else(|| bash_path_completion(arg));
+            } else {
+                words.extend(arg.get_long().map(|long| format!("--{long}")));
+                words.extend(arg.get_short().map(|short| format!("-{short}")));
+            }
+        }
+        let _ = writeln!(script, "        \"{}\")", path_of(path));
+        let _ = writeln!(script, "            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))", words.join(" "));
+        if let Some(completion) = positional_completion {
+            let _ = writeln!(script, "            [[ $cur != -* ]] && COMPREPLY+=({completion})");
+        }
+        let _ = writeln!(script, "            ;;");
+    }
+    let _ = writeln!(script, "    esac");
+    let _ = writeln!(script, "}}");
+    let _ = writeln!(script, "complete -F {function} {name}");
+    script
+}
+
+/// Completion of an option value: its possible values, paths or nothing (free text)
+fn bash_values(arg: &Arg) -> String {
+    let values = possible_values(arg);
+    if !values.is_empty() {
+        return format!("COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))", values.join(" "));
+    }
+    match bash_path_completion(arg) {
+        Some(completion) => format!("COMPREPLY=({completion})"),
+        None => "COMPREPLY=()".to_string(),
+    }
+}
+
+fn bash_path_completion(arg: &Arg) -> Option<&'static str> {
+    match arg.get_value_hint() {
+        ValueHint::DirPath => Some("$(compgen -d -- \"$cur\")"),
+        ValueHint::FilePath | ValueHint::AnyPath => Some("$(compgen -f -- \"$cur\")"),
+        _ => None,
+    }
+}
+
+fn fish(name: &str, commands: &[(Vec<&str>, &Command)]) -> String {
+    let quote = |text: &str| format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"));
+    let mut script = String::new();
+    // No file completion unless an argument takes paths
+    let _ = writeln!(script, "complete -c {name} -f");
+    for (path, command) in commands {
+        let subcommands: Vec<&Command> = command.get_subcommands().filter(|subcommand| !subcommand.is_hide_set()).collect();
+        let mut conditions: Vec<String> = path.iter().map(|name| format!("__fish_seen_subcommand_from {name}")).collect();
+        if path.is_empty() {
+            conditions.push("__fish_use_subcommand".to_string());
+        } else if !subcommands.is_empty() {
+            let names: Vec<&str> = subcommands.iter().map(|subcommand| subcommand.get_name()).collect();
+            conditions.push(format!("not __fish_seen_subcommand_from {}", names.join(" ")));
+        }
+        let condition = quote(&conditions.join("; and "));
+
+        for subcommand in subcommands {
+            let _ = writeln!(script, "complete -c {name} -n {condition} -a {} -d {}", subcommand.get_name(), quote(&summary(subcommand.get_about())));
+        }
+        for arg in visible_args(command) {
+            let mut line = format!("complete -c {name} -n {condition}");
+            if let Some(long) = arg.get_long() {
+                let _ = write!(line, " -l {long}");
+            }
+            if let Some(short) = arg.get_short() {
+                let _ = write!(line, " -s {short}");
+            }
+            let values = possible_values(arg);
+            if !values.is_empty() {
+                let _ = write!(line, " -a {}", quote(&values.join(" ")));
+            }
+            if !arg.is_positional() && arg.get_action().takes_values() {
+                line.push_str(" -r");
+            }
+            if matches!(arg.get_value_hint(), ValueHint::DirPath | ValueHint::FilePath | ValueHint::AnyPath) {
+                line.push_str(" -F");
+            } else if arg.is_positional() && values.is_empty() {
+                // Free text, nothing to offer
+                continue;
+            }
+            let _ = writeln!(script, "{line} -d {}", quote(&summary(arg.get_help())));
+        }
+    }
+    script
+}
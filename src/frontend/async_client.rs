@@ -0,0 +1,195 @@
+//! Async variant of `FrontendClient` for tokio applications: requests are futures and pushes
+//! arrive as a `Stream`, no threads of its own are needed. A task spawned on the current
+//! runtime reads the connection and hands answers to the pending request, pushes to the stream.
+
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use bytes::Bytes;
+use futures_core::Stream;
+use indexmap::IndexMap;
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tracing::debug;
+use crate::shared::protocol::{self, PeerInfo};
+use crate::shared::wire::{self, WireFormat};
+use crate::shared::{BackendError, BackendMessage, ClipboardContentType, ClipboardItemPreview, FrontendMessage, Health, HistoryStats, SortMode};
+
+/// Errors of the async client can move between tasks
+pub type AsyncClientError = Box<dyn std::error::Error + Send + Sync>;
+
+pub struct AsyncFrontendClient {
+    writer: OwnedWriteHalf,
+    responses: UnboundedReceiver<BackendMessage>,
+    pushes: Option<UnboundedReceiver<BackendMessage>>,
+    backend: PeerInfo,
+    format: WireFormat,
+}
+
+impl AsyncFrontendClient {
+    /// Connect to the daemon and exchange protocol versions and capabilities
+    pub async fn connect(format: WireFormat) -> Result<Self, AsyncClientError> {
+        Self::connect_to(Path::new(protocol::SOCKET_PATH), format).await
+    }
+
+    /// Like `connect`, for a backend listening on `socket` instead of the default socket
+    pub async fn connect_to(socket: &Path, format: WireFormat) -> Result<Self, AsyncClientError> {
+        let (reader, mut writer) = UnixStream::connect(socket).await?.into_split();
+        let mut reader = BufReader::new(reader);
+
+        // The handshake is read here, the wire format may change right after it
+        let hello = FrontendMessage::Hello {
+            protocol_version: protocol::PROTOCOL_VERSION,
+            capabilities: protocol::own_capabilities(),
+            wire_format: format,
+        };
+        writer.write_all(&wire::encode(WireFormat::Json, &hello)?).await?;
+        let body = wire::read_frame(&mut reader, WireFormat::Json).await?.ok_or("Backend closed the connection during the handshake")?;
+        let (backend, format) = match wire::decode(WireFormat::Json, &body)? {
+            BackendMessage::Hello { protocol_version, capabilities, wire_format } => (PeerInfo { protocol_version, capabilities }, wire_format),
+            _ => return Err("Backend does not support the handshake, please update the cursor-clip daemon".into()),
+        };
+
+        let (response_tx, responses) = mpsc::unbounded_channel();
+        let (push_tx, pushes) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            loop {
+                let body = match wire::read_frame(&mut reader, format).await {
+                    Ok(Some(body)) => body,
+                    Ok(None) => break,
+                    Err(e) => {
+                        debug!("Reading from backend failed: {e}");
+                        break;
+                    }
+                };
+                let message: BackendMessage = match wire::decode(format, &body) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        debug!("Ignoring unreadable message from backend: {e}");
+                        continue;
+                    }
+                };
+                if message.is_push() {
+                    // Nobody may be listening for pushes, that is fine
+                    let _ = push_tx.send(message);
+                } else if response_tx.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self { writer, responses, pushes: Some(pushes), backend, format })
+    }
+
+    /// Protocol version and capabilities of the connected backend
+    pub const fn backend(&self) -> &PeerInfo {
+        &self.backend
+    }
+
+    /// Push messages of this connection as a stream, can be taken once
+    pub fn pushes(&mut self) -> Option<PushStream> {
+        self.pushes.take().map(|receiver| PushStream { receiver })
+    }
+
+    /// Send a request and wait for its answer
+    pub async fn send_message(&mut self, message: FrontendMessage) -> Result<BackendMessage, AsyncClientError> {
+        self.writer.write_all(&wire::encode(self.format, &message)?).await?;
+        self.responses.recv().await.ok_or_else(|| "Backend closed the connection".into())
+    }
+
+    /// Get up to `limit` history items in `sort` order starting at `offset`, together with the
+    /// total item count
+    pub async fn get_history_page(&mut self, offset: u32, limit: u32, sort: SortMode) -> Result<(Vec<ClipboardItemPreview>, u32), AsyncClientError> {
+        match self.send_message(FrontendMessage::GetHistoryPage { offset, limit, sort }).await? {
+            BackendMessage::HistoryPage { items, total, .. } => Ok((items, total)),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Get a window of the items matching `query`, together with the number of matching items
+    pub async fn search_history(&mut self, query: &str, content_types: &[ClipboardContentType], offset: u32, limit: u32) -> Result<(Vec<ClipboardItemPreview>, u32), AsyncClientError> {
+        let request = FrontendMessage::SearchHistory {
+            query: query.to_string(),
+            content_types: content_types.to_vec(),
+            offset,
+            limit,
+            sort: SortMode::Recent,
+            tag: None,
+        };
+        match self.send_message(request).await? {
+            BackendMessage::HistoryPage { items, total, .. } => Ok((items, total)),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Set an item as the clipboard content
+    pub async fn set_clipboard_by_id(&mut self, id: u64) -> Result<(), AsyncClientError> {
+        match self.send_message(FrontendMessage::SetClipboardById { id, seat: None, original: false }).await? {
+            BackendMessage::ClipboardSet => Ok(()),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Get the payloads of an item by MIME type
+    pub async fn get_item_data(&mut self, id: u64) -> Result<IndexMap<String, Bytes>, AsyncClientError> {
+        match self.send_message(FrontendMessage::GetItemData { id }).await? {
+            BackendMessage::ItemData { mime_data, .. } => Ok(mime_data),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Delete one item (restorable for `UNDO_WINDOW_SECS`)
+    pub async fn delete_item(&mut self, id: u64) -> Result<(), AsyncClientError> {
+        match self.send_message(FrontendMessage::DeleteItem { id }).await? {
+            BackendMessage::ItemDeleted { .. } => Ok(()),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Clear the whole history (restorable for `UNDO_WINDOW_SECS`)
+    pub async fn clear_history(&mut self) -> Result<(), AsyncClientError> {
+        match self.send_message(FrontendMessage::ClearHistory).await? {
+            BackendMessage::HistoryCleared => Ok(()),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Get a summary of the history
+    pub async fn get_stats(&mut self) -> Result<HistoryStats, AsyncClientError> {
+        match self.send_message(FrontendMessage::GetStats).await? {
+            BackendMessage::Stats { stats } => Ok(stats),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Check that the daemon is alive and recording
+    pub async fn ping(&mut self) -> Result<Health, AsyncClientError> {
+        match self.send_message(FrontendMessage::Ping).await? {
+            BackendMessage::Pong { health } => Ok(health),
+            other => Err(response_error(other)),
+        }
+    }
+}
+
+/// Push messages of an `AsyncFrontendClient`, ends when the connection closes
+pub struct PushStream {
+    receiver: UnboundedReceiver<BackendMessage>,
+}
+
+impl Stream for PushStream {
+    type Item = BackendMessage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// Error for a response that does not answer the request, a `BackendError` if the backend reported one
+fn response_error(response: BackendMessage) -> AsyncClientError {
+    match response {
+        BackendMessage::Error { code, message, details } => Box::new(BackendError { code, message, details }),
+        _ => "Unexpected response".into(),
+    }
+}
@@ -0,0 +1,69 @@
+//! Demo mode (`--demo`): representative fake history for screenshots and screencasts. Nothing is
+//! fetched from the backend, so the real clipboard history can never show up.
+use gtk4::gdk;
+use gtk4::glib;
+use crate::shared::{ClipboardContentType, ClipboardItemPreview, EventStamp, TextStats};
+
+/// Shown instead of sensitive content
+const MASK: &str = "••••••••••••";
+
+/// Fake items, newest first, with ages spread over the last few days
+pub fn demo_items() -> Vec<ClipboardItemPreview> {
+    let samples: [(ClipboardContentType, &str, u64, u32); 8] = [
+        (ClipboardContentType::Text, "Let's sync on the release notes tomorrow morning", 5, 0),
+        (ClipboardContentType::Url, "https://github.com/Sirulex/cursor-clip", 4 * 60, 2),
+        (ClipboardContentType::Image, "Image (PNG, 640×400)", 20 * 60, 0),
+        (ClipboardContentType::Code, "fn main() {\n    println!(\"Hello, world!\");\n}", 2 * 3600, 0),
+        (ClipboardContentType::Password, MASK, 5 * 3600, 0),
+        (ClipboardContentType::File, "~/Documents/notes/roadmap.md", 26 * 3600, 0),
+        (ClipboardContentType::Text, "The quick brown fox jumps over the lazy dog", 2 * 86400, 1),
+        (ClipboardContentType::Url, "https://gtk-rs.org/gtk4-rs/stable/latest/book/", 3 * 86400, 0),
+    ];
+
+    let now_millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let count = samples.len() as u64;
+    samples
+        .into_iter()
+        .zip(0..)
+        .map(|((content_type, preview, age_secs, copy_count), index)| ClipboardItemPreview {
+            item_id: count - index,
+            content_preview: preview.to_string(),
+            content_type,
+            stamp: EventStamp { millis: now_millis.saturating_sub(age_secs * 1000), seq: count - index },
+            copy_count,
+            last_pasted: None,
+            possibly_truncated: false,
+            ocr_text: None,
+            tags: Vec::new(),
+            source_device: None,
+            seat: None,
+            source_app: None,
+            text_stats: TextStats::of(preview, content_type),
+            has_original: false,
+            group_id: None,
+        })
+        .collect()
+}
+
+/// Gradient image standing in for the thumbnail of a fake image item
+pub fn demo_thumbnail(max_px: u32) -> gdk::Texture {
+    let (width, height) = (max_px, max_px * 5 / 8);
+    let mut pixels = Vec::with_capacity((width * height * 3) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let t = (x + y) as f32 / (width + height) as f32;
+            pixels.extend_from_slice(&[(53.0 + 150.0 * t) as u8, (132.0 - 40.0 * t) as u8, (228.0 - 60.0 * t) as u8]);
+        }
+    }
+    gdk::MemoryTexture::new(
+        width as i32,
+        height as i32,
+        gdk::MemoryFormat::R8g8b8,
+        &glib::Bytes::from_owned(pixels),
+        (width * 3) as usize,
+    )
+    .into()
+}
@@ -5,7 +5,6 @@ use wayland_client::delegate_noop;
 // Core protocol objects
 use wayland_client::protocol::{
     wl_compositor::WlCompositor,
-    wl_surface::WlSurface,
     wl_region::WlRegion,
     wl_buffer::WlBuffer,
     wl_registry::WlRegistry,
@@ -26,7 +25,6 @@ use wayland_protocols::wp::single_pixel_buffer::v1::client::wp_single_pixel_buff
 // Generate the noop dispatch implementations
 delegate_noop!(State: WlCompositor);
 delegate_noop!(State: WlRegion);   
-delegate_noop!(State: WlSurface);
 delegate_noop!(State: ZwlrLayerShellV1);
 delegate_noop!(State: WpViewporter);
 delegate_noop!(State: WpViewport);
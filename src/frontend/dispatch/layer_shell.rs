@@ -6,7 +6,7 @@ use wayland_protocols::wp::viewporter::client::wp_viewport;
 
 use crate::frontend::frontend_state::State;
 use crate::frontend::dispatch::frame_callback::FrameCallbackData;
-use log::debug;
+use tracing::debug;
 
 impl Dispatch<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1, ()> for State {
     fn event(
@@ -26,6 +26,9 @@ impl Dispatch<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1, ()> for State {
                 layer_surface.ack_configure(serial);
                 debug!("Layer surface configured: {}x{}", width, height);
 
+                // Both helper layers span the whole output, so this is its logical size
+                state.cursor_output_size = Some((width as i32, height as i32));
+
                 let Some(capture_surface) = &state.capture_surface else {
                     return;
                 };
@@ -1,4 +1,5 @@
 pub mod frame_callback;
 pub mod layer_shell;
 pub mod pointer;
+pub mod output;
 pub mod empty_dispatch;
\ No newline at end of file
@@ -0,0 +1,47 @@
+use wayland_client::{Connection, Dispatch, Proxy, QueueHandle};
+use wayland_client::protocol::{wl_output, wl_surface};
+
+use crate::frontend::frontend_state::State;
+use tracing::debug;
+
+impl Dispatch<wl_output::WlOutput, ()> for State {
+    fn event(
+        state: &mut Self,
+        output: &wl_output::WlOutput,
+        event: wl_output::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Name { name } = event {
+            debug!("Output {:?} has connector name {name}", output.id());
+            if let Some(info) = state.outputs.iter_mut().find(|info| &info.output == output) {
+                info.name = Some(name);
+            }
+        }
+    }
+}
+
+impl Dispatch<wl_surface::WlSurface, ()> for State {
+    fn event(
+        state: &mut Self,
+        surface: &wl_surface::WlSurface,
+        event: wl_surface::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // The full-screen capture surface is placed on the output containing the cursor,
+        // remember it so the overlay can be opened on the same monitor
+        if let wl_surface::Event::Enter { output } = event
+            && state.capture_surface.as_ref() == Some(surface)
+        {
+            state.cursor_output = state
+                .outputs
+                .iter()
+                .find(|info| info.output == output)
+                .and_then(|info| info.name.clone());
+            debug!("Capture surface entered output {:?}", state.cursor_output);
+        }
+    }
+}
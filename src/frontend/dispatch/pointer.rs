@@ -2,7 +2,7 @@ use wayland_client::{Connection, Dispatch, QueueHandle, WEnum};
 use wayland_client::protocol::{wl_pointer, wl_seat};
 
 use crate::frontend::frontend_state::State;
-use log::{debug};
+use tracing::debug;
 
 impl Dispatch<wl_seat::WlSeat, ()> for State {
     fn event(
@@ -72,11 +72,11 @@ impl Dispatch<wl_pointer::WlPointer, ()> for State {
                 debug!("Pointer button {button:?} at time {time}: {button_state:?}");
                 
                 // Check for left mouse button click (button 272 = left click)
-                if button == 272 {
-                    if let WEnum::Value(wl_pointer::ButtonState::Pressed) = button_state {
-                        debug!("Left mouse button clicked on capture layer - requesting close");
-                        state.capture_layer_clicked = true; // future handling of outside click to close overlay
-                    }
+                if button == 272
+                    && let WEnum::Value(wl_pointer::ButtonState::Pressed) = button_state
+                {
+                    debug!("Left mouse button clicked on capture layer - requesting close");
+                    state.capture_layer_clicked = true; // clicks while the overlay is open go to its dismiss layer
                 }
             }
             _ => {}
@@ -1,5 +1,5 @@
 use wayland_client::protocol::{
-    wl_buffer, wl_callback, wl_compositor, wl_pointer, wl_seat, wl_shm, wl_shm_pool, wl_surface,
+    wl_buffer, wl_callback, wl_compositor, wl_output, wl_pointer, wl_seat, wl_shm, wl_shm_pool, wl_surface,
 };
 use std::fs::File;
 
@@ -15,6 +15,13 @@ use wayland_protocols::{
 };
 
 use crate::shared::ClipboardItemPreview;
+use crate::shared::config::Placement;
+
+/// A bound wl_output together with its connector name (wl_output v4 `name` event)
+pub struct OutputInfo {
+    pub output: wl_output::WlOutput,
+    pub name: Option<String>,
+}
 
 pub struct State {
     pub compositor: Option<wl_compositor::WlCompositor>,
@@ -29,6 +36,10 @@ pub struct State {
     pub coords_received: bool,
     pub received_x: f64,
     pub received_y: f64,
+    pub outputs: Vec<OutputInfo>,
+    // Connector name and logical size of the output the capture layer (and thus the cursor) is on
+    pub cursor_output: Option<String>,
+    pub cursor_output_size: Option<(i32, i32)>,
     pub capture_layer_clicked: bool,
     pub capture_layer_ready: bool,
     pub capture_surface: Option<wl_surface::WlSurface>,
@@ -39,6 +50,16 @@ pub struct State {
     pub update_layer_surface: Option<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1>,
     pub update_frame_callback: Option<wl_callback::WlCallback>,
     pub clipboard_history: Vec<ClipboardItemPreview>,
+    /// Show fake items instead of the real history (`--demo`)
+    pub demo_mode: bool,
+    /// Where the overlay opens (`--placement` or the config)
+    pub placement: Placement,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl State {
@@ -56,6 +77,9 @@ impl State {
             coords_received: false,
             received_x: 0.0,
             received_y: 0.0,
+            outputs: Vec::new(),
+            cursor_output: None,
+            cursor_output_size: None,
             capture_layer_clicked: false,
             capture_layer_ready: false,
             capture_surface: None,
@@ -66,6 +90,8 @@ impl State {
             update_layer_surface: None,
             update_frame_callback: None,
             clipboard_history: Vec::new(),
+            demo_mode: false,
+            placement: Placement::default(),
         }
     }
 }
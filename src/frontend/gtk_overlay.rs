@@ -1,21 +1,100 @@
 use gtk4::prelude::*;
-use gtk4::{Application, Button, Label, Box, Orientation, Align};
+use gtk4::{Application, Button, Box, Orientation};
+use gtk4::gio;
+use gtk4::glib::Variant;
 use gtk4_layer_shell::{Edge, Layer, LayerShell};
 use libadwaita::{self as adw, prelude::*};
 use std::sync::Once;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::cell::RefCell;
-use crate::shared::{ClipboardItemPreview, ClipboardContentType};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use crate::shared::{ClipboardItemPreview, SortMode};
+use crate::shared::Config;
+use crate::shared::config::{Density, Placement, ViewMode};
+use crate::frontend::theme;
+use crate::frontend::preferences;
+use crate::frontend::history_widget::ClipboardHistoryWidget;
 use crate::frontend::ipc_client::FrontendClient;
-use log::{info, debug, warn, error};
+use crate::shared::protocol;
+use crate::shared::i18n::{gettext, trf};
+use tracing::{debug, error, info, warn};
 
 static INIT: Once = Once::new();
+
+/// Application id, a second invocation finds a running overlay under it on the session bus
+const APP_ID: &str = "com.cursor-clip";
+
+/// Application action showing the overlay at the cursor position passed by another invocation
+const SHOW_ACTION: &str = "show";
+
+/// Application actions of the primary menu: pausing capture (boolean state), the sort and view
+/// mode (their names as state and target), the history window and the statistics, preferences
+/// and about dialogs
+const PAUSE_CAPTURE_ACTION: &str = "pause-capture";
+const SORT_MODE_ACTION: &str = "sort-mode";
+const VIEW_MODE_ACTION: &str = "view-mode";
+const TIMELINE_ACTION: &str = "timeline";
+const STATISTICS_ACTION: &str = "statistics";
+const PREFERENCES_ACTION: &str = "preferences";
+const ABOUT_ACTION: &str = "about";
+
+/// Parameter of the show action: cursor x and y, output name (empty if unknown), output width
+/// and height (0 if unknown) and the placement name
+type ShowParameter = (f64, f64, String, i32, i32, String);
+
+/// Gap between the docked overlay and the bottom edge of the output
+const DOCK_MARGIN: i32 = 12;
+
+/// Duration of the fade/slide when the overlay appears and disappears
+const ANIMATION_MS: u32 = 180;
+
+/// Distance the overlay slides while fading in at the cursor or the bottom edge
+const SLIDE_PX: i32 = 16;
 pub static CLOSE_REQUESTED: AtomicBool = AtomicBool::new(false);
 
 // Thread-local storage for the overlay state since GTK objects aren't Send/Sync
 thread_local! {
     static OVERLAY_WINDOW: RefCell<Option<adw::ApplicationWindow>> = const { RefCell::new(None) };
     static OVERLAY_APP: RefCell<Option<Application>> = const { RefCell::new(None) };
+    // Transparent full-screen surface beneath the overlay catching clicks outside of it
+    static DISMISS_LAYER: RefCell<Option<gtk4::ApplicationWindow>> = const { RefCell::new(None) };
+    // Show animation of the overlay, played backwards before quitting (unset without animations)
+    static OVERLAY_ANIMATION: RefCell<Option<adw::TimedAnimation>> = const { RefCell::new(None) };
+    // The show animation while it plays backwards, a new show request stops it
+    static CLOSE_ANIMATION: RefCell<Option<adw::TimedAnimation>> = const { RefCell::new(None) };
+    static OVERLAY_HISTORY: RefCell<Option<ClipboardHistoryWidget>> = const { RefCell::new(None) };
+    // Resident mode: closing only hides the overlay, the application keeps running
+    static RESIDENT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Where to open the overlay: cursor position relative to the output it is on
+#[derive(Debug, Clone, Default)]
+pub struct OverlayPosition {
+    pub x: f64,
+    pub y: f64,
+    /// Connector name of the output containing the cursor (e.g. "DP-1"), if known
+    pub output_name: Option<String>,
+    /// Logical size of that output, used to keep the overlay fully on screen
+    pub output_size: Option<(i32, i32)>,
+}
+
+impl OverlayPosition {
+    fn to_show_parameter(&self, placement: Placement) -> Variant {
+        let (width, height) = self.output_size.unwrap_or_default();
+        let output_name = self.output_name.clone().unwrap_or_default();
+        (self.x, self.y, output_name, width, height, placement.name().to_string()).to_variant()
+    }
+
+    fn from_show_parameter(parameter: Option<&Variant>) -> Option<(Self, Placement)> {
+        let (x, y, output_name, width, height, placement) = parameter?.get::<ShowParameter>()?;
+        let position = Self {
+            x,
+            y,
+            output_name: Some(output_name).filter(|name| !name.is_empty()),
+            output_size: Some((width, height)).filter(|&(width, height)| width > 0 && height > 0),
+        };
+        Some((position, Placement::from_name(&placement).unwrap_or_default()))
+    }
 }
 
 pub fn is_close_requested() -> bool {
@@ -26,78 +105,292 @@ pub fn reset_close_flags() {
     CLOSE_REQUESTED.store(false, Ordering::Relaxed);
 }
 
+/// Close the overlay, after playing its show animation backwards from wherever it is
+pub fn request_quit() {
+    match OVERLAY_ANIMATION.with(|a| a.borrow_mut().take()) {
+        Some(animation) => {
+            animation.set_value_to(animation.value());
+            animation.set_reverse(true);
+            animation.connect_done(|_| {
+                if CLOSE_ANIMATION.with(|a| a.borrow_mut().take()).is_some() {
+                    quit_now();
+                }
+            });
+            CLOSE_ANIMATION.with(|a| {
+                *a.borrow_mut() = Some(animation.clone());
+            });
+            animation.play();
+        }
+        None => quit_now(),
+    }
+}
+
 // Centralized quit path to avoid double-close reentrancy and ensure flags + app quit
-fn request_quit() {
+fn quit_now() {
+    if RESIDENT.with(Cell::get) {
+        // Keep the windows for the next invocation, hidden surfaces take no input
+        OVERLAY_WINDOW.with(|w| {
+            if let Some(ref win) = *w.borrow() {
+                win.set_visible(false);
+            }
+        });
+        DISMISS_LAYER.with(|w| {
+            if let Some(ref win) = *w.borrow() {
+                win.set_visible(false);
+            }
+        });
+        debug!("Overlay hidden, staying resident");
+        return;
+    }
     CLOSE_REQUESTED.store(true, Ordering::Relaxed);
     // Prefer quitting the application (cleaner teardown) over closing the window directly
     OVERLAY_APP.with(|a| {
         if let Some(ref app) = *a.borrow() {
             app.quit();
-            return;
         }
     });
 
-    // Fallback: close the window if app is unavailable
+    // Fallback: close the windows if app is unavailable
     OVERLAY_WINDOW.with(|w| {
         if let Some(ref win) = *w.borrow() {
             win.close();
         }
     });
+    DISMISS_LAYER.with(|w| {
+        if let Some(ref win) = *w.borrow() {
+            win.close();
+        }
+    });
 }
 
-pub fn init_clipboard_overlay(x: f64, y: f64, prefetched_items: Vec<ClipboardItemPreview>) -> Result<(), std::boxed::Box<dyn std::error::Error + Send + Sync>> {
+pub fn init_clipboard_overlay(position: OverlayPosition, placement: Placement, prefetched_items: Vec<ClipboardItemPreview>, demo: bool) -> Result<(), std::boxed::Box<dyn std::error::Error + Send + Sync>> {
+    let app = build_application(demo);
+    // A resident (or still open) overlay shows itself at the cursor instead, so this
+    // invocation skips initializing GTK altogether
+    if let Err(e) = app.register(gio::Cancellable::NONE) {
+        warn!("Could not register the overlay application: {e}");
+    }
+    if app.is_remote() {
+        debug!("Overlay already running, showing it at {position:?}");
+        app.activate_action(SHOW_ACTION, Some(&position.to_show_parameter(placement)));
+        // The action is sent asynchronously, deliver it before this process exits
+        if let Some(connection) = app.dbus_connection()
+            && let Err(e) = connection.flush_sync(gio::Cancellable::NONE)
+        {
+            warn!("Could not reach the running overlay: {e}");
+        }
+        CLOSE_REQUESTED.store(true, Ordering::Relaxed);
+        return Ok(());
+    }
+
     INIT.call_once(|| {
         adw::init().expect("Failed to initialize libadwaita");
     });
 
-    // Create the application (was returned from init_application())
+    app.connect_activate(move |app| {
+        setup_overlay(app, prefetched_items.clone(), demo);
+        present_overlay(&position, placement);
+        debug!("Libadwaita overlay window created ({}) for the cursor at ({}, {})", placement.name(), position.x, position.y);
+    });
+
+    // Run the application
+    app.run_with_args::<String>(&[]);
+    clear_overlay_state();
+    Ok(())
+}
+
+/// Keep the overlay loaded in the background: closing it only hides it, and later invocations
+/// show it again at their cursor instead of starting GTK and libadwaita from scratch
+pub fn run_resident_overlay(demo: bool) -> Result<(), std::boxed::Box<dyn std::error::Error>> {
+    let app = build_application(demo);
+    if let Err(e) = app.register(gio::Cancellable::NONE) {
+        return Err(format!("Could not register the overlay application: {e}").into());
+    }
+    if app.is_remote() {
+        info!("The overlay is already running, not starting another one");
+        return Ok(());
+    }
+
+    INIT.call_once(|| {
+        adw::init().expect("Failed to initialize libadwaita");
+    });
+    RESIDENT.with(|r| r.set(true));
+
+    // Build the (hidden) windows right away so the first invocation is as quick as the others
+    app.connect_activate(move |app| setup_overlay(app, Vec::new(), demo));
+    let _hold = app.hold();
+    info!("Overlay resident, run `cursor-clip` to show it");
+    app.run_with_args::<String>(&[]);
+    clear_overlay_state();
+    Ok(())
+}
+
+/// The overlay application with the action other invocations use to show its window
+fn build_application(demo: bool) -> Application {
     let app: Application = adw::Application::builder()
-        .application_id("com.cursor-clip")
+        .application_id(APP_ID)
         .build()
         .upcast();
-    
-    let app_clone = app.clone();
-    app.connect_activate(move |_| {
-        let window = create_layer_shell_window(&app_clone, x, y, prefetched_items.clone());
-        
-        // Store the window in our thread-local storage
-        OVERLAY_WINDOW.with(|w| {
-            *w.borrow_mut() = Some(window.clone());
-        });
-        
-        OVERLAY_APP.with(|a| {
-            *a.borrow_mut() = Some(app_clone.clone());
+
+    let show = gio::SimpleAction::new(SHOW_ACTION, Some(&ShowParameter::static_variant_type()));
+    let app_for_show = app.clone();
+    show.connect_activate(move |_, parameter| {
+        let Some((position, placement)) = OverlayPosition::from_show_parameter(parameter) else {
+            warn!("Ignoring an invalid request to show the overlay: {parameter:?}");
+            return;
+        };
+        // Fresh history and no leftover filter when an existing overlay is shown again
+        match OVERLAY_HISTORY.with(|h| h.borrow().clone()) {
+            Some(history) => history.reset(),
+            None => setup_overlay(&app_for_show, Vec::new(), demo),
+        }
+        present_overlay(&position, placement);
+        debug!("Overlay shown ({}) for the cursor at ({}, {})", placement.name(), position.x, position.y);
+    });
+    app.add_action(&show);
+    app
+}
+
+/// Create the overlay window (and the dismiss layer beneath it) without showing them
+fn setup_overlay(app: &Application, prefetched_items: Vec<ClipboardItemPreview>, demo: bool) {
+    if OVERLAY_WINDOW.with(|w| w.borrow().is_some()) {
+        return;
+    }
+    let config = Config::load();
+    if config.close_on_focus_loss {
+        let dismiss_layer = create_dismiss_layer(app);
+        DISMISS_LAYER.with(|w| {
+            *w.borrow_mut() = Some(dismiss_layer);
         });
-        
-        window.present();
-        
-        debug!("Libadwaita overlay window created at ({}, {})", x, y);
+    }
+    let (window, history) = create_layer_shell_window(app, &config, prefetched_items, demo);
+
+    // Store the window in our thread-local storage
+    OVERLAY_WINDOW.with(|w| {
+        *w.borrow_mut() = Some(window);
+    });
+    OVERLAY_HISTORY.with(|h| {
+        *h.borrow_mut() = Some(history);
     });
+    OVERLAY_APP.with(|a| {
+        *a.borrow_mut() = Some(app.clone());
+    });
+}
 
-    // Run the application
-    app.run_with_args::<String>(&[]);
+/// Move the overlay to the output with the cursor, place it there and show it
+fn present_overlay(position: &OverlayPosition, placement: Placement) {
+    let Some(window) = OVERLAY_WINDOW.with(|w| w.borrow().clone()) else {
+        return;
+    };
+    // Open on the monitor containing the cursor (otherwise the compositor picks one)
+    let monitor = position.output_name.as_deref().and_then(monitor_by_connector);
+    if monitor.is_none() {
+        debug!("Monitor for output {:?} not found, letting the compositor choose", position.output_name);
+    }
+
+    // Shown again while closing: keep it open and play the show animation again below
+    let closing = CLOSE_ANIMATION.with(|a| a.borrow_mut().take());
+    if let Some(ref animation) = closing {
+        animation.pause();
+    }
+
+    // Mapped first so it stacks beneath the overlay
+    DISMISS_LAYER.with(|w| {
+        if let Some(ref layer) = *w.borrow() {
+            move_to_monitor(layer, monitor.as_ref());
+            layer.present();
+        }
+    });
+
+    move_to_monitor(&window, monitor.as_ref());
+    // Anchors and margins, the cursor placement needs the window size and thus the content
+    apply_placement(&window, placement, position, monitor.as_ref());
+    // An overlay that is still open just moves
+    if !window.is_visible() || closing.is_some() {
+        if Config::load().animations {
+            animate_in(&window, placement);
+            // Mapping plays it, unless the overlay was closing and is still mapped
+            if window.is_mapped() {
+                OVERLAY_ANIMATION.with(|a| {
+                    if let Some(ref animation) = *a.borrow() {
+                        animation.play();
+                    }
+                });
+            }
+        } else {
+            window.set_opacity(1.0);
+        }
+    }
+    window.present();
+}
+
+/// Put a layer surface on `monitor`. An open surface stays where it is if it is on that monitor
+/// already or the cursor's monitor is unknown, since moving it remaps (re-creates) the surface.
+fn move_to_monitor(window: &(impl LayerShell + IsA<gtk4::Widget>), monitor: Option<&gtk4::gdk::Monitor>) {
+    if window.is_visible() && (monitor.is_none() || window.monitor().as_ref() == monitor) {
+        return;
+    }
+    window.set_monitor(monitor);
+}
 
-    // Belt-and-suspenders: clear TLS after run returns
+/// Clear the TLS after the application returned (belt-and-suspenders)
+fn clear_overlay_state() {
     OVERLAY_WINDOW.with(|w| {
         *w.borrow_mut() = None;
     });
+    OVERLAY_HISTORY.with(|h| {
+        *h.borrow_mut() = None;
+    });
     OVERLAY_APP.with(|a| {
         *a.borrow_mut() = None;
     });
-    Ok(())
+    OVERLAY_ANIMATION.with(|a| {
+        *a.borrow_mut() = None;
+    });
+    CLOSE_ANIMATION.with(|a| {
+        *a.borrow_mut() = None;
+    });
+    DISMISS_LAYER.with(|w| {
+        *w.borrow_mut() = None;
+    });
 }
 
-/// Create and configure the sync layer shell window
+/// Full-screen transparent surface on the overlay's output that swallows clicks outside of the
+/// popup and closes it. It belongs to the same application, so both windows go away together.
+fn create_dismiss_layer(app: &Application) -> gtk4::ApplicationWindow {
+    let window = gtk4::ApplicationWindow::builder().application(app).decorated(false).build();
+    window.init_layer_shell();
+    window.set_layer(Layer::Overlay);
+    window.set_namespace(Some("cursor-clip-dismiss"));
+    for edge in [Edge::Top, Edge::Bottom, Edge::Left, Edge::Right] {
+        window.set_anchor(edge, true);
+    }
+    window.set_exclusive_zone(-1);
+    window.set_keyboard_mode(gtk4_layer_shell::KeyboardMode::None);
+    theme::make_transparent(&window);
+
+    let click = gtk4::GestureClick::new();
+    // Any mouse button
+    click.set_button(0);
+    click.connect_pressed(|_, _, _, _| {
+        debug!("Clicked outside of the overlay, closing it");
+        request_quit();
+    });
+    window.add_controller(click);
+    window
+}
+
+/// Create and configure the sync layer shell window, it is placed when shown
 fn create_layer_shell_window(
     app: &Application, 
-    x: f64, 
-    y: f64,
-    prefetched_items: Vec<ClipboardItemPreview>
-) -> adw::ApplicationWindow {
+    config: &Config,
+    prefetched_items: Vec<ClipboardItemPreview>,
+    demo: bool,
+) -> (adw::ApplicationWindow, ClipboardHistoryWidget) {
     // Create the main window using Adwaita ApplicationWindow
     let window = adw::ApplicationWindow::builder()
         .application(app)
-        .title("Clipboard History")
+        .title(gettext("Clipboard History"))
         .decorated(false) 
         .build();
 
@@ -108,281 +401,509 @@ fn create_layer_shell_window(
     window.set_layer(Layer::Overlay);
     window.set_namespace(Some("cursor-clip"));
 
-    // Anchor to top-left corner for precise positioning
-    window.set_anchor(Edge::Top, true);
-    window.set_anchor(Edge::Left, true);
-    
-    // Set margins to position the window at the specified coordinates
-    window.set_margin(Edge::Top, y as i32);
-    window.set_margin(Edge::Left, x as i32);
-    
     window.set_exclusive_zone(-1); 
 
-    // Make window keyboard interactive
-    window.set_keyboard_mode(gtk4_layer_shell::KeyboardMode::Exclusive);
+    // Make window keyboard interactive. A popup that closes on focus loss grabs the keyboard,
+    // a sticky one shares it with the other windows
+    if config.close_on_focus_loss {
+        window.set_keyboard_mode(gtk4_layer_shell::KeyboardMode::Exclusive);
+        close_on_focus_loss(&window);
+    } else {
+        window.set_keyboard_mode(gtk4_layer_shell::KeyboardMode::OnDemand);
+    }
 
-    // Apply custom styling
-    apply_custom_styling(&window);
+    // Apply custom styling (user stylesheet or built-in theme)
+    theme::apply_color_scheme(config.color_scheme);
+    theme::apply_custom_styling(&window);
+    if config.density == Density::Compact {
+        window.add_css_class("compact");
+    }
 
-    // Create and set content (also obtain list_box for navigation)
-    let (content, list_box) = generate_overlay_content(prefetched_items);
+    // Create and set content (header bar and the embeddable history widget)
+    let (content, history) = generate_overlay_content(app, prefetched_items, demo);
     window.set_content(Some(&content));
 
-    // Add key controller (Esc/j/k/Enter navigation & activation)
-    let key_controller = generate_key_controller(&list_box);
-    window.add_controller(key_controller);
+    // Play the show animation (if any) whenever the overlay is mapped
+    window.connect_map(|_| {
+        OVERLAY_ANIMATION.with(|a| {
+            if let Some(ref animation) = *a.borrow() {
+                animation.play();
+            }
+        });
+    });
+
+    // Handle the history keys (Esc/j/k/Enter navigation & activation, type-to-filter)
+    // window-wide, so typing works while e.g. a header button has the focus
+    history.add_key_controller(&window);
 
     // Add close request handler to ensure any window close goes through our logic
     window.connect_close_request(|_window| {
-        println!("Window close requested - ensuring both overlay and capture layer close");
+        debug!("Window close requested - ensuring both overlay and dismiss layer close");
         request_quit();
         // Stop default handler to avoid double-close reentrancy during teardown
         gtk4::glib::Propagation::Stop
     });
 
-    window
+    (window, history)
 }
 
-/// Create a Windows 11-style clipboard history list with provided (prefetched) backend data.
-/// Falls back to a lazy on-demand fetch only if the provided vector is empty.
-fn generate_overlay_content(mut prefetched_items: Vec<ClipboardItemPreview>) -> (Box, gtk4::ListBox) {
+/// Create a Windows 11-style clipboard history window content: header bar and the history
+/// widget, populated with provided (prefetched) backend data or, in demo mode, fake items.
+/// The entries of the primary menu are added to `app` as actions.
+fn generate_overlay_content(app: &Application, prefetched_items: Vec<ClipboardItemPreview>, demo: bool) -> (Box, ClipboardHistoryWidget) {
     // Main container with standard libadwaita spacing
     let main_box = Box::new(Orientation::Vertical, 0);
 
-    // Header bar 
+    // Header bar (the subtitle shows the type-to-filter text)
     let header_bar = adw::HeaderBar::new();
-    header_bar.set_title_widget(Some(&Label::new(Some("Clipboard History"))));
+    let title = adw::WindowTitle::new(&gettext("Clipboard History"), "");
+    header_bar.set_title_widget(Some(&title));
     // Use standard end title buttons (includes the normal close button with Adwaita styling)
     header_bar.set_show_end_title_buttons(true);
     header_bar.set_show_start_title_buttons(false);
     
-    // Add a three-dot menu button (icon-only) next to the close button on the right
-    let three_dot_menu = Button::builder()
+    // Add the three-dot primary menu (icon-only) next to the close button on the right
+    let primary_menu = gtk4::MenuButton::builder()
         .icon_name("view-more-symbolic")
+        .tooltip_text(gettext("Main menu"))
+        .menu_model(&generate_primary_menu())
         .build();
-    three_dot_menu.add_css_class("flat");
-    three_dot_menu.set_tooltip_text(Some("Test Hide and Show overlay"));
-    header_bar.pack_end(&three_dot_menu);
+    primary_menu.add_css_class("flat");
+    primary_menu.update_property(&[gtk4::accessible::Property::Label(&gettext("Main menu"))]);
+    header_bar.pack_end(&primary_menu);
     
     // Add clear all button to header
-    let clear_button = Button::with_label("Clear All");
+    let clear_button = Button::with_label(&gettext("Clear All"));
     clear_button.add_css_class("destructive-action");
     header_bar.pack_start(&clear_button);
 
+    // "About history" popover with statistics, refreshed whenever it opens
+    let stats_popover = gtk4::Popover::new();
+    let stats_button = gtk4::MenuButton::builder()
+        .icon_name("dialog-information-symbolic")
+        .tooltip_text(gettext("About history"))
+        .popover(&stats_popover)
+        .build();
+    stats_button.add_css_class("flat");
+    stats_button.update_property(&[gtk4::accessible::Property::Label(&gettext("About history"))]);
+    header_bar.pack_end(&stats_button);
+
+    // Pause/resume recording of new selections, e.g. while handling credentials
+    let pause_toggle = gtk4::ToggleButton::new();
+    pause_toggle.set_icon_name("media-playback-pause-symbolic");
+    pause_toggle.add_css_class("flat");
+    header_bar.pack_start(&pause_toggle);
+
+    // Toggle between the list and the grid with large image previews
+    let view_toggle = gtk4::ToggleButton::new();
+    view_toggle.add_css_class("flat");
+    header_bar.pack_end(&view_toggle);
+
+    // Toggle between the newest and the most often pasted items first
+    let sort_toggle = gtk4::ToggleButton::new();
+    sort_toggle.add_css_class("flat");
+    header_bar.pack_end(&sort_toggle);
+
     main_box.append(&header_bar);
 
-    // Create scrolled window for the clipboard list
-    let scrolled_window = gtk4::ScrolledWindow::new();
-    scrolled_window.set_policy(gtk4::PolicyType::Never, gtk4::PolicyType::Automatic);
-    scrolled_window.set_min_content_width(200);
-    scrolled_window.set_min_content_height(400);
-
-    // Create list box for clipboard items
-    let list_box = gtk4::ListBox::new();
-    // Use custom styling instead of the default boxed-list to create floating cards
-    list_box.add_css_class("clipboard-list");
-    //list_box.set_margin_top(6);
-    list_box.set_margin_bottom(6);
-    list_box.set_margin_start(4);
-    list_box.set_margin_end(4);
-    list_box.set_selection_mode(gtk4::SelectionMode::Single);
-
-    // Start with prefetched items; if empty try one lazy fetch (non-fatal if it fails)
-    
-    if prefetched_items.is_empty() {
-        debug!("Prefetched clipboard history empty - trying on-demand fetch...");
-        if let Ok(mut client) = FrontendClient::new() {
-            match client.get_history() {
-                Ok(fetched) => prefetched_items = fetched,
-                Err(e) => warn!("Error fetching clipboard history on-demand: {}", e),
-            }
+    // F10 opens the main menu like in other GNOME apps, the other header buttons are reached with Tab
+    let shortcuts = gtk4::ShortcutController::new();
+    shortcuts.set_scope(gtk4::ShortcutScope::Global);
+    let primary_menu_for_key = primary_menu.clone();
+    shortcuts.add_shortcut(gtk4::Shortcut::new(
+        Some(gtk4::KeyvalTrigger::new(gtk4::gdk::Key::F10, gtk4::gdk::ModifierType::empty())),
+        Some(gtk4::CallbackAction::new(move |_, _| {
+            primary_menu_for_key.popup();
+            gtk4::glib::Propagation::Stop
+        })),
+    ));
+    main_box.add_controller(shortcuts);
+
+    let history = if demo {
+        ClipboardHistoryWidget::demo()
+    } else {
+        ClipboardHistoryWidget::with_items(prefetched_items)
+    };
+    main_box.append(history.widget());
+
+    // The overlay closes once an item was pasted (unless configured to stay open or Ctrl is
+    // held, to paste several items in a row) or Esc is pressed without a filter
+    let keep_open_after_paste = Rc::new(Cell::new(Config::load().keep_open_after_paste));
+    let keep_open_for_reload = keep_open_after_paste.clone();
+    history.connect_config_reloaded(move |config| {
+        keep_open_for_reload.set(config.keep_open_after_paste);
+        theme::apply_color_scheme(config.color_scheme);
+    });
+    let history_for_paste = history.clone();
+    history.connect_item_activated(move |_| {
+        if !keep_open_after_paste.get() && !history_for_paste.ctrl_held() {
+            request_quit();
         }
-    }
-
-        // Populate the list with clipboard items
-    for item in &prefetched_items {
-        let row = generate_listboxrow_from_preview(item);
-        list_box.append(&row);
-    }
-
-        // If no items, show a placeholder
-    if prefetched_items.is_empty() {
-        let placeholder_row = gtk4::ListBoxRow::new();
-        let placeholder_label = Label::new(Some("No clipboard history yet"));
-        placeholder_label.add_css_class("dim-label");
-        placeholder_label.set_margin_top(20);
-        placeholder_label.set_margin_bottom(20);
-        placeholder_row.set_child(Some(&placeholder_label));
-        list_box.append(&placeholder_row);
-    }
-
-    // Handle item activation (Enter/Space/double-click) instead of mere selection
-    let items_for_activation: Vec<ClipboardItemPreview> = prefetched_items;
-    list_box.connect_row_activated(move |_, row| {
-        let index = row.index() as usize;
-        if index < items_for_activation.len() {
-            let item = &items_for_activation[index];
-            debug!("Activated clipboard item ID {}: {}", item.item_id, item.content_preview);
-
-            match FrontendClient::new() {
-                Ok(mut client) => {
-                    if let Err(e) = client.set_clipboard_by_id(item.item_id) {
-                        error!("Error setting clipboard by ID: {}", e);
-                    } else {
-                        info!("Clipboard set by ID: {}", item.item_id);
-                        request_quit();
-                    }
-                }
-                Err(e) => {
-                    error!("Error creating frontend client: {}", e);
-                }
-            }
+    });
+    history.connect_close_requested(request_quit);
+    history.connect_filter_changed(move |filter| {
+        if filter.is_empty() {
+            title.set_subtitle("");
+        } else {
+            title.set_subtitle(&trf("Filter: {}", filter));
         }
     });
 
+    // Menu entries work through the header toggles, whose handlers keep the menu's state current
+    let view_action = gio::SimpleAction::new_stateful(VIEW_MODE_ACTION, Some(gtk4::glib::VariantTy::STRING), &history.view_mode().name().to_variant());
+    let view_toggle_for_action = view_toggle.clone();
+    view_action.connect_activate(move |_, parameter| {
+        if let Some(mode) = parameter.and_then(|p| p.str()).and_then(ViewMode::from_name) {
+            view_toggle_for_action.set_active(mode == ViewMode::Grid);
+        }
+    });
+    app.add_action(&view_action);
 
-    scrolled_window.set_child(Some(&list_box));
-    main_box.append(&scrolled_window);
+    let sort_action = gio::SimpleAction::new_stateful(SORT_MODE_ACTION, Some(gtk4::glib::VariantTy::STRING), &history.sort_mode().name().to_variant());
+    let sort_toggle_for_action = sort_toggle.clone();
+    sort_action.connect_activate(move |_, parameter| {
+        if let Some(mode) = parameter.and_then(|p| p.str()).and_then(SortMode::from_name) {
+            sort_toggle_for_action.set_active(mode == SortMode::MostUsed);
+        }
+    });
+    app.add_action(&sort_action);
+
+    let pause_action = gio::SimpleAction::new_stateful(PAUSE_CAPTURE_ACTION, None, &history.capture_paused().to_variant());
+    let pause_toggle_for_action = pause_toggle.clone();
+    pause_action.connect_activate(move |_, _| pause_toggle_for_action.emit_clicked());
+    app.add_action(&pause_action);
+
+    let timeline = gio::SimpleAction::new(TIMELINE_ACTION, None);
+    timeline.connect_activate(|_, _| open_timeline());
+    app.add_action(&timeline);
+
+    let statistics = gio::SimpleAction::new(STATISTICS_ACTION, None);
+    let stats_button_for_action = stats_button.clone();
+    statistics.connect_activate(move |_, _| {
+        // The menu's popover is still closing
+        let stats_button = stats_button_for_action.clone();
+        gtk4::glib::idle_add_local_once(move || stats_button.popup());
+    });
+    app.add_action(&statistics);
+
+    let preferences_action = gio::SimpleAction::new(PREFERENCES_ACTION, None);
+    let history_for_preferences = history.clone();
+    preferences_action.connect_activate(move |_, _| preferences::show_preferences(history_for_preferences.widget(), &history_for_preferences));
+    app.add_action(&preferences_action);
+
+    let about = gio::SimpleAction::new(ABOUT_ACTION, None);
+    let history_for_about = history.clone();
+    about.connect_activate(move |_, _| show_about(history_for_about.widget()));
+    app.add_action(&about);
+
+    update_view_toggle(&view_toggle, history.view_mode());
+    view_toggle.set_active(history.view_mode() == ViewMode::Grid);
+    let history_for_toggle = history.clone();
+    view_toggle.connect_toggled(move |toggle| {
+        let mode = if toggle.is_active() { ViewMode::Grid } else { ViewMode::List };
+        history_for_toggle.set_view_mode(mode);
+        update_view_toggle(toggle, mode);
+        view_action.set_state(&mode.name().to_variant());
+        history_for_toggle.focus_view();
+    });
 
-    // Connect button signals
-    // When the three-dot menu button is clicked: hide overlay, wait 0s, then show overlay again
-    three_dot_menu.connect_clicked(move |_| {
-        hide_overlay();
-        gtk4::glib::timeout_add_seconds_local(0, || {
-            show_overlay();
-            gtk4::glib::ControlFlow::Break
-        });
+    update_sort_toggle(&sort_toggle, history.sort_mode());
+    sort_toggle.set_active(history.sort_mode() == SortMode::MostUsed);
+    let history_for_sort = history.clone();
+    sort_toggle.connect_toggled(move |toggle| {
+        let mode = if toggle.is_active() { SortMode::MostUsed } else { SortMode::Recent };
+        history_for_sort.set_sort_mode(mode);
+        update_sort_toggle(toggle, mode);
+        sort_action.set_state(&mode.name().to_variant());
+        history_for_sort.focus_view();
     });
-    clear_button.connect_clicked(move |_| {
-    match FrontendClient::new() {
-            Ok(mut client) => {
-                if let Err(e) = client.clear_history() {
-                    error!("Error clearing clipboard history: {}", e);
-                } else {
-                    info!("Clipboard history cleared");
-                    // Close the overlay after clearing
-                    request_quit();
-                }
-            }
-            Err(e) => {
-                error!("Error creating frontend client: {}", e);
-            }
+
+    update_pause_toggle(&pause_toggle, history.capture_paused());
+    // "clicked" is only emitted for the user's toggling, not when following the backend state
+    let history_for_pause = history.clone();
+    let pause_action_for_toggle = pause_action.clone();
+    pause_toggle.connect_clicked(move |toggle| {
+        if let Err(e) = history_for_pause.set_capture_paused(toggle.is_active()) {
+            error!("Error pausing capture: {e}");
         }
+        update_pause_toggle(toggle, history_for_pause.capture_paused());
+        pause_action_for_toggle.set_state(&history_for_pause.capture_paused().to_variant());
+    });
+    let pause_toggle_for_changes = pause_toggle.clone();
+    history.connect_capture_paused_changed(move |paused| {
+        update_pause_toggle(&pause_toggle_for_changes, paused);
+        pause_action.set_state(&paused.to_variant());
+    });
+
+    let history_for_stats = history.clone();
+    stats_popover.connect_show(move |popover| {
+        popover.set_child(Some(&generate_stats_content(&history_for_stats)));
     });
 
-    (main_box, list_box)
+    // Connect button signals
+    let history_for_clear = history.clone();
+    clear_button.connect_clicked(move |button| confirm_clear(button, &history_for_clear));
+
+    (main_box, history)
 }
 
-/// Build the key controller handling Esc (close), j/k or arrows (navigate) and Enter (activate)
-fn generate_key_controller(list_box: &gtk4::ListBox) -> gtk4::EventControllerKey {
-    let controller = gtk4::EventControllerKey::new();
-    let list_box_for_keys = list_box.clone();
-    controller.connect_key_pressed(move |_, key, _, _| {
-        use gtk4::gdk::Key;
-        match key {
-            Key::Escape => {
-                request_quit();
-                gtk4::glib::Propagation::Stop
-            }
-            Key::j | Key::J | Key::Down => {
-                if let Some(current) = list_box_for_keys.selected_row() {
-                    let next_index = current.index() + 1;
-                    if let Some(next_row) = list_box_for_keys.row_at_index(next_index) {
-                        list_box_for_keys.select_row(Some(&next_row));
-                        next_row.grab_focus();
-                    }
-                } else if let Some(first_row) = list_box_for_keys.row_at_index(0) {
-                    list_box_for_keys.select_row(Some(&first_row));
-                    first_row.grab_focus();
-                }
-                gtk4::glib::Propagation::Stop
-            }
-            Key::k | Key::K | Key::Up => {
-                if let Some(current) = list_box_for_keys.selected_row() {
-                    if current.index() > 0 {
-                        let prev_index = current.index() - 1;
-                        if let Some(prev_row) = list_box_for_keys.row_at_index(prev_index) {
-                            list_box_for_keys.select_row(Some(&prev_row));
-                            prev_row.grab_focus();
-                        }
-                    }
-                } else if let Some(first_row) = list_box_for_keys.row_at_index(0) {
-                    list_box_for_keys.select_row(Some(&first_row));
-                    first_row.grab_focus();
-                }
-                gtk4::glib::Propagation::Stop
-            }
-            Key::Return | Key::KP_Enter => {
-                if let Some(row) = list_box_for_keys.selected_row() {
-                    row.emit_by_name::<()>("activate", &[]);
-                    return gtk4::glib::Propagation::Stop;
-                }
-                gtk4::glib::Propagation::Proceed
-            }
-            _ => gtk4::glib::Propagation::Proceed,
-        }
-    });
-    controller
+/// Primary menu of the header bar, its entries are the application actions added by
+/// `generate_overlay_content`
+fn generate_primary_menu() -> gio::Menu {
+    let menu = gio::Menu::new();
+    let capture = gio::Menu::new();
+    capture.append(Some(&gettext("Pause Capture")), Some(&format!("app.{PAUSE_CAPTURE_ACTION}")));
+    menu.append_section(None, &capture);
+
+    let sort = gio::Menu::new();
+    for (mode, label) in [(SortMode::Recent, gettext("Newest First")), (SortMode::MostUsed, gettext("Most Used First"))] {
+        let entry = gio::MenuItem::new(Some(&label), None);
+        entry.set_action_and_target_value(Some(&format!("app.{SORT_MODE_ACTION}")), Some(&mode.name().to_variant()));
+        sort.append_item(&entry);
+    }
+    menu.append_section(None, &sort);
+
+    let view = gio::Menu::new();
+    for (mode, label) in [(ViewMode::List, gettext("List")), (ViewMode::Grid, gettext("Grid"))] {
+        let entry = gio::MenuItem::new(Some(&label), None);
+        entry.set_action_and_target_value(Some(&format!("app.{VIEW_MODE_ACTION}")), Some(&mode.name().to_variant()));
+        view.append_item(&entry);
+    }
+    menu.append_section(None, &view);
+
+    let app_section = gio::Menu::new();
+    app_section.append(Some(&gettext("History by Day")), Some(&format!("app.{TIMELINE_ACTION}")));
+    app_section.append(Some(&gettext("Statistics")), Some(&format!("app.{STATISTICS_ACTION}")));
+    app_section.append(Some(&gettext("Preferences")), Some(&format!("app.{PREFERENCES_ACTION}")));
+    app_section.append(Some(&gettext("About Cursor Clip")), Some(&format!("app.{ABOUT_ACTION}")));
+    menu.append_section(None, &app_section);
+    menu
 }
 
-/// Apply custom CSS styling for modern GNOME-style rounded window
-fn apply_custom_styling(window: &adw::ApplicationWindow) {
-    let css_provider = gtk4::CssProvider::new();
-    css_provider.load_from_data(
-        "
-        window {
-            border-radius: 12px;
-            background: #222226;
+/// Open the history window in a process of its own, it outlives the overlay
+fn open_timeline() {
+    let executable = match std::env::current_exe() {
+        Ok(executable) => executable,
+        Err(e) => {
+            error!("Cannot locate the cursor-clip executable: {e}");
+            return;
         }
-
-        headerbar {
-            background: transparent;
-            box-shadow: none;
+    };
+    match std::process::Command::new(executable).arg("timeline").spawn() {
+        // Reap the window's process once it is closed
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
         }
+        Err(e) => error!("Could not open the history window: {e}"),
+    }
+}
 
-        .clipboard-list {
-            background: transparent;
+/// About dialog with the versions of the overlay and the daemon, which differ after an update
+/// until the daemon is restarted
+fn show_about(parent: &impl IsA<gtk4::Widget>) {
+    let overlay_version = format!("{} (protocol {})", env!("CARGO_PKG_VERSION"), protocol::PROTOCOL_VERSION);
+    let (comments, daemon_version) = match FrontendClient::new().and_then(|mut client| client.get_version()) {
+        Ok((version, protocol_version)) => {
+            let comments = if version == env!("CARGO_PKG_VERSION") && protocol_version == protocol::PROTOCOL_VERSION {
+                trf("Daemon version {}", &version)
+            } else {
+                trf("The daemon runs version {}, restart it to use this version", &format!("{version} (protocol {protocol_version})"))
+            };
+            (comments, format!("{version} (protocol {protocol_version})"))
         }
-
-        .clipboard-item {
-            background: #343437;
-            border: 2px solid transparent;
-            border-radius: 10px;
-            padding: 4px 4px;
-            margin: 6px 12px;
-            transition: border-color 150ms ease, box-shadow 150ms ease, background 150ms ease;
+        Err(e) => {
+            warn!("Could not get the daemon version: {e}");
+            (gettext("Daemon version unknown"), format!("unknown ({e})"))
         }
+    };
+    let dialog = adw::AboutDialog::builder()
+        .application_name("Cursor Clip")
+        .application_icon("edit-paste-symbolic")
+        .version(env!("CARGO_PKG_VERSION"))
+        .comments(comments)
+        .debug_info(format!("Overlay: {overlay_version}\nDaemon: {daemon_version}\n"))
+        .website("https://github.com/Sirulex/cursor-clip")
+        .issue_url("https://github.com/Sirulex/cursor-clip/issues")
+        .license_type(gtk4::License::Gpl30Only)
+        .translator_credits(gettext("translator-credits"))
+        .build();
+    dialog.present(Some(parent));
+}
 
-        .clipboard-item:hover {
-            border-color: #3584E4;
-            background: shade(#343437, 1.05);
+/// Ask before clearing the history, the overlay only closes once it was cleared
+fn confirm_clear(parent: &Button, history: &ClipboardHistoryWidget) {
+    let dialog = adw::AlertDialog::new(
+        Some(&gettext("Clear History?")),
+        Some(&gettext("All items are removed from the clipboard history.")),
+    );
+    dialog.add_responses(&[("cancel", gettext("_Cancel").as_str()), ("clear", gettext("_Clear All").as_str())]);
+    dialog.set_response_appearance("clear", adw::ResponseAppearance::Destructive);
+    dialog.set_default_response(Some("cancel"));
+    dialog.set_close_response("cancel");
+
+    let history = history.clone();
+    dialog.choose(parent, gtk4::gio::Cancellable::NONE, move |response| {
+        if response != "clear" {
+            return;
+        }
+        match history.clear_history() {
+            Ok(()) => request_quit(),
+            Err(e) => error!("Error clearing clipboard history: {}", e),
         }
+    });
+}
 
-        .clipboard-item:selected {
-            border-color: #3584E4;
-            background: alpha(#3584E4, 0.18);
+/// Content of the "About history" popover: a table of the history statistics
+fn generate_stats_content(history: &ClipboardHistoryWidget) -> gtk4::Widget {
+    let stats = match history.stats() {
+        Ok(stats) => stats,
+        Err(e) => {
+            error!("Error fetching history statistics: {e}");
+            let label = gtk4::Label::new(Some(&gettext("History statistics unavailable")));
+            label.add_css_class("dim-label");
+            return label.upcast();
         }
+    };
+
+    let grid = gtk4::Grid::builder().row_spacing(4).column_spacing(16).margin_top(6).margin_bottom(6).margin_start(6).margin_end(6).build();
+    let title = gtk4::Label::new(Some(&gettext("About history")));
+    title.add_css_class("heading");
+    title.set_halign(gtk4::Align::Start);
+    grid.attach(&title, 0, 0, 2, 1);
+    for (row, (label, value)) in stats.rows().into_iter().enumerate() {
+        let label = gtk4::Label::new(Some(&label));
+        label.add_css_class("dim-label");
+        label.set_halign(gtk4::Align::Start);
+        let value = gtk4::Label::new(Some(&value));
+        value.set_halign(gtk4::Align::End);
+        grid.attach(&label, 0, row as i32 + 1, 1, 1);
+        grid.attach(&value, 1, row as i32 + 1, 1, 1);
+    }
+    grid.upcast()
+}
 
-        .clipboard-preview {
-            opacity: 0.9;
+/// Show the backend's pause state on the toggle
+fn update_pause_toggle(toggle: &gtk4::ToggleButton, paused: bool) {
+    toggle.set_active(paused);
+    let tooltip = if paused { gettext("Capture paused — resume recording") } else { gettext("Pause recording") };
+    toggle.set_tooltip_text(Some(&tooltip));
+    toggle.update_property(&[gtk4::accessible::Property::Label(&tooltip)]);
+}
+
+/// Update the toggle to offer switching to the view mode that is not shown
+fn update_view_toggle(toggle: &gtk4::ToggleButton, mode: ViewMode) {
+    let (icon, tooltip) = match mode {
+        ViewMode::List => ("view-grid-symbolic", gettext("Show as grid")),
+        ViewMode::Grid => ("view-list-symbolic", gettext("Show as list")),
+    };
+    toggle.set_icon_name(icon);
+    toggle.set_tooltip_text(Some(&tooltip));
+    toggle.update_property(&[gtk4::accessible::Property::Label(&tooltip)]);
+}
+
+/// Show the order the history is listed in on the toggle
+fn update_sort_toggle(toggle: &gtk4::ToggleButton, mode: SortMode) {
+    toggle.set_label(&mode.label());
+    toggle.set_tooltip_text(Some(&match mode {
+        SortMode::Recent => gettext("Newest first — show the most used items first"),
+        SortMode::MostUsed => gettext("Most often pasted first — show the newest items first"),
+    }));
+}
+
+/// Find the GDK monitor for a Wayland output connector name
+fn monitor_by_connector(connector: &str) -> Option<gtk4::gdk::Monitor> {
+    let monitors = gtk4::gdk::Display::default()?.monitors();
+    (0..monitors.n_items())
+        .filter_map(|i| monitors.item(i).and_downcast::<gtk4::gdk::Monitor>())
+        .find(|monitor| monitor.connector().as_deref() == Some(connector))
+}
+
+/// Place the overlay's top-left corner at the cursor, shifted so the window never extends
+/// past the right/bottom edge of the output for cursor positions near the edges
+/// Anchor the layer surface according to `placement`: layer shell centers a surface along
+/// every axis it is not anchored on
+fn apply_placement(window: &adw::ApplicationWindow, placement: Placement, position: &OverlayPosition, monitor: Option<&gtk4::gdk::Monitor>) {
+    // A resident overlay keeps the anchors of its previous placement
+    for edge in [Edge::Top, Edge::Bottom, Edge::Left, Edge::Right] {
+        window.set_anchor(edge, false);
+        window.set_margin(edge, 0);
+    }
+    match placement {
+        Placement::Cursor => {
+            window.set_anchor(Edge::Top, true);
+            window.set_anchor(Edge::Left, true);
+            set_overlay_position(window, position, monitor);
+        }
+        Placement::Center => {}
+        Placement::Bottom => {
+            window.set_anchor(Edge::Bottom, true);
+            window.set_margin(Edge::Bottom, DOCK_MARGIN);
         }
+        Placement::Edge => {
+            for edge in [Edge::Top, Edge::Bottom, Edge::Right] {
+                window.set_anchor(edge, true);
+            }
+        }
+    }
+}
 
-        .clipboard-preview.monospace {
-            font-family: monospace;
+/// Close once the window was focused and loses the focus again (e.g. to a workspace switch)
+fn close_on_focus_loss(window: &adw::ApplicationWindow) {
+    let was_active = Cell::new(false);
+    window.connect_is_active_notify(move |window| {
+        if window.is_active() {
+            was_active.set(true);
+        } else if was_active.replace(false) && window.is_visible() {
+            debug!("Overlay lost the focus, closing it");
+            request_quit();
         }
+    });
+}
 
-        .clipboard-time {
-            font-size: 0.8em;
-            opacity: 0.6;
+/// Fade the overlay in when it is shown, sliding it towards its place from the edge it is
+/// anchored to (the edge panel slides in from outside the screen)
+fn animate_in(window: &adw::ApplicationWindow, placement: Placement) {
+    let (edge, offset) = match placement {
+        Placement::Cursor => (Some(Edge::Top), SLIDE_PX),
+        Placement::Center => (None, 0),
+        Placement::Bottom => (Some(Edge::Bottom), SLIDE_PX),
+        Placement::Edge => (Some(Edge::Right), window.preferred_size().1.width()),
+    };
+    let rest = edge.map_or(0, |edge| window.margin(edge));
+    let window_for_target = window.downgrade();
+    let target = adw::CallbackAnimationTarget::new(move |value| {
+        let Some(window) = window_for_target.upgrade() else { return };
+        window.set_opacity(value);
+        if let Some(edge) = edge {
+            window.set_margin(edge, rest - ((1.0 - value) * f64::from(offset)) as i32);
         }
-        "
-    );
+    });
+    let animation = adw::TimedAnimation::new(window, 0.0, 1.0, ANIMATION_MS, target);
+    animation.set_easing(adw::Easing::EaseOutCubic);
+    window.set_opacity(0.0);
+    OVERLAY_ANIMATION.with(|a| {
+        *a.borrow_mut() = Some(animation);
+    });
+}
 
-    gtk4::style_context_add_provider_for_display(
-        &gtk4::prelude::WidgetExt::display(window),
-        &css_provider,
-        gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
-    );
+pub fn set_overlay_position(window: &adw::ApplicationWindow, position: &OverlayPosition, monitor: Option<&gtk4::gdk::Monitor>) {
+    let output_size = position.output_size.or_else(|| {
+        monitor.map(|m| {
+            let geometry = m.geometry();
+            (geometry.width(), geometry.height())
+        })
+    });
+
+    let mut left = position.x as i32;
+    let mut top = position.y as i32;
+    if let Some((output_width, output_height)) = output_size {
+        let (_, natural) = window.preferred_size();
+        left = left.min(output_width - natural.width()).max(0);
+        top = top.min(output_height - natural.height()).max(0);
+    }
+
+    window.set_margin(Edge::Left, left);
+    window.set_margin(Edge::Top, top);
 }
 
 /// Show the overlay if it's hidden
@@ -403,77 +924,3 @@ pub fn hide_overlay() {
         }
     });
 }
-
-/// Create a clipboard history item row from backend data
-fn generate_listboxrow_from_preview(item: &ClipboardItemPreview) -> gtk4::ListBoxRow {
-    let row = gtk4::ListBoxRow::new();
-    row.add_css_class("clipboard-item");
-
-    let main_box = Box::new(Orientation::Vertical, 6);
-    main_box.set_margin_top(8);
-    main_box.set_margin_bottom(8);
-    main_box.set_margin_start(12);
-    main_box.set_margin_end(12);
-
-    // Header with content type and time
-    let header_box = Box::new(Orientation::Horizontal, 8);
-    
-    let type_label = Label::new(Some(item.content_type.icon()));
-    type_label.add_css_class("caption");
-    
-    let type_text = Label::new(Some(item.content_type.as_str()));
-    type_text.add_css_class("caption");
-    type_text.set_halign(Align::Start);
-    type_text.set_hexpand(true);
-    
-    let time_label = Label::new(Some(&format_timestamp(item.timestamp)));
-    time_label.add_css_class("caption");
-    time_label.add_css_class("clipboard-time");
-    time_label.set_halign(Align::End);
-
-    header_box.append(&type_label);
-    header_box.append(&type_text);
-    header_box.append(&time_label);
-    
-    main_box.append(&header_box);
-
-    let content_label = Label::new(Some(&item.content_preview));
-    content_label.add_css_class("clipboard-preview");
-    if matches!(item.content_type, ClipboardContentType::Code | ClipboardContentType::File) {
-        content_label.add_css_class("monospace");
-    }
-    content_label.set_halign(Align::Start);
-    content_label.set_wrap(true);
-    content_label.set_wrap_mode(gtk4::pango::WrapMode::WordChar);
-    content_label.set_max_width_chars(50);
-    content_label.set_lines(3);
-    content_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
-
-    main_box.append(&content_label);
-
-    row.set_child(Some(&main_box));
-    row
-}
-
-/// Format Unix timestamp to relative time string
-fn format_timestamp(timestamp: u64) -> String {
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    
-    let diff = now.saturating_sub(timestamp);
-    
-    if diff < 30 {
-        "Just now".to_string()
-    } else if diff < 3600 {
-        let minutes = diff / 60;
-        format!("{} minute{} ago", minutes, if minutes == 1 { "" } else { "s" })
-    } else if diff < 86400 {
-        let hours = diff / 3600;
-        format!("{} hour{} ago", hours, if hours == 1 { "" } else { "s" })
-    } else {
-        let days = diff / 86400;
-        format!("{} day{} ago", days, if days == 1 { "" } else { "s" })
-    }
-}
@@ -0,0 +1,82 @@
+//! Grid presentation of the history model with large image previews, an alternative to the list rows
+use gtk4::prelude::*;
+use gtk4::{Align, Label, Orientation};
+use crate::frontend::thumbnails::ThumbnailCache;
+use crate::frontend::history_list::{PastedMarks, add_drag_and_drop, set_accessible_text};
+use crate::frontend::history_model::item_from_object;
+use crate::shared::ClipboardContentType;
+use crate::shared::i18n::gettext;
+
+/// Size of the image thumbnails requested for grid cells
+const GRID_THUMBNAIL_PX: u32 = 256;
+
+/// Create the grid view over `selection` (shared with the list, so switching keeps the selection)
+pub fn generate_history_grid(selection: &gtk4::SingleSelection, thumbnails: &ThumbnailCache, pasted: &PastedMarks) -> gtk4::GridView {
+    let factory = gtk4::SignalListItemFactory::new();
+    factory.connect_setup(|_, object| {
+        let Some(list_item) = object.downcast_ref::<gtk4::ListItem>() else { return };
+        let cell = gtk4::Box::new(Orientation::Vertical, 6);
+        cell.add_css_class("clipboard-item");
+        cell.add_css_class("clipboard-grid-item");
+
+        let picture = gtk4::Picture::new();
+        picture.set_can_shrink(true);
+        picture.set_size_request(-1, GRID_THUMBNAIL_PX as i32 / 2);
+
+        let label = Label::new(None);
+        label.add_css_class("clipboard-preview");
+        label.set_halign(Align::Start);
+        label.set_valign(Align::Start);
+        label.set_wrap(true);
+        label.set_wrap_mode(gtk4::pango::WrapMode::WordChar);
+        label.set_max_width_chars(20);
+        label.set_lines(6);
+        label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+
+        cell.append(&picture);
+        cell.append(&label);
+        add_drag_and_drop(&cell, list_item);
+        list_item.set_child(Some(&cell));
+    });
+
+    let thumbnails = thumbnails.clone();
+    let pasted_for_bind = pasted.clone();
+    factory.connect_bind(move |_, object| {
+        let Some(list_item) = object.downcast_ref::<gtk4::ListItem>() else { return };
+        let Some(item) = list_item.item().and_then(|o| item_from_object(&o)) else { return };
+        let Some(cell) = list_item.child() else { return };
+        let Some(picture) = cell.first_child().and_downcast::<gtk4::Picture>() else { return };
+        let Some(label) = picture.next_sibling().and_downcast::<Label>() else { return };
+
+        let texture = matches!(item.content_type, ClipboardContentType::Image)
+            .then(|| thumbnails.texture(item.item_id, GRID_THUMBNAIL_PX))
+            .flatten();
+
+        picture.set_visible(texture.is_some());
+        picture.set_paintable(texture.as_ref());
+        if texture.is_some() {
+            label.set_text(&format!("{} {}", item.content_type.icon(), item.content_type.label()));
+        } else {
+            label.set_text(&item.content_preview);
+        }
+        label.set_tooltip_text(Some(&item.content_preview));
+        set_accessible_text(list_item, &item);
+        pasted_for_bind.bind(&cell, item.item_id);
+    });
+    let pasted = pasted.clone();
+    factory.connect_unbind(move |_, object| {
+        let Some(list_item) = object.downcast_ref::<gtk4::ListItem>() else { return };
+        if let Some(cell) = list_item.child() {
+            pasted.unbind(&cell);
+        }
+    });
+
+    let grid_view = gtk4::GridView::new(Some(selection.clone()), Some(factory));
+    grid_view.add_css_class("clipboard-list");
+    grid_view.set_min_columns(2);
+    grid_view.set_max_columns(3);
+    grid_view.set_single_click_activate(false);
+    grid_view.set_tab_behavior(gtk4::ListTabBehavior::Item);
+    grid_view.update_property(&[gtk4::accessible::Property::Label(&gettext("Clipboard history"))]);
+    grid_view
+}
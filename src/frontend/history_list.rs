@@ -0,0 +1,585 @@
+//! List presentation of the history model. Rows are recycled by the factory while scrolling,
+//! so only the visible items have widgets no matter how long the history is.
+use gtk4::prelude::*;
+use gtk4::{Align, Label, Orientation};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use crate::frontend::ipc_client::FrontendClient;
+use crate::frontend::thumbnails::ThumbnailCache;
+use crate::frontend::history_model::{HistoryModel, TimeGroup, item_from_object};
+use crate::frontend::time_format::{full_time, item_time};
+use crate::shared::{ClipboardContentType, ClipboardItemPreview, TextStats, TextTransform};
+use crate::shared::config::{ClockFormat, Config, Density, TimeFormat};
+use crate::shared::format::format_relative_time;
+use crate::shared::i18n::{gettext, trf, trn};
+use crate::shared::wire::WireFormat;
+use tracing::warn;
+
+/// Size of the image thumbnails requested for list rows
+const ROW_THUMBNAIL_PX: u32 = 160;
+/// Screen readers get the start of long previews only
+const ACCESSIBLE_LABEL_CHARS: usize = 200;
+
+/// Time labels of the currently bound rows with the copy time they show, so the relative
+/// times ("5 minutes ago") can be refreshed while the overlay stays open
+#[derive(Clone, Default)]
+pub struct TimeLabels {
+    labels: Rc<RefCell<HashMap<Label, u64>>>,
+    format: Rc<Cell<(TimeFormat, ClockFormat)>>,
+}
+
+impl TimeLabels {
+    fn bind(&self, label: &Label, secs: u64) {
+        let (format, clock) = self.format.get();
+        label.set_label(&item_time(secs, format, clock));
+        label.set_tooltip_text(Some(&full_time(secs, clock)));
+        self.labels.borrow_mut().insert(label.clone(), secs);
+    }
+
+    fn unbind(&self, label: &Label) {
+        self.labels.borrow_mut().remove(label);
+    }
+
+    /// Show the times of items as configured, taking effect on the next refresh
+    pub fn set_format(&self, format: TimeFormat, clock: ClockFormat) {
+        self.format.set((format, clock));
+    }
+
+    /// Re-render every bound label relative to the current time
+    pub fn refresh(&self) {
+        let (format, clock) = self.format.get();
+        for (label, secs) in self.labels.borrow().iter() {
+            label.set_label(&item_time(*secs, format, clock));
+            label.set_tooltip_text(Some(&full_time(*secs, clock)));
+        }
+    }
+}
+
+/// How the rows are laid out, from the config the list was created with
+#[derive(Debug, Clone, Copy)]
+pub struct RowLayout {
+    /// Lines of the content preview before it is ellipsized
+    pub preview_lines: i32,
+    pub density: Density,
+}
+
+impl RowLayout {
+    pub fn from_config(config: &Config) -> Self {
+        Self { preview_lines: config.preview_lines.clamp(1, i32::MAX as u32) as i32, density: config.density }
+    }
+}
+
+/// Items pasted while the overlay stayed open, marked with the `pasted` style class on their
+/// bound rows and cells
+#[derive(Clone, Default)]
+pub struct PastedMarks {
+    pasted: Rc<RefCell<HashSet<u64>>>,
+    bound: Rc<RefCell<HashMap<gtk4::Widget, u64>>>,
+}
+
+impl PastedMarks {
+    pub fn bind(&self, widget: &gtk4::Widget, item_id: u64) {
+        if self.pasted.borrow().contains(&item_id) {
+            widget.add_css_class("pasted");
+        } else {
+            widget.remove_css_class("pasted");
+        }
+        self.bound.borrow_mut().insert(widget.clone(), item_id);
+    }
+
+    pub fn unbind(&self, widget: &gtk4::Widget) {
+        self.bound.borrow_mut().remove(widget);
+    }
+
+    pub fn mark(&self, item_id: u64) {
+        self.pasted.borrow_mut().insert(item_id);
+        for (widget, _) in self.bound.borrow().iter().filter(|(_, id)| **id == item_id) {
+            widget.add_css_class("pasted");
+        }
+    }
+}
+
+/// Create the list view over `selection`, the rows of `model`'s groups can be expanded
+pub fn generate_history_list(selection: &gtk4::SingleSelection, model: &HistoryModel, thumbnails: &ThumbnailCache, time_labels: &TimeLabels, pasted: &PastedMarks, layout: RowLayout) -> gtk4::ListView {
+    let factory = gtk4::SignalListItemFactory::new();
+    factory.connect_setup(move |_, object| {
+        let Some(list_item) = object.downcast_ref::<gtk4::ListItem>() else { return };
+        let row = RowWidgets::new(layout);
+        add_drag_and_drop(&row.root, list_item);
+        list_item.set_child(Some(&row.root));
+
+        // Rows keep their widgets when filtering moves them, so renumber the badge here
+        list_item.connect_position_notify(|list_item| {
+            if let Some(row) = list_item.child().as_ref().and_then(RowWidgets::from_root) {
+                row.set_index_badge(list_item.position());
+            }
+        });
+    });
+    let thumbnails = thumbnails.clone();
+    let time_labels_for_bind = time_labels.clone();
+    let pasted_for_bind = pasted.clone();
+    let model = model.clone();
+    factory.connect_bind(move |_, object| {
+        let Some(list_item) = object.downcast_ref::<gtk4::ListItem>() else { return };
+        let Some(item) = list_item.item().and_then(|o| item_from_object(&o)) else { return };
+        let Some(row) = list_item.child().as_ref().and_then(RowWidgets::from_root) else { return };
+        row.bind(&item, &thumbnails);
+        row.bind_group(&item, &model);
+        row.set_index_badge(list_item.position());
+        set_accessible_text(list_item, &item);
+        time_labels_for_bind.bind(&row.time_label, item.stamp.secs());
+        pasted_for_bind.bind(row.root.upcast_ref(), item.item_id);
+    });
+    let time_labels = time_labels.clone();
+    let pasted = pasted.clone();
+    factory.connect_unbind(move |_, object| {
+        let Some(list_item) = object.downcast_ref::<gtk4::ListItem>() else { return };
+        let Some(row) = list_item.child().as_ref().and_then(RowWidgets::from_root) else { return };
+        time_labels.unbind(&row.time_label);
+        pasted.unbind(row.root.upcast_ref());
+    });
+
+    let list_view = gtk4::ListView::new(Some(selection.clone()), Some(factory));
+    list_view.set_header_factory(Some(&generate_section_header_factory()));
+    // Use custom styling instead of the default boxed-list to create floating cards
+    list_view.add_css_class("clipboard-list");
+    list_view.set_margin_bottom(6);
+    list_view.set_margin_start(4);
+    list_view.set_margin_end(4);
+    list_view.set_single_click_activate(true);
+    // Tab leaves the list after the buttons of the focused row instead of visiting every row,
+    // so the header and the filters stay reachable from the keyboard
+    list_view.set_tab_behavior(gtk4::ListTabBehavior::Item);
+    list_view.update_property(&[gtk4::accessible::Property::Label(&gettext("Clipboard history"))]);
+    list_view
+}
+
+/// What screen readers read for the row or cell of an item: its preview as the label, the
+/// content type, age and counters as the description. Selecting with the keys moves the focus
+/// to the row, so this is also what is announced when moving through the history.
+pub fn set_accessible_text(list_item: &gtk4::ListItem, item: &ClipboardItemPreview) {
+    let label: String = item.content_preview.chars().take(ACCESSIBLE_LABEL_CHARS).collect();
+    list_item.set_accessible_label(&label);
+
+    let mut parts = vec![item.content_type.label(), format_relative_time(item.stamp.secs())];
+    if let Some(device) = &item.source_device {
+        parts.push(trf("From {}", device));
+    }
+    if item.copy_count > 0 {
+        parts.push(trn("Copied {} time", "Copied {} times", item.copy_count.into()));
+    }
+    if !item.tags.is_empty() {
+        parts.push(trf("Tagged {}", &item.tags.join(", ")));
+    }
+    if item.possibly_truncated {
+        parts.push(gettext("The copied data may be incomplete"));
+    }
+    list_item.set_accessible_description(&parts.join(", "));
+}
+
+/// Rows can be dragged onto another row to move their item there (`history.move((item id,
+/// target item id))`) or into other applications, which receive the full payload in its
+/// original MIME types
+pub fn add_drag_and_drop(root: &impl IsA<gtk4::Widget>, list_item: &gtk4::ListItem) {
+    let bound_item = |list_item: &gtk4::glib::WeakRef<gtk4::ListItem>| {
+        list_item.upgrade().and_then(|list_item| list_item.item()).and_then(|object| item_from_object(&object))
+    };
+
+    let drag_source = gtk4::DragSource::new();
+    drag_source.set_actions(gtk4::gdk::DragAction::COPY | gtk4::gdk::DragAction::MOVE);
+    let list_item_for_drag = list_item.downgrade();
+    drag_source.connect_prepare(move |_, _, _| {
+        let item = bound_item(&list_item_for_drag)?;
+        Some(drag_content(&item))
+    });
+    root.add_controller(drag_source);
+
+    let drop_target = gtk4::DropTarget::new(u64::static_type(), gtk4::gdk::DragAction::MOVE);
+    let list_item_for_drop = list_item.downgrade();
+    drop_target.connect_drop(move |target, value, _, _| {
+        let (Ok(id), Some(onto)) = (value.get::<u64>(), bound_item(&list_item_for_drop)) else { return false };
+        if id == onto.item_id {
+            return false;
+        }
+        target
+            .widget()
+            .is_some_and(|widget| widget.activate_action("history.move", Some(&(id, onto.item_id).to_variant())).is_ok())
+    });
+    root.add_controller(drop_target);
+}
+
+/// Drag content of an item: its id for reordering inside the overlay plus every payload the
+/// backend stored for it. Without a backend (demo mode) the preview stands in for the text.
+fn drag_content(item: &ClipboardItemPreview) -> gtk4::gdk::ContentProvider {
+    let mut providers = vec![gtk4::gdk::ContentProvider::for_value(&item.item_id.to_value())];
+    // Payloads can be large images, which MessagePack frames carry as raw bytes
+    match FrontendClient::with_format(WireFormat::MsgPack).and_then(|mut client| client.get_item_data(item.item_id)) {
+        Ok(mime_data) => {
+            for (mime, data) in mime_data {
+                providers.push(gtk4::gdk::ContentProvider::for_bytes(&mime, &gtk4::glib::Bytes::from_owned(data)));
+            }
+        }
+        Err(e) => {
+            warn!("Failed to fetch the data of item {} for dragging: {e}", item.item_id);
+            if !matches!(item.content_type, ClipboardContentType::Image) {
+                providers.push(gtk4::gdk::ContentProvider::for_value(&item.content_preview.to_value()));
+            }
+        }
+    }
+    gtk4::gdk::ContentProvider::new_union(&providers)
+}
+
+/// Section headers ("Just now", "Today", ...) above the first row of each time group
+pub fn generate_section_header_factory() -> gtk4::SignalListItemFactory {
+    let factory = gtk4::SignalListItemFactory::new();
+    factory.connect_setup(|_, object| {
+        let Some(header) = object.downcast_ref::<gtk4::ListHeader>() else { return };
+        let label = Label::new(None);
+        label.add_css_class("clipboard-section-header");
+        label.set_halign(Align::Start);
+        header.set_child(Some(&label));
+    });
+    factory.connect_bind(|_, object| {
+        let Some(header) = object.downcast_ref::<gtk4::ListHeader>() else { return };
+        let Some(item) = header.item().and_then(|o| item_from_object(&o)) else { return };
+        let Some(label) = header.child().and_downcast::<Label>() else { return };
+        label.set_label(&TimeGroup::of(item.stamp.secs()).label());
+    });
+    factory
+}
+
+/// Widgets of one recycled row, created once in `setup` and refilled on every `bind`
+struct RowWidgets {
+    root: gtk4::Box,
+    index_badge: Label,
+    type_icon: Label,
+    /// Icon of the app the item was copied in, from its desktop file
+    app_icon: gtk4::Image,
+    type_text: Label,
+    count_label: Label,
+    warning_label: Label,
+    time_label: Label,
+    picture: gtk4::Picture,
+    content_label: Label,
+    /// "3 lines · 27 words · 182 chars" under the content of text items
+    stats_label: Label,
+    /// Tag chips, clicking one shows only the items carrying that tag
+    tags_box: gtk4::Box,
+    copy_text_button: gtk4::Button,
+    /// "+N" on the head of a group of rapid copies, expands it (`history.toggle-group(group id)`)
+    group_button: gtk4::Button,
+    menu_button: gtk4::MenuButton,
+}
+
+impl RowWidgets {
+    fn new(layout: RowLayout) -> Self {
+        let root = gtk4::Box::new(Orientation::Vertical, 0);
+        root.add_css_class("clipboard-item");
+
+        let (spacing, margin_y, margin_x) = match layout.density {
+            Density::Comfortable => (6, 8, 12),
+            Density::Compact => (2, 4, 8),
+        };
+        let main_box = gtk4::Box::new(Orientation::Vertical, spacing);
+        main_box.set_margin_top(margin_y);
+        main_box.set_margin_bottom(margin_y);
+        main_box.set_margin_start(margin_x);
+        main_box.set_margin_end(margin_x);
+
+        // Header with index badge, content type, counters and time
+        let header_box = gtk4::Box::new(Orientation::Horizontal, 8);
+
+        let index_badge = caption_label(&["clipboard-index"]);
+        let type_icon = caption_label(&[]);
+        let type_text = caption_label(&[]);
+        type_text.set_halign(Align::Start);
+        type_text.set_hexpand(true);
+        let count_label = caption_label(&["clipboard-time"]);
+        let warning_label = caption_label(&["warning"]);
+        warning_label.set_label("⚠");
+        let incomplete = gettext("The copied data may be incomplete");
+        warning_label.set_tooltip_text(Some(&incomplete));
+        warning_label.update_property(&[gtk4::accessible::Property::Label(&incomplete)]);
+        let time_label = caption_label(&["clipboard-time"]);
+        time_label.set_halign(Align::End);
+
+        for label in [&index_badge, &type_icon, &type_text, &count_label, &warning_label, &time_label] {
+            header_box.append(label);
+        }
+        let app_icon = gtk4::Image::new();
+        app_icon.set_pixel_size(16);
+        header_box.insert_child_after(&app_icon, Some(&type_icon));
+        let group_button = gtk4::Button::new();
+        group_button.add_css_class("flat");
+        group_button.add_css_class("caption");
+        group_button.add_css_class("clipboard-group-toggle");
+        group_button.set_focus_on_click(false);
+        group_button.set_action_name(Some("history.toggle-group"));
+        header_box.append(&group_button);
+        // Per-item actions, the menu is filled on bind
+        let menu_button = gtk4::MenuButton::new();
+        menu_button.set_icon_name("view-more-symbolic");
+        menu_button.add_css_class("flat");
+        menu_button.add_css_class("clipboard-row-menu");
+        let item_actions = gettext("Item actions");
+        menu_button.set_tooltip_text(Some(&item_actions));
+        menu_button.update_property(&[gtk4::accessible::Property::Label(&item_actions)]);
+        header_box.append(&menu_button);
+        main_box.append(&header_box);
+
+        let picture = gtk4::Picture::new();
+        picture.set_can_shrink(true);
+        picture.set_halign(Align::Start);
+        main_box.append(&picture);
+
+        let content_label = Label::new(None);
+        content_label.add_css_class("clipboard-preview");
+        content_label.set_halign(Align::Start);
+        content_label.set_wrap(true);
+        content_label.set_wrap_mode(gtk4::pango::WrapMode::WordChar);
+        content_label.set_max_width_chars(50);
+        content_label.set_lines(layout.preview_lines);
+        content_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+        main_box.append(&content_label);
+
+        let stats_label = caption_label(&["clipboard-time"]);
+        stats_label.set_halign(Align::Start);
+        main_box.append(&stats_label);
+
+        let tags_box = gtk4::Box::new(Orientation::Horizontal, 4);
+        tags_box.set_halign(Align::Start);
+        main_box.append(&tags_box);
+
+        // Paste the text recognized in an image instead of the image (`history.copy-text` action)
+        let copy_text_button = gtk4::Button::with_label(&gettext("Copy text"));
+        copy_text_button.add_css_class("flat");
+        copy_text_button.add_css_class("caption");
+        copy_text_button.set_halign(Align::Start);
+        copy_text_button.set_action_name(Some("history.copy-text"));
+        main_box.append(&copy_text_button);
+
+        root.append(&main_box);
+        Self { root, index_badge, type_icon, app_icon, type_text, count_label, warning_label, time_label, picture, content_label, stats_label, tags_box, copy_text_button, group_button, menu_button }
+    }
+
+    /// Recover the widgets from a row created by `new` (children are in creation order)
+    fn from_root(root: &gtk4::Widget) -> Option<Self> {
+        let main_box = root.first_child()?;
+        let header_box = main_box.first_child()?;
+        let picture = header_box.next_sibling().and_downcast::<gtk4::Picture>()?;
+        let content_label = picture.next_sibling().and_downcast::<Label>()?;
+        let stats_label = content_label.next_sibling().and_downcast::<Label>()?;
+        let tags_box = stats_label.next_sibling().and_downcast::<gtk4::Box>()?;
+        let copy_text_button = tags_box.next_sibling().and_downcast::<gtk4::Button>()?;
+        let menu_button = header_box.last_child().and_downcast::<gtk4::MenuButton>()?;
+        let group_button = menu_button.prev_sibling().and_downcast::<gtk4::Button>()?;
+
+        let labels: Vec<Label> = std::iter::successors(header_box.first_child(), |w| w.next_sibling())
+            .filter_map(|w| w.downcast::<Label>().ok())
+            .collect();
+        let [index_badge, type_icon, type_text, count_label, warning_label, time_label] = <[Label; 6]>::try_from(labels).ok()?;
+        let app_icon = type_icon.next_sibling().and_downcast::<gtk4::Image>()?;
+
+        Some(Self {
+            root: root.clone().downcast().ok()?,
+            index_badge,
+            type_icon,
+            app_icon,
+            type_text,
+            count_label,
+            warning_label,
+            time_label,
+            picture,
+            content_label,
+            stats_label,
+            tags_box,
+            copy_text_button,
+            group_button,
+            menu_button,
+        })
+    }
+
+    fn bind(&self, item: &ClipboardItemPreview, thumbnails: &ThumbnailCache) {
+        self.type_icon.set_label(item.content_type.icon());
+        let mut type_text = item.content_type.label();
+        if let Some(device) = &item.source_device {
+            type_text = format!("{type_text} · {}", trf("from {}", device));
+        }
+        // Only worth mentioning on multi-seat setups, the first seat is "seat0" by convention
+        if let Some(seat) = item.seat.as_ref().filter(|seat| *seat != "seat0") {
+            type_text = format!("{type_text} · {seat}");
+        }
+        self.type_text.set_label(&type_text);
+
+        let app = item.source_app.as_deref().and_then(source_app_icon);
+        self.app_icon.set_visible(app.is_some());
+        if let Some((icon, name)) = app {
+            self.app_icon.set_from_gicon(&icon);
+            let copied_in = trf("Copied in {}", &name);
+            self.app_icon.set_tooltip_text(Some(&copied_in));
+            self.app_icon.update_property(&[gtk4::accessible::Property::Label(&copied_in)]);
+        }
+
+        // Small "copied N times" counter, only shown once the item was re-copied
+        self.count_label.set_visible(item.copy_count > 0);
+        self.count_label.set_label(&format!("×{}", item.copy_count));
+        self.count_label.set_tooltip_text(Some(&trn("Copied {} time", "Copied {} times", item.copy_count.into())));
+        self.warning_label.set_visible(item.possibly_truncated);
+
+        let texture = matches!(item.content_type, ClipboardContentType::Image)
+            .then(|| thumbnails.texture(item.item_id, ROW_THUMBNAIL_PX))
+            .flatten();
+        self.picture.set_visible(texture.is_some());
+        self.picture.set_paintable(texture.as_ref());
+
+        self.content_label.set_label(&item.content_preview);
+        if matches!(item.content_type, ClipboardContentType::Code | ClipboardContentType::File) {
+            self.content_label.add_css_class("monospace");
+        } else {
+            self.content_label.remove_css_class("monospace");
+        }
+        self.stats_label.set_visible(item.text_stats.is_some());
+        self.stats_label.set_label(&item.text_stats.as_ref().map(TextStats::summary).unwrap_or_default());
+
+        while let Some(chip) = self.tags_box.first_child() {
+            self.tags_box.remove(&chip);
+        }
+        for tag in &item.tags {
+            let chip = gtk4::Button::with_label(&format!("#{tag}"));
+            chip.add_css_class("flat");
+            chip.add_css_class("clipboard-tag");
+            chip.set_focus_on_click(false);
+            chip.set_tooltip_text(Some(&trf("Show the items tagged {}", tag)));
+            chip.set_action_name(Some("history.show-tag"));
+            chip.set_action_target_value(Some(&tag.to_variant()));
+            self.tags_box.append(&chip);
+        }
+        self.tags_box.set_visible(!item.tags.is_empty());
+
+        self.copy_text_button.set_visible(item.ocr_text.is_some());
+        self.copy_text_button.set_action_target_value(Some(&item.item_id.to_variant()));
+        self.copy_text_button.set_tooltip_text(item.ocr_text.as_deref());
+
+        self.menu_button.set_menu_model(Some(&generate_item_menu(item)));
+    }
+
+    /// Show the size of a group on its head and indent the other items of expanded groups
+    fn bind_group(&self, item: &ClipboardItemPreview, model: &HistoryModel) {
+        if item.is_group_member() {
+            self.root.add_css_class("clipboard-group-member");
+        } else {
+            self.root.remove_css_class("clipboard-group-member");
+        }
+        let others = item.group_id.filter(|group| *group == item.item_id).map_or(0, |group| model.group_size(group).saturating_sub(1));
+        self.group_button.set_visible(others > 0);
+        if let Some(group) = item.group_id.filter(|_| others > 0) {
+            let expanded = model.group_expanded(group);
+            self.group_button.set_label(&if expanded { "−".to_string() } else { format!("+{others}") });
+            let tooltip = if expanded {
+                gettext("Hide the other copies")
+            } else {
+                trn("Show {} more copy from the same app", "Show {} more copies from the same app", others.into())
+            };
+            self.group_button.set_tooltip_text(Some(&tooltip));
+            // The button only shows "+N" or "−"
+            self.group_button.update_property(&[gtk4::accessible::Property::Label(&tooltip)]);
+            self.group_button.set_action_target_value(Some(&group.to_variant()));
+        }
+    }
+
+    /// Number the first nine rows for quick paste
+    fn set_index_badge(&self, position: u32) {
+        self.index_badge.set_visible(position < 9);
+        self.index_badge.set_label(&(position + 1).to_string());
+        self.index_badge.set_tooltip_text(Some(&trf("Press {} to paste", &(position + 1).to_string())));
+    }
+}
+
+thread_local! {
+    /// Desktop file lookups by app id, rows are bound over and over while scrolling
+    static APP_ICONS: RefCell<HashMap<String, Option<(gtk4::gio::Icon, String)>>> = RefCell::default();
+}
+
+/// Icon and name of the app with the given Wayland app id, None if it has no desktop file.
+/// App ids usually are the desktop file name, though some apps only match in lower case.
+fn source_app_icon(app_id: &str) -> Option<(gtk4::gio::Icon, String)> {
+    APP_ICONS.with(|icons| {
+        icons
+            .borrow_mut()
+            .entry(app_id.to_string())
+            .or_insert_with(|| {
+                let info = gtk4::gio::DesktopAppInfo::new(&format!("{app_id}.desktop"))
+                    .or_else(|| gtk4::gio::DesktopAppInfo::new(&format!("{}.desktop", app_id.to_lowercase())))?;
+                Some((info.icon()?, info.display_name().to_string()))
+            })
+            .clone()
+    })
+}
+
+/// Row menu with "Paste Original" (`history.paste-original(item id)`) for cleaned-up text,
+/// for text items the "Transform" submenu (`history.transform((item id,
+/// transform name))`) and "Send to Phone" (`history.send-to-phone(item id)`), for links "Paste
+/// Cleaned URL", and "Move to Top" (`history.move-to-top(item id)`), "Add Tag…"
+/// (`history.add-tag(item id)`), "Remove Tag" (`history.remove-tag((item id, tag))`),
+/// "Inspect…" (`history.inspect(item id)`) and "Delete" (`history.delete(item id)`) for every item
+fn generate_item_menu(item: &ClipboardItemPreview) -> gtk4::gio::Menu {
+    let (item_id, content_type) = (item.item_id, item.content_type);
+    let menu = gtk4::gio::Menu::new();
+    if item.has_original {
+        let original = gtk4::gio::MenuItem::new(Some(&gettext("Paste Original")), None);
+        original.set_action_and_target_value(Some("history.paste-original"), Some(&item_id.to_variant()));
+        menu.append_item(&original);
+    }
+    let move_to_top = gtk4::gio::MenuItem::new(Some(&gettext("Move to Top")), None);
+    move_to_top.set_action_and_target_value(Some("history.move-to-top"), Some(&item_id.to_variant()));
+    menu.append_item(&move_to_top);
+    if matches!(content_type, ClipboardContentType::Url) {
+        let clean = gtk4::gio::MenuItem::new(Some(&gettext("Paste Cleaned URL")), None);
+        clean.set_action_and_target_value(Some("history.transform"), Some(&(item_id, TextTransform::CleanUrl.name()).to_variant()));
+        menu.append_item(&clean);
+    }
+    if matches!(content_type, ClipboardContentType::Text | ClipboardContentType::Url | ClipboardContentType::Code) {
+        let transforms = gtk4::gio::Menu::new();
+        // Links have their own entry, other text would be refused
+        for op in TextTransform::ALL.into_iter().filter(|op| *op != TextTransform::CleanUrl) {
+            let entry = gtk4::gio::MenuItem::new(Some(&op.label()), None);
+            entry.set_action_and_target_value(Some("history.transform"), Some(&(item_id, op.name()).to_variant()));
+            transforms.append_item(&entry);
+        }
+        menu.append_submenu(Some(&gettext("Transform")), &transforms);
+        let send = gtk4::gio::MenuItem::new(Some(&gettext("Send to Phone")), None);
+        send.set_action_and_target_value(Some("history.send-to-phone"), Some(&item_id.to_variant()));
+        menu.append_item(&send);
+    }
+    let add_tag = gtk4::gio::MenuItem::new(Some(&gettext("Add Tag…")), None);
+    add_tag.set_action_and_target_value(Some("history.add-tag"), Some(&item_id.to_variant()));
+    menu.append_item(&add_tag);
+    if !item.tags.is_empty() {
+        let remove_tags = gtk4::gio::Menu::new();
+        for tag in &item.tags {
+            let entry = gtk4::gio::MenuItem::new(Some(&format!("#{tag}")), None);
+            entry.set_action_and_target_value(Some("history.remove-tag"), Some(&(item_id, tag.as_str()).to_variant()));
+            remove_tags.append_item(&entry);
+        }
+        menu.append_submenu(Some(&gettext("Remove Tag")), &remove_tags);
+    }
+    let inspect = gtk4::gio::MenuItem::new(Some(&gettext("Inspect…")), None);
+    inspect.set_action_and_target_value(Some("history.inspect"), Some(&item_id.to_variant()));
+    menu.append_item(&inspect);
+    let delete = gtk4::gio::MenuItem::new(Some(&gettext("Delete")), None);
+    delete.set_action_and_target_value(Some("history.delete"), Some(&item_id.to_variant()));
+    let delete_section = gtk4::gio::Menu::new();
+    delete_section.append_item(&delete);
+    menu.append_section(None, &delete_section);
+    menu
+}
+
+fn caption_label(css_classes: &[&str]) -> Label {
+    let label = Label::new(None);
+    label.add_css_class("caption");
+    for class in css_classes {
+        label.add_css_class(class);
+    }
+    label
+}
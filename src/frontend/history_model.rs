@@ -0,0 +1,466 @@
+//! List model layer of the overlay: clipboard items wrapped in GObjects, filtered by the
+//! type-to-filter text and split into time sections ("Today", "Yesterday", ...). List widgets
+//! bind to `model()` instead of appending rows directly. Items are fetched from the backend
+//! in pages as the views scroll towards the end.
+use gtk4::gio;
+use gtk4::glib::BoxedAnyObject;
+use gtk4::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::rc::Rc;
+use crate::frontend::ipc_client::FrontendClient;
+use crate::shared::{BackendMessage, ClipboardContentType, ClipboardItemPreview, SortMode};
+use crate::shared::i18n::gettext;
+use tracing::{debug, warn};
+
+/// Number of items fetched from the backend per page
+pub const HISTORY_PAGE_SIZE: u32 = 50;
+
+#[derive(Clone)]
+pub struct HistoryModel {
+    store: gio::ListStore,
+    filter: gtk4::CustomFilter,
+    /// The filtered items with sections by `TimeGroup`, the order is left unchanged
+    sectioned: gtk4::SortListModel,
+    /// Groups items by `TimeGroup`, only while they are listed newest first
+    section_sorter: gtk4::CustomSorter,
+    filter_text: Rc<RefCell<String>>,
+    /// Content types to show (all if empty), filtered by the backend so paging only sees matches
+    content_types: Rc<RefCell<Vec<ClipboardContentType>>>,
+    /// Only show items carrying this tag, filtered by the backend like the content types
+    tag: Rc<RefCell<Option<String>>>,
+    /// Groups of rapid copies showing all their items, other groups only show their head
+    expanded_groups: Rc<RefCell<HashSet<u64>>>,
+    /// Order of the items, the backend sorts the pages
+    sort: Rc<Cell<SortMode>>,
+    /// The model holds the complete history and never fetches from the backend
+    offline: bool,
+    /// Ids of loaded items, new copies shift the backend's offsets between page fetches
+    loaded_ids: Rc<RefCell<HashSet<u64>>>,
+    /// Total number of history items in the backend, unknown until the first page was fetched
+    total: Rc<Cell<Option<u32>>>,
+}
+
+impl HistoryModel {
+    /// Create the model with already fetched items (the first window of the history in `sort` order)
+    pub fn new(items: Vec<ClipboardItemPreview>, sort: SortMode) -> Self {
+        let store = gio::ListStore::new::<BoxedAnyObject>();
+
+        // Case-insensitive prefix match on the preview text and the selected content types
+        let filter_text = Rc::new(RefCell::new(String::new()));
+        let content_types: Rc<RefCell<Vec<ClipboardContentType>>> = Rc::default();
+        let tag: Rc<RefCell<Option<String>>> = Rc::default();
+        let text = filter_text.clone();
+        let types = content_types.clone();
+        let tag_for_filter = tag.clone();
+        let expanded_groups: Rc<RefCell<HashSet<u64>>> = Rc::default();
+        let expanded = expanded_groups.clone();
+        let filter = gtk4::CustomFilter::new(move |object| {
+            object.downcast_ref::<BoxedAnyObject>().is_some_and(|boxed| {
+                let item = boxed.borrow::<ClipboardItemPreview>();
+                let text = text.borrow();
+                // Filtering by text shows every match, grouped or not
+                let collapsed = text.is_empty() && item.is_group_member() && !item.group_id.is_some_and(|group| expanded.borrow().contains(&group));
+                !collapsed && item.matches(&text, &types.borrow()) && item.has_tag(tag_for_filter.borrow().as_deref())
+            })
+        });
+        let filtered = gtk4::FilterListModel::new(Some(store.clone()), Some(filter.clone()));
+
+        // Items are stored newest first, so the groups are contiguous and sorting by them keeps the order
+        let section_sorter = gtk4::CustomSorter::new(|a, b| {
+            let group = |object: &gtk4::glib::Object| item_from_object(object).map(|item| TimeGroup::of(item.stamp.secs()));
+            group(a).cmp(&group(b)).into()
+        });
+        let sectioned = gtk4::SortListModel::new(Some(filtered), None::<gtk4::Sorter>);
+        if sort == SortMode::Recent {
+            sectioned.set_section_sorter(Some(&section_sorter));
+        }
+
+        let model = Self {
+            store,
+            filter,
+            sectioned,
+            section_sorter,
+            filter_text,
+            content_types,
+            tag,
+            expanded_groups,
+            sort: Rc::new(Cell::new(sort)),
+            offline: false,
+            loaded_ids: Rc::default(),
+            total: Rc::default(),
+        };
+        model.append_items(items);
+        model
+    }
+
+    /// Create the model with the complete history, nothing is fetched from the backend
+    pub fn with_all_items(mut items: Vec<ClipboardItemPreview>, sort: SortMode) -> Self {
+        items.sort_by(|a, b| sort.compare(a, b));
+        let model = Self { offline: true, ..Self::new(items, sort) };
+        model.total.set(Some(model.store.n_items()));
+        model
+    }
+
+    /// Recompute the time sections, items move from "Just now" to "Today" etc. as time passes
+    pub fn refresh_sections(&self) {
+        if let Some(sorter) = self.sectioned.section_sorter() {
+            sorter.changed(gtk4::SorterChange::Different);
+        }
+    }
+
+    /// Have the views bind every row again, e.g. after a display setting changed
+    pub fn rebind_all(&self) {
+        let n_items = self.store.n_items();
+        self.store.items_changed(0, n_items, n_items);
+    }
+
+    /// The filtered, sectioned model list widgets should bind to
+    pub const fn model(&self) -> &gtk4::SortListModel {
+        &self.sectioned
+    }
+
+    /// Item at a position of the filtered model (i.e. a row index)
+    pub fn item_at(&self, position: u32) -> Option<ClipboardItemPreview> {
+        self.sectioned.item(position).and_then(|object| item_from_object(&object))
+    }
+
+    fn append_items(&self, items: Vec<ClipboardItemPreview>) {
+        let mut loaded_ids = self.loaded_ids.borrow_mut();
+        let objects: Vec<BoxedAnyObject> = items
+            .into_iter()
+            .filter(|item| loaded_ids.insert(item.item_id))
+            .map(BoxedAnyObject::new)
+            .collect();
+        self.store.extend_from_slice(&objects);
+    }
+
+    /// Apply a push from the backend: new items are inserted by their stamp, updated items
+    /// replace their loaded row, removed items disappear. Other messages are ignored.
+    pub fn apply_push(&self, message: &BackendMessage) {
+        match message {
+            BackendMessage::NewItem { item } => self.upsert_item(item.clone()),
+            BackendMessage::ItemsAdded { items } => {
+                for item in items {
+                    self.upsert_item(item.clone());
+                }
+            }
+            // Items that are not loaded yet arrive updated with their page once scrolled to
+            BackendMessage::ItemUpdated { item } if self.loaded_ids.borrow().contains(&item.item_id) => {
+                self.upsert_item(item.clone());
+                // Joined a group, the head counts its items
+                if let Some(group) = item.group_id.filter(|_| item.is_group_member()) {
+                    self.rebind_item(group);
+                }
+            }
+            BackendMessage::ItemsRemoved { ids } => {
+                for id in ids {
+                    if self.remove_item(*id) {
+                        self.total.set(self.total.get().map(|total| total.saturating_sub(1)));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Insert an item (replacing a loaded item with the same id) keeping the sort order
+    fn upsert_item(&self, item: ClipboardItemPreview) {
+        // Items of other content types or without the tag are not part of the backend's filtered pages either
+        if !item.matches("", &self.content_types.borrow()) || !item.has_tag(self.tag.borrow().as_deref()) {
+            if self.remove_item(item.item_id) {
+                self.total.set(self.total.get().map(|total| total.saturating_sub(1)));
+            }
+            return;
+        }
+        let replaced = self.remove_item(item.item_id);
+        let sort = self.sort.get();
+        let position = (0..self.store.n_items())
+            .find(|&i| {
+                self.store
+                    .item(i)
+                    .and_then(|object| item_from_object(&object))
+                    // Equal stamps sort in above, like in the backend
+                    .is_some_and(|existing| sort.compare(&item, &existing).is_le())
+            })
+            .unwrap_or(self.store.n_items());
+        self.loaded_ids.borrow_mut().insert(item.item_id);
+        self.store.insert(position, &BoxedAnyObject::new(item));
+        if !replaced {
+            self.total.set(self.total.get().map(|total| total + 1));
+        }
+    }
+
+    /// Remove a loaded item, returns false if it was not loaded
+    fn remove_item(&self, item_id: u64) -> bool {
+        if !self.loaded_ids.borrow_mut().remove(&item_id) {
+            return false;
+        }
+        let position = (0..self.store.n_items()).find(|&i| {
+            self.store
+                .item(i)
+                .and_then(|object| item_from_object(&object))
+                .is_some_and(|existing| existing.item_id == item_id)
+        });
+        if let Some(position) = position {
+            self.store.remove(position);
+        }
+        true
+    }
+
+    /// Position of a loaded item in the backend's history. Only known while all items are shown
+    /// newest first, otherwise the loaded items are a filtered or resorted subset.
+    pub fn history_position(&self, item_id: u64) -> Option<u32> {
+        if !self.content_types.borrow().is_empty() || self.tag.borrow().is_some() || self.sort.get() != SortMode::Recent {
+            return None;
+        }
+        (0..self.store.n_items()).find(|&i| {
+            self.store
+                .item(i)
+                .and_then(|object| item_from_object(&object))
+                .is_some_and(|existing| existing.item_id == item_id)
+        })
+    }
+
+    /// Whether the backend has items that were not fetched yet
+    pub fn has_more(&self) -> bool {
+        self.total.get().is_none_or(|total| self.store.n_items() < total)
+    }
+
+    /// Fetch the next page of the history from the backend, returns false if nothing was added
+    pub fn load_next_page(&self) -> bool {
+        if !self.has_more() {
+            return false;
+        }
+        let offset = self.store.n_items();
+        let content_types = self.content_types.borrow().clone();
+        let tag = self.tag.borrow().clone();
+        let sort = self.sort.get();
+        let page = FrontendClient::new().and_then(|mut client| {
+            if content_types.is_empty() && tag.is_none() {
+                client.get_history_page(offset, HISTORY_PAGE_SIZE, sort)
+            } else {
+                client.search_history("", &content_types, tag.as_deref(), sort, offset, HISTORY_PAGE_SIZE)
+            }
+        });
+        match page {
+            Ok((items, total)) => {
+                debug!("Fetched {} history items at offset {offset} (total {total})", items.len());
+                let received = items.len();
+                self.append_items(items);
+                // A short page means the end was reached, even if items were removed meanwhile
+                let loaded = self.store.n_items();
+                self.total.set(Some(if received < HISTORY_PAGE_SIZE as usize { loaded } else { total }));
+                loaded > offset
+            }
+            Err(e) => {
+                warn!("Error fetching clipboard history page at offset {offset}: {e}");
+                // Stop paging instead of retrying on every scroll event
+                self.total.set(Some(self.store.n_items()));
+                false
+            }
+        }
+    }
+
+    /// Drop all loaded items and fetch the first page again, e.g. after the backend restarted
+    pub fn reload(&self) {
+        self.clear();
+        self.total.set(None);
+        self.load_next_page();
+    }
+
+    /// Drop all loaded items, the backend is not asked for more afterwards
+    pub fn clear(&self) {
+        self.store.remove_all();
+        self.loaded_ids.borrow_mut().clear();
+        self.total.set(Some(0));
+    }
+
+    /// Fetch all remaining pages (filtering has to see the whole history)
+    pub fn load_all(&self) {
+        while self.load_next_page() {}
+    }
+
+    pub fn content_types(&self) -> Vec<ClipboardContentType> {
+        self.content_types.borrow().clone()
+    }
+
+    /// Only show items of `content_types` (all if empty), returns false if nothing changed.
+    /// The items are fetched again, filtered by the backend.
+    pub fn set_content_types(&self, content_types: Vec<ClipboardContentType>) -> bool {
+        if *self.content_types.borrow() == content_types {
+            return false;
+        }
+        *self.content_types.borrow_mut() = content_types;
+        if !self.offline {
+            self.reload();
+            // Filtering by text has to see all matches
+            if !self.filter_text.borrow().is_empty() {
+                self.load_all();
+            }
+        }
+        self.filter.changed(gtk4::FilterChange::Different);
+        true
+    }
+
+    pub fn tag(&self) -> Option<String> {
+        self.tag.borrow().clone()
+    }
+
+    /// Only show items carrying `tag` (all if None), returns false if nothing changed. The items
+    /// are fetched again, filtered by the backend.
+    pub fn set_tag(&self, tag: Option<String>) -> bool {
+        if *self.tag.borrow() == tag {
+            return false;
+        }
+        *self.tag.borrow_mut() = tag;
+        if !self.offline {
+            self.reload();
+            // Filtering by text has to see all matches
+            if !self.filter_text.borrow().is_empty() {
+                self.load_all();
+            }
+        }
+        self.filter.changed(gtk4::FilterChange::Different);
+        true
+    }
+
+    pub fn sort_mode(&self) -> SortMode {
+        self.sort.get()
+    }
+
+    /// List the items in `sort` order, returns false if nothing changed. The items are fetched
+    /// again in that order, time sections are only shown for the newest first order.
+    pub fn set_sort_mode(&self, sort: SortMode) -> bool {
+        if self.sort.replace(sort) == sort {
+            return false;
+        }
+        let section_sorter = (sort == SortMode::Recent).then_some(&self.section_sorter);
+        self.sectioned.set_section_sorter(section_sorter);
+        if self.offline {
+            let mut items: Vec<ClipboardItemPreview> = (0..self.store.n_items())
+                .filter_map(|i| self.store.item(i).and_then(|object| item_from_object(&object)))
+                .collect();
+            items.sort_by(|a, b| sort.compare(a, b));
+            let objects: Vec<BoxedAnyObject> = items.into_iter().map(BoxedAnyObject::new).collect();
+            self.store.splice(0, self.store.n_items(), &objects);
+        } else {
+            self.reload();
+            // Filtering by text has to see all matches
+            if !self.filter_text.borrow().is_empty() {
+                self.load_all();
+            }
+        }
+        true
+    }
+
+    /// Number of loaded items in a group of rapid copies
+    pub fn group_size(&self, group_id: u64) -> u32 {
+        (0..self.store.n_items())
+            .filter_map(|i| self.store.item(i).and_then(|object| item_from_object(&object)))
+            .filter(|item| item.group_id == Some(group_id))
+            .count() as u32
+    }
+
+    pub fn group_expanded(&self, group_id: u64) -> bool {
+        self.expanded_groups.borrow().contains(&group_id)
+    }
+
+    /// Show or hide the items of a group besides its head, returns whether it is expanded now
+    pub fn toggle_group(&self, group_id: u64) -> bool {
+        let expanded = {
+            let mut groups = self.expanded_groups.borrow_mut();
+            groups.insert(group_id) || !groups.remove(&group_id)
+        };
+        self.filter.changed(if expanded { gtk4::FilterChange::LessStrict } else { gtk4::FilterChange::MoreStrict });
+        // The head's toggle changes its label
+        self.rebind_item(group_id);
+        expanded
+    }
+
+    /// Have the views bind the row of an item again
+    fn rebind_item(&self, item_id: u64) {
+        let position = (0..self.store.n_items())
+            .find(|&i| self.store.item(i).and_then(|object| item_from_object(&object)).is_some_and(|item| item.item_id == item_id));
+        if let Some(position) = position {
+            self.store.items_changed(position, 1, 1);
+        }
+    }
+
+    pub fn filter_text(&self) -> String {
+        self.filter_text.borrow().clone()
+    }
+
+    pub fn push_filter_char(&self, c: char) {
+        if self.filter_text.borrow().is_empty() {
+            self.load_all();
+        }
+        self.filter_text.borrow_mut().extend(c.to_lowercase());
+        self.filter.changed(gtk4::FilterChange::MoreStrict);
+    }
+
+    /// Remove the last filter character, returns false if the filter was already empty
+    pub fn pop_filter_char(&self) -> bool {
+        let popped = self.filter_text.borrow_mut().pop().is_some();
+        if popped {
+            self.filter.changed(gtk4::FilterChange::LessStrict);
+        }
+        popped
+    }
+
+    /// Clear the filter, returns false if there was nothing to clear
+    pub fn clear_filter(&self) -> bool {
+        if self.filter_text.borrow().is_empty() {
+            return false;
+        }
+        self.filter_text.borrow_mut().clear();
+        self.filter.changed(gtk4::FilterChange::LessStrict);
+        true
+    }
+}
+
+/// Section of the history an item is shown in, by the age of the copy (newest first)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TimeGroup {
+    JustNow,
+    Today,
+    Yesterday,
+    Older,
+}
+
+impl TimeGroup {
+    /// Copies within this many seconds count as "Just now"
+    const JUST_NOW_SECS: i64 = 5 * 60;
+
+    /// Group of an item copied at the Unix time `secs`, by the local calendar day
+    pub fn of(secs: u64) -> Self {
+        let (Ok(now), Ok(copied)) = (gtk4::glib::DateTime::now_local(), gtk4::glib::DateTime::from_unix_local(secs as i64)) else {
+            return Self::Older;
+        };
+        if now.to_unix() - copied.to_unix() < Self::JUST_NOW_SECS {
+            Self::JustNow
+        } else if now.ymd() == copied.ymd() {
+            Self::Today
+        } else if now.add_days(-1).is_ok_and(|yesterday| yesterday.ymd() == copied.ymd()) {
+            Self::Yesterday
+        } else {
+            Self::Older
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Self::JustNow => gettext("Just now"),
+            Self::Today => gettext("Today"),
+            Self::Yesterday => gettext("Yesterday"),
+            Self::Older => gettext("Older"),
+        }
+    }
+}
+
+/// Extract the item from a model object handed to a row factory
+pub fn item_from_object(object: &gtk4::glib::Object) -> Option<ClipboardItemPreview> {
+    object
+        .downcast_ref::<BoxedAnyObject>()
+        .map(|boxed| boxed.borrow::<ClipboardItemPreview>().clone())
+}
@@ -0,0 +1,1255 @@
+//! Embeddable clipboard history: list/grid views over the history model with keyboard
+//! navigation, quick paste and type-to-filter. The overlay window is one host of this widget,
+//! other GTK apps can embed it as well; talking to the backend is handled internally.
+use gtk4::prelude::*;
+use gtk4::{Align, Label, Orientation};
+use libadwaita::{self as adw, prelude::*};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use crate::frontend::demo::demo_items;
+use crate::frontend::history_grid::generate_history_grid;
+use crate::frontend::history_list::{PastedMarks, RowLayout, TimeLabels, generate_history_list, generate_section_header_factory};
+use crate::frontend::history_model::{HistoryModel, item_from_object};
+use crate::frontend::ipc_client::{ConnectionState, FrontendClient, SubscriptionEvent};
+use crate::frontend::inspector::show_inspector;
+use crate::frontend::preview_pane::PreviewPane;
+use crate::frontend::thumbnails::ThumbnailCache;
+use crate::shared::{BackendError, BackendMessage, ClipboardContentType, ClipboardItemPreview, Config, ErrorCode, HistoryStats, MonitorStatus, SortMode, TextTransform};
+use crate::shared::config::{Preferences, ViewMode};
+use crate::shared::protocol;
+use crate::shared::i18n::{gettext, trf, trn};
+use crate::shared::wire::WireFormat;
+use tracing::{debug, info, warn, error};
+
+type ItemCallbacks = Rc<RefCell<Vec<std::boxed::Box<dyn Fn(&ClipboardItemPreview)>>>>;
+type FilterCallbacks = Rc<RefCell<Vec<std::boxed::Box<dyn Fn(&str)>>>>;
+type CloseCallbacks = Rc<RefCell<Vec<std::boxed::Box<dyn Fn()>>>>;
+type ConnectionCallbacks = Rc<RefCell<Vec<std::boxed::Box<dyn Fn(ConnectionState)>>>>;
+type PausedCallbacks = Rc<RefCell<Vec<std::boxed::Box<dyn Fn(bool)>>>>;
+type ConfigCallbacks = Rc<RefCell<Vec<std::boxed::Box<dyn Fn(&Config)>>>>;
+
+/// Content type filter chips under the header, "All" shows every item
+fn type_chips() -> [(String, &'static [ClipboardContentType]); 6] {
+    [
+        (gettext("All"), &[]),
+        (gettext("Text"), &[ClipboardContentType::Text]),
+        (gettext("Links"), &[ClipboardContentType::Url]),
+        (gettext("Images"), &[ClipboardContentType::Image]),
+        (gettext("Code"), &[ClipboardContentType::Code]),
+        (gettext("Files"), &[ClipboardContentType::File]),
+    ]
+}
+
+/// First entry of the tag filter, showing the items regardless of their tags
+fn all_tags() -> String {
+    gettext("All tags")
+}
+
+/// Smallest width of the grid, fitting two columns
+const GRID_MIN_WIDTH: u32 = 420;
+
+/// Interval of refreshing relative times ("5 minutes ago") and time sections
+const TIME_REFRESH_SECS: u32 = 30;
+
+/// Clipboard history widget. Cloning yields another handle to the same widget.
+#[derive(Clone)]
+pub struct ClipboardHistoryWidget {
+    root: gtk4::Box,
+    model: HistoryModel,
+    /// Selection shared by the list and the grid
+    selection: gtk4::SingleSelection,
+    /// Holds the "list" and "grid" pages, the visible one is the current view mode
+    stack: gtk4::Stack,
+    list_view: gtk4::ListView,
+    grid_view: gtk4::GridView,
+    placeholder: Label,
+    /// Tag filter next to the content type chips, hidden while no item is tagged
+    tag_filter: gtk4::DropDown,
+    /// Set while the tag filter is refilled, its selection changes are not the user's
+    tags_updating: Rc<Cell<bool>>,
+    /// "Daemon offline" banner, revealed while the backend is unreachable
+    offline_banner: gtk4::Revealer,
+    /// Shown while the backend cannot record selections (e.g. compositor unsupported)
+    status_banner: gtk4::Revealer,
+    /// "History cleared — Undo" and similar notices over the views
+    toasts: adw::ToastOverlay,
+    /// Full content of the selected item under the views, toggled with Space
+    preview: PreviewPane,
+    /// Items pasted from this widget, their rows are marked
+    pasted: PastedMarks,
+    /// Times of the bound rows, re-rendered when the time format changes
+    time_labels: TimeLabels,
+    thumbnails: ThumbnailCache,
+    connection_state: Rc<Cell<ConnectionState>>,
+    /// Whether the backend currently does not record new selections
+    capture_paused: Rc<Cell<bool>>,
+    /// Demo mode: fake items only, the backend is never contacted
+    demo: bool,
+    item_activated: ItemCallbacks,
+    filter_changed: FilterCallbacks,
+    close_requested: CloseCallbacks,
+    connection_changed: ConnectionCallbacks,
+    capture_paused_changed: PausedCallbacks,
+    config_reloaded: ConfigCallbacks,
+}
+
+impl Default for ClipboardHistoryWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClipboardHistoryWidget {
+    /// Create the widget, fetching the first page of the history from the backend
+    pub fn new() -> Self {
+        Self::with_items(Vec::new())
+    }
+
+    /// Create the widget with already fetched items (in the `sort_mode` of the config), further
+    /// pages are fetched while scrolling. Falls back to fetching the first page if `items` is empty.
+    pub fn with_items(items: Vec<ClipboardItemPreview>) -> Self {
+        // Items are wrapped in a (filterable) model, both views recycle their widgets
+        let model = HistoryModel::new(items, Config::load().sort_mode);
+        if model.model().n_items() == 0 {
+            debug!("Prefetched clipboard history empty - trying on-demand fetch...");
+            model.load_next_page();
+        }
+        Self::build(model, ThumbnailCache::default(), false)
+    }
+
+    /// Create the widget with representative fake items for screenshots and screencasts.
+    /// The real history is never fetched, pasting and clearing only affect the fake items.
+    pub fn demo() -> Self {
+        Self::build(HistoryModel::with_all_items(demo_items(), Config::load().sort_mode), ThumbnailCache::demo(), true)
+    }
+
+    fn build(model: HistoryModel, thumbnails: ThumbnailCache, demo: bool) -> Self {
+        let config = Config::load();
+        thumbnails.set_enabled(config.show_image_previews);
+        let selection = gtk4::SingleSelection::new(Some(model.model().clone()));
+
+        let time_labels = TimeLabels::default();
+        time_labels.set_format(config.time_format, config.clock);
+        let pasted = PastedMarks::default();
+        let list_view = generate_history_list(&selection, &model, &thumbnails, &time_labels, &pasted, RowLayout::from_config(&config));
+        let grid_view = generate_history_grid(&selection, &thumbnails, &pasted);
+
+        // Create scrolled windows for both views
+        let scrolled_window = gtk4::ScrolledWindow::new();
+        scrolled_window.set_policy(gtk4::PolicyType::Never, gtk4::PolicyType::Automatic);
+        scrolled_window.set_min_content_width(config.overlay_width.min(i32::MAX as u32) as i32);
+        scrolled_window.set_min_content_height(config.overlay_height.min(i32::MAX as u32) as i32);
+        scrolled_window.set_child(Some(&list_view));
+
+        let grid_scrolled_window = gtk4::ScrolledWindow::new();
+        grid_scrolled_window.set_policy(gtk4::PolicyType::Never, gtk4::PolicyType::Automatic);
+        grid_scrolled_window.set_min_content_width(config.overlay_width.clamp(GRID_MIN_WIDTH, i32::MAX as u32) as i32);
+        grid_scrolled_window.set_min_content_height(config.overlay_height.min(i32::MAX as u32) as i32);
+        grid_scrolled_window.set_child(Some(&grid_view));
+
+        // Fetch the next page of the history when scrolling reaches the end
+        for window in [&scrolled_window, &grid_scrolled_window] {
+            let model_for_paging = model.clone();
+            window.connect_edge_reached(move |_, edge| {
+                if edge == gtk4::PositionType::Bottom {
+                    model_for_paging.load_next_page();
+                }
+            });
+        }
+
+        let stack = gtk4::Stack::new();
+        stack.set_hhomogeneous(false);
+        stack.set_vhomogeneous(false);
+        stack.add_named(&scrolled_window, Some("list"));
+        stack.add_named(&grid_scrolled_window, Some("grid"));
+
+        // Shown on top of the views when there are no items (or none match the filter)
+        let placeholder = Label::new(Some(&gettext("No clipboard history yet")));
+        placeholder.add_css_class("dim-label");
+        placeholder.set_valign(Align::Start);
+        placeholder.set_margin_top(20);
+        placeholder.set_margin_bottom(20);
+        placeholder.set_can_target(false);
+        placeholder.set_visible(model.model().n_items() == 0);
+        let placeholder_for_model = placeholder.clone();
+        model.model().connect_items_changed(move |items, _, _, _| {
+            placeholder_for_model.set_visible(items.n_items() == 0);
+        });
+
+        let views = gtk4::Overlay::new();
+        views.set_child(Some(&stack));
+        views.add_overlay(&placeholder);
+        views.set_vexpand(true);
+
+        let banner_label = Label::new(Some(&gettext("Clipboard daemon offline — reconnecting…")));
+        banner_label.add_css_class("offline-banner");
+        banner_label.set_wrap(true);
+        let offline_banner = gtk4::Revealer::new();
+        offline_banner.set_transition_type(gtk4::RevealerTransitionType::SlideDown);
+        offline_banner.set_child(Some(&banner_label));
+
+        let status_label = Label::new(None);
+        status_label.add_css_class("offline-banner");
+        status_label.set_wrap(true);
+        let status_banner = gtk4::Revealer::new();
+        status_banner.set_transition_type(gtk4::RevealerTransitionType::SlideDown);
+        status_banner.set_child(Some(&status_label));
+
+        let toasts = adw::ToastOverlay::new();
+        toasts.set_child(Some(&views));
+
+        let root = gtk4::Box::new(Orientation::Vertical, 0);
+        root.append(&offline_banner);
+        root.append(&status_banner);
+        let chips = generate_type_chips(&model, &placeholder);
+        let tag_filter = generate_tag_filter();
+        chips.append(&tag_filter);
+        root.append(&chips);
+        root.append(&toasts);
+        let preview = PreviewPane::new(demo);
+        root.append(preview.widget());
+
+        let widget = Self {
+            root,
+            model,
+            selection,
+            stack,
+            list_view,
+            grid_view,
+            placeholder,
+            tag_filter,
+            tags_updating: Rc::default(),
+            offline_banner,
+            status_banner,
+            toasts,
+            preview,
+            pasted,
+            time_labels,
+            thumbnails,
+            connection_state: Rc::new(Cell::new(ConnectionState::Connected)),
+            capture_paused: Rc::default(),
+            demo,
+            item_activated: Rc::default(),
+            filter_changed: Rc::default(),
+            close_requested: Rc::default(),
+            connection_changed: Rc::default(),
+            capture_paused_changed: Rc::default(),
+            config_reloaded: Rc::default(),
+        };
+
+        // Handle item activation (Enter/click) instead of mere selection
+        let widget_for_activation = widget.clone();
+        widget.list_view.connect_activate(move |_, position| widget_for_activation.activate_position(position));
+        let widget_for_activation = widget.clone();
+        widget.grid_view.connect_activate(move |_, position| widget_for_activation.activate_position(position));
+
+        let preview = widget.preview.clone();
+        widget.selection.connect_selected_item_notify(move |selection| {
+            preview.show(selection.selected_item().and_then(|o| item_from_object(&o)).as_ref());
+        });
+
+        widget.root.insert_action_group("history", Some(&widget.generate_actions()));
+        widget.set_view_mode(config.view_mode);
+        widget.update_section_headers();
+        let widget_for_tags = widget.clone();
+        widget.tag_filter.connect_selected_notify(move |_| widget_for_tags.apply_tag_filter());
+        widget.refresh_tags();
+        widget.add_key_controller(&widget.root);
+        widget.start_time_ticker();
+        if !demo {
+            widget.refresh_capture_paused();
+            widget.refresh_monitor_status();
+            widget.follow_backend_pushes();
+        }
+        widget
+    }
+
+    /// Keep the shown history in sync with the backend while the widget exists: new copies
+    /// appear in real time, replaced and cleared items are removed. While the backend is
+    /// unreachable the offline banner is shown, after reconnecting the history is reloaded.
+    fn follow_backend_pushes(&self) {
+        let mut events = FrontendClient::subscribe();
+        let root = self.root.downgrade();
+        let widget = self.clone();
+        gtk4::glib::spawn_future_local(async move {
+            while let Some(event) = events.recv().await {
+                // Stop following once the widget is gone
+                if root.upgrade().is_none() {
+                    break;
+                }
+                match event {
+                    SubscriptionEvent::Push(message) => match *message {
+                        BackendMessage::CaptureStateChanged { paused } => widget.update_capture_paused(paused),
+                        BackendMessage::StatusChanged { status } => widget.update_monitor_status(&status),
+                        BackendMessage::ConfigReloaded => widget.reload_config(),
+                        // Fell behind reading pushes, some changes are missing
+                        BackendMessage::PushesDropped { .. } => {
+                            widget.model.reload();
+                            widget.refresh_capture_paused();
+                            widget.refresh_monitor_status();
+                            widget.refresh_tags();
+                        }
+                        message => {
+                            widget.apply_item_push(&message);
+                            if changes_tags(&message) {
+                                widget.refresh_tags();
+                            }
+                        }
+                    },
+                    SubscriptionEvent::ConnectionChanged(state) => widget.set_connection_state(state),
+                }
+            }
+        });
+    }
+
+    /// Re-read the config file after the daemon picked up a change to it
+    fn reload_config(&self) {
+        self.apply_config(&Config::load());
+    }
+
+    fn apply_config(&self, config: &Config) {
+        if self.thumbnails.enabled() != config.show_image_previews {
+            self.thumbnails.set_enabled(config.show_image_previews);
+            // Rebind the rows with or without their pictures
+            self.model.rebind_all();
+        }
+        self.time_labels.set_format(config.time_format, config.clock);
+        self.time_labels.refresh();
+        for callback in self.config_reloaded.borrow().iter() {
+            callback(config);
+        }
+    }
+
+    /// Keep relative times and time sections current while the widget exists
+    fn start_time_ticker(&self) {
+        let root = self.root.downgrade();
+        let time_labels = self.time_labels.clone();
+        let model = self.model.clone();
+        gtk4::glib::timeout_add_seconds_local(TIME_REFRESH_SECS, move || {
+            if root.upgrade().is_none() {
+                return gtk4::glib::ControlFlow::Break;
+            }
+            time_labels.refresh();
+            model.refresh_sections();
+            gtk4::glib::ControlFlow::Continue
+        });
+    }
+
+    fn set_connection_state(&self, state: ConnectionState) {
+        let previous = self.connection_state.replace(state);
+        if previous == state {
+            return;
+        }
+        self.offline_banner.set_reveal_child(state != ConnectionState::Connected);
+        if state == ConnectionState::Connected {
+            // The backend may have restarted with a different history and pause state
+            self.model.reload();
+            self.refresh_capture_paused();
+            self.refresh_monitor_status();
+        }
+        for callback in self.connection_changed.borrow().iter() {
+            callback(state);
+        }
+    }
+
+    /// The top-level GTK widget to put into a container
+    pub fn widget(&self) -> &gtk4::Box {
+        &self.root
+    }
+
+    pub fn view_mode(&self) -> ViewMode {
+        if self.stack.visible_child_name().as_deref() == Some("grid") {
+            ViewMode::Grid
+        } else {
+            ViewMode::List
+        }
+    }
+
+    pub fn set_view_mode(&self, mode: ViewMode) {
+        match mode {
+            ViewMode::List => self.stack.set_visible_child_name("list"),
+            ViewMode::Grid => self.stack.set_visible_child_name("grid"),
+        }
+    }
+
+    pub fn sort_mode(&self) -> SortMode {
+        self.model.sort_mode()
+    }
+
+    /// List the history in another order, e.g. the most used items first
+    pub fn set_sort_mode(&self, mode: SortMode) {
+        if !self.model.set_sort_mode(mode) {
+            return;
+        }
+        self.update_section_headers();
+        update_placeholder(&self.placeholder, &self.model);
+        if self.selection.n_items() > 0 {
+            self.scroll_to_item(0);
+        }
+    }
+
+    /// Only show the items carrying `tag` (all items if None)
+    pub fn show_tag(&self, tag: Option<&str>) {
+        let Some(tag) = tag else {
+            self.tag_filter.set_selected(0);
+            return;
+        };
+        if !self.tag_names().iter().any(|name| name == tag) {
+            self.refresh_tags();
+        }
+        if let Some(position) = self.tag_names().iter().position(|name| name == tag) {
+            self.tag_filter.set_selected(position as u32 + 1);
+        }
+    }
+
+    /// Tags offered by the tag filter (without "All tags")
+    fn tag_names(&self) -> Vec<String> {
+        let Some(list) = self.tag_filter.model().and_downcast::<gtk4::StringList>() else {
+            return Vec::new();
+        };
+        (1..list.n_items()).filter_map(|i| list.string(i)).map(String::from).collect()
+    }
+
+    /// Offer the tags used in the history in the tag filter, keeping the selected one
+    fn refresh_tags(&self) {
+        let mut tags = if self.demo {
+            let mut tags: Vec<String> = (0..self.model.model().n_items())
+                .filter_map(|position| self.model.item_at(position))
+                .flat_map(|item| item.tags)
+                .collect();
+            tags.sort();
+            tags.dedup();
+            tags
+        } else {
+            match FrontendClient::new().and_then(|mut client| client.list_tags()) {
+                Ok(tags) => tags,
+                Err(e) => {
+                    warn!("Could not fetch the tags: {e}");
+                    return;
+                }
+            }
+        };
+        // Keep offering the selected tag after its last item lost it, so it can still be reset
+        let selected = self.model.tag();
+        if let Some(tag) = &selected
+            && !tags.contains(tag)
+        {
+            tags.push(tag.clone());
+            tags.sort();
+        }
+        let Some(list) = self.tag_filter.model().and_downcast::<gtk4::StringList>() else { return };
+        let all_tags = all_tags();
+        let names: Vec<&str> = std::iter::once(all_tags.as_str()).chain(tags.iter().map(String::as_str)).collect();
+        self.tags_updating.set(true);
+        list.splice(0, list.n_items(), &names);
+        let position = selected.and_then(|tag| tags.iter().position(|name| *name == tag)).map_or(0, |position| position as u32 + 1);
+        self.tag_filter.set_selected(position);
+        self.tags_updating.set(false);
+        self.tag_filter.set_visible(!tags.is_empty());
+    }
+
+    /// Filter the history by the tag selected in the tag filter
+    fn apply_tag_filter(&self) {
+        if self.tags_updating.get() {
+            return;
+        }
+        let tag = match self.tag_filter.selected() {
+            0 | gtk4::INVALID_LIST_POSITION => None,
+            position => self.tag_names().get(position as usize - 1).cloned(),
+        };
+        if self.model.set_tag(tag) {
+            update_placeholder(&self.placeholder, &self.model);
+            if self.selection.n_items() > 0 {
+                self.scroll_to_item(0);
+            }
+        }
+    }
+
+    /// Ask for a tag and attach it to an item
+    fn prompt_tag(&self, id: u64) {
+        let entry = gtk4::Entry::builder().placeholder_text(gettext("work, personal, …")).activates_default(true).build();
+        let dialog = adw::AlertDialog::new(Some(&gettext("Add Tag")), Some(&gettext("The history can be filtered by tag.")));
+        dialog.set_extra_child(Some(&entry));
+        dialog.add_responses(&[("cancel", gettext("_Cancel").as_str()), ("add", gettext("_Add").as_str())]);
+        dialog.set_response_appearance("add", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("add"));
+        dialog.set_close_response("cancel");
+
+        let widget = self.clone();
+        dialog.choose(&self.root, gtk4::gio::Cancellable::NONE, move |response| {
+            if response == "add" {
+                widget.edit_tags(id, |client| client.add_tag(id, &entry.text()));
+            }
+        });
+    }
+
+    /// Add or remove a tag, the changed item comes back through the backend's ItemUpdated push
+    fn edit_tags(&self, id: u64, edit: impl FnOnce(&mut FrontendClient) -> Result<Vec<String>, std::boxed::Box<dyn std::error::Error>>) {
+        if self.demo {
+            info!("Demo mode: not changing the tags of item {id}");
+            return;
+        }
+        match FrontendClient::new().and_then(|mut client| edit(&mut client)) {
+            Ok(tags) => debug!("Clipboard item {id} tagged [{}]", tags.join(", ")),
+            Err(e) => error!("Error changing the tags of clipboard item {id}: {e}"),
+        }
+    }
+
+    /// Time section headers only make sense for the newest first order
+    fn update_section_headers(&self) {
+        let factory = (self.model.sort_mode() == SortMode::Recent).then(generate_section_header_factory);
+        self.list_view.set_header_factory(factory.as_ref());
+    }
+
+    /// Move keyboard focus to the visible view
+    pub fn focus_view(&self) {
+        match self.view_mode() {
+            ViewMode::Grid => self.grid_view.grab_focus(),
+            ViewMode::List => self.list_view.grab_focus(),
+        };
+    }
+
+    /// Prepare for being shown again (by the resident overlay): clear the filter, fetch the
+    /// first page of the history again and select the newest item
+    pub fn reset(&self) {
+        self.model.clear_filter();
+        if !self.demo {
+            self.model.reload();
+        }
+        self.update_filter_ui();
+    }
+
+    /// Whether the pane with the full content of the selected item is open
+    pub fn preview_open(&self) -> bool {
+        self.preview.is_open()
+    }
+
+    /// Open or close the pane with the full content of the selected item
+    pub fn set_preview_open(&self, open: bool) {
+        let selected = self.selection.selected_item().and_then(|o| item_from_object(&o));
+        self.preview.set_open(open, selected.as_ref());
+    }
+
+    /// Whether Ctrl is held, e.g. while an item is activated with Ctrl+Enter or Ctrl+click to
+    /// paste it without closing the host
+    pub fn ctrl_held(&self) -> bool {
+        self.root
+            .display()
+            .default_seat()
+            .and_then(|seat| seat.keyboard())
+            .is_some_and(|keyboard| keyboard.modifier_state().contains(gtk4::gdk::ModifierType::CONTROL_MASK))
+    }
+
+    /// Called after an item was activated and successfully made the current clipboard content
+    pub fn connect_item_activated(&self, callback: impl Fn(&ClipboardItemPreview) + 'static) {
+        self.item_activated.borrow_mut().push(std::boxed::Box::new(callback));
+    }
+
+    /// Called with the new type-to-filter text (empty when the filter was cleared)
+    pub fn connect_filter_changed(&self, callback: impl Fn(&str) + 'static) {
+        self.filter_changed.borrow_mut().push(std::boxed::Box::new(callback));
+    }
+
+    /// Called when Esc is pressed without an active filter
+    pub fn connect_close_requested(&self, callback: impl Fn() + 'static) {
+        self.close_requested.borrow_mut().push(std::boxed::Box::new(callback));
+    }
+
+    /// Current state of the connection to the backend
+    pub fn connection_state(&self) -> ConnectionState {
+        self.connection_state.get()
+    }
+
+    /// Called when the backend becomes unreachable or is reachable again
+    pub fn connect_connection_state_changed(&self, callback: impl Fn(ConnectionState) + 'static) {
+        self.connection_changed.borrow_mut().push(std::boxed::Box::new(callback));
+    }
+
+    /// Handle the widget's keys on `target` too, e.g. the toplevel window so typing works
+    /// while the focus is outside the widget. The widget itself is always handled.
+    pub fn add_key_controller(&self, target: &impl IsA<gtk4::Widget>) {
+        target.add_controller(self.generate_key_controller());
+    }
+
+    /// Summary of the history (of the fake items in demo mode)
+    pub fn stats(&self) -> Result<HistoryStats, std::boxed::Box<dyn std::error::Error>> {
+        if self.demo {
+            let items = demo_items();
+            let mut stats = HistoryStats {
+                item_count: items.len() as u32,
+                total_bytes: items.iter().map(|item| item.content_preview.len() as u64).sum(),
+                oldest: items.iter().map(|item| item.stamp).min(),
+                newest: items.iter().map(|item| item.stamp).max(),
+                ..HistoryStats::default()
+            };
+            for item in &items {
+                stats.count_type(item.content_type);
+            }
+            return Ok(stats);
+        }
+        FrontendClient::new()?.get_stats()
+    }
+
+    /// Whether the backend currently does not record new selections
+    pub fn capture_paused(&self) -> bool {
+        self.capture_paused.get()
+    }
+
+    /// Pause or resume recording of new selections in the backend (only the widget state in demo mode)
+    pub fn set_capture_paused(&self, paused: bool) -> Result<(), std::boxed::Box<dyn std::error::Error>> {
+        let paused = if self.demo { paused } else { FrontendClient::new()?.set_capture_paused(paused)? };
+        self.update_capture_paused(paused);
+        Ok(())
+    }
+
+    /// Called when capture is paused or resumed, also by other clients (e.g. `cursor-clip pause`)
+    pub fn connect_capture_paused_changed(&self, callback: impl Fn(bool) + 'static) {
+        self.capture_paused_changed.borrow_mut().push(std::boxed::Box::new(callback));
+    }
+
+    /// Change the preferences through the backend, which writes them to the config file (only
+    /// applied to the widget in demo mode). Returns the config now in effect.
+    pub fn set_config(&self, preferences: Preferences) -> Result<Config, std::boxed::Box<dyn std::error::Error>> {
+        if self.demo {
+            let mut config = Config::load();
+            preferences.apply_to(&mut config);
+            self.apply_config(&config);
+            return Ok(config);
+        }
+        FrontendClient::new()?.set_config(preferences)
+    }
+
+    /// Called with the new config when the config file changed while the widget exists
+    pub fn connect_config_reloaded(&self, callback: impl Fn(&Config) + 'static) {
+        self.config_reloaded.borrow_mut().push(std::boxed::Box::new(callback));
+    }
+
+    fn refresh_capture_paused(&self) {
+        match FrontendClient::new().and_then(|mut client| client.capture_paused()) {
+            Ok(paused) => self.update_capture_paused(paused),
+            Err(e) => warn!("Could not query the capture state: {e}"),
+        }
+    }
+
+    fn refresh_monitor_status(&self) {
+        match FrontendClient::new().and_then(|mut client| client.monitor_status()) {
+            Ok(status) => self.update_monitor_status(&status),
+            Err(e) => warn!("Could not query the monitoring state: {e}"),
+        }
+    }
+
+    /// Explain in a banner why new copies are not recorded
+    fn update_monitor_status(&self, status: &MonitorStatus) {
+        let message = status.message();
+        if let Some(label) = self.status_banner.child().and_downcast::<Label>() {
+            label.set_label(message.as_deref().unwrap_or_default());
+        }
+        self.status_banner.set_reveal_child(message.is_some());
+    }
+
+    fn update_capture_paused(&self, paused: bool) {
+        if self.capture_paused.replace(paused) == paused {
+            return;
+        }
+        for callback in self.capture_paused_changed.borrow().iter() {
+            callback(paused);
+        }
+    }
+
+    /// Clear the whole history in the backend, a toast offers to undo it for a few seconds
+    pub fn clear_history(&self) -> Result<(), std::boxed::Box<dyn std::error::Error>> {
+        if self.demo {
+            self.model.clear();
+            return Ok(());
+        }
+        let mut client = FrontendClient::new()?;
+        client.clear_history()?;
+        info!("Clipboard history cleared");
+        if client.supports_undo() {
+            self.show_undo_toast(&gettext("History cleared"), "history.undo-clear", None);
+        }
+        Ok(())
+    }
+
+    /// Delete one item in the backend, a toast offers to undo it for a few seconds
+    pub fn delete_item(&self, id: u64) -> Result<(), std::boxed::Box<dyn std::error::Error>> {
+        if self.demo {
+            self.model.apply_push(&BackendMessage::ItemsRemoved { ids: vec![id] });
+            return Ok(());
+        }
+        FrontendClient::new()?.delete_item(id)?;
+        info!("Clipboard item {id} deleted");
+        self.show_undo_toast(&gettext("Item deleted"), "history.undo-delete", Some(id.to_variant()));
+        Ok(())
+    }
+
+    /// Toast with an "Undo" button activating `action`, shown as long as the backend keeps
+    /// the deleted items
+    fn show_undo_toast(&self, title: &str, action: &str, target: Option<gtk4::glib::Variant>) {
+        let toast = adw::Toast::builder()
+            .title(title)
+            .button_label(gettext("Undo"))
+            .action_name(action)
+            .timeout(protocol::UNDO_WINDOW_SECS)
+            .build();
+        toast.set_action_target_value(target.as_ref());
+        self.toasts.add_toast(toast);
+    }
+
+    /// The restored items come back through the backend's ItemsAdded push
+    fn undo(&self, undo: impl FnOnce(&mut FrontendClient) -> Result<Vec<u64>, std::boxed::Box<dyn std::error::Error>>) {
+        match FrontendClient::new().and_then(|mut client| undo(&mut client)) {
+            Ok(ids) => info!("Restored {} clipboard items", ids.len()),
+            Err(e) => error!("Error undoing the deletion: {e}"),
+        }
+    }
+
+    fn activate_position(&self, position: u32) {
+        if let Some(item) = self.model.item_at(position) {
+            self.activate_item(&item);
+        }
+    }
+
+    /// Paste an item: make it the current clipboard selection and notify the host
+    fn activate_item(&self, item: &ClipboardItemPreview) {
+        debug!("Activated clipboard item ID {}: {}", item.item_id, item.content_preview);
+
+        if self.demo {
+            info!("Demo mode: not pasting item {}", item.item_id);
+            for callback in self.item_activated.borrow().iter() {
+                callback(item);
+            }
+            return;
+        }
+
+        match FrontendClient::new() {
+            Ok(mut client) => {
+                if let Err(e) = client.set_clipboard_by_id(item.item_id) {
+                    match e.downcast_ref::<BackendError>().map(|e| e.code) {
+                        // Removed in the backend before the push reached us, drop the stale row
+                        Some(ErrorCode::NotFound) => {
+                            warn!("Clipboard item {} no longer exists", item.item_id);
+                            self.model.apply_push(&BackendMessage::ItemsRemoved { ids: vec![item.item_id] });
+                        }
+                        Some(ErrorCode::WaylandUnavailable) => error!("Cannot paste, the backend has no clipboard access: {e}"),
+                        _ => error!("Error setting clipboard by ID: {}", e),
+                    }
+                } else {
+                    info!("Clipboard set by ID: {}", item.item_id);
+                    self.pasted.mark(item.item_id);
+                    for callback in self.item_activated.borrow().iter() {
+                        callback(item);
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Error creating frontend client: {}", e);
+            }
+        }
+    }
+
+    /// Row actions, `history.copy-text(item id)` pastes the text recognized in an image item,
+    /// `history.add-tag(item id)` asks for a tag to attach, `history.remove-tag((item id, tag))`
+    /// removes one, `history.show-tag(tag)` filters by a tag,
+    /// `history.transform((item id, transform name))` pastes the item's text transformed,
+    /// `history.send-to-phone(item id)` sends the item's text to the paired phones,
+    /// `history.toggle-group(group id)` expands or collapses a group of rapid copies,
+    /// `history.paste-original(item id)` pastes the text of an item as copied (before clean-up),
+    /// `history.inspect(item id)` lists the item's MIME types to paste one of them,
+    /// `history.delete(item id)` deletes the item, `history.undo-delete(item id)` and
+    /// `history.undo-clear` restore deleted items (used by the toasts), `history.move-to-top(item
+    /// id)` and `history.move((item id, target item id))` reorder the history
+    fn generate_actions(&self) -> gtk4::gio::SimpleActionGroup {
+        let actions = gtk4::gio::SimpleActionGroup::new();
+        let copy_text = gtk4::gio::SimpleAction::new("copy-text", Some(gtk4::glib::VariantTy::UINT64));
+        let widget = self.clone();
+        copy_text.connect_activate(move |_, parameter| {
+            if let Some(id) = parameter.and_then(|p| p.get::<u64>()) {
+                widget.copy_recognized_text(id);
+            }
+        });
+        actions.add_action(&copy_text);
+
+        let transform = gtk4::gio::SimpleAction::new("transform", Some(&<(u64, String)>::static_variant_type()));
+        let widget = self.clone();
+        transform.connect_activate(move |_, parameter| {
+            if let Some((id, name)) = parameter.and_then(|p| p.get::<(u64, String)>())
+                && let Some(op) = TextTransform::from_name(&name)
+            {
+                widget.paste_transformed(id, op);
+            }
+        });
+        actions.add_action(&transform);
+
+        let add_tag = gtk4::gio::SimpleAction::new("add-tag", Some(gtk4::glib::VariantTy::UINT64));
+        let widget = self.clone();
+        add_tag.connect_activate(move |_, parameter| {
+            if let Some(id) = parameter.and_then(|p| p.get::<u64>()) {
+                widget.prompt_tag(id);
+            }
+        });
+        actions.add_action(&add_tag);
+
+        let remove_tag = gtk4::gio::SimpleAction::new("remove-tag", Some(&<(u64, String)>::static_variant_type()));
+        let widget = self.clone();
+        remove_tag.connect_activate(move |_, parameter| {
+            if let Some((id, tag)) = parameter.and_then(|p| p.get::<(u64, String)>()) {
+                widget.edit_tags(id, |client| client.remove_tag(id, &tag));
+            }
+        });
+        actions.add_action(&remove_tag);
+
+        let show_tag = gtk4::gio::SimpleAction::new("show-tag", Some(gtk4::glib::VariantTy::STRING));
+        let widget = self.clone();
+        show_tag.connect_activate(move |_, parameter| {
+            if let Some(tag) = parameter.and_then(|p| p.get::<String>()) {
+                widget.show_tag(Some(&tag));
+            }
+        });
+        actions.add_action(&show_tag);
+
+        let send_to_phone = gtk4::gio::SimpleAction::new("send-to-phone", Some(gtk4::glib::VariantTy::UINT64));
+        let widget = self.clone();
+        send_to_phone.connect_activate(move |_, parameter| {
+            if let Some(id) = parameter.and_then(|p| p.get::<u64>()) {
+                widget.send_to_phone(id);
+            }
+        });
+        actions.add_action(&send_to_phone);
+
+        let toggle_group = gtk4::gio::SimpleAction::new("toggle-group", Some(gtk4::glib::VariantTy::UINT64));
+        let widget = self.clone();
+        toggle_group.connect_activate(move |_, parameter| {
+            if let Some(group) = parameter.and_then(|p| p.get::<u64>()) {
+                widget.model.toggle_group(group);
+            }
+        });
+        actions.add_action(&toggle_group);
+
+        let paste_original = gtk4::gio::SimpleAction::new("paste-original", Some(gtk4::glib::VariantTy::UINT64));
+        let widget = self.clone();
+        paste_original.connect_activate(move |_, parameter| {
+            if let Some(id) = parameter.and_then(|p| p.get::<u64>()) {
+                widget.paste_original(id);
+            }
+        });
+        actions.add_action(&paste_original);
+
+        let inspect = gtk4::gio::SimpleAction::new("inspect", Some(gtk4::glib::VariantTy::UINT64));
+        let widget = self.clone();
+        inspect.connect_activate(move |_, parameter| {
+            if let Some(id) = parameter.and_then(|p| p.get::<u64>()) {
+                widget.inspect_item(id);
+            }
+        });
+        actions.add_action(&inspect);
+
+        let delete = gtk4::gio::SimpleAction::new("delete", Some(gtk4::glib::VariantTy::UINT64));
+        let widget = self.clone();
+        delete.connect_activate(move |_, parameter| {
+            if let Some(id) = parameter.and_then(|p| p.get::<u64>())
+                && let Err(e) = widget.delete_item(id)
+            {
+                error!("Error deleting clipboard item: {e}");
+            }
+        });
+        actions.add_action(&delete);
+
+        let undo_delete = gtk4::gio::SimpleAction::new("undo-delete", Some(gtk4::glib::VariantTy::UINT64));
+        let widget = self.clone();
+        undo_delete.connect_activate(move |_, parameter| {
+            if let Some(id) = parameter.and_then(|p| p.get::<u64>()) {
+                widget.undo(|client| client.undo_delete(id));
+            }
+        });
+        actions.add_action(&undo_delete);
+
+        let undo_clear = gtk4::gio::SimpleAction::new("undo-clear", None);
+        let widget = self.clone();
+        undo_clear.connect_activate(move |_, _| widget.undo(FrontendClient::undo_clear));
+        actions.add_action(&undo_clear);
+
+        let move_to_top = gtk4::gio::SimpleAction::new("move-to-top", Some(gtk4::glib::VariantTy::UINT64));
+        let widget = self.clone();
+        move_to_top.connect_activate(move |_, parameter| {
+            if let Some(id) = parameter.and_then(|p| p.get::<u64>()) {
+                widget.move_item(id, 0);
+            }
+        });
+        actions.add_action(&move_to_top);
+
+        // Dropped onto another row: take that row's place
+        let move_onto = gtk4::gio::SimpleAction::new("move", Some(&<(u64, u64)>::static_variant_type()));
+        let widget = self.clone();
+        move_onto.connect_activate(move |_, parameter| {
+            let Some((id, onto)) = parameter.and_then(|p| p.get::<(u64, u64)>()) else { return };
+            match widget.model.history_position(onto) {
+                Some(position) => widget.move_item(id, position),
+                None => info!("Items can only be reordered while all content types are shown"),
+            }
+        });
+        actions.add_action(&move_onto);
+        actions
+    }
+
+    fn loaded_item(&self, id: u64) -> Option<ClipboardItemPreview> {
+        (0..self.model.model().n_items())
+            .filter_map(|position| self.model.item_at(position))
+            .find(|item| item.item_id == id)
+    }
+
+    /// Paste the text of an item run through a transform and notify the host like for a pasted item
+    fn paste_transformed(&self, id: u64, op: TextTransform) {
+        let Some(item) = self.loaded_item(id) else { return };
+        debug!("Applying {op:?} to clipboard item ID {id}");
+
+        if self.demo {
+            info!("Demo mode: not transforming item {id}");
+        } else {
+            let result = FrontendClient::new().and_then(|mut client| client.transform_item(id, op));
+            if let Err(e) = result {
+                error!("Error transforming clipboard item: {e}");
+                return;
+            }
+        }
+        for callback in self.item_activated.borrow().iter() {
+            callback(&item);
+        }
+    }
+
+    /// Move an item to `position` of the history (0 is the top), the row follows with the
+    /// backend's push of the restamped item
+    fn move_item(&self, id: u64, position: u32) {
+        if self.demo {
+            info!("Demo mode: not moving item {id}");
+            return;
+        }
+        match FrontendClient::new().and_then(|mut client| client.move_item(id, position)) {
+            Ok(position) => debug!("Moved clipboard item {id} to position {position}"),
+            Err(e) => error!("Error moving clipboard item: {e}"),
+        }
+    }
+
+    /// Send the text of an item to every reachable phone, the overlay stays open
+    fn send_to_phone(&self, id: u64) {
+        if self.demo {
+            info!("Demo mode: not sending item {id} to a phone");
+            return;
+        }
+        // Reaching the phone may take a moment, don't block the UI
+        std::thread::spawn(move || {
+            match FrontendClient::new().and_then(|mut client| client.send_to_phone(id, None)) {
+                Ok(devices) => info!("Sent clipboard item {id} to {}", devices.join(", ")),
+                Err(e) => error!("Error sending clipboard item to phone: {e}"),
+            }
+        });
+    }
+
+    /// Paste the text of an item as copied and notify the host like for a pasted item
+    fn paste_original(&self, id: u64) {
+        let Some(item) = self.loaded_item(id) else { return };
+        debug!("Pasting the original text of clipboard item ID {id}");
+        if let Err(e) = FrontendClient::new().and_then(|mut client| client.set_clipboard_original(id)) {
+            error!("Error pasting the original text of clipboard item {id}: {e}");
+            return;
+        }
+        for callback in self.item_activated.borrow().iter() {
+            callback(&item);
+        }
+    }
+
+    /// Open the inspector with every MIME type stored for an item
+    fn inspect_item(&self, id: u64) {
+        if self.demo {
+            info!("Demo mode: no payloads to inspect for item {id}");
+            return;
+        }
+        match FrontendClient::with_format(WireFormat::MsgPack).and_then(|mut client| client.get_item_data(id)) {
+            Ok(mime_data) => {
+                let widget = self.clone();
+                show_inspector(&self.root, &mime_data, move |mime| widget.paste_mime(id, mime));
+            }
+            Err(e) => error!("Error fetching the data of clipboard item {id}: {e}"),
+        }
+    }
+
+    /// Paste a single MIME type of an item and notify the host like for a pasted item
+    fn paste_mime(&self, id: u64, mime: &str) {
+        let Some(item) = self.loaded_item(id) else { return };
+        debug!("Pasting {mime} of clipboard item ID {id}");
+        if let Err(e) = FrontendClient::new().and_then(|mut client| client.set_clipboard_mime(id, mime)) {
+            error!("Error pasting {mime} of clipboard item {id}: {e}");
+            return;
+        }
+        for callback in self.item_activated.borrow().iter() {
+            callback(&item);
+        }
+    }
+
+    /// Paste the text recognized in an image item and notify the host like for a pasted item
+    fn copy_recognized_text(&self, id: u64) {
+        let Some(item) = self.loaded_item(id) else { return };
+        debug!("Copying recognized text of clipboard item ID {id}");
+
+        if self.demo {
+            info!("Demo mode: not copying the text of item {id}");
+        } else {
+            let result = FrontendClient::new().and_then(|mut client| client.copy_ocr_text(id));
+            if let Err(e) = result {
+                error!("Error copying recognized text: {e}");
+                return;
+            }
+        }
+        for callback in self.item_activated.borrow().iter() {
+            callback(&item);
+        }
+    }
+
+    /// Build the key controller handling Esc (clear filter, then close), j/k or arrows (navigate),
+    /// Home/End and PageUp/PageDown (first/last item, a screenful up or down), Enter (activate), Delete (delete the selected item), Space (toggle the preview pane), 1-9
+    /// (activate the n-th item directly) and type-to-filter.
+    /// While a filter is active, j/k, Space and digits are typed into the filter instead.
+    /// In grid mode the arrow keys are left to the grid's own navigation.
+    fn generate_key_controller(&self) -> gtk4::EventControllerKey {
+        let controller = gtk4::EventControllerKey::new();
+        let widget = self.clone();
+        controller.connect_key_pressed(move |_, key, _, _| {
+            use gtk4::gdk::Key;
+            let filtering = !widget.model.filter_text().is_empty();
+            let grid_mode = widget.view_mode() == ViewMode::Grid;
+            match key {
+                Key::Escape => {
+                    if widget.model.clear_filter() {
+                        widget.update_filter_ui();
+                    } else {
+                        for callback in widget.close_requested.borrow().iter() {
+                            callback();
+                        }
+                    }
+                    gtk4::glib::Propagation::Stop
+                }
+                Key::BackSpace => {
+                    if widget.model.pop_filter_char() {
+                        widget.update_filter_ui();
+                    }
+                    gtk4::glib::Propagation::Stop
+                }
+                Key::Down if !grid_mode => {
+                    widget.select_relative_item(1);
+                    gtk4::glib::Propagation::Stop
+                }
+                Key::Up if !grid_mode => {
+                    widget.select_relative_item(-1);
+                    gtk4::glib::Propagation::Stop
+                }
+                Key::j | Key::J if !filtering => {
+                    widget.select_relative_item(1);
+                    gtk4::glib::Propagation::Stop
+                }
+                Key::k | Key::K if !filtering => {
+                    widget.select_relative_item(-1);
+                    gtk4::glib::Propagation::Stop
+                }
+                Key::Home | Key::KP_Home => {
+                    if widget.selection.n_items() > 0 {
+                        widget.scroll_to_item(0);
+                    }
+                    gtk4::glib::Propagation::Stop
+                }
+                Key::End | Key::KP_End => {
+                    // The last loaded item, reaching the end of the view loads the next page
+                    if let Some(last) = widget.selection.n_items().checked_sub(1) {
+                        widget.scroll_to_item(last);
+                    }
+                    gtk4::glib::Propagation::Stop
+                }
+                Key::Page_Up | Key::KP_Page_Up => {
+                    widget.select_relative_item(-widget.page_step());
+                    gtk4::glib::Propagation::Stop
+                }
+                Key::Page_Down | Key::KP_Page_Down => {
+                    widget.select_relative_item(widget.page_step());
+                    gtk4::glib::Propagation::Stop
+                }
+                Key::Delete | Key::KP_Delete => {
+                    if let Some(item) = widget.selection.selected_item().and_then(|o| item_from_object(&o)) {
+                        if let Err(e) = widget.delete_item(item.item_id) {
+                            error!("Error deleting clipboard item: {e}");
+                        }
+                        return gtk4::glib::Propagation::Stop;
+                    }
+                    gtk4::glib::Propagation::Proceed
+                }
+                Key::space if !filtering => {
+                    widget.set_preview_open(!widget.preview_open());
+                    gtk4::glib::Propagation::Stop
+                }
+                Key::Return | Key::KP_Enter => {
+                    if let Some(item) = widget.selection.selected_item().and_then(|o| item_from_object(&o)) {
+                        widget.activate_item(&item);
+                        return gtk4::glib::Propagation::Stop;
+                    }
+                    gtk4::glib::Propagation::Proceed
+                }
+                _ => {
+                    let Some(c) = key.to_unicode() else {
+                        return gtk4::glib::Propagation::Proceed;
+                    };
+                    // Quick paste: 1-9 (top row or keypad) activate the corresponding item
+                    if !filtering
+                        && let Some(digit) = c.to_digit(10).filter(|d| (1..=9).contains(d))
+                    {
+                        widget.activate_position(digit - 1);
+                        return gtk4::glib::Propagation::Stop;
+                    }
+                    // Alphanumeric keys start a filter, once active any printable character extends it
+                    if c.is_alphanumeric() || (filtering && !c.is_control()) {
+                        widget.model.push_filter_char(c);
+                        widget.update_filter_ui();
+                        return gtk4::glib::Propagation::Stop;
+                    }
+                    gtk4::glib::Propagation::Proceed
+                }
+            }
+        });
+        controller
+    }
+
+    /// Move the selection by `offset` items (selecting the first item if nothing is selected)
+    /// and scroll the visible view to it
+    fn select_relative_item(&self, offset: i32) {
+        let mut count = self.selection.n_items();
+        if count == 0 {
+            return;
+        }
+        let current = self.selection.selected();
+        if current == gtk4::INVALID_LIST_POSITION {
+            self.scroll_to_item(0);
+            return;
+        }
+        let wanted = current.saturating_add_signed(offset);
+        // Moving past the loaded items fetches the next page instead of stopping at the last
+        // row, like scrolling to the end does
+        while wanted >= count && self.model.load_next_page() {
+            count = self.selection.n_items();
+        }
+        self.scroll_to_item(wanted.min(count - 1));
+    }
+
+    /// Number of items a screenful of the visible view shows (at least one), the step of PageUp/PageDown
+    fn page_step(&self) -> i32 {
+        let count = self.selection.n_items() as f64;
+        self.visible_vadjustment()
+            .filter(|adjustment| adjustment.upper() > 0.0)
+            .map_or(1, |adjustment| (count * adjustment.page_size() / adjustment.upper()).floor().max(1.0) as i32)
+    }
+
+    /// Vertical scroll position of the visible view
+    fn visible_vadjustment(&self) -> Option<gtk4::Adjustment> {
+        match self.view_mode() {
+            ViewMode::Grid => self.grid_view.vadjustment(),
+            ViewMode::List => self.list_view.vadjustment(),
+        }
+    }
+
+    /// Apply a pushed change of the history without moving what is shown. The views keep the
+    /// rows in view in place when items are inserted above them, but at the very top that hides
+    /// new copies above the first row, so there the view stays scrolled to the top instead.
+    fn apply_item_push(&self, message: &BackendMessage) {
+        let at_top = self.visible_vadjustment().is_none_or(|adjustment| adjustment.value() <= adjustment.lower());
+        self.model.apply_push(message);
+        if at_top && self.selection.n_items() > 0 {
+            match self.view_mode() {
+                ViewMode::Grid => self.grid_view.scroll_to(0, gtk4::ListScrollFlags::NONE, None),
+                ViewMode::List => self.list_view.scroll_to(0, gtk4::ListScrollFlags::NONE, None),
+            }
+        }
+    }
+
+    /// Select, focus and scroll to the item at `position` in the visible view
+    fn scroll_to_item(&self, position: u32) {
+        let flags = gtk4::ListScrollFlags::FOCUS | gtk4::ListScrollFlags::SELECT;
+        match self.view_mode() {
+            ViewMode::Grid => self.grid_view.scroll_to(position, flags, None),
+            ViewMode::List => self.list_view.scroll_to(position, flags, None),
+        }
+    }
+
+    /// Reflect a changed type-to-filter text in the placeholder and selection, and tell the host
+    fn update_filter_ui(&self) {
+        let filter = self.model.filter_text();
+        update_placeholder(&self.placeholder, &self.model);
+        // There is no search entry, screen readers learn about the filter from the views
+        let description = if filter.is_empty() {
+            String::new()
+        } else {
+            trf("Filtered by “{}”", &filter) + ", " + &trn("{} matching item", "{} matching items", self.selection.n_items().into())
+        };
+        for view in [self.list_view.upcast_ref::<gtk4::Widget>(), self.grid_view.upcast_ref()] {
+            view.update_property(&[gtk4::accessible::Property::Description(&description)]);
+        }
+
+        if self.selection.n_items() > 0 {
+            self.scroll_to_item(0);
+        }
+        for callback in self.filter_changed.borrow().iter() {
+            callback(&filter);
+        }
+    }
+}
+
+/// Row of toggle chips filtering the history by content type
+fn generate_type_chips(model: &HistoryModel, placeholder: &Label) -> gtk4::Box {
+    let chips = gtk4::Box::new(Orientation::Horizontal, 4);
+    chips.add_css_class("clipboard-chips");
+    let mut first: Option<gtk4::ToggleButton> = None;
+    for (label, content_types) in type_chips() {
+        let chip = gtk4::ToggleButton::with_label(&label);
+        chip.add_css_class("clipboard-chip");
+        chip.set_focus_on_click(false);
+        chip.set_group(first.as_ref());
+        chip.set_active(content_types.is_empty());
+        let model = model.clone();
+        let placeholder = placeholder.clone();
+        chip.connect_toggled(move |chip| {
+            if chip.is_active() && model.set_content_types(content_types.to_vec()) {
+                update_placeholder(&placeholder, &model);
+            }
+        });
+        chips.append(&chip);
+        first.get_or_insert(chip);
+    }
+    chips
+}
+
+/// Dropdown filtering the history by tag, filled by `refresh_tags`
+fn generate_tag_filter() -> gtk4::DropDown {
+    let dropdown = gtk4::DropDown::from_strings(&[all_tags().as_str()]);
+    dropdown.add_css_class("flat");
+    dropdown.set_hexpand(true);
+    dropdown.set_halign(Align::End);
+    dropdown.set_focus_on_click(false);
+    dropdown.set_tooltip_text(Some(&gettext("Show only the items with a tag")));
+    dropdown.update_property(&[gtk4::accessible::Property::Label(&gettext("Filter by tag"))]);
+    dropdown.set_visible(false);
+    dropdown
+}
+
+/// Whether a push may change the tags used in the history
+fn changes_tags(message: &BackendMessage) -> bool {
+    match message {
+        BackendMessage::NewItem { item } => !item.tags.is_empty(),
+        BackendMessage::ItemsAdded { items } => items.iter().any(|item| !item.tags.is_empty()),
+        BackendMessage::ItemUpdated { .. } | BackendMessage::ItemsRemoved { .. } => true,
+        _ => false,
+    }
+}
+
+/// Explain an empty view: no history at all, or nothing matching the filters
+fn update_placeholder(placeholder: &Label, model: &HistoryModel) {
+    if model.filter_text().is_empty() && model.content_types().is_empty() && model.tag().is_none() {
+        placeholder.set_label(&gettext("No clipboard history yet"));
+    } else {
+        placeholder.set_label(&gettext("No matching items"));
+    }
+}
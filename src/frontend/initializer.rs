@@ -1,7 +1,7 @@
 use wayland_client::{
-    Connection, EventQueue,
+    Connection, EventQueue, Proxy,
     globals::{GlobalList, registry_queue_init},
-    protocol::{wl_compositor, wl_seat, wl_shm},
+    protocol::{wl_compositor, wl_output, wl_seat, wl_shm},
 };
 use wayland_protocols_wlr::{
     layer_shell::v1::client::{zwlr_layer_shell_v1, zwlr_layer_surface_v1},
@@ -13,10 +13,13 @@ use wayland_protocols::{
     },
 };
 
-use crate::frontend::{frontend_state::State, gtk_overlay};
+use crate::frontend::{frontend_state::{OutputInfo, State}, gtk_overlay};
 use crate::frontend::dispatch::layer_shell::cleanup_capture_layer;
 use crate::frontend::ipc_client::FrontendClient;
-use log::{debug, warn, error};
+use crate::frontend::history_model::HISTORY_PAGE_SIZE;
+use crate::shared::Config;
+use crate::shared::config::Placement;
+use tracing::{debug, info, warn, error};
 use memmap2::{MmapMut, MmapOptions};
 use std::fs::OpenOptions;
 use std::os::fd::BorrowedFd;
@@ -34,12 +37,21 @@ fn run_main_event_loop(
 
         // Create GTK overlay window when coordinates are received
         if state.coords_received && !gtk_window_created {
-            let x = state.received_x;
-            let y = state.received_y;
-            debug!("Capture layer ready; creating GTK overlay window at ({x}, {y})");
+            let position = gtk_overlay::OverlayPosition {
+                x: state.received_x,
+                y: state.received_y,
+                output_name: state.cursor_output.clone(),
+                output_size: state.cursor_output_size,
+            };
+            debug!("Capture layer ready; creating GTK overlay window at {position:?}");
+
+            // The cursor position is known, the overlay brings its own dismiss layer for clicks
+            // outside of it
+            cleanup_capture_layer(state);
+            queue.flush()?;
 
             // Create the GTK window using the unified client backend communication
-            if let Err(e) = gtk_overlay::init_clipboard_overlay(x, y, state.clipboard_history.clone()) {
+            if let Err(e) = gtk_overlay::init_clipboard_overlay(position, state.placement, state.clipboard_history.clone(), state.demo_mode) {
                 error!("Error creating GTK overlay: {e:?}");
             }
             
@@ -63,19 +75,23 @@ fn run_main_event_loop(
 }
 
 // Frontend always uses its own Wayland connection (may change in future to support shared connection/hide feature)
-pub async fn run_frontend() -> Result<(), Box<dyn std::error::Error>> {
+pub async fn run_frontend(demo: bool, placement: Option<Placement>) -> Result<(), Box<dyn std::error::Error>> {
     let mut state = State::new();
-    // Prefetch clipboard history for instant GTK overlay population
-    if let Ok(mut client) = FrontendClient::new() {
-        match client.get_history() {
-            Ok(items) => {
+    state.demo_mode = demo;
+    let config = Config::load();
+    state.placement = placement.unwrap_or(config.placement);
+    // Prefetch the first page of the clipboard history for instant GTK overlay population,
+    // the overlay fetches further pages while scrolling. The demo never touches the real history.
+    if demo {
+        info!("Demo mode: showing fake clipboard items");
+    } else if let Some(mut client) = check_backend() {
+        match client.get_history_page(0, HISTORY_PAGE_SIZE, config.sort_mode) {
+            Ok((items, total)) => {
                 state.clipboard_history = items;
-                debug!("Prefetched {} clipboard history items", state.clipboard_history.len());
+                debug!("Prefetched {} of {total} clipboard history items", state.clipboard_history.len());
             }
             Err(e) => warn!("Failed to prefetch clipboard history: {e}"),
         }
-    } else {
-        warn!("Failed to connect to backend for history prefetch");
     }
 
     // Initialize Wayland for layer shell capture
@@ -95,6 +111,26 @@ pub async fn run_frontend() -> Result<(), Box<dyn std::error::Error>> {
     run_main_event_loop(&mut state, &mut queue)
 }
 
+/// Ping the backend before showing the overlay, returns a client if it is alive. Without a
+/// reachable backend the overlay still opens and shows the offline banner until it comes up.
+fn check_backend() -> Option<FrontendClient> {
+    let mut client = match FrontendClient::new() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Clipboard daemon not reachable ({e}), is `cursor-clip --daemon` running?");
+            return None;
+        }
+    };
+    match client.ping() {
+        Ok(health) if !health.status.is_active() => {
+            warn!("Clipboard daemon is not recording: {}", health.status.message().unwrap_or_default());
+        }
+        Ok(health) => debug!("Clipboard daemon up for {}s with {} items", health.uptime_secs, health.item_count),
+        Err(e) => debug!("Ping failed: {e}"),
+    }
+    Some(client)
+}
+
 fn init_wayland_protocols(
     globals: &GlobalList,
     queue: &EventQueue<State>,
@@ -137,6 +173,20 @@ fn init_wayland_protocols(
         std::process::exit(1);
     }
 
+    // Bind all outputs (v4 provides connector names) to find the monitor containing the cursor
+    let output_globals: Vec<(u32, u32)> = globals.contents().with_list(|list| {
+        list.iter()
+            .filter(|global| global.interface == wl_output::WlOutput::interface().name)
+            .map(|global| (global.name, global.version))
+            .collect()
+    });
+    for (name, version) in output_globals {
+        let output = globals
+            .registry()
+            .bind::<wl_output::WlOutput, _, _>(name, version.min(4), &queue.handle(), ());
+        state.outputs.push(OutputInfo { output, name: None });
+    }
+
     // Bind wp_viewporter
     if let Ok(viewporter) = globals.bind::<wp_viewporter::WpViewporter, _, _>(&queue.handle(), 1..=1, ()) {
         state.viewporter = Some(viewporter);
@@ -240,7 +290,7 @@ fn create_transparent_buffer(
     let shm = state
         .shm
         .as_ref()
-        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::Other, "wl_shm not available for fallback"))?;
+        .ok_or_else(|| std::io::Error::other("wl_shm not available for fallback"))?;
 
     // 1x1 pixel ARGB8888 (4 bytes)
     let size: i32 = 4;
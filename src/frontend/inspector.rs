@@ -0,0 +1,100 @@
+//! Inspector dialog listing every MIME type stored for an item with its size and a text or hex
+//! preview. Each representation can be pasted on its own, handy to debug apps copying odd formats.
+use gtk4::prelude::*;
+use libadwaita::{self as adw, prelude::*};
+use indexmap::IndexMap;
+use bytes::Bytes;
+use std::fmt::Write;
+use std::rc::Rc;
+use crate::shared::format::format_bytes;
+use crate::shared::i18n::{gettext, trf, trn};
+
+/// Bytes of each payload shown in its preview
+const PREVIEW_BYTES: usize = 2048;
+
+/// Bytes per line of the hex dump
+const HEXDUMP_WIDTH: usize = 16;
+
+/// Open the inspector for the payloads of an item, `paste` is called with the MIME type the
+/// user chose to paste
+pub fn show_inspector(parent: &impl IsA<gtk4::Widget>, mime_data: &IndexMap<String, Bytes>, paste: impl Fn(&str) + 'static) {
+    let dialog = adw::Dialog::builder().title(gettext("Inspect Item")).content_width(560).content_height(520).build();
+    let paste = Rc::new(paste);
+
+    let total: usize = mime_data.values().map(Bytes::len).sum();
+    let group = adw::PreferencesGroup::builder()
+        .title(gettext("MIME Types"))
+        .description(format!("{}, {}", trn("{} type", "{} types", mime_data.len() as u64), trf("{} in total", &format_bytes(total as u64))))
+        .build();
+    for (mime, data) in mime_data {
+        let row = adw::ExpanderRow::builder().title(mime).subtitle(format_bytes(data.len() as u64)).build();
+
+        let paste_button = gtk4::Button::from_icon_name("edit-paste-symbolic");
+        paste_button.set_tooltip_text(Some(&gettext("Paste Only This Type")));
+        paste_button.update_property(&[gtk4::accessible::Property::Label(&gettext("Paste Only This Type"))]);
+        paste_button.set_valign(gtk4::Align::Center);
+        paste_button.add_css_class("flat");
+        let (paste, dialog_ref, mime) = (paste.clone(), dialog.downgrade(), mime.clone());
+        paste_button.connect_clicked(move |_| {
+            paste(&mime);
+            if let Some(dialog) = dialog_ref.upgrade() {
+                dialog.close();
+            }
+        });
+        row.add_suffix(&paste_button);
+
+        let preview = gtk4::Label::new(Some(&preview_text(data)));
+        preview.set_xalign(0.0);
+        preview.set_wrap(true);
+        preview.set_wrap_mode(gtk4::pango::WrapMode::WordChar);
+        preview.set_selectable(true);
+        preview.add_css_class("monospace");
+        preview.set_margin_top(8);
+        preview.set_margin_bottom(8);
+        preview.set_margin_start(12);
+        preview.set_margin_end(12);
+        row.add_row(&preview);
+        group.add(&row);
+    }
+
+    let page = adw::PreferencesPage::new();
+    page.add(&group);
+    let toolbar = adw::ToolbarView::new();
+    toolbar.add_top_bar(&adw::HeaderBar::new());
+    toolbar.set_content(Some(&page));
+    dialog.set_child(Some(&toolbar));
+    dialog.present(Some(parent));
+}
+
+/// The start of a payload as text if it is UTF-8, otherwise as a hex dump
+fn preview_text(data: &[u8]) -> String {
+    let shown = &data[..data.len().min(PREVIEW_BYTES)];
+    let mut text = match std::str::from_utf8(shown) {
+        Ok(text) => text.to_string(),
+        // Only cut off in the middle of a character
+        Err(e) if e.error_len().is_none() => String::from_utf8_lossy(&shown[..e.valid_up_to()]).into_owned(),
+        Err(_) => hexdump(shown),
+    };
+    if data.len() > shown.len() {
+        let _ = write!(text, "\n… {} more", format_bytes((data.len() - shown.len()) as u64));
+    }
+    text
+}
+
+/// Offset, hex bytes and printable ASCII, like `hexdump -C`
+fn hexdump(data: &[u8]) -> String {
+    let mut dump = String::new();
+    for (line, chunk) in data.chunks(HEXDUMP_WIDTH).enumerate() {
+        let _ = write!(dump, "{:08x}  ", line * HEXDUMP_WIDTH);
+        for column in 0..HEXDUMP_WIDTH {
+            match chunk.get(column) {
+                Some(byte) => { let _ = write!(dump, "{byte:02x} "); }
+                None => dump.push_str("   "),
+            }
+        }
+        let ascii: String = chunk.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+        let _ = writeln!(dump, " |{ascii}|");
+    }
+    dump.truncate(dump.trim_end().len());
+    dump
+}
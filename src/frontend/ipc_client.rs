@@ -1,52 +1,572 @@
 use std::os::unix::net::UnixStream;
-use std::io::{BufRead, BufReader, Write};
-use crate::shared::{FrontendMessage, BackendMessage, ClipboardItemPreview};
+use std::io::{BufRead, BufReader, Read, Write};
+use crate::shared::{FrontendMessage, BackendError, BackendMessage, ClientInfo, ClipboardContentType, ClipboardItemPreview, Config, Health, HistoryStats, Metrics, MonitorStatus, PhoneDevice, PushEvent, PushFilter, SortMode, SyncStatus, TextTransform};
+use crate::shared::config::Preferences;
+use crate::shared::protocol::{self, PeerInfo};
+use crate::shared::wire::{self, WireFormat};
+use bytes::Bytes;
+use indexmap::IndexMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tracing::{debug, info, warn};
 
-const SOCKET_PATH: &str = "/tmp/cursor-clip.sock";
+/// Reconnect delays double from the initial delay up to the maximum
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Reconnect attempts before a request fails, requests block the caller so keep this short
+const REQUEST_RECONNECT_ATTEMPTS: u32 = 3;
+
+/// State of the connection to the backend, as reported by a push subscription
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connected,
+    /// The backend is not reachable (e.g. restarting), `attempt` counts the failed reconnects
+    Reconnecting { attempt: u32 },
+}
+
+/// Event delivered by a push subscription
+#[derive(Debug, Clone)]
+pub enum SubscriptionEvent {
+    /// Boxed, item pushes are much larger than connection changes
+    Push(Box<BackendMessage>),
+    /// Only sent on changes, a subscription starts out connected
+    ConnectionChanged(ConnectionState),
+}
+
+/// Delay before reconnect `attempt` (starting at 1)
+fn reconnect_delay(attempt: u32) -> Duration {
+    INITIAL_RECONNECT_DELAY
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_RECONNECT_DELAY)
+}
+
+type NewItemCallbacks = Vec<Box<dyn FnMut(&ClipboardItemPreview)>>;
+type HistoryCallbacks = Vec<Box<dyn FnMut(&[ClipboardItemPreview])>>;
+type ErrorCallbacks = Vec<Box<dyn FnMut(&(dyn std::error::Error + 'static))>>;
+
+/// Callbacks registered by embedders, run on the thread using the client
+#[derive(Default)]
+struct Observers {
+    new_item: NewItemCallbacks,
+    history: HistoryCallbacks,
+    error: ErrorCallbacks,
+}
 
 /// Frontend client for communicating with the backend
 pub struct FrontendClient {
     stream: UnixStream,
+    reader: BufReader<UnixStream>,
+    /// Protocol version and capabilities of the backend, from the handshake
+    backend: PeerInfo,
+    /// Wire format agreed on in the handshake
+    format: WireFormat,
+    /// Socket of the backend, reconnects go there as well
+    socket: PathBuf,
+    observers: Observers,
 }
 
 impl FrontendClient {
-    /// Create a new client
+    /// Create a new client and exchange protocol versions and capabilities with the backend
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let stream = UnixStream::connect(SOCKET_PATH)?;
-        Ok(Self { stream })
+        Self::with_format(WireFormat::Json)
+    }
+
+    /// Create a new client that asks the backend to switch to `format` after the handshake,
+    /// e.g. MessagePack for moving image data. Older backends keep using JSON.
+    pub fn with_format(format: WireFormat) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::connect_to(Path::new(protocol::SOCKET_PATH), format)
+    }
+
+    /// Like `with_format`, for a backend listening on `socket` instead of the default socket
+    pub fn connect_to(socket: &Path, format: WireFormat) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut client = Self::connect(socket)?;
+        let hello = FrontendMessage::Hello {
+            protocol_version: protocol::PROTOCOL_VERSION,
+            capabilities: protocol::own_capabilities(),
+            wire_format: format,
+        };
+        match client.exchange(&hello) {
+            Ok(BackendMessage::Hello { protocol_version, capabilities, wire_format }) => {
+                if protocol_version != protocol::PROTOCOL_VERSION {
+                    debug!("Backend speaks protocol version {protocol_version}, frontend {}", protocol::PROTOCOL_VERSION);
+                }
+                client.backend = PeerInfo { protocol_version, capabilities };
+                client.format = wire_format;
+            }
+            // Backends before the handshake answer with an error or drop the connection
+            Ok(_) => debug!("Backend does not support the handshake, using the basic message set"),
+            Err(e) if e.downcast_ref::<std::io::Error>().is_some() => {
+                debug!("Backend dropped the handshake ({e}), using the basic message set");
+                client = Self::connect(socket)?;
+            }
+            Err(e) => return Err(e),
+        }
+        Ok(client)
     }
 
-    /// Send a message and get response
+    fn connect(socket: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let stream = UnixStream::connect(socket)?;
+        let reader = BufReader::new(stream.try_clone()?);
+        Ok(Self { stream, reader, backend: PeerInfo::legacy(), format: WireFormat::Json, socket: socket.to_path_buf(), observers: Observers::default() })
+    }
+
+    /// Protocol version and capabilities of the connected backend
+    pub const fn backend(&self) -> &PeerInfo {
+        &self.backend
+    }
+
+    /// Call `callback` for every item the backend reports as new while this client waits for a
+    /// response or `listen`s, bulk additions included
+    pub fn on_new_item(&mut self, callback: impl FnMut(&ClipboardItemPreview) + 'static) {
+        self.observers.new_item.push(Box::new(callback));
+    }
+
+    /// Call `callback` with the items of every history page or search result received
+    pub fn on_history(&mut self, callback: impl FnMut(&[ClipboardItemPreview]) + 'static) {
+        self.observers.history.push(Box::new(callback));
+    }
+
+    /// Call `callback` for every failed request, whether the backend reported an error or the
+    /// connection could not be restored
+    pub fn on_error(&mut self, callback: impl FnMut(&(dyn std::error::Error + 'static)) + 'static) {
+        self.observers.error.push(Box::new(callback));
+    }
+
+    /// Send a message and get response. If the connection was lost (e.g. the backend
+    /// restarted), reconnect and send the message once more, unless the backend may already
+    /// have acted on it: a request that changes something is only repeated if writing it failed.
     pub fn send_message(&mut self, message: FrontendMessage) -> Result<BackendMessage, Box<dyn std::error::Error>> {
-        let message_json = serde_json::to_string(&message)?;
-        self.stream.write_all(message_json.as_bytes())?;
-        self.stream.write_all(b"\n")?;
+        let result = match self.write_request(&message) {
+            Ok(()) => match self.read_response() {
+                Err(e) if e.downcast_ref::<std::io::Error>().is_some() && message.is_idempotent() => {
+                    debug!("Connection to backend lost ({e}), reconnecting");
+                    self.reconnect().and_then(|()| self.exchange(&message))
+                }
+                result => result,
+            },
+            Err(e) if e.downcast_ref::<std::io::Error>().is_some() => {
+                debug!("Connection to backend lost ({e}), reconnecting");
+                self.reconnect().and_then(|()| self.exchange(&message))
+            }
+            Err(e) => Err(e),
+        };
+        self.notify(&result);
+        result
+    }
+
+    /// Handle pushes on this connection until it closes, for clients that only react to them
+    /// through the registered callbacks
+    pub fn listen(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        loop {
+            let body = match self.read_frame() {
+                Ok(body) => body,
+                Err(e) if e.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::UnexpectedEof) => return Ok(()),
+                Err(e) => return Err(e),
+            };
+            let message: BackendMessage = wire::decode(self.format, &body)?;
+            if message.is_push() {
+                self.handle_push(&message);
+            }
+        }
+    }
 
-        let mut reader = BufReader::new(&self.stream);
-        let mut line = String::new();
-        reader.read_line(&mut line)?;
-        
-    let response: BackendMessage = serde_json::from_str(line.trim())?;
-        Ok(response)
+    /// Run the callbacks interested in the outcome of a request
+    fn notify(&mut self, result: &Result<BackendMessage, Box<dyn std::error::Error>>) {
+        match result {
+            Ok(BackendMessage::History { items } | BackendMessage::HistoryPage { items, .. }) => {
+                self.observers.history.iter_mut().for_each(|callback| callback(items.as_slice()));
+            }
+            Ok(BackendMessage::Error { code, message, details }) => {
+                let error = BackendError { code: *code, message: message.clone(), details: details.clone() };
+                self.observers.error.iter_mut().for_each(|callback| callback(&error));
+            }
+            Ok(_) => {}
+            Err(e) => self.observers.error.iter_mut().for_each(|callback| callback(e.as_ref())),
+        }
+    }
+
+    /// Reconnect to the backend with backoff, giving up after a few attempts
+    fn reconnect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut attempt = 0;
+        loop {
+            match Self::connect_to(&self.socket, self.format) {
+                Ok(client) => {
+                    let observers = std::mem::take(&mut self.observers);
+                    *self = client;
+                    self.observers = observers;
+                    return Ok(());
+                }
+                Err(e) if attempt >= REQUEST_RECONNECT_ATTEMPTS => return Err(e),
+                Err(_) => {
+                    attempt += 1;
+                    std::thread::sleep(reconnect_delay(attempt));
+                }
+            }
+        }
+    }
+
+    fn exchange(&mut self, message: &FrontendMessage) -> Result<BackendMessage, Box<dyn std::error::Error>> {
+        self.write_request(message)?;
+        self.read_response()
+    }
+
+    fn write_request(&mut self, message: &FrontendMessage) -> Result<(), Box<dyn std::error::Error>> {
+        self.stream.write_all(&wire::encode(self.format, message)?)?;
+        Ok(())
+    }
+
+    /// The response to the request written last. Pushes may arrive before it, they are handled
+    /// while waiting.
+    fn read_response(&mut self) -> Result<BackendMessage, Box<dyn std::error::Error>> {
+        loop {
+            let body = self.read_frame()?;
+            let response: BackendMessage = wire::decode(self.format, &body)?;
+            if response.is_push() {
+                self.handle_push(&response);
+            } else {
+                return Ok(response);
+            }
+        }
+    }
+
+    /// Read the next message frame in the current wire format
+    fn read_frame(&mut self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        match self.format {
+            WireFormat::Json => {
+                let mut line = String::new();
+                if self.reader.read_line(&mut line)? == 0 {
+                    return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "Backend closed the connection").into());
+                }
+                Ok(line.trim_end().as_bytes().to_vec())
+            }
+            WireFormat::MsgPack => {
+                let mut len = [0; 4];
+                self.reader.read_exact(&mut len)?;
+                let len = u32::from_be_bytes(len);
+                if len > wire::MAX_FRAME_LEN {
+                    return Err(format!("Response frame of {len} bytes is too large").into());
+                }
+                let mut body = vec![0; len as usize];
+                self.reader.read_exact(&mut body)?;
+                Ok(body)
+            }
+        }
     }
 
-    /// Get clipboard history
-    pub fn get_history(&mut self) -> Result<Vec<ClipboardItemPreview>, Box<dyn std::error::Error>> {
-    let response = self.send_message(FrontendMessage::GetHistory)?;
+    /// Handle an unsolicited push message from the backend
+    fn handle_push(&mut self, message: &BackendMessage) {
+        match message {
+            BackendMessage::NewItem { item } => {
+                debug!("Backend reported new clipboard item {}", item.item_id);
+                self.observers.new_item.iter_mut().for_each(|callback| callback(item));
+            }
+            BackendMessage::ItemsAdded { items } => {
+                debug!("Backend reported {} new clipboard items", items.len());
+                // Newest first on the wire, report them in the order they were added
+                for item in items.iter().rev() {
+                    self.observers.new_item.iter_mut().for_each(|callback| callback(item));
+                }
+            }
+            BackendMessage::SelectionTakenByExternal => debug!("Selection is no longer owned by cursor-clip"),
+            BackendMessage::ItemsRemoved { ids } => debug!("Backend reported {} removed clipboard items", ids.len()),
+            _ => {}
+        }
+    }
+
+    /// Open a dedicated connection that forwards every push message from the backend to the
+    /// returned channel. The receiver does not need a tokio runtime, so it can be awaited on the
+    /// GLib main loop. If the backend goes away, the subscription reports it and reconnects with
+    /// exponential backoff until the backend is back or the receiver is dropped.
+    pub fn subscribe() -> UnboundedReceiver<SubscriptionEvent> {
+        Self::subscribe_to(PathBuf::from(protocol::SOCKET_PATH))
+    }
+
+    /// Like `subscribe`, for a backend listening on `socket` instead of the default socket
+    pub fn subscribe_to(socket: PathBuf) -> UnboundedReceiver<SubscriptionEvent> {
+        Self::spawn_subscription(socket, None)
+    }
+
+    /// Like `subscribe`, forwarding only pushes of the kinds in `events`. Saves wakeups for
+    /// long-lived clients interested in a few of them (backends without `CAP_SUBSCRIBE` send all).
+    pub fn subscribe_events(events: Vec<PushEvent>) -> UnboundedReceiver<SubscriptionEvent> {
+        Self::spawn_subscription(PathBuf::from(protocol::SOCKET_PATH), Some(events))
+    }
+
+    fn spawn_subscription(socket: PathBuf, events: Option<Vec<PushEvent>>) -> UnboundedReceiver<SubscriptionEvent> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            let mut attempt = 0;
+            while !tx.is_closed() {
+                match UnixStream::connect(&socket) {
+                    Ok(stream) => {
+                        if attempt > 0 {
+                            info!("Reconnected to backend");
+                            let _ = tx.send(SubscriptionEvent::ConnectionChanged(ConnectionState::Connected));
+                        }
+                        attempt = 0;
+                        if let Some(events) = &events
+                            && let Err(e) = request_events(&stream, events)
+                        {
+                            debug!("Subscribing to {events:?} failed: {e}");
+                        }
+                        forward_pushes(stream, &tx);
+                    }
+                    Err(e) => debug!("Connecting to backend failed: {e}"),
+                }
+                if tx.is_closed() {
+                    break;
+                }
+                attempt += 1;
+                if attempt == 1 {
+                    warn!("Backend not reachable, reconnecting");
+                }
+                let _ = tx.send(SubscriptionEvent::ConnectionChanged(ConnectionState::Reconnecting { attempt }));
+                std::thread::sleep(reconnect_delay(attempt));
+            }
+            debug!("Push subscription closed");
+        });
+        rx
+    }
+
+    /// Get up to `limit` history items in `sort` order starting at `offset`, together with the
+    /// total item count
+    pub fn get_history_page(&mut self, offset: u32, limit: u32, sort: SortMode) -> Result<(Vec<ClipboardItemPreview>, u32), Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_HISTORY_PAGING) {
+            // Older backends only send the whole history
+            let response = self.send_message(FrontendMessage::GetHistory)?;
+            return match response {
+                BackendMessage::History { mut items } => {
+                    let total = items.len() as u32;
+                    items.sort_by(|a, b| sort.compare(a, b));
+                    Ok((items.into_iter().skip(offset as usize).take(limit as usize).collect(), total))
+                }
+                other => Err(response_error(other)),
+            };
+        }
+        self.check_sort_mode(sort)?;
+        let response = self.send_message(FrontendMessage::GetHistoryPage { offset, limit, sort })?;
         match response {
-            BackendMessage::History { items } => Ok(items),
-            BackendMessage::Error { message } => Err(message.into()),
-            _ => Err("Unexpected response".into()),
+            BackendMessage::HistoryPage { items, total, .. } => Ok((items, total)),
+            other => Err(response_error(other)),
         }
     }
 
+    /// Get up to `limit` items matching `query` and `content_types` (and carrying `tag`) in `sort`
+    /// order starting at `offset`, together with the number of matching items
+    pub fn search_history(&mut self, query: &str, content_types: &[ClipboardContentType], tag: Option<&str>, sort: SortMode, offset: u32, limit: u32) -> Result<(Vec<ClipboardItemPreview>, u32), Box<dyn std::error::Error>> {
+        if tag.is_some() && !self.backend.supports(protocol::CAP_TAGS) {
+            return Err("Backend cannot filter by tag, please update the cursor-clip daemon".into());
+        }
+        if !self.backend.supports(protocol::CAP_SEARCH) {
+            // Older backends cannot search, filter the whole history here
+            let response = self.send_message(FrontendMessage::GetHistory)?;
+            return match response {
+                BackendMessage::History { items } => {
+                    let mut matching: Vec<ClipboardItemPreview> = items.into_iter().filter(|item| item.matches(query, content_types)).collect();
+                    matching.sort_by(|a, b| sort.compare(a, b));
+                    let total = matching.len() as u32;
+                    Ok((matching.into_iter().skip(offset as usize).take(limit as usize).collect(), total))
+                }
+                other => Err(response_error(other)),
+            };
+        }
+        self.check_sort_mode(sort)?;
+        let request = FrontendMessage::SearchHistory {
+            query: query.to_string(),
+            content_types: content_types.to_vec(),
+            offset,
+            limit,
+            sort,
+            tag: tag.map(str::to_string),
+        };
+        match self.send_message(request)? {
+            BackendMessage::HistoryPage { items, total, .. } => Ok((items, total)),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Older backends ignore `sort` and list the newest items first
+    fn check_sort_mode(&self, sort: SortMode) -> Result<(), Box<dyn std::error::Error>> {
+        if sort != SortMode::Recent && !self.backend.supports(protocol::CAP_SORT_MODES) {
+            return Err(format!("The backend cannot sort by \"{}\", please update the cursor-clip daemon", sort.label()).into());
+        }
+        Ok(())
+    }
+
     /// Set clipboard by ID 
     pub fn set_clipboard_by_id(&mut self, id: u64) -> Result<(), Box<dyn std::error::Error>> {
-    let response = self.send_message(FrontendMessage::SetClipboardById { id })?;
+    let response = self.send_message(FrontendMessage::SetClipboardById { id, seat: None, original: false })?;
+        match response {
+            BackendMessage::ClipboardSet => Ok(()),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Set clipboard content by ID on the Wayland seat with the given name
+    pub fn set_clipboard_on_seat(&mut self, id: u64, seat: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_SEATS) {
+            return Err("Backend does not support selecting a seat".into());
+        }
+        let response = self.send_message(FrontendMessage::SetClipboardById { id, seat: Some(seat.to_string()), original: false })?;
+        match response {
+            BackendMessage::ClipboardSet => Ok(()),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Set clipboard content by ID, serving the text as copied if it was cleaned up
+    pub fn set_clipboard_original(&mut self, id: u64) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_ORIGINAL_PAYLOADS) {
+            return Err("Backend cannot paste the original text, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::SetClipboardById { id, seat: None, original: true })? {
+            BackendMessage::ClipboardSet => Ok(()),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Set the clipboard to the payload of an item in a single MIME type
+    pub fn set_clipboard_mime(&mut self, id: u64, mime: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_SINGLE_MIME) {
+            return Err("Backend cannot paste a single MIME type, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::SetClipboardByIdMime { id, mime: mime.to_string() })? {
+            BackendMessage::ClipboardSet => Ok(()),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Copy the text recognized in an image item (added to the history as a new text item)
+    pub fn copy_ocr_text(&mut self, id: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let response = self.send_message(FrontendMessage::CopyOcrText { id })?;
         match response {
             BackendMessage::ClipboardSet => Ok(()),
-            BackendMessage::Error { message } => Err(message.into()),
-            _ => Err("Unexpected response".into()),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Copy the text of an item run through a transform (added to the history as a new item)
+    pub fn transform_item(&mut self, id: u64, op: TextTransform) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_TRANSFORMS) {
+            return Err("Backend does not support text transforms".into());
+        }
+        let response = self.send_message(FrontendMessage::TransformItem { id, op })?;
+        match response {
+            BackendMessage::ClipboardSet => Ok(()),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Pause or resume recording of new selections, returns whether capture is paused now
+    pub fn set_capture_paused(&mut self, paused: bool) -> Result<bool, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_CAPTURE_PAUSE) {
+            return Err("Backend does not support pausing capture".into());
+        }
+        let request = if paused { FrontendMessage::PauseCapture } else { FrontendMessage::ResumeCapture };
+        match self.send_message(request)? {
+            BackendMessage::CaptureState { paused } => Ok(paused),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Whether recording of new selections is paused (never for backends that cannot pause)
+    pub fn capture_paused(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_CAPTURE_PAUSE) {
+            return Ok(false);
+        }
+        match self.send_message(FrontendMessage::GetCaptureState)? {
+            BackendMessage::CaptureState { paused } => Ok(paused),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Check that the backend is alive and how it is doing
+    pub fn ping(&mut self) -> Result<Health, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_PING) {
+            return Err("Backend does not answer pings, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::Ping)? {
+            BackendMessage::Pong { health } => Ok(health),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Capture and IPC counters and latencies of the backend
+    pub fn get_metrics(&mut self) -> Result<Metrics, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_METRICS) {
+            return Err("Backend does not record metrics, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::GetMetrics)? {
+            BackendMessage::Metrics { metrics } => Ok(metrics),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Replace the daemon's log filter (RUST_LOG syntax), returns the filter now in effect
+    pub fn set_log_level(&mut self, filter: &str) -> Result<String, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_LOG_LEVEL) {
+            return Err("Backend cannot change its log level, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::SetLogLevel { filter: filter.to_string() })? {
+            BackendMessage::LogLevel { filter } => Ok(filter),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// State of the clipboard monitoring in the backend (assumed active for older backends)
+    pub fn monitor_status(&mut self) -> Result<MonitorStatus, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_MONITOR_STATUS) {
+            return Ok(MonitorStatus::Active);
+        }
+        match self.send_message(FrontendMessage::GetStatus)? {
+            BackendMessage::Status { status } => Ok(status),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Phones reachable through KDE Connect or GSConnect
+    pub fn list_phones(&mut self) -> Result<Vec<PhoneDevice>, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_PHONE) {
+            return Err("Backend does not support phones".into());
+        }
+        match self.send_message(FrontendMessage::ListPhones)? {
+            BackendMessage::PhoneList { devices } => Ok(devices),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Send the text of an item to a phone (id or name, every reachable phone if None),
+    /// returns the names of the phones it was sent to
+    pub fn send_to_phone(&mut self, id: u64, device: Option<String>) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_PHONE) {
+            return Err("Backend does not support phones".into());
+        }
+        match self.send_message(FrontendMessage::SendToPhone { id, device })? {
+            BackendMessage::PhonesReached { devices } => Ok(devices),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Ask a phone for its clipboard, returns the name of the phone asked
+    pub fn pull_from_phone(&mut self, device: Option<String>) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_PHONE) {
+            return Err("Backend does not support phones".into());
+        }
+        match self.send_message(FrontendMessage::PullFromPhone { device })? {
+            BackendMessage::PhonesReached { devices } => Ok(devices),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// State of history sync in the backend
+    pub fn get_sync_status(&mut self) -> Result<SyncStatus, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_SYNC) {
+            return Err("Backend does not support history sync".into());
+        }
+        match self.send_message(FrontendMessage::GetSyncStatus)? {
+            BackendMessage::SyncStatus { status } => Ok(status),
+            other => Err(response_error(other)),
         }
     }
 
@@ -55,8 +575,294 @@ impl FrontendClient {
     let response = self.send_message(FrontendMessage::ClearHistory)?;
         match response {
             BackendMessage::HistoryCleared => Ok(()),
-            BackendMessage::Error { message } => Err(message.into()),
-            _ => Err("Unexpected response".into()),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Whether deleting single items and undoing deletions is supported by the backend
+    pub fn supports_undo(&self) -> bool {
+        self.backend.supports(protocol::CAP_UNDO)
+    }
+
+    /// Delete one item, it can be restored with `undo_delete` for `UNDO_WINDOW_SECS`
+    pub fn delete_item(&mut self, id: u64) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.supports_undo() {
+            return Err("Backend cannot delete single items, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::DeleteItem { id })? {
+            BackendMessage::ItemDeleted { .. } => Ok(()),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Restore a deleted item, returns the ids of the restored items
+    pub fn undo_delete(&mut self, id: u64) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+        self.undo(FrontendMessage::UndoDelete { id })
+    }
+
+    /// Restore the items removed by the most recent clear, returns their ids
+    pub fn undo_clear(&mut self) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+        self.undo(FrontendMessage::UndoClear)
+    }
+
+    fn undo(&mut self, message: FrontendMessage) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+        if !self.supports_undo() {
+            return Err("Backend cannot undo deletions, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(message)? {
+            BackendMessage::Restored { ids } => Ok(ids),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Move an item to `position` (0 is the top) of the history, returns where it ended up
+    pub fn move_item(&mut self, id: u64, position: u32) -> Result<u32, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_MOVE_ITEMS) {
+            return Err("Backend cannot reorder items, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::MoveItem { id, position })? {
+            BackendMessage::ItemMoved { position, .. } => Ok(position),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Attach a tag to an item, returns the item's tags
+    pub fn add_tag(&mut self, id: u64, tag: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_TAGS) {
+            return Err("Backend cannot tag items, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::AddTag { id, tag: tag.to_string() })? {
+            BackendMessage::ItemTags { tags, .. } => Ok(tags),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Remove a tag from an item, returns the item's tags
+    pub fn remove_tag(&mut self, id: u64, tag: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_TAGS) {
+            return Err("Backend cannot tag items, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::RemoveTag { id, tag: tag.to_string() })? {
+            BackendMessage::ItemTags { tags, .. } => Ok(tags),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Tags used in the history, sorted by name (none from backends without tag support)
+    pub fn list_tags(&mut self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_TAGS) {
+            return Ok(Vec::new());
+        }
+        match self.send_message(FrontendMessage::ListTags)? {
+            BackendMessage::TagList { tags } => Ok(tags),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Set the next item of the copy stack as the clipboard content (see `FrontendMessage::PopAndPaste`),
+    /// returns it and the number of items left on the stack
+    pub fn pop_and_paste(&mut self, depth: Option<u32>) -> Result<(ClipboardItemPreview, u32), Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_COPY_STACK) {
+            return Err("Backend has no copy stack, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::PopAndPaste { depth })? {
+            BackendMessage::StackPopped { item, remaining } => Ok((item, remaining)),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Version of the running daemon and of the protocol it speaks
+    pub fn get_version(&mut self) -> Result<(String, u32), Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_VERSION) {
+            return Err("Backend does not report its version, it is older than this cursor-clip".into());
+        }
+        match self.send_message(FrontendMessage::GetVersion)? {
+            BackendMessage::Version { version, protocol_version } => Ok((version, protocol_version)),
+            other => Err(response_error(other)),
         }
     }
-}
\ No newline at end of file
+
+    /// Change the preferences of the daemon, which writes them to the config file, returns the
+    /// config now in effect
+    pub fn set_config(&mut self, preferences: Preferences) -> Result<Config, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_SET_CONFIG) {
+            return Err("Backend cannot change its config, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::SetConfig { preferences })? {
+            BackendMessage::ConfigApplied { config } => Ok(config),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Fetch the full payloads of an item in all its MIME types
+    pub fn get_item_data(&mut self, id: u64) -> Result<IndexMap<String, Bytes>, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_ITEM_DATA) {
+            return Err("Backend cannot send item data, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::GetItemData { id })? {
+            BackendMessage::ItemData { mime_data, .. } => Ok(mime_data),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Get the items copied from `from` until before `to` (Unix time in milliseconds), newest first
+    pub fn get_history_range(&mut self, from: u64, to: u64) -> Result<Vec<ClipboardItemPreview>, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_HISTORY_RANGE) {
+            return Err("Backend cannot look up items by date, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::GetHistoryRange { from, to })? {
+            BackendMessage::HistoryRange { items, .. } => Ok(items),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Add an item with these payloads to the history as if it had been copied, optionally
+    /// setting it as the clipboard content. Returns the id of the new item.
+    pub fn add_item(&mut self, mime_data: IndexMap<String, Bytes>, set_clipboard: bool) -> Result<u64, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_ADD_ITEM) {
+            return Err("Backend cannot add items, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::AddItem { mime_data, set_clipboard })? {
+            BackendMessage::ItemAdded { id } => Ok(id),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Get a summary of the history
+    pub fn get_stats(&mut self) -> Result<HistoryStats, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_STATS) {
+            return Err("Backend does not provide history statistics".into());
+        }
+        let response = self.send_message(FrontendMessage::GetStats)?;
+        match response {
+            BackendMessage::Stats { stats } => Ok(stats),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Name this connection in `ListClients` and choose the pushes it gets, returns its client id
+    pub fn identify(&mut self, name: &str, filter: PushFilter) -> Result<u64, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_CLIENTS) {
+            return Err("Backend does not know about clients, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::Identify { name: name.to_string(), filter })? {
+            BackendMessage::Identified { client_id } => Ok(client_id),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Only get pushes of the kinds in `events` on this connection, returns the subscribed kinds
+    pub fn set_push_events(&mut self, events: &[PushEvent]) -> Result<Vec<PushEvent>, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_SUBSCRIBE) {
+            return Err("Backend cannot filter pushes, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::Subscribe { events: events.to_vec() })? {
+            BackendMessage::Subscribed { events } => Ok(events),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Get the clients connected to the backend, this one included
+    pub fn list_clients(&mut self) -> Result<Vec<ClientInfo>, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_CLIENTS) {
+            return Err("Backend does not list its clients, please update the cursor-clip daemon".into());
+        }
+        match self.send_message(FrontendMessage::ListClients)? {
+            BackendMessage::ClientList { clients } => Ok(clients),
+            other => Err(response_error(other)),
+        }
+    }
+
+    /// Get a PNG thumbnail of an image item that fits into a max_px square
+    pub fn get_thumbnail(&mut self, id: u64, max_px: u32) -> Result<Bytes, Box<dyn std::error::Error>> {
+        if !self.backend.supports(protocol::CAP_THUMBNAILS) {
+            return Err("Backend does not provide thumbnails".into());
+        }
+        let response = self.send_message(FrontendMessage::GetThumbnail { id, max_px })?;
+        match response {
+            BackendMessage::Thumbnail { png, .. } => Ok(png),
+            other => Err(response_error(other)),
+        }
+    }
+}
+
+/// Error for a response that does not answer the request, a `BackendError` if the backend reported one
+fn response_error(response: BackendMessage) -> Box<dyn std::error::Error> {
+    match response {
+        BackendMessage::Error { code, message, details } => Box::new(BackendError { code, message, details }),
+        _ => "Unexpected response".into(),
+    }
+}
+
+/// Ask the backend for pushes of the kinds in `events` only on a subscription connection, the
+/// answer is skipped by `forward_pushes` like any other non-push message
+fn request_events(mut stream: &UnixStream, events: &[PushEvent]) -> Result<(), Box<dyn std::error::Error>> {
+    let request = FrontendMessage::Subscribe { events: events.to_vec() };
+    stream.write_all(&wire::encode(WireFormat::Json, &request)?)?;
+    Ok(())
+}
+
+/// Forward push messages from `stream` until the connection closes or the receiver is dropped
+fn forward_pushes(stream: UnixStream, tx: &UnboundedSender<SubscriptionEvent>) {
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { break };
+        match serde_json::from_str::<BackendMessage>(line.trim()) {
+            Ok(message) if message.is_push() => {
+                if tx.send(SubscriptionEvent::Push(Box::new(message))).is_err() {
+                    break;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => debug!("Ignoring unreadable message from backend: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixListener;
+    use std::sync::{Arc, Mutex};
+    use super::*;
+
+    /// A backend answering the handshake that reads one request per connection and hangs up
+    /// without answering it. Returns its socket and the names of the requests it read.
+    fn hanging_up_backend(name: &str) -> (PathBuf, Arc<Mutex<Vec<String>>>) {
+        let socket = std::env::temp_dir().join(format!("cursor-clip-client-test-{}-{name}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let hello = BackendMessage::Hello {
+                    protocol_version: protocol::PROTOCOL_VERSION,
+                    capabilities: protocol::own_capabilities(),
+                    wire_format: WireFormat::Json,
+                };
+                stream.write_all(&wire::encode(WireFormat::Json, &hello).unwrap()).unwrap();
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+                let request: FrontendMessage = serde_json::from_str(&line).unwrap();
+                seen.lock().unwrap().push(request.name().to_string());
+            }
+        });
+        (socket, requests)
+    }
+
+    #[test]
+    fn only_idempotent_requests_are_sent_again() {
+        let (socket, requests) = hanging_up_backend("retry");
+        let mut client = FrontendClient::connect_to(&socket, WireFormat::Json).unwrap();
+        assert!(client.send_message(FrontendMessage::DeleteItem { id: 1 }).is_err());
+        assert_eq!(*requests.lock().unwrap(), ["DeleteItem"]);
+
+        let mut client = FrontendClient::connect_to(&socket, WireFormat::Json).unwrap();
+        assert!(client.send_message(FrontendMessage::GetStats).is_err());
+        assert_eq!(*requests.lock().unwrap(), ["DeleteItem", "GetStats", "GetStats"]);
+        let _ = std::fs::remove_file(&socket);
+    }
+}
@@ -1,7 +1,44 @@
+#[cfg(feature = "gtk")]
 pub mod initializer;
+#[cfg(feature = "gtk")]
 pub mod frontend_state;
+#[cfg(feature = "gtk")]
 pub mod dispatch;
+#[cfg(feature = "gtk")]
 pub mod gtk_overlay;
 pub mod ipc_client;
+pub mod async_client;
+#[cfg(feature = "gtk")]
+pub mod theme;
+#[cfg(feature = "gtk")]
+pub mod history_model;
+#[cfg(feature = "gtk")]
+pub mod history_grid;
+#[cfg(feature = "gtk")]
+pub mod history_list;
+#[cfg(feature = "gtk")]
+pub mod history_widget;
+#[cfg(feature = "gtk")]
+pub mod preview_pane;
+#[cfg(feature = "gtk")]
+pub mod inspector;
+#[cfg(feature = "gtk")]
+pub mod preferences;
+#[cfg(feature = "gtk")]
+pub mod thumbnails;
+#[cfg(feature = "gtk")]
+pub mod demo;
+#[cfg(feature = "gtk")]
+pub mod tray;
+#[cfg(feature = "gtk")]
+pub mod search_provider;
+#[cfg(feature = "gtk")]
+pub mod screenshot;
+#[cfg(feature = "gtk")]
+pub mod time_format;
+#[cfg(feature = "gtk")]
+pub mod timeline;
+pub mod tui;
 
+#[cfg(feature = "gtk")]
 pub use initializer::*;
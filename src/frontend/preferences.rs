@@ -0,0 +1,161 @@
+//! Preferences dialog of the overlay with the most common options of the config file. Changes
+//! go through the daemon, which writes the file and tells every client about the new config.
+use gtk4::prelude::*;
+use libadwaita::{self as adw, prelude::*};
+use crate::frontend::history_widget::ClipboardHistoryWidget;
+use crate::shared::Config;
+use crate::shared::config::{ClockFormat, ColorScheme, Preferences, SecretsPolicy, TimeFormat};
+use crate::shared::i18n::gettext;
+use tracing::error;
+
+fn color_schemes() -> [(ColorScheme, String); 3] {
+    [
+        (ColorScheme::System, gettext("Follow system")),
+        (ColorScheme::Light, gettext("Light")),
+        (ColorScheme::Dark, gettext("Dark")),
+    ]
+}
+
+fn time_formats() -> [(TimeFormat, String); 2] {
+    [
+        (TimeFormat::Relative, gettext("Relative")),
+        (TimeFormat::Absolute, gettext("Date and time")),
+    ]
+}
+
+fn clock_formats() -> [(ClockFormat, String); 3] {
+    [
+        (ClockFormat::System, gettext("Follow system")),
+        (ClockFormat::TwelveHour, gettext("12-hour")),
+        (ClockFormat::TwentyFourHour, gettext("24-hour")),
+    ]
+}
+
+fn secrets_policies() -> [(SecretsPolicy, String); 2] {
+    [
+        (SecretsPolicy::Record, gettext("Record")),
+        (SecretsPolicy::Ignore, gettext("Don't record")),
+    ]
+}
+
+/// Largest history size offered, every item is kept in the daemon's memory
+const MAX_HISTORY_ITEMS: f64 = 10_000.0;
+
+/// Open the preferences over the overlay, showing the current config file
+pub fn show_preferences(parent: &impl IsA<gtk4::Widget>, history: &ClipboardHistoryWidget) {
+    let config = Config::load();
+    let dialog = adw::PreferencesDialog::new();
+    dialog.set_title(&gettext("Preferences"));
+    let page = adw::PreferencesPage::new();
+
+    let history_group = adw::PreferencesGroup::builder().title(gettext("History")).build();
+    let size_row = adw::SpinRow::with_range(1.0, MAX_HISTORY_ITEMS, 10.0);
+    size_row.set_title(&gettext("History size"));
+    size_row.set_subtitle(&gettext("Older items are removed"));
+    size_row.set_value(f64::from(config.max_history_items));
+    let history_for_size = history.clone();
+    size_row.connect_value_notify(move |row| {
+        let items = row.value() as u32;
+        change_config(row, &history_for_size, |preferences| preferences.max_history_items = items);
+    });
+    history_group.add(&size_row);
+
+    let secrets_row = combo_row(&gettext("Password manager secrets"), secrets_policies().map(|(_, label)| label), secrets_policies().iter().position(|(policy, _)| *policy == config.secrets));
+    secrets_row.set_subtitle(&gettext("Items a password manager marks as secret"));
+    let history_for_secrets = history.clone();
+    secrets_row.connect_selected_notify(move |row| {
+        let Some((policy, _)) = secrets_policies().into_iter().nth(row.selected() as usize) else { return };
+        change_config(row, &history_for_secrets, |preferences| preferences.secrets = policy);
+    });
+    history_group.add(&secrets_row);
+    page.add(&history_group);
+
+    let appearance_group = adw::PreferencesGroup::builder().title(gettext("Appearance")).build();
+    let scheme_row = combo_row(&gettext("Color scheme"), color_schemes().map(|(_, label)| label), color_schemes().iter().position(|(scheme, _)| *scheme == config.color_scheme));
+    let history_for_scheme = history.clone();
+    scheme_row.connect_selected_notify(move |row| {
+        let Some((scheme, _)) = color_schemes().into_iter().nth(row.selected() as usize) else { return };
+        change_config(row, &history_for_scheme, |preferences| preferences.color_scheme = scheme);
+    });
+    appearance_group.add(&scheme_row);
+
+    let images_row = switch_row(&gettext("Image previews"), &gettext("Show thumbnails of copied images"), config.show_image_previews);
+    let history_for_images = history.clone();
+    images_row.connect_active_notify(move |row| {
+        let active = row.is_active();
+        change_config(row, &history_for_images, |preferences| preferences.show_image_previews = active);
+    });
+    appearance_group.add(&images_row);
+
+    let time_row = combo_row(&gettext("Time of items"), time_formats().map(|(_, label)| label), time_formats().iter().position(|(format, _)| *format == config.time_format));
+    time_row.set_subtitle(&gettext("\"5 minutes ago\" or when they were copied"));
+    let history_for_time = history.clone();
+    time_row.connect_selected_notify(move |row| {
+        let Some((format, _)) = time_formats().into_iter().nth(row.selected() as usize) else { return };
+        change_config(row, &history_for_time, |preferences| preferences.time_format = format);
+    });
+    appearance_group.add(&time_row);
+
+    let clock_row = combo_row(&gettext("Clock"), clock_formats().map(|(_, label)| label), clock_formats().iter().position(|(clock, _)| *clock == config.clock));
+    let history_for_clock = history.clone();
+    clock_row.connect_selected_notify(move |row| {
+        let Some((clock, _)) = clock_formats().into_iter().nth(row.selected() as usize) else { return };
+        change_config(row, &history_for_clock, |preferences| preferences.clock = clock);
+    });
+    appearance_group.add(&clock_row);
+    page.add(&appearance_group);
+
+    let behavior_group = adw::PreferencesGroup::builder().title(gettext("Behavior")).build();
+    let keep_open_row = switch_row(&gettext("Keep open after pasting"), &gettext("Otherwise only while Ctrl is held"), config.keep_open_after_paste);
+    let history_for_keep_open = history.clone();
+    keep_open_row.connect_active_notify(move |row| {
+        let active = row.is_active();
+        change_config(row, &history_for_keep_open, |preferences| preferences.keep_open_after_paste = active);
+    });
+    behavior_group.add(&keep_open_row);
+
+    let focus_row = switch_row(&gettext("Close on outside clicks"), &gettext("Takes effect the next time the overlay opens"), config.close_on_focus_loss);
+    let history_for_focus = history.clone();
+    focus_row.connect_active_notify(move |row| {
+        let active = row.is_active();
+        change_config(row, &history_for_focus, |preferences| preferences.close_on_focus_loss = active);
+    });
+    behavior_group.add(&focus_row);
+    page.add(&behavior_group);
+
+    dialog.add(&page);
+    dialog.present(Some(parent));
+}
+
+fn combo_row<const N: usize>(title: &str, labels: [String; N], selected: Option<usize>) -> adw::ComboRow {
+    let row = adw::ComboRow::new();
+    row.set_title(title);
+    row.set_model(Some(&gtk4::StringList::new(&labels.each_ref().map(String::as_str))));
+    row.set_selected(selected.unwrap_or(0) as u32);
+    row
+}
+
+fn switch_row(title: &str, subtitle: &str, active: bool) -> adw::SwitchRow {
+    let row = adw::SwitchRow::new();
+    row.set_title(title);
+    row.set_subtitle(subtitle);
+    row.set_active(active);
+    row
+}
+
+/// Change the preferences in the config file through the backend, starting from the current
+/// content of the file. Failures are shown as a toast in the dialog of `row`.
+fn change_config(row: &impl IsA<gtk4::Widget>, history: &ClipboardHistoryWidget, change: impl FnOnce(&mut Preferences)) {
+    let current = Preferences::of(&Config::load());
+    let mut preferences = current.clone();
+    change(&mut preferences);
+    if preferences == current {
+        return;
+    }
+    if let Err(e) = history.set_config(preferences) {
+        error!("Error saving the preferences: {e}");
+        if let Some(dialog) = row.ancestor(adw::PreferencesDialog::static_type()).and_downcast::<adw::PreferencesDialog>() {
+            dialog.add_toast(adw::Toast::new(&gettext("Could not save the preferences")));
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Expandable pane under the history views showing the full content of the selected item:
+//! scrollable text, the full-resolution image or the copied file list. The payload is only
+//! fetched from the backend while the pane is open.
+use gtk4::prelude::*;
+use gtk4::{Align, Label, Orientation};
+use indexmap::IndexMap;
+use bytes::Bytes;
+use std::cell::Cell;
+use std::rc::Rc;
+use crate::frontend::demo::demo_thumbnail;
+use crate::frontend::ipc_client::FrontendClient;
+use crate::shared::{ClipboardContentType, ClipboardItemPreview};
+use crate::shared::wire::WireFormat;
+use crate::shared::i18n::{gettext, trf};
+use tracing::warn;
+
+/// Text MIME types in order of preference
+const TEXT_MIME_TYPES: [&str; 3] = ["text/plain;charset=utf-8", "UTF8_STRING", "text/plain"];
+
+/// Longest text put into the text view, huge selections would make it sluggish
+const MAX_PREVIEW_CHARS: usize = 512 * 1024;
+
+/// Size of the placeholder image shown in demo mode
+const DEMO_IMAGE_PX: u32 = 640;
+
+#[derive(Clone)]
+pub struct PreviewPane {
+    revealer: gtk4::Revealer,
+    stack: gtk4::Stack,
+    text_view: gtk4::TextView,
+    picture: gtk4::Picture,
+    files: gtk4::Box,
+    message: Label,
+    /// Item currently shown, so rebinding the same selection does not fetch it again
+    shown: Rc<Cell<Option<u64>>>,
+    demo: bool,
+}
+
+impl PreviewPane {
+    pub fn new(demo: bool) -> Self {
+        let text_view = gtk4::TextView::new();
+        text_view.set_editable(false);
+        text_view.set_cursor_visible(false);
+        text_view.set_wrap_mode(gtk4::WrapMode::WordChar);
+        text_view.set_top_margin(6);
+        text_view.set_bottom_margin(6);
+        text_view.set_left_margin(8);
+        text_view.set_right_margin(8);
+        text_view.add_css_class("clipboard-preview-text");
+
+        let picture = gtk4::Picture::new();
+        picture.set_can_shrink(true);
+        picture.set_content_fit(gtk4::ContentFit::Contain);
+
+        let files = gtk4::Box::new(Orientation::Vertical, 2);
+        files.set_margin_top(6);
+        files.set_margin_start(8);
+        files.set_margin_end(8);
+
+        let message = Label::new(None);
+        message.add_css_class("dim-label");
+        message.set_wrap(true);
+        message.set_valign(Align::Center);
+
+        let stack = gtk4::Stack::new();
+        stack.add_named(&scrolled(&text_view), Some("text"));
+        stack.add_named(&picture, Some("image"));
+        stack.add_named(&scrolled(&files), Some("files"));
+        stack.add_named(&message, Some("message"));
+        stack.set_size_request(-1, 220);
+        stack.add_css_class("clipboard-preview-pane");
+
+        let revealer = gtk4::Revealer::new();
+        revealer.set_transition_type(gtk4::RevealerTransitionType::SlideUp);
+        revealer.set_child(Some(&stack));
+
+        Self { revealer, stack, text_view, picture, files, message, shown: Rc::default(), demo }
+    }
+
+    /// The revealer holding the pane, put it under the views
+    pub fn widget(&self) -> &gtk4::Revealer {
+        &self.revealer
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.revealer.reveals_child()
+    }
+
+    /// Open the pane on `item` (or an empty pane without a selection), or close it
+    pub fn set_open(&self, open: bool, item: Option<&ClipboardItemPreview>) {
+        self.revealer.set_reveal_child(open);
+        if open {
+            self.show(item);
+        } else {
+            // Fetch again when reopened, the item may have changed meanwhile
+            self.shown.set(None);
+        }
+    }
+
+    /// Show `item` if the pane is open
+    pub fn show(&self, item: Option<&ClipboardItemPreview>) {
+        if !self.is_open() || self.shown.get() == item.map(|item| item.item_id) {
+            return;
+        }
+        self.shown.set(item.map(|item| item.item_id));
+        let Some(item) = item else {
+            self.show_message(&gettext("Nothing selected"));
+            return;
+        };
+        if matches!(item.content_type, ClipboardContentType::Password) {
+            self.show_message(&gettext("Passwords are not previewed, paste the item to use it"));
+            return;
+        }
+        if self.demo {
+            self.show_demo(item);
+            return;
+        }
+        // Payloads can be large images, which MessagePack frames carry as raw bytes
+        match FrontendClient::with_format(WireFormat::MsgPack).and_then(|mut client| client.get_item_data(item.item_id)) {
+            Ok(mime_data) => self.show_data(item, &mime_data),
+            Err(e) => {
+                warn!("Failed to fetch the data of item {} for the preview: {e}", item.item_id);
+                self.show_message(&trf("Could not load the item: {}", &e.to_string()));
+            }
+        }
+    }
+
+    fn show_demo(&self, item: &ClipboardItemPreview) {
+        if matches!(item.content_type, ClipboardContentType::Image) {
+            self.show_image(&demo_thumbnail(DEMO_IMAGE_PX));
+        } else {
+            self.show_text(&item.content_preview, item.content_type);
+        }
+    }
+
+    fn show_data(&self, item: &ClipboardItemPreview, mime_data: &IndexMap<String, Bytes>) {
+        if let Some(uris) = mime_data.get("text/uri-list")
+            && matches!(item.content_type, ClipboardContentType::File)
+        {
+            self.show_files(&String::from_utf8_lossy(uris));
+            return;
+        }
+        for (mime, data) in mime_data.iter().filter(|(mime, _)| mime.starts_with("image/")) {
+            match gtk4::gdk::Texture::from_bytes(&gtk4::glib::Bytes::from(data.as_ref())) {
+                Ok(texture) => {
+                    self.show_image(&texture);
+                    return;
+                }
+                Err(e) => warn!("Could not load {mime} of item {} for the preview: {e}", item.item_id),
+            }
+        }
+        match TEXT_MIME_TYPES.iter().find_map(|mime| mime_data.get(*mime)) {
+            Some(text) => self.show_text(&String::from_utf8_lossy(text), item.content_type),
+            None => {
+                let formats: Vec<&str> = mime_data.keys().map(String::as_str).collect();
+                self.show_message(&format!("{}\n{}", gettext("No preview for this item"), formats.join(", ")));
+            }
+        }
+    }
+
+    fn show_text(&self, text: &str, content_type: ClipboardContentType) {
+        let shown = match text.char_indices().nth(MAX_PREVIEW_CHARS) {
+            Some((end, _)) => format!("{}\n…", &text[..end]),
+            None => text.to_string(),
+        };
+        self.text_view.buffer().set_text(&shown);
+        self.text_view.set_monospace(matches!(content_type, ClipboardContentType::Code));
+        self.stack.set_visible_child_name("text");
+    }
+
+    fn show_image(&self, texture: &gtk4::gdk::Texture) {
+        self.picture.set_paintable(Some(texture));
+        self.picture.set_tooltip_text(Some(&format!("{}×{}", texture.width(), texture.height())));
+        self.stack.set_visible_child_name("image");
+    }
+
+    fn show_files(&self, uri_list: &str) {
+        while let Some(child) = self.files.first_child() {
+            self.files.remove(&child);
+        }
+        // text/uri-list: one URI per line, lines starting with # are comments
+        for uri in uri_list.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let file = gtk4::gio::File::for_uri(uri);
+            let name = file.path().map(|path| path.display().to_string()).unwrap_or_else(|| uri.to_string());
+            let label = Label::new(Some(&format!("{} {name}", ClipboardContentType::File.icon())));
+            label.set_halign(Align::Start);
+            label.set_selectable(true);
+            label.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
+            self.files.append(&label);
+        }
+        self.stack.set_visible_child_name("files");
+    }
+
+    fn show_message(&self, message: &str) {
+        self.message.set_label(message);
+        self.stack.set_visible_child_name("message");
+    }
+}
+
+fn scrolled(child: &impl IsA<gtk4::Widget>) -> gtk4::ScrolledWindow {
+    let window = gtk4::ScrolledWindow::new();
+    window.set_policy(gtk4::PolicyType::Never, gtk4::PolicyType::Automatic);
+    window.set_child(Some(child));
+    window
+}
@@ -0,0 +1,79 @@
+//! Screenshots through the XDG desktop portal (`cursor-clip shot`): the portal takes the
+//! screenshot, asking the user what to capture if interactive, and the PNG goes straight into
+//! the history instead of waiting for a screenshot tool to set the selection.
+use gtk4::gio;
+use gtk4::glib::{self, Variant};
+use gtk4::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use tracing::debug;
+
+const PORTAL_BUS_NAME: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SCREENSHOT_INTERFACE: &str = "org.freedesktop.portal.Screenshot";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+
+/// Response code and results of a portal request
+type PortalResponse = (u32, HashMap<String, Variant>);
+
+/// Take a screenshot through the portal and return it as PNG. The portal saves it as a file as
+/// well, which is left where it is.
+pub fn take_screenshot(interactive: bool) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let connection = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE)?;
+    let sender = connection.unique_name().ok_or("No unique name on the session bus")?;
+    let token = format!("cursor_clip_{}", std::process::id());
+    // The portal answers on a request object named after our connection and the token,
+    // subscribe before asking so the answer cannot be missed
+    let request_path = format!("{PORTAL_PATH}/request/{}/{token}", sender.trim_start_matches(':').replace('.', "_"));
+
+    let response: Rc<RefCell<Option<PortalResponse>>> = Rc::default();
+    let main_loop = glib::MainLoop::new(None, false);
+    let subscription = {
+        let response = response.clone();
+        let main_loop = main_loop.clone();
+        connection.subscribe_to_signal(
+            Some(PORTAL_BUS_NAME),
+            Some(REQUEST_INTERFACE),
+            Some("Response"),
+            Some(&request_path),
+            None,
+            gio::DBusSignalFlags::NONE,
+            move |signal| {
+                *response.borrow_mut() = signal.parameters.get();
+                main_loop.quit();
+            },
+        )
+    };
+
+    let mut options = HashMap::new();
+    options.insert("handle_token".to_string(), token.to_variant());
+    options.insert("interactive".to_string(), interactive.to_variant());
+    let requested = connection.call_sync(
+        Some(PORTAL_BUS_NAME),
+        PORTAL_PATH,
+        SCREENSHOT_INTERFACE,
+        "Screenshot",
+        Some(&("", options).to_variant()),
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+        gio::Cancellable::NONE,
+    );
+    if let Err(e) = requested {
+        return Err(format!("The screenshot portal is not available: {e}").into());
+    }
+    debug!("Waiting for the screenshot portal on {request_path}");
+    main_loop.run();
+    drop(subscription);
+
+    let (code, results) = response.take().ok_or("The screenshot portal sent an unreadable response")?;
+    match code {
+        0 => {}
+        1 => return Err("Screenshot cancelled".into()),
+        _ => return Err("The screenshot portal failed to take a screenshot".into()),
+    }
+    let uri = results.get("uri").and_then(Variant::get::<String>).ok_or("The screenshot portal sent no image")?;
+    let path = gio::File::for_uri(&uri).path().ok_or_else(|| format!("Screenshot {uri} is not a local file"))?;
+    Ok(std::fs::read(path)?)
+}
@@ -0,0 +1,172 @@
+//! GNOME Shell search provider (`cursor-clip search-provider`): implements
+//! `org.gnome.Shell.SearchProvider2` so typing in the overview searches the clipboard history,
+//! and activating a result sets it as the clipboard. Works where the layer-shell overlay cannot
+//! run; the backend is reached through `FrontendClient` like from the overlay.
+use gtk4::gio;
+use gtk4::glib::{self, Variant};
+use gtk4::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::frontend::ipc_client::FrontendClient;
+use crate::shared::format;
+use crate::shared::{ClipboardContentType, ClipboardItemPreview, SortMode};
+use tracing::{debug, error, info};
+
+pub const BUS_NAME: &str = "io.github.Tomakin.CursorClip.SearchProvider";
+pub const OBJECT_PATH: &str = "/io/github/Tomakin/CursorClip/SearchProvider";
+const INTERFACE: &str = "org.gnome.Shell.SearchProvider2";
+/// The overview shows a handful of results per provider
+const MAX_RESULTS: u32 = 10;
+const NAME_CHARS: usize = 80;
+
+const INTROSPECTION: &str = r#"
+<node>
+  <interface name="org.gnome.Shell.SearchProvider2">
+    <method name="GetInitialResultSet">
+      <arg name="terms" type="as" direction="in"/>
+      <arg name="results" type="as" direction="out"/>
+    </method>
+    <method name="GetSubsearchResultSet">
+      <arg name="previous_results" type="as" direction="in"/>
+      <arg name="terms" type="as" direction="in"/>
+      <arg name="results" type="as" direction="out"/>
+    </method>
+    <method name="GetResultMetas">
+      <arg name="identifiers" type="as" direction="in"/>
+      <arg name="metas" type="aa{sv}" direction="out"/>
+    </method>
+    <method name="ActivateResult">
+      <arg name="identifier" type="s" direction="in"/>
+      <arg name="terms" type="as" direction="in"/>
+      <arg name="timestamp" type="u" direction="in"/>
+    </method>
+    <method name="LaunchSearch">
+      <arg name="terms" type="as" direction="in"/>
+      <arg name="timestamp" type="u" direction="in"/>
+    </method>
+  </interface>
+</node>
+"#;
+
+/// Items of the last search, the shell asks for their metas right after
+#[derive(Clone, Default)]
+struct SearchProvider {
+    results: Rc<RefCell<HashMap<u64, ClipboardItemPreview>>>,
+}
+
+/// Serve searches until the process is terminated
+pub fn run_search_provider() -> Result<(), Box<dyn std::error::Error>> {
+    let connection = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE)?;
+    let node = gio::DBusNodeInfo::for_xml(INTROSPECTION)?;
+    let interface = node.lookup_interface(INTERFACE).ok_or("missing search provider interface")?;
+    let provider = SearchProvider::default();
+    connection
+        .register_object(OBJECT_PATH, &interface)
+        .method_call(move |_, _, _, _, method, parameters, invocation| {
+            let reply = provider.call(method, &parameters);
+            invocation.return_value(reply.as_ref());
+        })
+        .build()?;
+
+    connection.call_sync(
+        Some("org.freedesktop.DBus"),
+        "/org/freedesktop/DBus",
+        "org.freedesktop.DBus",
+        "RequestName",
+        Some(&(BUS_NAME, 0u32).to_variant()),
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+        gio::Cancellable::NONE,
+    )?;
+    info!("Search provider registered as {BUS_NAME}");
+
+    glib::MainLoop::new(None, false).run();
+    Ok(())
+}
+
+impl SearchProvider {
+    /// Answer a method call, None for methods without a return value
+    fn call(&self, method: &str, parameters: &Variant) -> Option<Variant> {
+        match method {
+            "GetInitialResultSet" => {
+                let (terms,) = parameters.get::<(Vec<String>,)>()?;
+                Some((self.search(&terms),).to_variant())
+            }
+            // The backend searches fast enough to start over instead of narrowing down
+            "GetSubsearchResultSet" => {
+                let (_, terms) = parameters.get::<(Vec<String>, Vec<String>)>()?;
+                Some((self.search(&terms),).to_variant())
+            }
+            "GetResultMetas" => {
+                let (identifiers,) = parameters.get::<(Vec<String>,)>()?;
+                let results = self.results.borrow();
+                let metas: Vec<HashMap<String, Variant>> = identifiers
+                    .iter()
+                    .filter_map(|identifier| results.get(&identifier.parse::<u64>().ok()?))
+                    .map(result_meta)
+                    .collect();
+                Some((metas,).to_variant())
+            }
+            "ActivateResult" => {
+                let (identifier, _, _) = parameters.get::<(String, Vec<String>, u32)>()?;
+                match identifier.parse::<u64>() {
+                    Ok(id) => {
+                        if let Err(e) = FrontendClient::new().and_then(|mut client| client.set_clipboard_by_id(id)) {
+                            error!("Error setting clipboard item {id}: {e}");
+                        }
+                    }
+                    Err(_) => debug!("Ignoring activation of unknown result {identifier}"),
+                }
+                None
+            }
+            // LaunchSearch: the overlay needs layer shell, which is missing where this provider is useful
+            _ => None,
+        }
+    }
+
+    /// Ids of the items matching `terms` (searched as one phrase), newest first. Passwords never show up in the shell.
+    fn search(&self, terms: &[String]) -> Vec<String> {
+        let query = terms.join(" ");
+        let items = match FrontendClient::new().and_then(|mut client| client.search_history(&query, &[], None, SortMode::Recent, 0, MAX_RESULTS)) {
+            Ok((items, _)) => items,
+            Err(e) => {
+                debug!("Searching the clipboard history failed: {e}");
+                Vec::new()
+            }
+        };
+        let mut results = self.results.borrow_mut();
+        results.clear();
+        items
+            .into_iter()
+            .filter(|item| item.content_type != ClipboardContentType::Password)
+            .map(|item| {
+                let id = item.item_id.to_string();
+                results.insert(item.item_id, item);
+                id
+            })
+            .collect()
+    }
+}
+
+fn result_meta(item: &ClipboardItemPreview) -> HashMap<String, Variant> {
+    let description = format!("{} · {}", item.content_type.label(), format::format_relative_time(item.stamp.secs()));
+    let mut meta = HashMap::new();
+    meta.insert("id".to_string(), item.item_id.to_string().to_variant());
+    meta.insert("name".to_string(), format::preview_line(item, NAME_CHARS).to_variant());
+    meta.insert("description".to_string(), description.to_variant());
+    meta.insert("gicon".to_string(), icon_name(item.content_type).to_variant());
+    meta
+}
+
+fn icon_name(content_type: ClipboardContentType) -> &'static str {
+    match content_type {
+        ClipboardContentType::Text => "text-x-generic",
+        ClipboardContentType::Url => "text-html",
+        ClipboardContentType::Code => "text-x-script",
+        ClipboardContentType::File => "folder",
+        ClipboardContentType::Image => "image-x-generic",
+        ClipboardContentType::Password | ClipboardContentType::Other => "edit-paste",
+    }
+}
@@ -0,0 +1,369 @@
+use gtk4::gio;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use crate::shared::config::{ColorScheme, config_dir};
+use tracing::{debug, warn};
+
+/// Built-in dark theme, used when no user stylesheet exists
+const BUILTIN_CSS_DARK: &str = "
+    window {
+        border-radius: 12px;
+        background: #222226;
+    }
+
+    headerbar {
+        background: transparent;
+        box-shadow: none;
+    }
+
+    .clipboard-list {
+        background: transparent;
+    }
+
+    .clipboard-item {
+        background: #343437;
+        border: 2px solid transparent;
+        border-radius: 10px;
+        padding: 4px 4px;
+        margin: 6px 12px;
+        transition: border-color 150ms ease, box-shadow 150ms ease, background 150ms ease;
+    }
+
+    .clipboard-item:hover {
+        border-color: #3584E4;
+        background: shade(#343437, 1.05);
+    }
+
+    .clipboard-list > row,
+    .clipboard-list > child {
+        background: transparent;
+        padding: 0;
+    }
+
+    .clipboard-list > row:selected > .clipboard-item,
+    .clipboard-list > child:selected > .clipboard-item {
+        border-color: #3584E4;
+        background: alpha(#3584E4, 0.18);
+    }
+
+    .clipboard-item.pasted {
+        border-color: alpha(#2ec27e, 0.8);
+    }
+
+    .compact .clipboard-item {
+        padding: 0 2px;
+        margin: 2px 8px;
+    }
+
+    .clipboard-preview {
+        opacity: 0.9;
+    }
+
+    .clipboard-preview.monospace {
+        font-family: monospace;
+    }
+
+    .clipboard-time {
+        font-size: 0.8em;
+        opacity: 0.6;
+    }
+
+    .clipboard-index {
+        min-width: 16px;
+        padding: 0 4px;
+        border-radius: 6px;
+        background: alpha(white, 0.1);
+        font-weight: bold;
+    }
+
+    .clipboard-group-member {
+        margin-left: 16px;
+    }
+
+    .clipboard-group-toggle {
+        min-height: 0;
+        padding: 0 6px;
+    }
+
+    .clipboard-chips {
+        margin: 0 16px 4px 16px;
+    }
+
+    .clipboard-chip {
+        border-radius: 999px;
+        padding: 2px 10px;
+        min-height: 0;
+        font-size: 0.9em;
+    }
+
+    .clipboard-chip:checked {
+        background: alpha(#3584E4, 0.25);
+    }
+
+    .clipboard-tag {
+        border-radius: 999px;
+        padding: 0 8px;
+        min-height: 0;
+        font-size: 0.85em;
+        background: alpha(#3584E4, 0.15);
+    }
+
+    .clipboard-section-header {
+        margin: 10px 16px 0 16px;
+        font-size: 0.85em;
+        font-weight: bold;
+        opacity: 0.6;
+    }
+
+    .clipboard-list > header {
+        background: transparent;
+        padding: 0;
+    }
+
+    .clipboard-grid-item {
+        margin: 6px;
+        padding: 8px;
+        min-width: 140px;
+    }
+
+    .offline-banner {
+        margin: 6px 12px;
+        padding: 6px 10px;
+        border-radius: 8px;
+        background: alpha(#e5a50a, 0.2);
+    }
+
+    .clipboard-preview-pane {
+        margin: 6px 4px 0 4px;
+        border-top: 1px solid alpha(currentColor, 0.15);
+    }
+";
+
+/// Built-in light theme, used when no user stylesheet exists
+const BUILTIN_CSS_LIGHT: &str = "
+    window {
+        border-radius: 12px;
+        background: #fafafb;
+    }
+
+    headerbar {
+        background: transparent;
+        box-shadow: none;
+    }
+
+    .clipboard-list {
+        background: transparent;
+    }
+
+    .clipboard-item {
+        background: #ffffff;
+        border: 2px solid transparent;
+        border-radius: 10px;
+        padding: 4px 4px;
+        margin: 6px 12px;
+        box-shadow: 0 1px 2px alpha(black, 0.08);
+        transition: border-color 150ms ease, box-shadow 150ms ease, background 150ms ease;
+    }
+
+    .clipboard-item:hover {
+        border-color: #3584E4;
+        background: shade(#ffffff, 0.97);
+    }
+
+    .clipboard-list > row,
+    .clipboard-list > child {
+        background: transparent;
+        padding: 0;
+    }
+
+    .clipboard-list > row:selected > .clipboard-item,
+    .clipboard-list > child:selected > .clipboard-item {
+        border-color: #3584E4;
+        background: alpha(#3584E4, 0.12);
+    }
+
+    .clipboard-item.pasted {
+        border-color: alpha(#2ec27e, 0.8);
+    }
+
+    .compact .clipboard-item {
+        padding: 0 2px;
+        margin: 2px 8px;
+    }
+
+    .clipboard-preview {
+        opacity: 0.9;
+    }
+
+    .clipboard-preview.monospace {
+        font-family: monospace;
+    }
+
+    .clipboard-time {
+        font-size: 0.8em;
+        opacity: 0.6;
+    }
+
+    .clipboard-index {
+        min-width: 16px;
+        padding: 0 4px;
+        border-radius: 6px;
+        background: alpha(black, 0.07);
+        font-weight: bold;
+    }
+
+    .clipboard-group-member {
+        margin-left: 16px;
+    }
+
+    .clipboard-group-toggle {
+        min-height: 0;
+        padding: 0 6px;
+    }
+
+    .clipboard-chips {
+        margin: 0 16px 4px 16px;
+    }
+
+    .clipboard-chip {
+        border-radius: 999px;
+        padding: 2px 10px;
+        min-height: 0;
+        font-size: 0.9em;
+    }
+
+    .clipboard-chip:checked {
+        background: alpha(#3584E4, 0.25);
+    }
+
+    .clipboard-tag {
+        border-radius: 999px;
+        padding: 0 8px;
+        min-height: 0;
+        font-size: 0.85em;
+        background: alpha(#3584E4, 0.15);
+    }
+
+    .clipboard-section-header {
+        margin: 10px 16px 0 16px;
+        font-size: 0.85em;
+        font-weight: bold;
+        opacity: 0.6;
+    }
+
+    .clipboard-list > header {
+        background: transparent;
+        padding: 0;
+    }
+
+    .clipboard-grid-item {
+        margin: 6px;
+        padding: 8px;
+        min-width: 140px;
+    }
+
+    .offline-banner {
+        margin: 6px 12px;
+        padding: 6px 10px;
+        border-radius: 8px;
+        background: alpha(#e5a50a, 0.2);
+    }
+
+    .clipboard-preview-pane {
+        margin: 6px 4px 0 4px;
+        border-top: 1px solid alpha(currentColor, 0.15);
+    }
+";
+
+// The directory monitor must stay alive for hot-reloading to keep working
+/// Used for the dismiss layer beneath the overlay
+const TRANSPARENT_CSS: &str = "window.cursor-clip-transparent { background: none; box-shadow: none; }";
+
+thread_local! {
+    static STYLE_MONITOR: RefCell<Option<gio::FileMonitor>> = const { RefCell::new(None) };
+}
+
+/// Apply the overlay stylesheet, following the light/dark preference and reloading it
+/// whenever the user edits a stylesheet in the config directory
+pub fn apply_custom_styling(window: &adw::ApplicationWindow) {
+    let css_provider = gtk4::CssProvider::new();
+    css_provider.connect_parsing_error(|_, section, error| {
+        warn!("Stylesheet error at {}: {error}", section.to_str());
+    });
+    load_overlay_css(&css_provider);
+
+    gtk4::style_context_add_provider_for_display(
+        &gtk4::prelude::WidgetExt::display(window),
+        &css_provider,
+        gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+    );
+
+    // Switch variants when the system color scheme changes
+    let provider = css_provider.clone();
+    adw::StyleManager::default().connect_dark_notify(move |_| load_overlay_css(&provider));
+
+    // Hot-reload on changes (watching the directory also catches newly created files)
+    let directory = gio::File::for_path(config_dir());
+    match directory.monitor_directory(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE) {
+        Ok(monitor) => {
+            let provider = css_provider.clone();
+            monitor.connect_changed(move |_, file, _, event| {
+                let is_stylesheet = file.basename().is_some_and(|name| name.extension().is_some_and(|ext| ext == "css"));
+                if is_stylesheet && matches!(
+                    event,
+                    gio::FileMonitorEvent::ChangesDoneHint | gio::FileMonitorEvent::Created | gio::FileMonitorEvent::Deleted
+                ) {
+                    debug!("Stylesheet {:?} changed, reloading", file.path());
+                    load_overlay_css(&provider);
+                }
+            });
+            STYLE_MONITOR.with(|m| *m.borrow_mut() = Some(monitor));
+        }
+        Err(e) => debug!("Cannot watch config directory for stylesheet changes: {e}"),
+    }
+}
+
+/// Force the light or dark variant (of libadwaita and the overlay stylesheet) or follow the system
+pub fn apply_color_scheme(scheme: ColorScheme) {
+    adw::StyleManager::default().set_color_scheme(match scheme {
+        ColorScheme::System => adw::ColorScheme::Default,
+        ColorScheme::Light => adw::ColorScheme::ForceLight,
+        ColorScheme::Dark => adw::ColorScheme::ForceDark,
+    });
+}
+
+/// Give `window` no background at all, independent of the (user replaceable) theme
+pub fn make_transparent(window: &gtk4::ApplicationWindow) {
+    let css_provider = gtk4::CssProvider::new();
+    css_provider.load_from_string(TRANSPARENT_CSS);
+    gtk4::style_context_add_provider_for_display(
+        &gtk4::prelude::WidgetExt::display(window),
+        &css_provider,
+        gtk4::STYLE_PROVIDER_PRIORITY_USER,
+    );
+    window.add_css_class("cursor-clip-transparent");
+}
+
+/// Load `style-dark.css`/`style-light.css` or `style.css` from the config directory,
+/// falling back to the built-in theme for the current color scheme
+fn load_overlay_css(provider: &gtk4::CssProvider) {
+    let dark = adw::StyleManager::default().is_dark();
+    match user_stylesheet(dark) {
+        Some((path, css)) => {
+            debug!("Loading user stylesheet {}", path.display());
+            provider.load_from_string(&css);
+        }
+        None => provider.load_from_string(if dark { BUILTIN_CSS_DARK } else { BUILTIN_CSS_LIGHT }),
+    }
+}
+
+fn user_stylesheet(dark: bool) -> Option<(PathBuf, String)> {
+    let variant = if dark { "style-dark.css" } else { "style-light.css" };
+    let directory = config_dir();
+    [variant, "style.css"]
+        .into_iter()
+        .map(|name| directory.join(name))
+        .find_map(|path| std::fs::read_to_string(&path).ok().map(|css| (path, css)))
+}
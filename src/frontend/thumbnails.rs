@@ -0,0 +1,73 @@
+//! Thumbnail textures of image items, fetched from the backend and kept per widget
+use gtk4::gdk::Texture;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::frontend::demo::demo_thumbnail;
+use crate::frontend::ipc_client::FrontendClient;
+use crate::shared::wire::WireFormat;
+use tracing::warn;
+
+/// Thumbnails keyed by (item id, max_px), None if the backend could not provide one
+type TextureMap = HashMap<(u64, u32), Option<Texture>>;
+
+/// Thumbnail textures fetched from the backend. Rows are rebound while scrolling, so each
+/// thumbnail (or the failure to get one) is only requested once per widget.
+#[derive(Clone, Default)]
+pub struct ThumbnailCache {
+    textures: Rc<RefCell<TextureMap>>,
+    /// Set while image previews are turned off in the config
+    disabled: Rc<Cell<bool>>,
+    /// Demo mode: generate placeholder images instead of asking the backend
+    demo: bool,
+}
+
+impl ThumbnailCache {
+    pub fn demo() -> Self {
+        Self { demo: true, ..Self::default() }
+    }
+
+    /// Turn thumbnails on or off, rows bound while they are off show no picture
+    pub fn set_enabled(&self, enabled: bool) {
+        self.disabled.set(!enabled);
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.disabled.get()
+    }
+
+    pub fn texture(&self, item_id: u64, max_px: u32) -> Option<Texture> {
+        if self.disabled.get() {
+            return None;
+        }
+        if let Some(cached) = self.textures.borrow().get(&(item_id, max_px)) {
+            return cached.clone();
+        }
+        let texture = if self.demo {
+            Some(demo_thumbnail(max_px))
+        } else {
+            fetch_thumbnail_texture(item_id, max_px)
+        };
+        self.textures.borrow_mut().insert((item_id, max_px), texture.clone());
+        texture
+    }
+}
+
+/// Fetch a thumbnail of an image item from the backend as a texture
+fn fetch_thumbnail_texture(item_id: u64, max_px: u32) -> Option<Texture> {
+    // PNG bytes are much more compact in MessagePack frames than as a JSON number array
+    let png = match FrontendClient::with_format(WireFormat::MsgPack).and_then(|mut client| client.get_thumbnail(item_id, max_px)) {
+        Ok(png) => png,
+        Err(e) => {
+            warn!("Failed to fetch thumbnail for item {item_id}: {e}");
+            return None;
+        }
+    };
+    match Texture::from_bytes(&gtk4::glib::Bytes::from_owned(png)) {
+        Ok(texture) => Some(texture),
+        Err(e) => {
+            warn!("Failed to load thumbnail for item {item_id}: {e}");
+            None
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Times of items as the overlay and the history window show them, following `time_format` and
+//! `clock` of the config. Dates and weekdays come out in the user's language.
+use gtk4::gio;
+use gtk4::glib::DateTime;
+use gtk4::prelude::*;
+use std::cell::OnceCell;
+use crate::shared::config::{ClockFormat, TimeFormat};
+use crate::shared::format::format_relative_time;
+use crate::shared::i18n::gettext;
+
+const DESKTOP_INTERFACE_SCHEMA: &str = "org.gnome.desktop.interface";
+
+thread_local! {
+    /// GNOME's clock format, read once per process
+    static DESKTOP_12H: OnceCell<bool> = const { OnceCell::new() };
+}
+
+/// How a row shows the time an item was copied at (Unix time in seconds)
+pub fn item_time(secs: u64, format: TimeFormat, clock: ClockFormat) -> String {
+    if format == TimeFormat::Relative {
+        return format_relative_time(secs);
+    }
+    let (Some(time), Ok(now)) = (local_time(secs), DateTime::now_local()) else {
+        return format_relative_time(secs);
+    };
+    if time.ymd() == now.ymd() {
+        return clock_time(&time, clock);
+    }
+    let date_format = if time.year() == now.year() {
+        // Translators: date of an item copied this year, see the format codes of g_date_time_format()
+        gettext("%e %b")
+    } else {
+        // Translators: date of an item copied in an earlier year
+        gettext("%e %b %Y")
+    };
+    let date = time.format(&date_format).map(String::from).unwrap_or_default();
+    format!("{}, {}", date.trim_start(), clock_time(&time, clock))
+}
+
+/// Full date and time for tooltips, e.g. "Monday, 3 March 2025, 14:05:10"
+pub fn full_time(secs: u64, clock: ClockFormat) -> String {
+    let Some(time) = local_time(secs) else { return String::new() };
+    // Translators: full date in the tooltip of an item's time
+    let date = time.format(&gettext("%A, %e %B %Y")).map(String::from).unwrap_or_default();
+    let seconds_format = if uses_12h(clock) { gettext("%l:%M:%S %p") } else { gettext("%H:%M:%S") };
+    let clock_time = time.format(&seconds_format).map(String::from).unwrap_or_default();
+    format!("{date}, {}", clock_time.trim_start())
+}
+
+/// Hours and minutes of `time` on the configured clock
+pub fn clock_time(time: &DateTime, clock: ClockFormat) -> String {
+    let format = if uses_12h(clock) { gettext("%l:%M %p") } else { gettext("%H:%M") };
+    time.format(&format).map(|text| text.trim_start().to_string()).unwrap_or_default()
+}
+
+fn local_time(secs: u64) -> Option<DateTime> {
+    DateTime::from_unix_local(secs as i64).ok()
+}
+
+fn uses_12h(clock: ClockFormat) -> bool {
+    match clock {
+        ClockFormat::TwelveHour => true,
+        ClockFormat::TwentyFourHour => false,
+        ClockFormat::System => DESKTOP_12H.with(|desktop| *desktop.get_or_init(desktop_uses_12h)),
+    }
+}
+
+/// Whether GNOME is set to a 12-hour clock, false where its settings are not installed
+fn desktop_uses_12h() -> bool {
+    let installed = gio::SettingsSchemaSource::default()
+        .and_then(|source| source.lookup(DESKTOP_INTERFACE_SCHEMA, true))
+        .is_some_and(|schema| schema.has_key("clock-format"));
+    installed && gio::Settings::new(DESKTOP_INTERFACE_SCHEMA).string("clock-format") == "12h"
+}
@@ -0,0 +1,233 @@
+//! History window (`cursor-clip timeline`): the items of a month grouped by day, with a calendar
+//! marking the days something was copied on to jump to one of them. Unlike the overlay it is a
+//! regular window that stays open; activating an item sets it as the clipboard.
+use gtk4::glib;
+use gtk4::prelude::*;
+use libadwaita::{self as adw, prelude::*};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use crate::frontend::ipc_client::FrontendClient;
+use crate::frontend::time_format::{clock_time, full_time};
+use crate::shared::config::{ClockFormat, Config};
+use crate::shared::format;
+use crate::shared::i18n::gettext;
+use crate::shared::ClipboardItemPreview;
+use tracing::{error, warn};
+
+const APP_ID: &str = "com.cursor-clip.Timeline";
+const PREVIEW_CHARS: usize = 120;
+
+/// What the window shows, shared between its signal handlers
+#[derive(Clone)]
+struct Timeline {
+    calendar: gtk4::Calendar,
+    list: gtk4::ListBox,
+    toasts: adw::ToastOverlay,
+    /// Items of the shown month, newest first, in the order of the list rows
+    items: Rc<RefCell<Vec<ClipboardItemPreview>>>,
+    /// Year and month shown, None until the first load
+    month: Rc<Cell<Option<(i32, i32)>>>,
+    /// 12 or 24-hour clock for the times of the rows
+    clock: ClockFormat,
+}
+
+/// Open the history window and run until it is closed
+pub fn run_timeline() -> Result<(), Box<dyn std::error::Error>> {
+    let app = adw::Application::builder().application_id(APP_ID).build();
+    app.connect_activate(|app| {
+        // A second invocation presents the window of the first one
+        if let Some(window) = app.active_window() {
+            window.present();
+            return;
+        }
+        build_window(app).present();
+    });
+    app.run_with_args::<String>(&[]);
+    Ok(())
+}
+
+fn build_window(app: &adw::Application) -> adw::ApplicationWindow {
+    let calendar = gtk4::Calendar::new();
+    calendar.set_margin_top(12);
+    calendar.set_margin_bottom(12);
+    calendar.set_margin_start(12);
+    calendar.set_margin_end(12);
+
+    let list = gtk4::ListBox::new();
+    list.set_selection_mode(gtk4::SelectionMode::None);
+    list.add_css_class("boxed-list");
+    list.set_margin_top(12);
+    list.set_margin_bottom(12);
+    list.set_margin_start(12);
+    list.set_margin_end(12);
+    list.set_valign(gtk4::Align::Start);
+    let placeholder = adw::StatusPage::builder()
+        .icon_name("edit-paste-symbolic")
+        .title(gettext("Nothing Copied"))
+        .description(gettext("No items were copied this month"))
+        .build();
+    list.set_placeholder(Some(&placeholder));
+
+    let scrolled = gtk4::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk4::PolicyType::Never)
+        .hexpand(true)
+        .vexpand(true)
+        .child(&list)
+        .build();
+    let toasts = adw::ToastOverlay::new();
+    toasts.set_child(Some(&scrolled));
+
+    let today = gtk4::Button::with_label(&gettext("Today"));
+    let reload = gtk4::Button::from_icon_name("view-refresh-symbolic");
+    reload.set_tooltip_text(Some(&gettext("Reload")));
+    reload.update_property(&[gtk4::accessible::Property::Label(&gettext("Reload"))]);
+    let header = adw::HeaderBar::new();
+    header.pack_start(&today);
+    header.pack_end(&reload);
+
+    let content = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+    content.append(&calendar);
+    content.append(&gtk4::Separator::new(gtk4::Orientation::Vertical));
+    content.append(&toasts);
+    let toolbar = adw::ToolbarView::new();
+    toolbar.add_top_bar(&header);
+    toolbar.set_content(Some(&content));
+
+    let window = adw::ApplicationWindow::builder()
+        .application(app)
+        .title(gettext("Clipboard History"))
+        .default_width(900)
+        .default_height(640)
+        .content(&toolbar)
+        .build();
+
+    let timeline = Timeline {
+        calendar: calendar.clone(),
+        list: list.clone(),
+        toasts,
+        items: Rc::default(),
+        month: Rc::default(),
+        clock: Config::load().clock,
+    };
+    timeline.set_header_func();
+
+    let timeline_for_day = timeline.clone();
+    calendar.connect_day_selected(move |_| timeline_for_day.show_selected_day());
+    // Switching months keeps the selected day number, and `day-selected` is not emitted for it
+    for property in ["month", "year"] {
+        let timeline = timeline.clone();
+        calendar.connect_notify_local(Some(property), move |_, _| timeline.show_selected_day());
+    }
+    let timeline_for_today = timeline.clone();
+    today.connect_clicked(move |_| {
+        if let Ok(now) = glib::DateTime::now_local() {
+            timeline_for_today.calendar.select_day(&now);
+            timeline_for_today.show_selected_day();
+        }
+    });
+    let timeline_for_reload = timeline.clone();
+    reload.connect_clicked(move |_| {
+        timeline_for_reload.month.set(None);
+        timeline_for_reload.show_selected_day();
+    });
+    let timeline_for_rows = timeline.clone();
+    list.connect_row_activated(move |_, row| timeline_for_rows.activate(row.index()));
+
+    timeline.show_selected_day();
+    window
+}
+
+impl Timeline {
+    /// Load the month of the selected day if it is not shown yet, then scroll to the day
+    fn show_selected_day(&self) {
+        let date = self.calendar.date();
+        let month = (date.year(), date.month());
+        if self.month.get() != Some(month) {
+            self.load_month(month);
+        }
+        let day = date.day_of_month();
+        let items = self.items.borrow();
+        // Newest first, the first row of the day is its latest item and carries the header
+        if let Some(index) = items.iter().position(|item| local_time(item).is_some_and(|time| time.day_of_month() == day))
+            && let Some(row) = self.list.row_at_index(index as i32)
+        {
+            row.grab_focus();
+        }
+    }
+
+    fn load_month(&self, (year, month): (i32, i32)) {
+        self.month.set(Some((year, month)));
+        let Ok(start) = glib::DateTime::from_local(year, month, 1, 0, 0, 0.0) else { return };
+        let Ok(end) = start.add_months(1) else { return };
+        let (from, to) = (start.to_unix().max(0) as u64 * 1000, end.to_unix().max(0) as u64 * 1000);
+        let items = match FrontendClient::new().and_then(|mut client| client.get_history_range(from, to)) {
+            Ok(items) => items,
+            Err(e) => {
+                warn!("Could not load the history of {year}-{month:02}: {e}");
+                self.toasts.add_toast(adw::Toast::new(&gettext("Could not reach the clipboard daemon")));
+                Vec::new()
+            }
+        };
+
+        self.calendar.clear_marks();
+        self.list.remove_all();
+        for item in &items {
+            if let Some(time) = local_time(item) {
+                self.calendar.mark_day(time.day_of_month() as u32);
+            }
+            self.list.append(&item_row(item, self.clock));
+        }
+        *self.items.borrow_mut() = items;
+        self.list.invalidate_headers();
+    }
+
+    /// Put a header with the date above the first row of every day
+    fn set_header_func(&self) {
+        let items = self.items.clone();
+        self.list.set_header_func(move |row, before| {
+            let items = items.borrow();
+            let day_of = |row: &gtk4::ListBoxRow| items.get(row.index() as usize).and_then(local_time).map(|time| time.day_of_year());
+            let day = day_of(row);
+            if day.is_none() || before.and_then(day_of) == day {
+                row.set_header(None::<&gtk4::Widget>);
+                return;
+            }
+            // Translators: date of a day header, see the format codes of g_date_time_format()
+            let title = items.get(row.index() as usize).and_then(local_time).and_then(|time| time.format(&gettext("%A, %e %B %Y")).ok());
+            let label = gtk4::Label::new(title.as_deref());
+            label.set_xalign(0.0);
+            label.add_css_class("heading");
+            label.set_margin_top(18);
+            label.set_margin_bottom(6);
+            label.set_margin_start(6);
+            row.set_header(Some(&label));
+        });
+    }
+
+    fn activate(&self, index: i32) {
+        let Some(id) = self.items.borrow().get(index as usize).map(|item| item.item_id) else { return };
+        match FrontendClient::new().and_then(|mut client| client.set_clipboard_by_id(id)) {
+            Ok(()) => self.toasts.add_toast(adw::Toast::new(&gettext("Copied to the clipboard"))),
+            Err(e) => {
+                error!("Error setting clipboard item {id}: {e}");
+                self.toasts.add_toast(adw::Toast::new(&gettext("Could not set the clipboard")));
+            }
+        }
+    }
+}
+
+fn item_row(item: &ClipboardItemPreview, clock: ClockFormat) -> adw::ActionRow {
+    let time = local_time(item).map(|time| clock_time(&time, clock)).unwrap_or_default();
+    let row = adw::ActionRow::builder()
+        .title(glib::markup_escape_text(&format::preview_line(item, PREVIEW_CHARS)))
+        .subtitle(format!("{time} · {}", item.content_type.label()))
+        .tooltip_text(full_time(item.stamp.secs(), clock))
+        .activatable(true)
+        .build();
+    row.add_prefix(&gtk4::Label::new(Some(item.content_type.icon())));
+    row
+}
+
+fn local_time(item: &ClipboardItemPreview) -> Option<glib::DateTime> {
+    glib::DateTime::from_unix_local(item.stamp.secs() as i64).ok()
+}
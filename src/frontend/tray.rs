@@ -0,0 +1,383 @@
+//! Tray icon (`cursor-clip tray`): a StatusNotifierItem with a menu to open the history, pause
+//! capture and clear the history. The menu is exported with the `com.canonical.dbusmenu`
+//! interface that SNI hosts (Waybar, KDE Plasma, ...) expect; the backend is reached through
+//! `FrontendClient` like from the overlay.
+use gtk4::gio;
+use gtk4::glib::{self, Variant};
+use gtk4::prelude::*;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::frontend::ipc_client::{ConnectionState, FrontendClient, SubscriptionEvent};
+use crate::shared::{BackendMessage, PushEvent};
+use crate::shared::i18n::gettext;
+use tracing::{debug, error, info, warn};
+
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const MENU_PATH: &str = "/MenuBar";
+const ITEM_INTERFACE: &str = "org.kde.StatusNotifierItem";
+const MENU_INTERFACE: &str = "com.canonical.dbusmenu";
+
+const INTROSPECTION: &str = r#"
+<node>
+  <interface name="org.kde.StatusNotifierItem">
+    <property name="Category" type="s" access="read"/>
+    <property name="Id" type="s" access="read"/>
+    <property name="Title" type="s" access="read"/>
+    <property name="Status" type="s" access="read"/>
+    <property name="IconName" type="s" access="read"/>
+    <property name="ToolTip" type="(sa(iiay)ss)" access="read"/>
+    <property name="ItemIsMenu" type="b" access="read"/>
+    <property name="Menu" type="o" access="read"/>
+    <method name="Activate"><arg name="x" type="i" direction="in"/><arg name="y" type="i" direction="in"/></method>
+    <method name="SecondaryActivate"><arg name="x" type="i" direction="in"/><arg name="y" type="i" direction="in"/></method>
+    <method name="ContextMenu"><arg name="x" type="i" direction="in"/><arg name="y" type="i" direction="in"/></method>
+    <method name="Scroll"><arg name="delta" type="i" direction="in"/><arg name="orientation" type="s" direction="in"/></method>
+    <signal name="NewIcon"/>
+    <signal name="NewToolTip"/>
+    <signal name="NewStatus"><arg name="status" type="s"/></signal>
+  </interface>
+  <interface name="com.canonical.dbusmenu">
+    <property name="Version" type="u" access="read"/>
+    <property name="TextDirection" type="s" access="read"/>
+    <property name="Status" type="s" access="read"/>
+    <property name="IconThemePath" type="as" access="read"/>
+    <method name="GetLayout">
+      <arg name="parentId" type="i" direction="in"/>
+      <arg name="recursionDepth" type="i" direction="in"/>
+      <arg name="propertyNames" type="as" direction="in"/>
+      <arg name="revision" type="u" direction="out"/>
+      <arg name="layout" type="(ia{sv}av)" direction="out"/>
+    </method>
+    <method name="GetGroupProperties">
+      <arg name="ids" type="ai" direction="in"/>
+      <arg name="propertyNames" type="as" direction="in"/>
+      <arg name="properties" type="a(ia{sv})" direction="out"/>
+    </method>
+    <method name="GetProperty">
+      <arg name="id" type="i" direction="in"/>
+      <arg name="name" type="s" direction="in"/>
+      <arg name="value" type="v" direction="out"/>
+    </method>
+    <method name="Event">
+      <arg name="id" type="i" direction="in"/>
+      <arg name="eventId" type="s" direction="in"/>
+      <arg name="data" type="v" direction="in"/>
+      <arg name="timestamp" type="u" direction="in"/>
+    </method>
+    <method name="EventGroup">
+      <arg name="events" type="a(isvu)" direction="in"/>
+      <arg name="idErrors" type="ai" direction="out"/>
+    </method>
+    <method name="AboutToShow">
+      <arg name="id" type="i" direction="in"/>
+      <arg name="needUpdate" type="b" direction="out"/>
+    </method>
+    <method name="AboutToShowGroup">
+      <arg name="ids" type="ai" direction="in"/>
+      <arg name="updatesNeeded" type="ai" direction="out"/>
+      <arg name="idErrors" type="ai" direction="out"/>
+    </method>
+    <signal name="LayoutUpdated"><arg name="revision" type="u"/><arg name="parent" type="i"/></signal>
+  </interface>
+</node>
+"#;
+
+/// Entries of the tray menu, the discriminant is the dbusmenu item id (0 is the root)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuEntry {
+    ShowHistory = 1,
+    PauseCapture = 2,
+    Separator = 3,
+    ClearHistory = 4,
+}
+
+impl MenuEntry {
+    const ALL: [Self; 4] = [Self::ShowHistory, Self::PauseCapture, Self::Separator, Self::ClearHistory];
+
+    fn from_id(id: i32) -> Option<Self> {
+        Self::ALL.into_iter().find(|entry| *entry as i32 == id)
+    }
+}
+
+/// What the tray shows, shared between the D-Bus handlers and the backend subscription
+#[derive(Clone)]
+struct Tray {
+    connection: gio::DBusConnection,
+    paused: Rc<Cell<bool>>,
+    online: Rc<Cell<bool>>,
+    revision: Rc<Cell<u32>>,
+}
+
+/// Run the tray icon until the process is terminated
+pub fn run_tray() -> Result<(), Box<dyn std::error::Error>> {
+    let connection = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE)?;
+    let node = gio::DBusNodeInfo::for_xml(INTROSPECTION)?;
+    let tray = Tray {
+        connection: connection.clone(),
+        paused: Rc::new(Cell::new(false)),
+        online: Rc::new(Cell::new(false)),
+        revision: Rc::new(Cell::new(1)),
+    };
+
+    let item_interface = node.lookup_interface(ITEM_INTERFACE).ok_or("missing item interface")?;
+    let tray_for_calls = tray.clone();
+    let tray_for_properties = tray.clone();
+    connection
+        .register_object(ITEM_PATH, &item_interface)
+        .method_call(move |_, _, _, _, method, _, invocation| {
+            if method == "Activate" {
+                tray_for_calls.activate(MenuEntry::ShowHistory);
+            }
+            invocation.return_value(None);
+        })
+        .property(move |_, _, _, _, property| tray_for_properties.item_property(property))
+        .build()?;
+
+    let menu_interface = node.lookup_interface(MENU_INTERFACE).ok_or("missing menu interface")?;
+    let tray_for_calls = tray.clone();
+    connection
+        .register_object(MENU_PATH, &menu_interface)
+        .method_call(move |_, _, _, _, method, parameters, invocation| {
+            let reply = tray_for_calls.menu_call(method, &parameters);
+            invocation.return_value(reply.as_ref());
+        })
+        .property(|_, _, _, _, property| match property {
+            "Version" => 3u32.to_variant(),
+            "TextDirection" => "ltr".to_variant(),
+            "Status" => "normal".to_variant(),
+            _ => Vec::<String>::new().to_variant(),
+        })
+        .build()?;
+
+    // Hosts find the item through the watcher under a well-known name
+    let bus_name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+    connection.call_sync(
+        Some("org.freedesktop.DBus"),
+        "/org/freedesktop/DBus",
+        "org.freedesktop.DBus",
+        "RequestName",
+        Some(&(bus_name.as_str(), 0u32).to_variant()),
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+        gio::Cancellable::NONE,
+    )?;
+    if let Err(e) = connection.call_sync(
+        Some("org.kde.StatusNotifierWatcher"),
+        "/StatusNotifierWatcher",
+        "org.kde.StatusNotifierWatcher",
+        "RegisterStatusNotifierItem",
+        Some(&(bus_name.as_str(),).to_variant()),
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+        gio::Cancellable::NONE,
+    ) {
+        warn!("No StatusNotifierWatcher (is a tray running?): {e}");
+    }
+    info!("Tray icon registered as {bus_name}");
+
+    tray.follow_backend();
+    glib::MainLoop::new(None, false).run();
+    Ok(())
+}
+
+impl Tray {
+    fn item_property(&self, property: &str) -> Variant {
+        match property {
+            "Category" => "ApplicationStatus".to_variant(),
+            "Id" => "cursor-clip".to_variant(),
+            "Title" => "Cursor Clip".to_variant(),
+            "Status" => self.status().to_variant(),
+            "IconName" => self.icon_name().to_variant(),
+            "ToolTip" => ("", Vec::<(i32, i32, Vec<u8>)>::new(), "Cursor Clip", self.tooltip()).to_variant(),
+            "ItemIsMenu" => false.to_variant(),
+            "Menu" => glib::variant::ObjectPath::try_from(MENU_PATH.to_string()).unwrap().to_variant(),
+            _ => "".to_variant(),
+        }
+    }
+
+    fn status(&self) -> &'static str {
+        if self.paused.get() { "NeedsAttention" } else { "Active" }
+    }
+
+    fn icon_name(&self) -> &'static str {
+        if self.paused.get() { "media-playback-pause-symbolic" } else { "edit-paste-symbolic" }
+    }
+
+    fn tooltip(&self) -> String {
+        match (self.online.get(), self.paused.get()) {
+            (false, _) => gettext("Clipboard daemon offline"),
+            (true, true) => gettext("Clipboard capture paused"),
+            (true, false) => gettext("Recording clipboard history"),
+        }
+    }
+
+    /// Answer a dbusmenu method call, None for methods without a return value
+    fn menu_call(&self, method: &str, parameters: &Variant) -> Option<Variant> {
+        match method {
+            "GetLayout" => Some((self.revision.get(), self.layout()).to_variant()),
+            "GetGroupProperties" => {
+                let (ids, _) = parameters.get::<(Vec<i32>, Vec<String>)>()?;
+                let properties: Vec<(i32, HashMap<String, Variant>)> = MenuEntry::ALL
+                    .into_iter()
+                    .filter(|entry| ids.is_empty() || ids.contains(&(*entry as i32)))
+                    .map(|entry| (entry as i32, self.entry_properties(entry)))
+                    .collect();
+                Some((properties,).to_variant())
+            }
+            "GetProperty" => {
+                let (id, name) = parameters.get::<(i32, String)>()?;
+                let value = MenuEntry::from_id(id)
+                    .and_then(|entry| self.entry_properties(entry).remove(&name))
+                    .unwrap_or_else(|| "".to_variant());
+                Some((value,).to_variant())
+            }
+            "Event" => {
+                let (id, event, _, _) = parameters.get::<(i32, String, Variant, u32)>()?;
+                if event == "clicked"
+                    && let Some(entry) = MenuEntry::from_id(id)
+                {
+                    self.activate(entry);
+                }
+                None
+            }
+            "EventGroup" => {
+                let events = parameters.child_value(0);
+                for event in events.iter() {
+                    if let Some((id, name, _, _)) = event.get::<(i32, String, Variant, u32)>()
+                        && name == "clicked"
+                        && let Some(entry) = MenuEntry::from_id(id)
+                    {
+                        self.activate(entry);
+                    }
+                }
+                Some((Vec::<i32>::new(),).to_variant())
+            }
+            "AboutToShow" => Some((false,).to_variant()),
+            "AboutToShowGroup" => Some((Vec::<i32>::new(), Vec::<i32>::new()).to_variant()),
+            _ => None,
+        }
+    }
+
+    /// The whole menu, the root (id 0) with all entries as children
+    fn layout(&self) -> Variant {
+        let children: Vec<Variant> = MenuEntry::ALL
+            .into_iter()
+            .map(|entry| (entry as i32, self.entry_properties(entry), Vec::<Variant>::new()).to_variant())
+            .collect();
+        let mut root = HashMap::new();
+        root.insert("children-display".to_string(), "submenu".to_variant());
+        (0i32, root, children).to_variant()
+    }
+
+    fn entry_properties(&self, entry: MenuEntry) -> HashMap<String, Variant> {
+        let mut properties = HashMap::new();
+        let online = self.online.get();
+        match entry {
+            MenuEntry::ShowHistory => {
+                properties.insert("label".to_string(), gettext("Show history").to_variant());
+            }
+            MenuEntry::PauseCapture => {
+                properties.insert("label".to_string(), gettext("Pause capture").to_variant());
+                properties.insert("toggle-type".to_string(), "checkmark".to_variant());
+                properties.insert("toggle-state".to_string(), i32::from(self.paused.get()).to_variant());
+                properties.insert("enabled".to_string(), online.to_variant());
+            }
+            MenuEntry::Separator => {
+                properties.insert("type".to_string(), "separator".to_variant());
+            }
+            MenuEntry::ClearHistory => {
+                properties.insert("label".to_string(), gettext("Clear history").to_variant());
+                properties.insert("enabled".to_string(), online.to_variant());
+            }
+        }
+        properties
+    }
+
+    fn activate(&self, entry: MenuEntry) {
+        debug!("Tray menu entry {entry:?} activated");
+        match entry {
+            MenuEntry::ShowHistory => show_history(),
+            MenuEntry::PauseCapture => {
+                let paused = !self.paused.get();
+                match FrontendClient::new().and_then(|mut client| client.set_capture_paused(paused)) {
+                    Ok(paused) => self.set_paused(paused),
+                    Err(e) => error!("Error pausing capture: {e}"),
+                }
+            }
+            MenuEntry::ClearHistory => {
+                if let Err(e) = FrontendClient::new().and_then(|mut client| client.clear_history()) {
+                    error!("Error clearing history: {e}");
+                }
+            }
+            MenuEntry::Separator => {}
+        }
+    }
+
+    /// Follow pause state changes and connection loss of the backend
+    fn follow_backend(&self) {
+        let mut events = FrontendClient::subscribe_events(vec![PushEvent::CaptureStateChanged]);
+        let tray = self.clone();
+        glib::spawn_future_local(async move {
+            while let Some(event) = events.recv().await {
+                match event {
+                    SubscriptionEvent::Push(message) => {
+                        if let BackendMessage::CaptureStateChanged { paused } = *message {
+                            tray.set_paused(paused);
+                        }
+                    }
+                    SubscriptionEvent::ConnectionChanged(ConnectionState::Connected) => {
+                        tray.online.set(true);
+                        let paused = FrontendClient::new().and_then(|mut client| client.capture_paused());
+                        tray.set_paused(paused.unwrap_or(false));
+                    }
+                    SubscriptionEvent::ConnectionChanged(ConnectionState::Reconnecting { .. }) => {
+                        tray.online.set(false);
+                        tray.changed();
+                    }
+                }
+            }
+        });
+    }
+
+    fn set_paused(&self, paused: bool) {
+        self.paused.set(paused);
+        self.changed();
+    }
+
+    /// Tell the host to re-read icon, tooltip, status and menu
+    fn changed(&self) {
+        self.revision.set(self.revision.get() + 1);
+        let signals: [(&str, &str, &str, Option<Variant>); 4] = [
+            (ITEM_PATH, ITEM_INTERFACE, "NewIcon", None),
+            (ITEM_PATH, ITEM_INTERFACE, "NewToolTip", None),
+            (ITEM_PATH, ITEM_INTERFACE, "NewStatus", Some((self.status(),).to_variant())),
+            (MENU_PATH, MENU_INTERFACE, "LayoutUpdated", Some((self.revision.get(), 0i32).to_variant())),
+        ];
+        for (path, interface, signal, parameters) in signals {
+            if let Err(e) = self.connection.emit_signal(None, path, interface, signal, parameters.as_ref()) {
+                warn!("Could not emit {signal}: {e}");
+            }
+        }
+    }
+}
+
+/// Open the overlay by starting this executable without arguments
+fn show_history() {
+    let executable = match std::env::current_exe() {
+        Ok(executable) => executable,
+        Err(e) => {
+            error!("Cannot locate the cursor-clip executable: {e}");
+            return;
+        }
+    };
+    match std::process::Command::new(executable).spawn() {
+        // Reap the overlay once it is closed
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => error!("Could not open the overlay: {e}"),
+    }
+}
@@ -0,0 +1,236 @@
+//! Terminal frontend (`cursor-clip tui`): the history as a list with fuzzy search, for machines
+//! without a graphical session of their own (e.g. over SSH with waypipe). Talks to the daemon
+//! like the overlay does and follows its pushes.
+
+use std::cmp::Reverse;
+use std::time::Duration;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use tokio::sync::mpsc::UnboundedReceiver;
+use crate::frontend::ipc_client::{ConnectionState, FrontendClient, SubscriptionEvent};
+use crate::shared::format;
+use crate::shared::i18n::{gettext, trf};
+use crate::shared::{ClipboardItemPreview, SortMode};
+
+/// The daemon keeps a few hundred items at most, one page has all of them
+const PAGE_SIZE: u32 = 1000;
+const PREVIEW_CHARS: usize = 200;
+/// How long to wait for a key before looking for pushes again
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Default)]
+struct Tui {
+    items: Vec<ClipboardItemPreview>,
+    /// One-line previews of `items`, what the search matches against
+    lines: Vec<String>,
+    /// Indices into `items` matching the query, the best match first
+    matches: Vec<usize>,
+    query: String,
+    /// Typed characters go to the query instead of moving the selection
+    searching: bool,
+    list: ListState,
+    /// Replaces the key help until the next key press
+    status: Option<String>,
+}
+
+/// Show the history in the terminal until an item is pasted or the user quits
+pub fn run_tui() -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = FrontendClient::new()?;
+    let mut pushes = FrontendClient::subscribe();
+    let mut tui = Tui::default();
+    tui.reload(&mut client)?;
+
+    let mut terminal = ratatui::init();
+    let result = tui.run(&mut terminal, &mut client, &mut pushes);
+    ratatui::restore();
+    if let Some(id) = result? {
+        println!("{}", trf("Pasted item {}", &id.to_string()));
+    }
+    Ok(())
+}
+
+impl Tui {
+    /// Handle keys and pushes, returns the id of the pasted item
+    fn run(&mut self, terminal: &mut DefaultTerminal, client: &mut FrontendClient, pushes: &mut UnboundedReceiver<SubscriptionEvent>) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            // Any push may change the listed items, so does a daemon restart
+            let mut changed = false;
+            while let Ok(event) = pushes.try_recv() {
+                changed |= !matches!(event, SubscriptionEvent::ConnectionChanged(state) if state != ConnectionState::Connected);
+            }
+            if changed && let Err(e) = self.reload(client) {
+                self.status = Some(trf("Reloading the history failed: {}", &e.to_string()));
+            }
+
+            if !event::poll(POLL_INTERVAL)? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else { continue };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            self.status = None;
+            if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+                return Ok(None);
+            }
+            if key.code == KeyCode::Enter {
+                let Some(id) = self.selected_id() else { continue };
+                match client.set_clipboard_by_id(id) {
+                    Ok(()) => return Ok(Some(id)),
+                    Err(e) => self.status = Some(trf("Pasting failed: {}", &e.to_string())),
+                }
+                continue;
+            }
+
+            if self.searching {
+                match key.code {
+                    KeyCode::Esc => self.searching = false,
+                    KeyCode::Down => self.list.select_next(),
+                    KeyCode::Up => self.list.select_previous(),
+                    KeyCode::Backspace => {
+                        self.query.pop();
+                        self.refilter();
+                    }
+                    KeyCode::Char(c) => {
+                        self.query.push(c);
+                        self.refilter();
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') => return Ok(None),
+                KeyCode::Esc if !self.query.is_empty() => {
+                    self.query.clear();
+                    self.refilter();
+                }
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Char('/') => self.searching = true,
+                KeyCode::Char('j') | KeyCode::Down => self.list.select_next(),
+                KeyCode::Char('k') | KeyCode::Up => self.list.select_previous(),
+                KeyCode::Char('g') | KeyCode::Home => self.list.select_first(),
+                KeyCode::Char('G') | KeyCode::End => self.list.select_last(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Fetch the history again, keeping the query and if possible the selected item
+    fn reload(&mut self, client: &mut FrontendClient) -> Result<(), Box<dyn std::error::Error>> {
+        let selected = self.selected_id();
+        let (items, _) = client.get_history_page(0, PAGE_SIZE, SortMode::Recent)?;
+        self.lines = items.iter().map(|item| format::preview_line(item, PREVIEW_CHARS)).collect();
+        self.items = items;
+        self.refilter();
+        if let Some(position) = self.matches.iter().position(|&index| Some(self.items[index].item_id) == selected) {
+            self.list.select(Some(position));
+        }
+        Ok(())
+    }
+
+    fn refilter(&mut self) {
+        if self.query.is_empty() {
+            self.matches = (0..self.items.len()).collect();
+        } else {
+            let mut scored: Vec<_> = self.lines.iter().enumerate().filter_map(|(index, line)| Some((index, fuzzy_score(&self.query, line)?))).collect();
+            // Stable, equally good matches stay newest first
+            scored.sort_by_key(|&(_, score)| Reverse(score));
+            self.matches = scored.into_iter().map(|(index, _)| index).collect();
+        }
+        self.list.select((!self.matches.is_empty()).then_some(0));
+    }
+
+    fn selected_id(&self) -> Option<u64> {
+        let index = *self.matches.get(self.list.selected()?)?;
+        Some(self.items[index].item_id)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [search_area, list_area, help_area] = Layout::vertical([Constraint::Length(3), Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let dim = Style::new().add_modifier(Modifier::DIM);
+
+        let title = if self.searching { gettext("Search") } else { gettext("Search (/)") };
+        frame.render_widget(Paragraph::new(self.query.as_str()).block(Block::bordered().title(title)), search_area);
+        if self.searching {
+            frame.set_cursor_position((search_area.x + 1 + self.query.chars().count() as u16, search_area.y + 1));
+        }
+
+        let rows: Vec<ListItem> = self
+            .matches
+            .iter()
+            .map(|&index| {
+                let item = &self.items[index];
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:<14} ", format::format_relative_time(item.stamp.secs())), dim),
+                    Span::raw(format!("{} {}", item.content_type.icon(), self.lines[index])),
+                ]))
+            })
+            .collect();
+        let title = trf("Clipboard history ({})", &format!("{}/{}", self.matches.len(), self.items.len()));
+        let list = List::new(rows)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, list_area, &mut self.list);
+
+        let help = self.status.clone().unwrap_or_else(|| if self.searching {
+            gettext("type to filter · ↑/↓ move · Enter paste · Esc done")
+        } else {
+            gettext("j/k move · / search · Enter paste · Esc clear search · q quit")
+        });
+        frame.render_widget(Paragraph::new(help).style(dim), help_area);
+    }
+}
+
+/// How well `text` matches a fuzzy `query`: all characters of the query must appear in order
+/// (ignoring case), runs of them and matches at word starts score higher. None if it does not match.
+fn fuzzy_score(query: &str, text: &str) -> Option<u32> {
+    let mut wanted = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut in_run = false;
+    for c in text.chars().flat_map(char::to_lowercase) {
+        let Some(&next) = wanted.peek() else { break };
+        if c == next {
+            wanted.next();
+            score += 1;
+            if in_run {
+                score += 2;
+            }
+            if previous.is_none_or(|p| !p.is_alphanumeric()) {
+                score += 3;
+            }
+            in_run = true;
+        } else {
+            in_run = false;
+        }
+        previous = Some(c);
+    }
+    wanted.peek().is_none().then_some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_matches_characters_in_order() {
+        assert!(fuzzy_score("crs", "cursor-clip").is_some());
+        assert!(fuzzy_score("CLIP", "cursor-clip").is_some());
+        assert_eq!(fuzzy_score("pilc", "cursor-clip"), None);
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn fuzzy_prefers_runs_and_word_starts() {
+        assert!(fuzzy_score("clip", "clipboard").unwrap() > fuzzy_score("clip", "cool list in pets").unwrap());
+        assert!(fuzzy_score("gs", "git status").unwrap() > fuzzy_score("gs", "gases").unwrap());
+    }
+}
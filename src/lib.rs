@@ -0,0 +1,48 @@
+//! Cursor Clip: a Wayland clipboard manager with a history overlay at the cursor.
+//!
+//! The binary runs either the backend daemon (`backend::run_backend`) or the overlay
+//! (`frontend::run_frontend`). GTK apps can embed the clipboard history itself through
+//! [`ClipboardHistoryWidget`], which talks to a running backend daemon.
+//!
+//! Alternative frontends (egui, a TUI, ...) should only need the [`core`] and [`ipc`] modules,
+//! the API that is kept stable across releases. Everything GTK is in `gtk`, built with the
+//! default `gtk` feature; without it GTK is not linked. The modules below them are internal and
+//! may change at any time.
+
+pub mod backend;
+pub mod frontend;
+pub mod shared;
+
+/// The history and the Wayland monitor: run or embed a daemon, or drive the history without a
+/// compositor through `MockClipboard`
+pub mod core {
+    pub use crate::backend::backend_state::BackendState;
+    pub use crate::backend::clipboard_backend::{ClipboardBackend, MockClipboard, MockSelection, WaylandClipboard};
+    pub use crate::backend::ipc_server::{bind_socket, run_backend, serve};
+    pub use crate::backend::wayland_clipboard::{MonitorError, WaylandClipboardMonitor};
+    pub use crate::shared::{ClipboardItem, Config, Metrics};
+}
+
+/// The protocol spoken over the daemon socket and clients for it
+pub mod ipc {
+    pub use crate::frontend::async_client::{AsyncClientError, AsyncFrontendClient, PushStream};
+    pub use crate::frontend::ipc_client::{ConnectionState, FrontendClient, SubscriptionEvent};
+    pub use crate::shared::protocol::{self, PeerInfo, PROTOCOL_VERSION, SOCKET_PATH};
+    pub use crate::shared::wire::{self, WireFormat};
+    pub use crate::shared::{
+        BackendError, BackendMessage, ClientInfo, ClipboardContentType, ClipboardItemPreview, ErrorCode, FrontendMessage, Health,
+        HistoryStats, PushEvent, PushFilter, SortMode,
+    };
+}
+
+/// The GTK overlay and the embeddable history widget
+#[cfg(feature = "gtk")]
+pub mod gtk {
+    pub use crate::frontend::gtk_overlay::run_resident_overlay;
+    pub use crate::frontend::history_widget::ClipboardHistoryWidget;
+    pub use crate::frontend::initializer::run_frontend;
+}
+
+#[cfg(feature = "gtk")]
+pub use frontend::history_widget::ClipboardHistoryWidget;
+pub use frontend::ipc_client::ConnectionState;
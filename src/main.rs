@@ -1,49 +1,359 @@
-use clap::{Arg, Command};
-use log::{info, error};
+mod cli;
+mod completions;
 
-mod backend;
-mod frontend;
-mod shared;
+use clap::{CommandFactory, Parser};
+use cli::{Cli, Commands, ConfigCommand, PhoneCommand, SyncCommand};
+use std::io::Write;
+use cursor_clip::{backend, frontend, shared};
+use cursor_clip::frontend::ipc_client::FrontendClient;
+use tracing::{info, error};
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 2)]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize logging (RUST_LOG overrides, default to info)
-    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-        .format_timestamp_secs()
-        .try_init();
-
-    let matches = Command::new("cursor-clip")
-        .version("0.1.0")
-        .about("Clipboard manager with GUI overlay")
-        .arg(
-            Arg::new("daemon")
-                .long("daemon")
-                .help("Run as background daemon")
-                .action(clap::ArgAction::SetTrue),
-        )
-        .arg(
-            Arg::new("monitor-only")
-                .long("monitor-only")
-                .help("Do not take ownership of a newly received external selection; just record it. This does not automatically ensure clipboard persistence if the original application is closed. You can still paste the selection by choosing it in the GUI. If unsure, you probably want to keep the default behaviour and don't use this flag.")
-                .action(clap::ArgAction::SetTrue),
-        )
-        .get_matches();
-
-    let monitor_only = matches.get_flag("monitor-only");
-    let run_daemon = matches.get_flag("daemon");
-
-    if monitor_only && !run_daemon {
-        error!("--monitor-only can only be used together with --daemon");
+    shared::logging::init();
+    shared::i18n::init();
+
+    let cli = Cli::parse();
+    let (daemon, overlay) = (cli.daemon_options, cli.overlay_options);
+    let overlay_flags_given = overlay.demo || overlay.resident || overlay.placement.is_some();
+    let daemon_flags_given = daemon.monitor_only || daemon.simulate;
+
+    // `--daemon`, `--demo` etc. predate the `daemon` and `overlay` commands and stay for existing setups
+    let command = match cli.command {
+        None if cli.daemon => Commands::Daemon(daemon),
+        None => {
+            if daemon_flags_given {
+                error!("--monitor-only and --simulate can only be used together with --daemon");
+                std::process::exit(1);
+            }
+            Commands::Overlay(overlay)
+        }
+        Some(command) => {
+            if cli.daemon || daemon_flags_given || overlay_flags_given {
+                error!("--daemon, --monitor-only, --simulate, --demo, --resident and --placement go after `daemon` or `overlay`, not before a command");
+                std::process::exit(1);
+            }
+            command
+        }
+    };
+    if cli.daemon && overlay_flags_given {
+        error!("--demo, --resident and --placement only apply to the overlay and cannot be used together with --daemon");
         std::process::exit(1);
     }
 
-    if run_daemon {
-        info!("Starting clipboard backend daemon...");
-        backend::run_backend(monitor_only).await?;
-    } else {
-        info!("Starting clipboard frontend...");
-        frontend::run_frontend().await?;
+    match command {
+        Commands::Daemon(options) => {
+            info!("Starting clipboard backend daemon...");
+            backend::run_backend(options.monitor_only, options.simulate).await?;
+        }
+        #[cfg(feature = "gtk")]
+        Commands::Overlay(options) if options.resident => {
+            info!("Starting resident clipboard frontend...");
+            frontend::gtk_overlay::run_resident_overlay(options.demo)?;
+        }
+        #[cfg(feature = "gtk")]
+        Commands::Overlay(options) => {
+            info!("Starting clipboard frontend...");
+            frontend::run_frontend(options.demo, options.placement).await?;
+        }
+        Commands::Copy { id, seat } => {
+            let mut client = FrontendClient::new()?;
+            match seat {
+                Some(seat) => client.set_clipboard_on_seat(id, &seat)?,
+                None => client.set_clipboard_by_id(id)?,
+            }
+        }
+        Commands::Paste { id, mime } => run_paste_command(id, mime.as_deref())?,
+        Commands::History { limit, content_types, since, search, json } => {
+            run_history_command(limit as usize, &content_types, since, search.as_deref().unwrap_or_default(), json)?;
+        }
+        Commands::Pick { dmenu } => run_pick_command(dmenu)?,
+        Commands::Tui => frontend::tui::run_tui()?,
+        #[cfg(feature = "gtk")]
+        Commands::Timeline => frontend::timeline::run_timeline()?,
+        #[cfg(feature = "gtk")]
+        Commands::Shot { interactive, show } => {
+            let png = frontend::screenshot::take_screenshot(interactive)?;
+            let mime_data = indexmap::IndexMap::from([("image/png".to_string(), bytes::Bytes::from(png))]);
+            let id = FrontendClient::with_format(shared::wire::WireFormat::MsgPack)?.add_item(mime_data, true)?;
+            info!("Screenshot added to the history as item {id}");
+            if show {
+                frontend::run_frontend(false, None).await?;
+            }
+        }
+        Commands::Pop { depth } => {
+            let (item, remaining) = FrontendClient::new()?.pop_and_paste(depth)?;
+            println!("Pasted item {} from the copy stack, {remaining} left", item.item_id);
+        }
+        Commands::Stats => {
+            let stats = FrontendClient::new()?.get_stats()?;
+            for (label, value) in stats.rows() {
+                println!("{label:<14} {value}");
+            }
+        }
+        Commands::Clients => {
+            for client in FrontendClient::new()?.list_clients()? {
+                let who = match (&client.name, &client.process) {
+                    (Some(name), _) => name.clone(),
+                    (None, Some(process)) => process.clone(),
+                    (None, None) => "?".to_string(),
+                };
+                let pid = client.pid.map_or_else(|| "?".to_string(), |pid| pid.to_string());
+                let events = match &client.events {
+                    Some(events) => format!("  only {}", events.iter().map(|event| format!("{event:?}")).collect::<Vec<_>>().join(", ")),
+                    None => String::new(),
+                };
+                let dropped = if client.pushes_dropped > 0 { format!("  {} dropped", client.pushes_dropped) } else { String::new() };
+                println!(
+                    "{:>4}  {who:<20} pid {pid:<8} {:>6} pushes  connected {}{dropped}{events}",
+                    client.id,
+                    client.pushes_sent,
+                    shared::format::format_relative_time(client.connected_at / 1000),
+                );
+            }
+        }
+        Commands::Ping => {
+            let health = match FrontendClient::new().and_then(|mut client| client.ping()) {
+                Ok(health) => health,
+                Err(e) => {
+                    println!("Daemon not reachable: {e}");
+                    std::process::exit(1);
+                }
+            };
+            for (label, value) in health.rows() {
+                println!("{label:<14} {value}");
+            }
+            if !health.is_recording() {
+                std::process::exit(2);
+            }
+        }
+        Commands::Version => {
+            println!("cursor-clip {} (protocol {})", env!("CARGO_PKG_VERSION"), shared::protocol::PROTOCOL_VERSION);
+            match FrontendClient::new().and_then(|mut client| client.get_version()) {
+                Ok((version, protocol_version)) => println!("daemon      {version} (protocol {protocol_version})"),
+                Err(e) => println!("daemon      unknown: {e}"),
+            }
+        }
+        Commands::Metrics { prometheus } => {
+            let metrics = FrontendClient::new()?.get_metrics()?;
+            if prometheus {
+                print!("{}", metrics.to_prometheus());
+            } else {
+                for (label, value) in metrics.rows() {
+                    println!("{label:<20} {value}");
+                }
+            }
+        }
+        Commands::LogLevel { filter } => {
+            let filter = FrontendClient::new()?.set_log_level(&filter)?;
+            println!("Daemon log filter: {filter}");
+        }
+        Commands::Pause | Commands::Resume => {
+            let paused = FrontendClient::new()?.set_capture_paused(matches!(command, Commands::Pause))?;
+            println!("Clipboard capture {}", if paused { "paused" } else { "active" });
+        }
+        Commands::Sync(sync) => run_sync_command(sync)?,
+        Commands::Phone(phone) => {
+            let mut client = FrontendClient::new()?;
+            match phone {
+                PhoneCommand::List => {
+                    let devices = client.list_phones()?;
+                    if devices.is_empty() {
+                        println!("No reachable phone paired with KDE Connect or GSConnect");
+                    }
+                    for device in devices {
+                        println!("{:<20} {:<12} {}", device.name, device.service.as_str(), device.id);
+                    }
+                }
+                PhoneCommand::Pull { device } => {
+                    let devices = client.pull_from_phone(device)?;
+                    println!("Asked {} for its clipboard", devices.join(", "));
+                }
+            }
+        }
+        #[cfg(feature = "gtk")]
+        Commands::Tray => frontend::tray::run_tray()?,
+        #[cfg(feature = "gtk")]
+        Commands::SearchProvider => frontend::search_provider::run_search_provider()?,
+        #[cfg(not(feature = "gtk"))]
+        Commands::Overlay(_) | Commands::Timeline | Commands::Shot { .. } | Commands::Tray | Commands::SearchProvider => {
+            error!("This cursor-clip was built without the `gtk` feature, only the daemon, the command line and `cursor-clip tui` are available");
+            std::process::exit(1);
+        }
+        Commands::Config(ConfigCommand::Path) => println!("{}", shared::config::config_file_path().display()),
+        Commands::Config(ConfigCommand::Show) => print!("{}", toml::to_string_pretty(&shared::Config::load())?),
+        Commands::Completions { shell } => print!("{}", completions::generate(shell, &Cli::command())),
+    }
+
+    Ok(())
+}
+
+/// `cursor-clip paste`: writes the chosen payload of an item as it is, without a trailing newline
+fn run_paste_command(id: Option<u64>, mime: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    const TEXT_MIME_TYPES: [&str; 3] = ["text/plain;charset=utf-8", "UTF8_STRING", "text/plain"];
+    let mut client = FrontendClient::new()?;
+    let id = match id {
+        Some(id) => id,
+        None => {
+            let (items, _) = client.get_history_page(0, 1, shared::SortMode::Recent)?;
+            items.first().map(|item| item.item_id).ok_or("The clipboard history is empty")?
+        }
+    };
+    let mime_data = client.get_item_data(id)?;
+    let payload = match mime {
+        Some(mime) => mime_data.get(mime).ok_or_else(|| {
+            let offered: Vec<&str> = mime_data.keys().map(String::as_str).collect();
+            format!("Item {id} has no {mime} data, it has: {}", offered.join(", "))
+        })?,
+        None => TEXT_MIME_TYPES
+            .iter()
+            .find_map(|mime| mime_data.get(*mime))
+            .or_else(|| mime_data.values().next())
+            .ok_or_else(|| format!("Item {id} has no data"))?,
+    };
+    std::io::stdout().lock().write_all(payload)?;
+    Ok(())
+}
+
+/// `cursor-clip history`: pages through the matching items until the limit or the `--since` cutoff
+fn run_history_command(limit: usize, content_types: &[shared::ClipboardContentType], since: Option<u64>, query: &str, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    use shared::{ClipboardContentType, SortMode};
+    const PAGE_SIZE: u32 = 100;
+
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+    let cutoff = since.map(|secs| now.saturating_sub(secs));
+
+    let mut client = FrontendClient::new()?;
+    let mut items = Vec::new();
+    let mut offset = 0;
+    'pages: loop {
+        let (page, total) = client.search_history(query, content_types, None, SortMode::Recent, offset, PAGE_SIZE)?;
+        offset += page.len() as u32;
+        let last_page = page.is_empty() || offset >= total;
+        for mut item in page {
+            // Newest first, everything after the first older item is older too
+            if cutoff.is_some_and(|cutoff| item.stamp.secs() < cutoff) || items.len() == limit {
+                break 'pages;
+            }
+            if item.content_type == ClipboardContentType::Password {
+                item.content_preview = shared::format::MASK.to_string();
+            }
+            items.push(item);
+        }
+        if last_page {
+            break;
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&items)?);
+        return Ok(());
+    }
+    if items.is_empty() {
+        println!("No matching clipboard items");
+        return Ok(());
+    }
+    println!("{:>5}  {:<14}  {:<8}  {:>6}  Content", "ID", "Copied", "Type", "Pasted");
+    for item in &items {
+        let mut preview = shared::format::preview_line(item, 60);
+        for tag in &item.tags {
+            preview.push_str(&format!(" #{tag}"));
+        }
+        println!(
+            "{:>5}  {:<14}  {:<8}  {:>6}  {preview}",
+            item.item_id,
+            shared::format::format_relative_time(item.stamp.secs()),
+            item.content_type.as_str(),
+            item.copy_count,
+        );
+    }
+    Ok(())
+}
+
+/// `cursor-clip pick`: with `dmenu`, list the history for a picker, otherwise paste the item whose
+/// line the picker printed (a cancelled picker prints nothing, which exits with 1)
+fn run_pick_command(dmenu: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = FrontendClient::new()?;
+    if dmenu {
+        let mut stdout = std::io::stdout().lock();
+        // The daemon keeps at most 100 items, one page has all of them
+        let (items, _) = client.get_history_page(0, 200, shared::SortMode::Recent)?;
+        for item in items {
+            let line = shared::format::preview_line(&item, 200);
+            // A closed pipe (picker already exited) is no error
+            if writeln!(stdout, "{}\t{} {line}", item.item_id, item.content_type.icon()).is_err() {
+                break;
+            }
+        }
+        return Ok(());
     }
 
+    let mut chosen = String::new();
+    std::io::stdin().read_line(&mut chosen)?;
+    if chosen.trim().is_empty() {
+        std::process::exit(1);
+    }
+    let id = chosen
+        .split_whitespace()
+        .next()
+        .and_then(|id| id.parse::<u64>().ok())
+        .ok_or_else(|| format!("Expected a line of `cursor-clip pick --dmenu`, got: {}", chosen.trim()))?;
+    client.set_clipboard_by_id(id)?;
+    Ok(())
+}
+
+/// `cursor-clip sync enable/disable/status`
+fn run_sync_command(command: SyncCommand) -> Result<(), Box<dyn std::error::Error>> {
+    use cursor_clip::shared::config::SyncConfig;
+    match command {
+        SyncCommand::Enable { dir, key, device } => {
+            let dir = std::path::absolute(dir)?;
+            std::fs::create_dir_all(&dir)?;
+            let key = match key {
+                Some(key) => {
+                    backend::sync::parse_key(&key)?;
+                    key.trim().to_lowercase()
+                }
+                None => SyncConfig::load().map_or_else(backend::sync::generate_key, |config| config.key),
+            };
+            let device = device.unwrap_or_else(|| {
+                std::fs::read_to_string("/proc/sys/kernel/hostname").map_or_else(|_| "unknown".to_string(), |name| name.trim().to_string())
+            });
+            SyncConfig { dir: dir.clone(), key: key.clone(), device }.save()?;
+            println!("History sync enabled through {}", dir.display());
+            println!("Enable it on your other devices with:");
+            println!("  cursor-clip sync enable <DIR> --key {key}");
+        }
+        SyncCommand::Disable => {
+            match SyncConfig::remove() {
+                Ok(()) => println!("History sync disabled"),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => println!("History sync is not enabled"),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        SyncCommand::Status => {
+            let Some(config) = SyncConfig::load() else {
+                println!("History sync is not enabled");
+                return Ok(());
+            };
+            let status = FrontendClient::new().and_then(|mut client| client.get_sync_status());
+            match status {
+                Ok(status) => {
+                    for (label, value) in status.rows() {
+                        println!("{label:<14} {value}");
+                    }
+                    if !status.enabled {
+                        println!("The daemon picks up the settings within a few seconds");
+                    }
+                }
+                Err(e) => {
+                    println!("{:<14} {}", "Folder", config.dir.display());
+                    println!("{:<14} {}", "Device", config.device);
+                    println!("Daemon not reachable: {e}");
+                }
+            }
+        }
+    }
     Ok(())
 }
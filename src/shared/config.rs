@@ -0,0 +1,578 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use super::{ClipboardContentType, SortMode};
+use tracing::{debug, warn};
+
+/// User configuration loaded from `$XDG_CONFIG_HOME/cursor-clip/config.toml`.
+/// Missing keys fall back to their defaults, a missing file yields the default config.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Merge a URL and its page title copied shortly after each other into one link item. Off
+    /// by default: any short line of text copied within the window of a link is taken for its
+    /// title and not kept as an item of its own.
+    pub merge_link_titles: bool,
+    /// Maximum time in seconds between the URL and the title copy to be merged
+    pub link_merge_window_secs: u64,
+    /// Fetch the page title of copied links to show it instead of the bare URL (privacy opt-in,
+    /// only available when built with the `link-titles` feature)
+    pub fetch_link_titles: bool,
+    /// Give up fetching a page title after this many seconds
+    pub link_title_timeout_secs: u64,
+    /// Number of items kept in the history, older ones are removed
+    pub max_history_items: u32,
+    /// Items a password manager marks as secret are recorded like any other or not at all
+    pub secrets: SecretsPolicy,
+    /// Light or dark overlay, by default following the system color scheme
+    pub color_scheme: ColorScheme,
+    /// Show thumbnails of image items (otherwise only their description)
+    pub show_image_previews: bool,
+    /// Show when items were copied as "5 minutes ago" or as the time (and date) of the copy
+    pub time_format: TimeFormat,
+    /// 12 or 24-hour clock for the times of items, by default the desktop's setting
+    pub clock: ClockFormat,
+    /// Smallest width of the overlay's history in pixels, it grows with wide items
+    pub overlay_width: u32,
+    /// Height of the overlay's history in pixels
+    pub overlay_height: u32,
+    /// Lines of text shown of each item in the list, longer previews are ellipsized
+    pub preview_lines: u32,
+    /// Spacing of the list rows
+    pub density: Density,
+    /// Layout the overlay opens with (can be toggled from the header bar)
+    pub view_mode: ViewMode,
+    /// Order the overlay lists the history in when it opens (can be toggled from the header bar)
+    pub sort_mode: SortMode,
+    /// Where the overlay opens (`--placement` overrides it)
+    pub placement: Placement,
+    /// Fade and slide the overlay in and out (also off when animations are disabled in GTK)
+    pub animations: bool,
+    /// Close the overlay when clicking outside of it or when it loses the keyboard focus
+    /// (otherwise it stays open next to the other windows until closed)
+    pub close_on_focus_loss: bool,
+    /// Keep the overlay open after pasting an item (otherwise only when Ctrl is held)
+    pub keep_open_after_paste: bool,
+    /// A selection with the content of the previous one, set less than this many milliseconds
+    /// after it, is ignored (apps re-setting the clipboard in a loop)
+    pub coalesce_window_ms: u64,
+    /// Read at most this many new selections per second (0: no limit)
+    pub max_captures_per_sec: u32,
+    /// Copies from the same app within this many milliseconds of each other are grouped into one
+    /// expandable row (0 turns grouping off)
+    pub group_window_ms: u64,
+    /// Number of recent items the copy stack takes (`cursor-clip pop --depth` overrides it)
+    pub copy_stack_depth: u32,
+    /// Whether to stop taking ownership of new selections when another clipboard manager is active
+    pub defer_mode: DeferMode,
+    /// When an external selection is re-served by cursor-clip (see `OwnershipPolicy`)
+    pub ownership: OwnershipPolicy,
+    /// Users (besides the one running the daemon) whose processes may connect to the backend
+    pub allowed_client_uids: Vec<u32>,
+    /// Refuse clients running as root, even if the daemon runs as root itself
+    pub reject_root_clients: bool,
+    /// Executables run on every new clipboard item (`[[plugins]]` tables, run in order)
+    pub plugins: Vec<PluginConfig>,
+    /// Command (program and arguments) started whenever a new item is recorded
+    pub on_copy: Vec<String>,
+    /// Command started whenever an item is pasted through cursor-clip
+    pub on_paste: Vec<String>,
+    /// Show a desktop notification when a new item is captured
+    pub notifications: bool,
+    /// Content types that trigger a notification
+    pub notification_content_types: Vec<ClipboardContentType>,
+    /// Also notify about passwords and items marked secret (their content is never shown)
+    pub notify_secrets: bool,
+    /// Capture is paused while a window matching one of these rules is focused (`[[incognito]]` tables)
+    pub incognito: Vec<IncognitoRule>,
+    /// Serve metrics in the Prometheus text format on this port of 127.0.0.1 (off if unset)
+    pub metrics_port: Option<u16>,
+    /// Remove items older than this many days (kept until the history limit pushes them out if unset)
+    pub max_item_age_days: Option<u64>,
+    /// Remove tracking parameters from copied links before they are recorded
+    pub clean_urls: bool,
+    /// Query parameters removed from links, a trailing `*` matches every parameter with that prefix
+    pub tracking_params: Vec<String>,
+    /// Clean-up of copied text before it is recorded (`[normalize]` table)
+    pub normalize: NormalizeConfig,
+    /// Content type of new items whose preview matches a pattern, checked in order before the
+    /// built-in guess (`[[content_rules]]` tables)
+    pub content_rules: Vec<ContentRule>,
+}
+
+/// Windows to pause capture for: regular expressions searched in the app id and the window
+/// title, all given patterns must match
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IncognitoRule {
+    pub app_id: Option<String>,
+    pub title: Option<String>,
+}
+
+/// Items whose preview text matches the regular expression `pattern` get `content_type`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentRule {
+    pub pattern: String,
+    pub content_type: ClipboardContentType,
+}
+
+/// A user plugin: `command` gets the payload of a matching MIME type on stdin
+/// (and the type in `$CURSOR_CLIP_MIME_TYPE`), its stdout is used according to `output`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginConfig {
+    /// Shown in log messages
+    pub name: String,
+    /// Program and arguments, not run through a shell
+    pub command: Vec<String>,
+    /// MIME types the plugin runs on, `type/*` matches a whole family, empty matches everything
+    pub mime_types: Vec<String>,
+    pub output: PluginOutput,
+    /// The plugin is killed (and the item left unchanged) after this many milliseconds
+    pub timeout_ms: u64,
+}
+
+/// What a plugin's stdout means
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginOutput {
+    /// Nothing, the plugin only observes (logging, sync, ...)
+    #[default]
+    Ignore,
+    /// Non-empty output replaces the payload of every matching MIME type (redaction, cleanup)
+    Payload,
+    /// Each non-empty line is a tag attached to the item
+    Tags,
+}
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            name: "plugin".to_string(),
+            command: Vec::new(),
+            mime_types: Vec::new(),
+            output: PluginOutput::default(),
+            timeout_ms: 2000,
+        }
+    }
+}
+
+impl PluginConfig {
+    pub fn accepts(&self, mime: &str) -> bool {
+        self.mime_types.is_empty()
+            || self.mime_types.iter().any(|filter| match filter.strip_suffix("/*") {
+                Some(family) => mime.split('/').next() == Some(family),
+                None => filter == mime,
+            })
+    }
+}
+
+/// Overlay layout: compact list rows or a grid with large image previews
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ViewMode {
+    #[default]
+    List,
+    Grid,
+}
+
+impl ViewMode {
+    pub const ALL: [Self; 2] = [Self::List, Self::Grid];
+
+    /// Name in the config file, e.g. for GTK action targets
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::List => "list",
+            Self::Grid => "grid",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+}
+
+/// Where the overlay opens, always on the output the cursor is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Placement {
+    /// Top-left corner at the cursor (moved to stay on screen)
+    #[default]
+    Cursor,
+    /// Centered on the output
+    Center,
+    /// Docked to the bottom edge, horizontally centered
+    Bottom,
+    /// Full-height panel sliding in from the right edge
+    Edge,
+}
+
+impl Placement {
+    pub const ALL: [Self; 4] = [Self::Cursor, Self::Center, Self::Bottom, Self::Edge];
+
+    /// Name in the config file and on the command line
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Cursor => "cursor",
+            Self::Center => "center",
+            Self::Bottom => "bottom",
+            Self::Edge => "edge",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|placement| placement.name() == name)
+    }
+}
+
+/// What happens to items marked secret by the source (password managers)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretsPolicy {
+    /// Record them (they are never synced)
+    #[default]
+    Record,
+    /// Never add them to the history
+    Ignore,
+}
+
+/// Color scheme of the overlay
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorScheme {
+    /// Follow the system's light/dark preference
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// How the times of items are shown, the full date and time is in their tooltips either way
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeFormat {
+    /// "5 minutes ago", "2 days ago"
+    #[default]
+    Relative,
+    /// "14:05" for today's items, with the date for older ones
+    Absolute,
+}
+
+/// Clock of absolute times
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ClockFormat {
+    /// Follow the desktop (GNOME's clock format, 24-hour elsewhere)
+    #[default]
+    #[serde(rename = "system")]
+    System,
+    #[serde(rename = "12h")]
+    TwelveHour,
+    #[serde(rename = "24h")]
+    TwentyFourHour,
+}
+
+/// Spacing of the overlay's rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Density {
+    #[default]
+    Comfortable,
+    /// Smaller margins to fit more items into the overlay
+    Compact,
+}
+
+/// Cooperation with other clipboard managers: normally cursor-clip re-serves every new selection
+/// itself (so it survives the source app closing). When deferring, it only records history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeferMode {
+    /// Defer while another manager is detected (marker MIME type or ownership churn)
+    #[default]
+    Auto,
+    /// Always only record, never take ownership
+    Always,
+    /// Always take ownership, even if another manager is active
+    Never,
+}
+
+/// Clean-ups of the text of new items, all off by default. The text as copied is kept along
+/// with the cleaned one and can still be pasted ("Paste Original").
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalizeConfig {
+    /// Remove whitespace and newlines at the end
+    pub trim_trailing: bool,
+    /// Turn Windows line endings (CRLF) into plain newlines
+    pub crlf: bool,
+    /// Replace tabs with this many spaces
+    pub tab_width: Option<u32>,
+}
+
+/// When cursor-clip takes over a new external selection. Re-serving it keeps the content
+/// available after the source app closes, but replaces the source's own offer (which may
+/// generate some formats on demand).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OwnershipPolicy {
+    /// Take it over as soon as it is recorded
+    #[default]
+    Always,
+    /// Only record it, the selection is gone with its source
+    Never,
+    /// Leave it to its source and take it over once the source goes away
+    OnSourceExit,
+}
+
+/// Common click and campaign identifiers added by newsletters, ads and share buttons
+const DEFAULT_TRACKING_PARAMS: [&str; 14] = [
+    "utm_*", "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "twclid", "igshid", "mc_cid", "mc_eid", "_hsenc", "_hsmi",
+];
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            merge_link_titles: false,
+            link_merge_window_secs: 10,
+            fetch_link_titles: false,
+            link_title_timeout_secs: 5,
+            max_history_items: 100,
+            secrets: SecretsPolicy::default(),
+            color_scheme: ColorScheme::default(),
+            show_image_previews: true,
+            time_format: TimeFormat::default(),
+            clock: ClockFormat::default(),
+            overlay_width: 200,
+            overlay_height: 400,
+            preview_lines: 3,
+            density: Density::default(),
+            view_mode: ViewMode::default(),
+            sort_mode: SortMode::default(),
+            placement: Placement::default(),
+            animations: true,
+            close_on_focus_loss: true,
+            keep_open_after_paste: false,
+            coalesce_window_ms: 500,
+            max_captures_per_sec: 10,
+            group_window_ms: 1000,
+            copy_stack_depth: 5,
+            defer_mode: DeferMode::default(),
+            ownership: OwnershipPolicy::default(),
+            allowed_client_uids: Vec::new(),
+            reject_root_clients: false,
+            plugins: Vec::new(),
+            on_copy: Vec::new(),
+            on_paste: Vec::new(),
+            notifications: false,
+            notification_content_types: vec![
+                ClipboardContentType::Text,
+                ClipboardContentType::Url,
+                ClipboardContentType::Code,
+                ClipboardContentType::File,
+                ClipboardContentType::Image,
+                ClipboardContentType::Other,
+            ],
+            notify_secrets: false,
+            incognito: Vec::new(),
+            metrics_port: None,
+            max_item_age_days: None,
+            clean_urls: false,
+            tracking_params: DEFAULT_TRACKING_PARAMS.iter().map(|param| param.to_string()).collect(),
+            normalize: NormalizeConfig::default(),
+            content_rules: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file, falling back to defaults if it is missing or invalid
+    pub fn load() -> Self {
+        Self::try_load().unwrap_or_else(|e| {
+            warn!("{e}; using defaults");
+            Self::default()
+        })
+    }
+
+    /// Like `load`, but an invalid file is an error instead of the defaults (a missing one is not)
+    pub fn try_load() -> Result<Self, String> {
+        let path = config_file_path();
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            debug!("No config file at {}; using defaults", path.display());
+            return Ok(Self::default());
+        };
+        let config = toml::from_str(&content).map_err(|e| format!("Invalid config file {}: {e}", path.display()))?;
+        debug!("Loaded config from {}", path.display());
+        Ok(config)
+    }
+
+}
+
+/// The options of the preferences dialog, the only part of the config clients may change through
+/// the daemon (`FrontendMessage::SetConfig`). Everything else, like the commands the daemon runs
+/// and who may connect to it, only changes by editing the config file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preferences {
+    pub max_history_items: u32,
+    pub secrets: SecretsPolicy,
+    pub color_scheme: ColorScheme,
+    pub show_image_previews: bool,
+    pub time_format: TimeFormat,
+    pub clock: ClockFormat,
+    pub close_on_focus_loss: bool,
+    pub keep_open_after_paste: bool,
+}
+
+impl Preferences {
+    pub fn of(config: &Config) -> Self {
+        Self {
+            max_history_items: config.max_history_items,
+            secrets: config.secrets,
+            color_scheme: config.color_scheme,
+            show_image_previews: config.show_image_previews,
+            time_format: config.time_format,
+            clock: config.clock,
+            close_on_focus_loss: config.close_on_focus_loss,
+            keep_open_after_paste: config.keep_open_after_paste,
+        }
+    }
+
+    pub fn apply_to(&self, config: &mut Config) {
+        config.max_history_items = self.max_history_items;
+        config.secrets = self.secrets;
+        config.color_scheme = self.color_scheme;
+        config.show_image_previews = self.show_image_previews;
+        config.time_format = self.time_format;
+        config.clock = self.clock;
+        config.close_on_focus_loss = self.close_on_focus_loss;
+        config.keep_open_after_paste = self.keep_open_after_paste;
+    }
+
+    /// Write the preferences into the config file at `path` (normally `config_file_path()`), the
+    /// rest of it (other options, comments and formatting) stays as it is. A file with errors is
+    /// left alone.
+    pub fn save_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let patched = self.patch(&content).map_err(std::io::Error::other)?;
+        std::fs::write(path, patched)
+    }
+
+    /// `content` of a config file with the preference keys set to these values
+    fn patch(&self, content: &str) -> Result<String, String> {
+        let mut document: toml_edit::DocumentMut = content.parse().map_err(|e| format!("Invalid config file: {e}"))?;
+        let values = toml_edit::ser::to_document(self).map_err(|e| e.to_string())?;
+        for (key, item) in values.iter() {
+            let Some(value) = item.as_value() else { continue };
+            match document.get_mut(key).and_then(toml_edit::Item::as_value_mut) {
+                // Keep the comment after the old value
+                Some(old) => {
+                    let decor = old.decor().clone();
+                    *old = value.clone();
+                    *old.decor_mut() = decor;
+                }
+                None => document[key] = toml_edit::Item::Value(value.clone()),
+            }
+        }
+        Ok(document.to_string())
+    }
+}
+
+/// Directory holding all user configuration (`$XDG_CONFIG_HOME/cursor-clip` or `~/.config/cursor-clip`)
+pub fn config_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("/tmp"));
+    base.join("cursor-clip")
+}
+
+pub fn config_file_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+/// History sync settings, written by `cursor-clip sync enable` to `sync.toml` in the config
+/// directory (separate from config.toml since it holds the key). Sync is enabled while it exists.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConfig {
+    /// Folder shared between the devices (e.g. by Syncthing), holding one encrypted file per item
+    pub dir: PathBuf,
+    /// Hex encoded 256-bit key, the same on every device
+    pub key: String,
+    /// Name of this device, recorded with the items it exports
+    pub device: String,
+}
+
+impl SyncConfig {
+    pub fn path() -> PathBuf {
+        config_dir().join("sync.toml")
+    }
+
+    /// The sync settings, None while sync is disabled
+    pub fn load() -> Option<Self> {
+        let content = std::fs::read_to_string(Self::path()).ok()?;
+        toml::from_str(&content).map_err(|e| warn!("Invalid sync settings {}: {e}", Self::path().display())).ok()
+    }
+
+    /// Write the settings readable only by the current user
+    pub fn save(&self) -> std::io::Result<()> {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+        std::fs::create_dir_all(config_dir())?;
+        let content = toml::to_string(self).map_err(std::io::Error::other)?;
+        let mut file = std::fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(Self::path())?;
+        file.write_all(content.as_bytes())
+    }
+
+    pub fn remove() -> std::io::Result<()> {
+        std::fs::remove_file(Self::path())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HAND_WRITTEN: &str = r#"# My clipboard
+max_history_items = 50 # plenty
+on_copy = ["notify-send", "copied"]
+
+[normalize]
+crlf = true
+"#;
+
+    #[test]
+    fn preferences_are_patched_into_the_file() {
+        let mut preferences = Preferences::of(&toml::from_str(HAND_WRITTEN).unwrap());
+        preferences.max_history_items = 500;
+        preferences.secrets = SecretsPolicy::Ignore;
+        let patched = preferences.patch(HAND_WRITTEN).unwrap();
+
+        assert!(patched.starts_with("# My clipboard\nmax_history_items = 500 # plenty\n"));
+        assert!(patched.contains("on_copy = [\"notify-send\", \"copied\"]\n"));
+        assert!(patched.contains("secrets = \"ignore\"\n"));
+        // New keys go to the top level, not into the last table
+        assert!(patched.find("secrets").unwrap() < patched.find("[normalize]").unwrap());
+        let config: Config = toml::from_str(&patched).unwrap();
+        assert_eq!(Preferences::of(&config), preferences);
+        assert_eq!(config.on_copy, ["notify-send", "copied"]);
+        assert!(config.normalize.crlf);
+    }
+
+    #[test]
+    fn patching_an_empty_file_writes_only_the_preferences() {
+        let preferences = Preferences::of(&Config::default());
+        let config: Config = toml::from_str(&preferences.patch("").unwrap()).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn files_with_errors_are_not_patched() {
+        let preferences = Preferences::of(&Config::default());
+        assert!(preferences.patch("max_history_items = ").is_err());
+    }
+}
@@ -1,14 +1,58 @@
 use serde::{Deserialize, Serialize};
 use indexmap::IndexMap;
 use bytes::Bytes;
+use super::protocol;
+use super::wire::WireFormat;
+use super::metrics::Metrics;
+use super::config::{Config, Preferences};
+use super::format::{format_bytes, format_duration, format_relative_time, preview_line};
+use super::i18n::{gettext, trf, trn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClipboardItem {
     pub item_id: u64,
     pub content_preview: String,
     pub content_type: ClipboardContentType,
-    pub timestamp: u64, // Unix timestamp
+    pub stamp: EventStamp, // when the item was recorded, also defines the history order
+    pub copy_count: u32, // times re-copied via cursor-clip
+    #[serde(default)]
+    pub last_pasted: Option<u64>, // Unix time in milliseconds of the last re-copy via cursor-clip
     pub mime_data: IndexMap<String, Bytes>, // content type -> payload bytes
+    pub suspect_mime_types: Vec<String>, // payloads that looked truncated when read
+    #[serde(default)]
+    pub ocr_text: Option<String>, // text recognized in an image item (OCR feature)
+    #[serde(default)]
+    pub tags: Vec<String>, // added by plugins or the user
+    #[serde(default)]
+    pub source_device: Option<String>, // phone or synced device the item was copied on, None for local copies
+    #[serde(default)]
+    pub seat: Option<String>, // Wayland seat the selection was made on
+    #[serde(default)]
+    pub source_app: Option<String>, // app id of the window focused when the item was copied
+    #[serde(default)]
+    pub text_stats: Option<TextStats>, // size of the text of text and code items
+    #[serde(default)]
+    pub group_id: Option<u64>, // rapid copies from the same app, the id of the group's newest item
+}
+
+/// Prefix of the MIME keys keeping text payloads as copied when the stored text was cleaned up
+/// (see `NormalizeConfig`). They are never offered, their payload is served instead of the
+/// cleaned one when pasting the original.
+pub const ORIGINAL_MIME_PREFIX: &str = "original/";
+
+impl ClipboardItem {
+    /// Whether the text was cleaned up and the text as copied is kept as well
+    pub fn has_original(&self) -> bool {
+        self.mime_data.keys().any(|mime| mime.starts_with(ORIGINAL_MIME_PREFIX))
+    }
+
+    /// Payload served for `mime`, the one as copied if `original` and the text was cleaned up
+    pub fn payload(&self, mime: &str, original: bool) -> Option<&Bytes> {
+        original
+            .then(|| self.mime_data.get(&format!("{ORIGINAL_MIME_PREFIX}{mime}")))
+            .flatten()
+            .or_else(|| self.mime_data.get(mime))
+    }
 }
 
 /// Lightweight version sent to the frontend in history listings (no payload bytes)
@@ -17,7 +61,36 @@ pub struct ClipboardItemPreview {
     pub item_id: u64,
     pub content_preview: String,
     pub content_type: ClipboardContentType,
-    pub timestamp: u64, // Unix timestamp
+    pub stamp: EventStamp,
+    pub copy_count: u32,
+    /// Unix time in milliseconds the item was last pasted through cursor-clip
+    #[serde(default)]
+    pub last_pasted: Option<u64>,
+    pub possibly_truncated: bool,
+    /// Text recognized in an image item, searchable and pasteable on its own
+    #[serde(default)]
+    pub ocr_text: Option<String>,
+    /// Tags attached by plugins or the user
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Device the item was copied on, if not this one
+    #[serde(default)]
+    pub source_device: Option<String>,
+    /// Wayland seat the item was copied on
+    #[serde(default)]
+    pub seat: Option<String>,
+    /// App id of the window that was focused when the item was copied
+    #[serde(default)]
+    pub source_app: Option<String>,
+    /// Size of the full text of text and code items
+    #[serde(default)]
+    pub text_stats: Option<TextStats>,
+    /// The text was cleaned up, the text as copied can be pasted as well
+    #[serde(default)]
+    pub has_original: bool,
+    /// Group of rapid copies from the same app, the id of the group's newest item (its head)
+    #[serde(default)]
+    pub group_id: Option<u64>,
 }
 
 impl From<&ClipboardItem> for ClipboardItemPreview {
@@ -26,12 +99,389 @@ impl From<&ClipboardItem> for ClipboardItemPreview {
             item_id: full.item_id,
             content_preview: full.content_preview.clone(),
             content_type: full.content_type,
-            timestamp: full.timestamp,
+            stamp: full.stamp,
+            copy_count: full.copy_count,
+            last_pasted: full.last_pasted,
+            possibly_truncated: !full.suspect_mime_types.is_empty(),
+            ocr_text: full.ocr_text.clone(),
+            tags: full.tags.clone(),
+            source_device: full.source_device.clone(),
+            seat: full.seat.clone(),
+            source_app: full.source_app.clone(),
+            text_stats: full.text_stats.clone(),
+            has_original: full.has_original(),
+            group_id: full.group_id,
+        }
+    }
+}
+
+impl ClipboardItemPreview {
+    /// Whether the item is part of a group without being its head
+    pub fn is_group_member(&self) -> bool {
+        self.group_id.is_some_and(|group| group != self.item_id)
+    }
+
+    /// Search match: the preview or a tag starts with `query` or the recognized text contains it
+    /// (ignoring case and leading whitespace), and the item is of one of `content_types` (any type if empty)
+    pub fn matches(&self, query: &str, content_types: &[ClipboardContentType]) -> bool {
+        let query = query.to_lowercase();
+        (content_types.is_empty() || content_types.contains(&self.content_type))
+            && (query.is_empty()
+                || self.content_preview.trim_start().to_lowercase().starts_with(&query)
+                || self.tags.iter().any(|tag| tag.to_lowercase().starts_with(&query))
+                || self.ocr_text.as_ref().is_some_and(|text| text.to_lowercase().contains(&query)))
+    }
+
+    /// Whether the item carries `tag` (any item if None)
+    pub fn has_tag(&self, tag: Option<&str>) -> bool {
+        tag.is_none_or(|tag| self.tags.iter().any(|t| t == tag))
+    }
+}
+
+/// Size of a text item, computed once when it is recorded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextStats {
+    pub chars: u32,
+    pub words: u32,
+    pub lines: u32,
+    /// Programming language guessed from keywords, for code items
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+impl TextStats {
+    /// Stats of the full text of a text or code item, None for other content types
+    pub fn of(text: &str, content_type: ClipboardContentType) -> Option<Self> {
+        if !matches!(content_type, ClipboardContentType::Text | ClipboardContentType::Code) {
+            return None;
+        }
+        Some(Self {
+            chars: text.chars().count() as u32,
+            words: text.split_whitespace().count() as u32,
+            lines: text.lines().count().max(1) as u32,
+            language: matches!(content_type, ClipboardContentType::Code)
+                .then(|| guess_language(text))
+                .flatten()
+                .map(str::to_string),
+        })
+    }
+
+    /// Row subtitle, e.g. "Rust · 3 lines · 182 chars"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(language) = &self.language {
+            parts.push(language.clone());
+        }
+        if self.lines > 1 {
+            parts.push(trn("{} line", "{} lines", self.lines.into()));
+        }
+        parts.push(trn("{} word", "{} words", self.words.into()));
+        parts.push(trn("{} char", "{} chars", self.chars.into()));
+        parts.join(" · ")
+    }
+}
+
+/// Characteristic snippets per language, the first language with two matches wins
+const LANGUAGE_HINTS: [(&str, &[&str]); 8] = [
+    ("Rust", &["fn ", "let mut ", "impl ", "pub ", "::", "-> ", "&self", "match "]),
+    ("Python", &["def ", "import ", "self.", "elif ", "print(", "__init__", "None", "):\n"]),
+    ("JavaScript", &["function ", "const ", "=> ", "console.", "===", "document.", "export ", "require("]),
+    ("Go", &["func ", "package ", ":= ", "fmt.", "go ", "chan ", "defer "]),
+    ("C", &["#include", "int main", "printf(", "->", "void ", "malloc(", "NULL"]),
+    ("Java", &["public class", "System.out", "private ", "static void", "new ", "@Override"]),
+    ("Shell", &["#!/bin/", "echo ", "fi\n", "then", "$(", "done", "export "]),
+    ("SQL", &["SELECT ", "FROM ", "WHERE ", "INSERT INTO", "JOIN ", "GROUP BY"]),
+];
+
+fn guess_language(text: &str) -> Option<&'static str> {
+    LANGUAGE_HINTS
+        .iter()
+        .find(|(_, hints)| hints.iter().filter(|hint| text.contains(**hint)).count() >= 2)
+        .map(|(language, _)| *language)
+}
+
+/// Length of the previews in the "Most copied" rows of `HistoryStats::rows`
+const MOST_COPIED_PREVIEW_CHARS: usize = 40;
+
+/// Summary of the history, for `cursor-clip stats` and the overlay's "About history" popover
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistoryStats {
+    pub item_count: u32,
+    /// Number of items per content type, types without items are left out
+    pub counts_by_type: Vec<(ClipboardContentType, u32)>,
+    /// Size of all stored MIME payloads
+    pub total_bytes: u64,
+    pub oldest: Option<EventStamp>,
+    pub newest: Option<EventStamp>,
+    /// Selections ignored as a repeat of the previous one since the daemon started
+    #[serde(default)]
+    pub captures_coalesced: u64,
+    /// Selections delayed or skipped by the capture rate limit since the daemon started
+    #[serde(default)]
+    pub captures_throttled: u64,
+    /// Times items were copied back from the history, summed over the items still in it
+    #[serde(default)]
+    pub total_copies: u64,
+    /// Items copied back most often, most copied first, items never copied back are left out
+    #[serde(default)]
+    pub most_copied: Vec<ClipboardItemPreview>,
+}
+
+impl HistoryStats {
+    /// Count one more item of `content_type`, keeping the most common types first
+    pub fn count_type(&mut self, content_type: ClipboardContentType) {
+        match self.counts_by_type.iter_mut().find(|(existing, _)| *existing == content_type) {
+            Some((_, count)) => *count += 1,
+            None => self.counts_by_type.push((content_type, 1)),
+        }
+        self.counts_by_type.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    }
+
+    /// Label/value rows for display
+    pub fn rows(&self) -> Vec<(String, String)> {
+        let mut rows = vec![
+            (gettext("Items"), self.item_count.to_string()),
+            (gettext("Stored data"), format_bytes(self.total_bytes)),
+        ];
+        if let Some(oldest) = self.oldest {
+            rows.push((gettext("Oldest item"), format_relative_time(oldest.secs())));
+        }
+        if let Some(newest) = self.newest {
+            rows.push((gettext("Newest item"), format_relative_time(newest.secs())));
+        }
+        for (content_type, count) in &self.counts_by_type {
+            rows.push((format!("{} {}", content_type.icon(), content_type.label()), count.to_string()));
+        }
+        if self.total_copies > 0 {
+            rows.push((gettext("Copied back"), trn("{} time", "{} times", self.total_copies)));
+        }
+        for item in &self.most_copied {
+            rows.push((
+                trf("Most copied: {}", &preview_line(item, MOST_COPIED_PREVIEW_CHARS)),
+                trn("{} time", "{} times", item.copy_count as u64),
+            ));
+        }
+        if self.captures_coalesced > 0 {
+            rows.push((gettext("Repeats ignored"), self.captures_coalesced.to_string()));
+        }
+        if self.captures_throttled > 0 {
+            rows.push((gettext("Throttled"), self.captures_throttled.to_string()));
+        }
+        rows
+    }
+}
+
+/// Answer to `Ping`: whether the daemon is alive and recording, for frontends and monitoring scripts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Health {
+    pub uptime_secs: u64,
+    /// State of the Wayland clipboard monitoring
+    pub status: MonitorStatus,
+    pub item_count: u32,
+    pub capture_paused: bool,
+}
+
+impl Health {
+    /// Whether new selections are being recorded
+    pub const fn is_recording(&self) -> bool {
+        self.status.is_active() && !self.capture_paused
+    }
+
+    /// Label/value rows for display
+    pub fn rows(&self) -> Vec<(String, String)> {
+        let monitoring = match &self.status {
+            _ if self.capture_paused && self.status.is_active() => gettext("paused"),
+            MonitorStatus::Active => gettext("active"),
+            MonitorStatus::Simulated => gettext("simulated"),
+            status => status.message().unwrap_or_default(),
+        };
+        vec![
+            (gettext("Uptime"), format_duration(self.uptime_secs)),
+            (gettext("Clipboard"), monitoring),
+            (gettext("Items"), self.item_count.to_string()),
+        ]
+    }
+}
+
+/// Kinds of push messages, clients can `Subscribe` to some of them only
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PushEvent {
+    NewItem,
+    ItemsAdded,
+    ItemUpdated,
+    ItemsRemoved,
+    CaptureStateChanged,
+    StatusChanged,
+    SelectionTakenByExternal,
+    ConfigReloaded,
+    PushesDropped,
+}
+
+/// Pushes a client does not want, set with `Identify`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PushFilter {
+    /// No pushes at all, for connections that only send requests
+    pub no_pushes: bool,
+    /// Leave image items out of item pushes
+    pub no_images: bool,
+}
+
+impl PushFilter {
+    /// `message` as the client gets it, None if nothing of it is left
+    pub fn apply(&self, message: &BackendMessage) -> Option<BackendMessage> {
+        if !message.is_push() {
+            return Some(message.clone());
+        }
+        if self.no_pushes {
+            return None;
+        }
+        if !self.no_images {
+            return Some(message.clone());
+        }
+        let wanted = |item: &ClipboardItemPreview| item.content_type != ClipboardContentType::Image;
+        match message {
+            BackendMessage::NewItem { item } | BackendMessage::ItemUpdated { item } if !wanted(item) => None,
+            BackendMessage::ItemsAdded { items } => {
+                let items: Vec<_> = items.iter().filter(|item| wanted(item)).cloned().collect();
+                (!items.is_empty()).then_some(BackendMessage::ItemsAdded { items })
+            }
+            message => Some(message.clone()),
+        }
+    }
+}
+
+/// A client connected to the backend, listed by `ListClients`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientInfo {
+    pub id: u64,
+    /// Name the client gave itself with `Identify`
+    pub name: Option<String>,
+    pub pid: Option<u32>,
+    /// Command name of the client process
+    pub process: Option<String>,
+    /// Unix time in milliseconds
+    pub connected_at: u64,
+    pub filter: PushFilter,
+    /// Pushes the client subscribed to, None for all of them
+    pub events: Option<Vec<PushEvent>>,
+    pub pushes_sent: u64,
+    /// Pushes left out because the client did not keep up reading them
+    #[serde(default)]
+    pub pushes_dropped: u64,
+}
+
+/// State of the clipboard monitoring in the backend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MonitorStatus {
+    /// Connecting to the compositor
+    Starting,
+    /// Recording selections
+    Active,
+    /// Synthetic traffic (`--simulate`), no compositor involved
+    Simulated,
+    /// The compositor lacks a protocol cursor-clip needs, retried periodically
+    Unsupported { reason: String },
+    /// Not connected to the compositor
+    Disconnected { reason: String },
+}
+
+impl MonitorStatus {
+    /// Whether new selections can be recorded
+    pub const fn is_active(&self) -> bool {
+        matches!(self, Self::Active | Self::Simulated)
+    }
+
+    /// Short explanation for users, None while active
+    pub fn message(&self) -> Option<String> {
+        match self {
+            Self::Active | Self::Simulated => None,
+            Self::Starting => Some(gettext("Connecting to the compositor…")),
+            Self::Unsupported { reason } => Some(trf("Compositor unsupported: {}", reason)),
+            Self::Disconnected { reason } => Some(trf("Not connected to the compositor: {}", reason)),
+        }
+    }
+}
+
+/// Phone reachable through KDE Connect or GSConnect
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhoneDevice {
+    /// Device id assigned by the service
+    pub id: String,
+    pub name: String,
+    pub service: PhoneService,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PhoneService {
+    KdeConnect,
+    GSConnect,
+}
+
+impl PhoneService {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::KdeConnect => "KDE Connect",
+            Self::GSConnect => "GSConnect",
+        }
+    }
+}
+
+/// State of history sync, for `cursor-clip sync status`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncStatus {
+    pub enabled: bool,
+    pub dir: Option<String>,
+    pub device: Option<String>,
+    /// Items written to / read from the sync folder since the daemon started
+    pub exported: u32,
+    pub imported: u32,
+    /// Unix time in milliseconds of the last completed sync run
+    pub last_sync: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+impl SyncStatus {
+    /// Label/value rows for display
+    pub fn rows(&self) -> Vec<(String, String)> {
+        let state = if self.enabled { gettext("enabled") } else { gettext("disabled") };
+        let mut rows = vec![(gettext("Sync"), state)];
+        if let Some(dir) = &self.dir {
+            rows.push((gettext("Folder"), dir.clone()));
+        }
+        if let Some(device) = &self.device {
+            rows.push((gettext("Device"), device.clone()));
         }
+        if self.enabled {
+            rows.push((gettext("Exported"), self.exported.to_string()));
+            rows.push((gettext("Imported"), self.imported.to_string()));
+            let last_sync = self.last_sync.map_or_else(|| gettext("never"), |millis| format_relative_time(millis / 1000));
+            rows.push((gettext("Last sync"), last_sync));
+        }
+        if let Some(error) = &self.last_error {
+            rows.push((gettext("Last error"), error.clone()));
+        }
+        rows
     }
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+/// When an item was recorded: Unix time in milliseconds plus a sequence number of the recording
+/// device. Stamps of one device strictly increase, even for copies within the same millisecond
+/// or after the wall clock went backwards. Ordering compares the time first, then the sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct EventStamp {
+    pub millis: u64,
+    pub seq: u64,
+}
+
+impl EventStamp {
+    /// Unix time in whole seconds
+    pub const fn secs(&self) -> u64 {
+        self.millis / 1000
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ClipboardContentType {
     Text,
     Url,
@@ -42,31 +492,537 @@ pub enum ClipboardContentType {
     Other,
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+/// Order of history listings (`GetHistoryPage`, `SearchHistory`)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortMode {
+    /// Newest first
+    #[default]
+    Recent,
+    /// Most often pasted through cursor-clip first, ties broken by the last paste, then the newest
+    MostUsed,
+}
+
+impl SortMode {
+    pub const ALL: [Self; 2] = [Self::Recent, Self::MostUsed];
+
+    /// Name in the config file, e.g. for GTK action targets
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Recent => "recent",
+            Self::MostUsed => "most-used",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    /// Name of the mode for display, in the user's language
+    pub fn label(self) -> String {
+        match self {
+            Self::Recent => gettext("Recent"),
+            Self::MostUsed => gettext("Most used"),
+        }
+    }
+
+    /// Order of two items in this mode, `Less` if `a` is listed above `b`
+    pub fn compare(self, a: &ClipboardItemPreview, b: &ClipboardItemPreview) -> std::cmp::Ordering {
+        let newest_first = b.stamp.cmp(&a.stamp);
+        match self {
+            Self::Recent => newest_first,
+            Self::MostUsed => b
+                .copy_count
+                .cmp(&a.copy_count)
+                .then(b.last_pasted.cmp(&a.last_pasted))
+                .then(newest_first),
+        }
+    }
+}
+
+/// Built-in text transforms of `FrontendMessage::TransformItem`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextTransform {
+    Uppercase,
+    Lowercase,
+    Trim,
+    Base64Encode,
+    Base64Decode,
+    JsonPretty,
+    XmlPretty,
+    UrlDecode,
+    /// Remove tracking parameters (`tracking_params` of the config) from a link
+    CleanUrl,
+}
+
+impl TextTransform {
+    pub const ALL: [Self; 9] = [
+        Self::Uppercase,
+        Self::Lowercase,
+        Self::Trim,
+        Self::Base64Encode,
+        Self::Base64Decode,
+        Self::JsonPretty,
+        Self::XmlPretty,
+        Self::UrlDecode,
+        Self::CleanUrl,
+    ];
+
+    /// Menu label, in the user's language
+    pub fn label(self) -> String {
+        match self {
+            Self::Uppercase => gettext("UPPERCASE"),
+            Self::Lowercase => gettext("lowercase"),
+            Self::Trim => gettext("Trim whitespace"),
+            Self::Base64Encode => gettext("Base64 encode"),
+            Self::Base64Decode => gettext("Base64 decode"),
+            Self::JsonPretty => gettext("Pretty-print JSON"),
+            Self::XmlPretty => gettext("Pretty-print XML"),
+            Self::UrlDecode => gettext("URL decode"),
+            Self::CleanUrl => gettext("Remove tracking parameters"),
+        }
+    }
+
+    /// Stable name, e.g. for GTK action targets
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Uppercase => "uppercase",
+            Self::Lowercase => "lowercase",
+            Self::Trim => "trim",
+            Self::Base64Encode => "base64-encode",
+            Self::Base64Decode => "base64-decode",
+            Self::JsonPretty => "json-pretty",
+            Self::XmlPretty => "xml-pretty",
+            Self::UrlDecode => "url-decode",
+            Self::CleanUrl => "clean-url",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|op| op.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FrontendMessage {
+    /// First message of a connection: the client's protocol version and capabilities,
+    /// and the wire format it wants to switch to after the handshake
+    Hello {
+        protocol_version: u32,
+        capabilities: Vec<String>,
+        #[serde(default)]
+        wire_format: WireFormat,
+    },
     /// Request clipboard history
     GetHistory,
-    /// Set clipboard content by ID
-    SetClipboardById { id: u64 },
-    /// Clear all clipboard history
+    /// Request a window of the history (newest first, or in `sort` order which needs
+    /// `CAP_SORT_MODES`), for lazy loading while scrolling
+    GetHistoryPage {
+        offset: u32,
+        limit: u32,
+        #[serde(default)]
+        sort: SortMode,
+    },
+    /// Set clipboard content by ID, on the named seat (needs `CAP_SEATS`) or by default the seat
+    /// the item was copied on
+    SetClipboardById {
+        id: u64,
+        #[serde(default)]
+        seat: Option<String>,
+        /// Serve the text as copied instead of the cleaned-up one (needs `CAP_ORIGINAL_PAYLOADS`)
+        #[serde(default)]
+        original: bool,
+    },
+    /// Set the clipboard to a single MIME type of an item (needs `CAP_SINGLE_MIME`), answered
+    /// with `ClipboardSet`
+    SetClipboardByIdMime { id: u64, mime: String },
+    /// Clear all clipboard history (restorable with `UndoClear` for `UNDO_WINDOW_SECS`)
     ClearHistory,
+    /// Delete one item (restorable with `UndoDelete` for `UNDO_WINDOW_SECS`), answered with `ItemDeleted`
+    DeleteItem { id: u64 },
+    /// Restore a deleted item, answered with `Restored`
+    UndoDelete { id: u64 },
+    /// Restore the items removed by the most recent `ClearHistory`, answered with `Restored`
+    UndoClear,
+    /// Move an item to `position` (0 is the top) of the history, answered with `ItemMoved`.
+    /// The item is stamped to sort in there, clients get it again with a `NewItem` push.
+    MoveItem { id: u64, position: u32 },
+    /// Request the full payloads of an item in all its MIME types, e.g. to drag it into another
+    /// application, answered with `ItemData`
+    GetItemData { id: u64 },
+    /// Request a PNG thumbnail of an image item fitting into a max_px square
+    GetThumbnail { id: u64, max_px: u32 },
+    /// Request a summary of the history
+    GetStats,
+    /// Stop recording new selections until `ResumeCapture`, answered with `CaptureState`
+    PauseCapture,
+    ResumeCapture,
+    /// Request whether capture is paused
+    GetCaptureState,
+    /// Request the state of history sync
+    GetSyncStatus,
+    /// Request the state of the clipboard monitoring, answered with `Status`
+    GetStatus,
+    /// Check that the backend is alive, answered with `Pong`
+    Ping,
+    /// Request the daemon's version, answered with `Version`
+    GetVersion,
+    /// Replace the daemon's log filter (RUST_LOG syntax, e.g. `debug` or
+    /// `cursor_clip::backend::wayland_clipboard=trace`), answered with `LogLevel`
+    SetLogLevel { filter: String },
+    /// Request capture and IPC counters and latencies, answered with `Metrics`
+    GetMetrics,
+    /// Request the reachable phones, answered with `PhoneList`
+    ListPhones,
+    /// Send the text of an item to the phone with the given id or name (every reachable phone if
+    /// None), answered with `PhonesReached`
+    SendToPhone { id: u64, device: Option<String> },
+    /// Ask a phone for its clipboard, which arrives as a new item from that phone. Answered with
+    /// `PhonesReached`.
+    PullFromPhone { device: Option<String> },
+    /// Add the text recognized in an image item as a new text item and make it the clipboard content
+    CopyOcrText { id: u64 },
+    /// Add the text of an item run through `op` as a new item and make it the clipboard content
+    TransformItem { id: u64, op: TextTransform },
+    /// Request a window of the items matching a query (see `ClipboardItemPreview::matches`),
+    /// answered with a `HistoryPage` whose total counts the matching items. With a `tag` (needs
+    /// `CAP_TAGS`), only items carrying it match.
+    SearchHistory {
+        query: String,
+        content_types: Vec<ClipboardContentType>,
+        offset: u32,
+        limit: u32,
+        #[serde(default)]
+        sort: SortMode,
+        #[serde(default)]
+        tag: Option<String>,
+    },
+    /// Attach a tag to an item, answered with `ItemTags`
+    AddTag { id: u64, tag: String },
+    /// Remove a tag from an item, answered with `ItemTags`
+    RemoveTag { id: u64, tag: String },
+    /// Request the tags used in the history, answered with `TagList`
+    ListTags,
+    /// Set the next item of the copy stack as the clipboard content, answered with `StackPopped`.
+    /// The first pop after a new copy puts the `depth` most recent items (the daemon's
+    /// `copy_stack_depth` if None) on the stack, following pops go through them in copy order.
+    PopAndPaste { depth: Option<u32> },
+    /// Change the options of the preferences dialog and write them to the config file, answered
+    /// with `ConfigApplied`. Only accepted from the daemon's own user. Other clients learn about
+    /// it from the `ConfigReloaded` push.
+    SetConfig { preferences: Preferences },
+    /// Name this connection and choose the pushes it gets, answered with `Identified`
+    Identify {
+        name: String,
+        #[serde(default)]
+        filter: PushFilter,
+    },
+    /// Request the connected clients, answered with `ClientList`
+    ListClients,
+    /// Only get pushes of these kinds on this connection from now on (every kind until then),
+    /// answered with `Subscribed`
+    Subscribe { events: Vec<PushEvent> },
+    /// Request the items copied from `from` until before `to` (Unix time in milliseconds), newest
+    /// first, answered with `HistoryRange`
+    GetHistoryRange { from: u64, to: u64 },
+    /// Add an item with these payloads to the history as if it had been copied, e.g. a
+    /// screenshot, and set it as the clipboard content if `set_clipboard`. Answered with `ItemAdded`.
+    AddItem {
+        mime_data: IndexMap<String, Bytes>,
+        #[serde(default)]
+        set_clipboard: bool,
+    },
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl FrontendMessage {
+    /// Name of the request without its fields, for logs
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Hello { .. } => "Hello",
+            Self::GetHistory => "GetHistory",
+            Self::GetHistoryPage { .. } => "GetHistoryPage",
+            Self::SetClipboardById { .. } => "SetClipboardById",
+            Self::SetClipboardByIdMime { .. } => "SetClipboardByIdMime",
+            Self::ClearHistory => "ClearHistory",
+            Self::DeleteItem { .. } => "DeleteItem",
+            Self::UndoDelete { .. } => "UndoDelete",
+            Self::UndoClear => "UndoClear",
+            Self::MoveItem { .. } => "MoveItem",
+            Self::GetItemData { .. } => "GetItemData",
+            Self::GetThumbnail { .. } => "GetThumbnail",
+            Self::GetStats => "GetStats",
+            Self::PauseCapture => "PauseCapture",
+            Self::ResumeCapture => "ResumeCapture",
+            Self::GetCaptureState => "GetCaptureState",
+            Self::GetSyncStatus => "GetSyncStatus",
+            Self::GetStatus => "GetStatus",
+            Self::Ping => "Ping",
+            Self::GetVersion => "GetVersion",
+            Self::SetLogLevel { .. } => "SetLogLevel",
+            Self::GetMetrics => "GetMetrics",
+            Self::ListPhones => "ListPhones",
+            Self::SendToPhone { .. } => "SendToPhone",
+            Self::PullFromPhone { .. } => "PullFromPhone",
+            Self::CopyOcrText { .. } => "CopyOcrText",
+            Self::TransformItem { .. } => "TransformItem",
+            Self::SearchHistory { .. } => "SearchHistory",
+            Self::AddTag { .. } => "AddTag",
+            Self::RemoveTag { .. } => "RemoveTag",
+            Self::ListTags => "ListTags",
+            Self::PopAndPaste { .. } => "PopAndPaste",
+            Self::SetConfig { .. } => "SetConfig",
+            Self::Identify { .. } => "Identify",
+            Self::ListClients => "ListClients",
+            Self::Subscribe { .. } => "Subscribe",
+            Self::AddItem { .. } => "AddItem",
+            Self::GetHistoryRange { .. } => "GetHistoryRange",
+        }
+    }
+
+    /// Whether sending the request twice has the same effect as sending it once: it only reads,
+    /// or sets a state to a given value
+    pub const fn is_idempotent(&self) -> bool {
+        matches!(
+            self,
+            Self::Hello { .. }
+                | Self::GetHistory
+                | Self::GetHistoryPage { .. }
+                | Self::GetItemData { .. }
+                | Self::GetThumbnail { .. }
+                | Self::GetStats
+                | Self::PauseCapture
+                | Self::ResumeCapture
+                | Self::GetCaptureState
+                | Self::GetSyncStatus
+                | Self::GetStatus
+                | Self::Ping
+                | Self::GetVersion
+                | Self::SetLogLevel { .. }
+                | Self::GetMetrics
+                | Self::ListPhones
+                | Self::SearchHistory { .. }
+                | Self::ListTags
+                | Self::SetConfig { .. }
+                | Self::Identify { .. }
+                | Self::ListClients
+                | Self::Subscribe { .. }
+                | Self::GetHistoryRange { .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum BackendMessage {
+    /// Answer to `Hello` with the backend's protocol version and capabilities, and the wire
+    /// format both sides use for every following message
+    Hello {
+        protocol_version: u32,
+        capabilities: Vec<String>,
+        #[serde(default)]
+        wire_format: WireFormat,
+    },
     /// Response with clipboard history (previews only, no mime payloads)
     History { items: Vec<ClipboardItemPreview> },
+    /// Response with one window of the history and the total number of items
+    HistoryPage { offset: u32, items: Vec<ClipboardItemPreview>, total: u32 },
     /// New clipboard item added (preview only)
     NewItem { item: ClipboardItemPreview },
+    /// Several items added at once by a bulk operation (newest first)
+    ItemsAdded { items: Vec<ClipboardItemPreview> },
     /// Clipboard content set successfully
     ClipboardSet,
     /// History cleared
     HistoryCleared,
+    /// Item moved to the trash
+    ItemDeleted { id: u64 },
+    /// Ids of the items brought back by an undo (items copied again meanwhile are not restored)
+    Restored { ids: Vec<u64> },
+    /// Position the item ended up at
+    ItemMoved { id: u64, position: u32 },
+    /// Tags of an item after adding or removing one
+    ItemTags { id: u64, tags: Vec<String> },
+    /// Tags used in the history, sorted by name
+    TagList { tags: Vec<String> },
+    /// Item set as the clipboard content by `PopAndPaste` and the number of items left on the stack
+    StackPopped { item: ClipboardItemPreview, remaining: u32 },
+    /// Config now in effect in the daemon
+    ConfigApplied { config: Config },
+    /// Payloads of an item by MIME type, in the order they were offered
+    ItemData { id: u64, mime_data: IndexMap<String, Bytes> },
+    /// PNG thumbnail of an image item
+    Thumbnail { id: u64, max_px: u32, png: Bytes },
+    /// Summary of the history
+    Stats { stats: HistoryStats },
+    /// Whether recording of new selections is paused
+    CaptureState { paused: bool },
+    /// Push: capture was paused or resumed
+    CaptureStateChanged { paused: bool },
+    /// State of history sync
+    SyncStatus { status: SyncStatus },
+    /// State of the clipboard monitoring
+    Status { status: MonitorStatus },
+    /// Push: the clipboard monitoring started, failed or recovered
+    StatusChanged { status: MonitorStatus },
+    /// Answer to `Ping`
+    Pong { health: Health },
+    /// Version of the daemon (its package version) and of the protocol it speaks
+    Version { version: String, protocol_version: u32 },
+    /// Log filter now in effect in the daemon
+    LogLevel { filter: String },
+    /// Capture and IPC counters and latencies since the daemon started
+    Metrics { metrics: Metrics },
+    /// Reachable phones
+    PhoneList { devices: Vec<PhoneDevice> },
+    /// Names of the phones an item was sent to, or that were asked for their clipboard
+    PhonesReached { devices: Vec<String> },
+    /// Id the backend gave the connection
+    Identified { client_id: u64 },
+    /// Connected clients, oldest connection first
+    ClientList { clients: Vec<ClientInfo> },
+    /// Push kinds the connection is subscribed to now
+    Subscribed { events: Vec<PushEvent> },
+    /// Id of the item added by `AddItem`
+    ItemAdded { id: u64 },
+    /// Items copied within the requested time range, newest first
+    HistoryRange { from: u64, to: u64, items: Vec<ClipboardItemPreview> },
+    /// Push: another client took over the selection we were serving
+    SelectionTakenByExternal,
+    /// Push: the daemon picked up a changed config file, clients re-read the settings they use
+    ConfigReloaded,
+    /// Push: the backend changed an item after adding it (fetched page title, recognized text,
+    /// pasted again)
+    ItemUpdated { item: ClipboardItemPreview },
+    /// Push: items left the history (replaced by a re-copy, evicted, or the history was cleared)
+    ItemsRemoved { ids: Vec<u64> },
+    /// Push: the client fell behind reading and `count` pushes were left out, it should reload
+    /// what it shows from the backend
+    PushesDropped { count: u64 },
     /// Error occurred
-    Error { message: String },
+    Error {
+        code: ErrorCode,
+        message: String,
+        #[serde(default)]
+        details: Option<String>,
+    },
+}
+
+/// Machine-readable reason of a `BackendMessage::Error`, so clients can react to specific failures
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorCode {
+    /// The referenced item is not (or no longer) in the history
+    NotFound,
+    /// No Wayland data control protocol is (yet) available to set the selection
+    WaylandUnavailable,
+    /// The backend cannot handle the request right now, retrying later may work
+    Busy,
+    /// The request could not be parsed or does not apply to the referenced item
+    InvalidRequest,
+    /// The data is too large to be handled
+    TooLarge,
+    /// Unexpected failure in the backend
+    Internal,
+}
+
+/// Failure of a request to the backend, sent to clients as `BackendMessage::Error`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub details: Option<String>,
+}
+
+impl BackendError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), details: None }
+    }
+
+    pub fn with_details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+}
+
+impl std::fmt::Display for BackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.details {
+            Some(details) => write!(f, "{} ({details})", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+impl From<BackendError> for BackendMessage {
+    fn from(error: BackendError) -> Self {
+        Self::Error { code: error.code, message: error.message, details: error.details }
+    }
+}
+
+impl BackendMessage {
+    /// Capability a client must have announced to be sent this message (None: basic message set)
+    pub const fn required_capability(&self) -> Option<&'static str> {
+        match self {
+            Self::HistoryPage { .. } => Some(protocol::CAP_HISTORY_PAGING),
+            Self::Thumbnail { .. } => Some(protocol::CAP_THUMBNAILS),
+            Self::ItemsAdded { .. } => Some(protocol::CAP_PUSH_ITEMS_ADDED),
+            Self::ItemsRemoved { .. } => Some(protocol::CAP_PUSH_ITEMS_REMOVED),
+            Self::ItemUpdated { .. } => Some(protocol::CAP_PUSH_ITEM_UPDATED),
+            Self::CaptureState { .. } | Self::CaptureStateChanged { .. } => Some(protocol::CAP_CAPTURE_PAUSE),
+            Self::SyncStatus { .. } => Some(protocol::CAP_SYNC),
+            Self::Status { .. } | Self::StatusChanged { .. } => Some(protocol::CAP_MONITOR_STATUS),
+            Self::Pong { .. } => Some(protocol::CAP_PING),
+            Self::Version { .. } => Some(protocol::CAP_VERSION),
+            Self::LogLevel { .. } => Some(protocol::CAP_LOG_LEVEL),
+            Self::Metrics { .. } => Some(protocol::CAP_METRICS),
+            Self::ItemDeleted { .. } | Self::Restored { .. } => Some(protocol::CAP_UNDO),
+            Self::ItemMoved { .. } => Some(protocol::CAP_MOVE_ITEMS),
+            Self::ItemTags { .. } | Self::TagList { .. } => Some(protocol::CAP_TAGS),
+            Self::StackPopped { .. } => Some(protocol::CAP_COPY_STACK),
+            Self::ConfigApplied { .. } => Some(protocol::CAP_SET_CONFIG),
+            Self::Identified { .. } | Self::ClientList { .. } => Some(protocol::CAP_CLIENTS),
+            Self::Subscribed { .. } => Some(protocol::CAP_SUBSCRIBE),
+            Self::ItemAdded { .. } => Some(protocol::CAP_ADD_ITEM),
+            Self::HistoryRange { .. } => Some(protocol::CAP_HISTORY_RANGE),
+            Self::PushesDropped { .. } => Some(protocol::CAP_PUSH_PUSHES_DROPPED),
+            Self::ItemData { .. } => Some(protocol::CAP_ITEM_DATA),
+            Self::PhoneList { .. } | Self::PhonesReached { .. } => Some(protocol::CAP_PHONE),
+            Self::SelectionTakenByExternal => Some(protocol::CAP_PUSH_SELECTION_TAKEN),
+            Self::ConfigReloaded => Some(protocol::CAP_PUSH_CONFIG_RELOADED),
+            _ => None,
+        }
+    }
+
+    /// Pushes are broadcast unsolicited to all clients, everything else answers a request
+    pub const fn is_push(&self) -> bool {
+        self.push_event().is_some()
+    }
+
+    /// Kind of a push message, None for answers to requests
+    pub const fn push_event(&self) -> Option<PushEvent> {
+        match self {
+            Self::NewItem { .. } => Some(PushEvent::NewItem),
+            Self::ItemsAdded { .. } => Some(PushEvent::ItemsAdded),
+            Self::ItemUpdated { .. } => Some(PushEvent::ItemUpdated),
+            Self::ItemsRemoved { .. } => Some(PushEvent::ItemsRemoved),
+            Self::CaptureStateChanged { .. } => Some(PushEvent::CaptureStateChanged),
+            Self::StatusChanged { .. } => Some(PushEvent::StatusChanged),
+            Self::SelectionTakenByExternal => Some(PushEvent::SelectionTakenByExternal),
+            Self::ConfigReloaded => Some(PushEvent::ConfigReloaded),
+            Self::PushesDropped { .. } => Some(PushEvent::PushesDropped),
+            _ => None,
+        }
+    }
 }
 
 impl ClipboardContentType {
+    pub const ALL: [Self; 7] = [Self::Text, Self::Url, Self::Code, Self::Password, Self::File, Self::Image, Self::Other];
+
+    /// Content type by its `as_str` name, ignoring case (e.g. `cursor-clip history --type url`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|content_type| content_type.as_str().eq_ignore_ascii_case(name))
+    }
+
     pub fn type_from_preview(content: &str) -> Self {
         const PASSWORD_SPECIALS: &str = "!@#$%^&*()-_=+[]{};:,.<>?/\\|`~";
         if content.starts_with("http://") || content.starts_with("https://") {
@@ -82,7 +1038,7 @@ impl ClipboardContentType {
         }
     }
 
-    // Return a static string representation of the content type (future multi-language support)
+    // Stable English name, also matched by `from_name` and passed to hooks; see `label` for display
     pub const fn as_str(self) -> &'static str {
         match self {
             // Return capitalized labels directly so callers don't need to post-process
@@ -96,6 +1052,19 @@ impl ClipboardContentType {
         }
     }
 
+    /// Name of the content type for display, in the user's language
+    pub fn label(self) -> String {
+        match self {
+            Self::Text => gettext("Text"),
+            Self::Url => gettext("Url"),
+            Self::Code => gettext("Code"),
+            Self::Password => gettext("Password"),
+            Self::File => gettext("File"),
+            Self::Image => gettext("Image"),
+            Self::Other => gettext("Other"),
+        }
+    }
+
     pub const fn icon(self) -> &'static str {
         match self {
             Self::Text => "📝",
@@ -0,0 +1,85 @@
+//! Human-readable formatting shared by the overlay and the command line
+
+use super::{ClipboardContentType, ClipboardItemPreview};
+use super::i18n::{gettext, trn};
+
+/// Shown instead of passwords on the command line
+pub const MASK: &str = "••••••••";
+
+/// Format Unix timestamp to relative time string in the user's language
+pub fn format_relative_time(timestamp: u64) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    
+    let diff = now.saturating_sub(timestamp);
+    
+    if diff < 30 {
+        gettext("Just now")
+    } else if diff < 3600 {
+        trn("{} minute ago", "{} minutes ago", diff / 60)
+    } else if diff < 86400 {
+        trn("{} hour ago", "{} hours ago", diff / 3600)
+    } else {
+        trn("{} day ago", "{} days ago", diff / 86400)
+    }
+}
+
+/// Format a byte count with a binary unit, e.g. "1.5 MiB"
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// First non-empty line of an item's preview cut to `max_chars`, with … if anything was left out
+/// and passwords masked, for one-line listings on the command line
+pub fn preview_line(item: &ClipboardItemPreview, max_chars: usize) -> String {
+    if item.content_type == ClipboardContentType::Password {
+        return MASK.to_string();
+    }
+    let first_line = item.content_preview.lines().find(|line| !line.trim().is_empty()).unwrap_or_default().trim();
+    let mut line: String = first_line.chars().take(max_chars).collect();
+    if first_line.chars().count() > max_chars || item.content_preview.trim().lines().count() > 1 {
+        line.push('…');
+    }
+    line
+}
+
+/// Parse a duration like "90s", "30m", "2h" or "7d" into seconds (a bare number counts as seconds)
+pub fn parse_duration(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let (number, unit) = text.split_at(text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len()));
+    let factor = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(factor)
+}
+
+/// Format a duration in seconds with its two largest units, e.g. "3h 12m"
+pub fn format_duration(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else if minutes > 0 {
+        format!("{minutes}m {}s", secs % 60)
+    } else {
+        format!("{secs}s")
+    }
+}
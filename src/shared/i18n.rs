@@ -0,0 +1,39 @@
+//! Translations of user-visible strings through gettext. The English strings are the message
+//! ids, without an installed catalog for the user's locale they are shown unchanged.
+//! `po/` has the list of translated sources and the translations themselves.
+use gettextrs::LocaleCategory;
+use tracing::debug;
+
+pub use gettextrs::{gettext, ngettext};
+
+/// Text domain of the catalogs, installed as `<locale dir>/<lang>/LC_MESSAGES/cursor-clip.mo`
+pub const GETTEXT_PACKAGE: &str = "cursor-clip";
+
+/// Where the catalogs are installed, set at build time for other prefixes
+const LOCALEDIR: &str = match option_env!("CURSOR_CLIP_LOCALEDIR") {
+    Some(dir) => dir,
+    None => "/usr/share/locale",
+};
+
+/// Pick up the locale from the environment and load the catalogs, before any string is shown
+pub fn init() {
+    gettextrs::setlocale(LocaleCategory::LcAll, "");
+    let dir = std::env::var("CURSOR_CLIP_LOCALEDIR").unwrap_or_else(|_| LOCALEDIR.to_string());
+    if let Err(e) = gettextrs::bindtextdomain(GETTEXT_PACKAGE, dir)
+        .and_then(|_| gettextrs::bind_textdomain_codeset(GETTEXT_PACKAGE, "UTF-8"))
+        .and_then(|_| gettextrs::textdomain(GETTEXT_PACKAGE))
+    {
+        debug!("Translations unavailable: {e}");
+    }
+}
+
+/// Translate a message with one `{}` placeholder, e.g. `trf("Copied in {}", &name)`
+pub fn trf(msgid: &str, arg: &str) -> String {
+    gettext(msgid).replacen("{}", arg, 1)
+}
+
+/// Translate a message for a count, picking the plural form of the user's language, and put
+/// the count in place of its `{}`
+pub fn trn(singular: &str, plural: &str, n: u64) -> String {
+    ngettext(singular, plural, n.min(u32::MAX as u64) as u32).replacen("{}", &n.to_string(), 1)
+}
@@ -0,0 +1,31 @@
+// Log output of both the daemon and the overlay. Targets follow the module paths, so a subsystem
+// can be made verbose on its own, e.g. `cursor_clip::backend::wayland_clipboard=debug`.
+
+use std::sync::OnceLock;
+use tracing_subscriber::{EnvFilter, Registry, fmt, prelude::*, reload};
+
+/// Filter used when RUST_LOG is not set
+pub const DEFAULT_FILTER: &str = "info";
+
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Log to stderr, filtered by RUST_LOG (default `info`). The filter can be replaced later with
+/// `set_filter`; records of crates using `log` are picked up as well.
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let (filter, handle) = reload::Layer::new(filter);
+    let subscriber = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(std::io::stderr));
+    if subscriber.try_init().is_ok() {
+        let _ = FILTER_HANDLE.set(handle);
+    }
+}
+
+/// Replace the active filter with `directives` (RUST_LOG syntax), returns the filter now in effect
+pub fn set_filter(directives: &str) -> Result<String, String> {
+    let filter = EnvFilter::try_new(directives).map_err(|e| e.to_string())?;
+    let handle = FILTER_HANDLE.get().ok_or("Logging is not initialized")?;
+    handle.reload(filter).map_err(|e| e.to_string())?;
+    handle.with_current(ToString::to_string).map_err(|e| e.to_string())
+}
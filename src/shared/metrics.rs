@@ -0,0 +1,229 @@
+//! Counters and latency histograms kept by the daemon, answered to `GetMetrics` and served in
+//! the Prometheus text format if `metrics_port` is configured
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+use super::format::format_bytes;
+use super::i18n::{gettext, trf};
+
+/// Upper bounds (in milliseconds) of the latency histogram buckets, plus an implicit +Inf bucket
+pub const LATENCY_BUCKETS_MS: [u64; 10] = [1, 5, 10, 25, 50, 100, 250, 500, 1000, 5000];
+
+/// MIME types counted on their own in `Metrics::bytes_read`. Others add up under `OTHER_MIME`,
+/// so apps offering made-up types cannot grow the map (and the scraped label set) without bound.
+const TRACKED_MIME_TYPES: [&str; 13] = [
+    "text/plain",
+    "text/plain;charset=utf-8",
+    "UTF8_STRING",
+    "text/html",
+    "text/rtf",
+    "text/uri-list",
+    "x-special/gnome-copied-files",
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/bmp",
+    "image/svg+xml",
+];
+pub const OTHER_MIME: &str = "other";
+
+/// Distribution of the durations of one operation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    pub count: u64,
+    pub sum_us: u64,
+    pub max_us: u64,
+    /// Observations per bucket of `LATENCY_BUCKETS_MS` (not cumulative), the last one is +Inf
+    pub buckets: Vec<u64>,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, elapsed: Duration) {
+        let us = elapsed.as_micros() as u64;
+        self.count += 1;
+        self.sum_us += us;
+        self.max_us = self.max_us.max(us);
+        self.buckets.resize(LATENCY_BUCKETS_MS.len() + 1, 0);
+        let bucket = LATENCY_BUCKETS_MS.iter().position(|bound| us <= bound * 1000).unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[bucket] += 1;
+    }
+
+    pub fn mean_ms(&self) -> f64 {
+        if self.count == 0 { 0.0 } else { self.sum_us as f64 / self.count as f64 / 1000.0 }
+    }
+
+    /// Short summary for display, e.g. "12 × mean 3.1 ms, max 40.2 ms"
+    pub fn summary(&self) -> String {
+        gettext("{} × mean {} ms, max {} ms")
+            .replacen("{}", &self.count.to_string(), 1)
+            .replacen("{}", &format!("{:.1}", self.mean_ms()), 1)
+            .replacen("{}", &format!("{:.1}", self.max_us as f64 / 1000.0), 1)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Metrics {
+    /// Selections recorded as new items since the daemon started
+    pub items_captured: u64,
+    /// Selections ignored as a repeat of the previous one (`coalesce_window_ms`)
+    #[serde(default)]
+    pub captures_coalesced: u64,
+    /// Selections over `max_captures_per_sec`, read later or replaced by a newer one
+    #[serde(default)]
+    pub captures_throttled: u64,
+    /// Bytes read from clipboard sources per MIME type, uncommon types as `OTHER_MIME`
+    pub bytes_read: BTreeMap<String, u64>,
+    /// From the compositor announcing a selection to the item being stored
+    pub capture_latency: LatencyHistogram,
+    /// Time to answer IPC requests, per request name
+    pub ipc_latency: BTreeMap<String, LatencyHistogram>,
+    /// Size of all stored MIME payloads
+    pub history_bytes: u64,
+    /// Resident memory of the daemon, where the platform reports it
+    pub resident_bytes: Option<u64>,
+}
+
+impl Metrics {
+    pub fn record_read(&mut self, mime: &str, bytes: usize) {
+        let mime = if TRACKED_MIME_TYPES.contains(&mime) { mime } else { OTHER_MIME };
+        *self.bytes_read.entry(mime.to_string()).or_default() += bytes as u64;
+    }
+
+    pub fn record_capture(&mut self, elapsed: Duration) {
+        self.items_captured += 1;
+        self.capture_latency.record(elapsed);
+    }
+
+    pub fn record_request(&mut self, request: &str, elapsed: Duration) {
+        self.ipc_latency.entry(request.to_string()).or_default().record(elapsed);
+    }
+
+    /// Label/value rows for display
+    pub fn rows(&self) -> Vec<(String, String)> {
+        let mut rows = vec![
+            (gettext("Captured"), self.items_captured.to_string()),
+            (gettext("Repeats ignored"), self.captures_coalesced.to_string()),
+            (gettext("Throttled"), self.captures_throttled.to_string()),
+            (gettext("Capture time"), self.capture_latency.summary()),
+            (gettext("Stored data"), format_bytes(self.history_bytes)),
+        ];
+        if let Some(resident) = self.resident_bytes {
+            rows.push((gettext("Memory"), format_bytes(resident)));
+        }
+        for (mime, bytes) in &self.bytes_read {
+            let mime = if mime == OTHER_MIME { gettext("other types") } else { mime.clone() };
+            rows.push((trf("Read {}", &mime), format_bytes(*bytes)));
+        }
+        for (request, latency) in &self.ipc_latency {
+            rows.push((request.clone(), latency.summary()));
+        }
+        rows
+    }
+
+    /// Prometheus text exposition format (version 0.0.4)
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# HELP cursor_clip_items_captured_total Selections recorded as new items.");
+        let _ = writeln!(out, "# TYPE cursor_clip_items_captured_total counter");
+        let _ = writeln!(out, "cursor_clip_items_captured_total {}", self.items_captured);
+
+        let _ = writeln!(out, "# HELP cursor_clip_captures_coalesced_total Selections ignored as a repeat of the previous one.");
+        let _ = writeln!(out, "# TYPE cursor_clip_captures_coalesced_total counter");
+        let _ = writeln!(out, "cursor_clip_captures_coalesced_total {}", self.captures_coalesced);
+
+        let _ = writeln!(out, "# HELP cursor_clip_captures_throttled_total Selections delayed or skipped because of the capture rate limit.");
+        let _ = writeln!(out, "# TYPE cursor_clip_captures_throttled_total counter");
+        let _ = writeln!(out, "cursor_clip_captures_throttled_total {}", self.captures_throttled);
+
+        let _ = writeln!(out, "# HELP cursor_clip_bytes_read_total Bytes read from clipboard sources.");
+        let _ = writeln!(out, "# TYPE cursor_clip_bytes_read_total counter");
+        for (mime, bytes) in &self.bytes_read {
+            let _ = writeln!(out, "cursor_clip_bytes_read_total{{mime=\"{}\"}} {bytes}", escape_label(mime));
+        }
+
+        let _ = writeln!(out, "# HELP cursor_clip_capture_latency_seconds Time from a new selection to the stored item.");
+        let _ = writeln!(out, "# TYPE cursor_clip_capture_latency_seconds histogram");
+        write_histogram(&mut out, "cursor_clip_capture_latency_seconds", "", &self.capture_latency);
+
+        let _ = writeln!(out, "# HELP cursor_clip_ipc_request_latency_seconds Time to answer IPC requests.");
+        let _ = writeln!(out, "# TYPE cursor_clip_ipc_request_latency_seconds histogram");
+        for (request, latency) in &self.ipc_latency {
+            write_histogram(&mut out, "cursor_clip_ipc_request_latency_seconds", &format!("request=\"{}\"", escape_label(request)), latency);
+        }
+
+        let _ = writeln!(out, "# HELP cursor_clip_history_bytes Size of all stored clipboard payloads.");
+        let _ = writeln!(out, "# TYPE cursor_clip_history_bytes gauge");
+        let _ = writeln!(out, "cursor_clip_history_bytes {}", self.history_bytes);
+        if let Some(resident) = self.resident_bytes {
+            let _ = writeln!(out, "# HELP cursor_clip_resident_memory_bytes Resident memory of the daemon.");
+            let _ = writeln!(out, "# TYPE cursor_clip_resident_memory_bytes gauge");
+            let _ = writeln!(out, "cursor_clip_resident_memory_bytes {resident}");
+        }
+        out
+    }
+}
+
+fn write_histogram(out: &mut String, name: &str, labels: &str, histogram: &LatencyHistogram) {
+    let separator = if labels.is_empty() { "" } else { "," };
+    let mut cumulative = 0;
+    for (i, bound) in LATENCY_BUCKETS_MS.iter().enumerate() {
+        cumulative += histogram.buckets.get(i).copied().unwrap_or(0);
+        let _ = writeln!(out, "{name}_bucket{{{labels}{separator}le=\"{}\"}} {cumulative}", *bound as f64 / 1000.0);
+    }
+    let _ = writeln!(out, "{name}_bucket{{{labels}{separator}le=\"+Inf\"}} {}", histogram.count);
+    let braces = if labels.is_empty() { String::new() } else { format!("{{{labels}}}") };
+    let _ = writeln!(out, "{name}_sum{braces} {}", histogram.sum_us as f64 / 1_000_000.0);
+    let _ = writeln!(out, "{name}_count{braces} {}", histogram.count);
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uncommon_mime_types_are_counted_together() {
+        let mut metrics = Metrics::default();
+        metrics.record_read("text/plain", 10);
+        metrics.record_read("application/x-made-up-1", 5);
+        metrics.record_read("application/x-made-up-2", 7);
+        assert_eq!(metrics.bytes_read, BTreeMap::from([("other".to_string(), 12), ("text/plain".to_string(), 10)]));
+    }
+
+    #[test]
+    fn metrics_are_exposed_in_the_prometheus_format() {
+        let mut metrics = Metrics { captures_throttled: 2, history_bytes: 4096, ..Metrics::default() };
+        metrics.record_read("text/plain", 10);
+        metrics.record_capture(Duration::from_millis(3));
+        metrics.record_capture(Duration::from_millis(700));
+        metrics.record_request("Get\"Items", Duration::from_micros(500));
+        let text = metrics.to_prometheus();
+        let lines: Vec<&str> = text.lines().collect();
+
+        for line in [
+            "cursor_clip_items_captured_total 2",
+            "cursor_clip_captures_throttled_total 2",
+            "cursor_clip_bytes_read_total{mime=\"text/plain\"} 10",
+            "cursor_clip_capture_latency_seconds_bucket{le=\"0.001\"} 0",
+            "cursor_clip_capture_latency_seconds_bucket{le=\"0.005\"} 1",
+            "cursor_clip_capture_latency_seconds_bucket{le=\"1\"} 2",
+            "cursor_clip_capture_latency_seconds_bucket{le=\"+Inf\"} 2",
+            "cursor_clip_capture_latency_seconds_sum 0.703",
+            "cursor_clip_capture_latency_seconds_count 2",
+            "cursor_clip_ipc_request_latency_seconds_bucket{request=\"Get\\\"Items\",le=\"0.001\"} 1",
+            "cursor_clip_ipc_request_latency_seconds_count{request=\"Get\\\"Items\"} 1",
+            "cursor_clip_history_bytes 4096",
+        ] {
+            assert!(lines.contains(&line), "missing {line} in\n{text}");
+        }
+        // Every sample has a TYPE line, the memory gauge only if known
+        assert_eq!(lines.iter().filter(|line| line.starts_with("# TYPE")).count(), 7);
+        assert!(!text.contains("resident"));
+    }
+}
@@ -1,3 +1,12 @@
 pub mod data_structures;
+pub mod config;
+pub mod protocol;
+pub mod wire;
+pub mod format;
+pub mod i18n;
+pub mod logging;
+pub mod metrics;
 
 pub use data_structures::*;
+pub use config::Config;
+pub use metrics::Metrics;
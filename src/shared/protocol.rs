@@ -0,0 +1,144 @@
+//! IPC protocol version and capabilities, exchanged with `Hello` when a client connects so
+//! frontends and backends of different versions only use messages the other side understands
+
+/// Version of the message set, bumped on incompatible changes
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Unix socket the daemon listens on
+pub const SOCKET_PATH: &str = "/tmp/cursor-clip.sock";
+
+/// `GetHistoryPage` requests (otherwise the whole history is fetched with `GetHistory`)
+pub const CAP_HISTORY_PAGING: &str = "history-paging";
+/// `GetThumbnail` requests for image items
+pub const CAP_THUMBNAILS: &str = "thumbnails";
+/// `ItemsAdded` pushes after bulk operations
+pub const CAP_PUSH_ITEMS_ADDED: &str = "push-items-added";
+/// `ItemsRemoved` pushes
+pub const CAP_PUSH_ITEMS_REMOVED: &str = "push-items-removed";
+/// `ItemUpdated` pushes when the backend changes an item (page title, recognized text, paste count)
+pub const CAP_PUSH_ITEM_UPDATED: &str = "push-item-updated";
+/// `ConfigReloaded` pushes after the daemon picked up a changed config file
+pub const CAP_PUSH_CONFIG_RELOADED: &str = "push-config-reloaded";
+/// `SelectionTakenByExternal` pushes
+pub const CAP_PUSH_SELECTION_TAKEN: &str = "push-selection-taken";
+/// `PushesDropped` push messages
+pub const CAP_PUSH_PUSHES_DROPPED: &str = "push-pushes-dropped";
+/// Error responses carry an `ErrorCode`
+pub const CAP_ERROR_CODES: &str = "error-codes";
+/// `GetStats` requests
+pub const CAP_STATS: &str = "stats";
+/// `SearchHistory` requests
+pub const CAP_SEARCH: &str = "search";
+/// `TransformItem` requests
+pub const CAP_TRANSFORMS: &str = "transforms";
+/// `GetSyncStatus` requests
+pub const CAP_SYNC: &str = "sync";
+/// `ListPhones`/`SendToPhone`/`PullFromPhone` requests (KDE Connect and GSConnect)
+pub const CAP_PHONE: &str = "phone";
+/// `seat` of `SetClipboardById`
+pub const CAP_SEATS: &str = "seats";
+/// `GetStatus` requests and `StatusChanged` pushes
+pub const CAP_MONITOR_STATUS: &str = "monitor-status";
+/// `Ping` requests
+pub const CAP_PING: &str = "ping";
+/// `GetVersion` requests
+pub const CAP_VERSION: &str = "version";
+/// `SetLogLevel` requests
+pub const CAP_LOG_LEVEL: &str = "log-level";
+/// `GetMetrics` requests
+pub const CAP_METRICS: &str = "metrics";
+/// `DeleteItem`, `UndoDelete` and `UndoClear` requests
+pub const CAP_UNDO: &str = "undo";
+/// `MoveItem` requests
+pub const CAP_MOVE_ITEMS: &str = "move-items";
+/// `GetItemData` requests
+pub const CAP_ITEM_DATA: &str = "item-data";
+/// `PauseCapture`/`ResumeCapture`/`GetCaptureState` requests and `CaptureStateChanged` pushes
+pub const CAP_CAPTURE_PAUSE: &str = "capture-pause";
+/// `sort` of `GetHistoryPage`/`SearchHistory`
+pub const CAP_SORT_MODES: &str = "sort-modes";
+/// `AddTag`/`RemoveTag`/`ListTags` requests and `tag` of `SearchHistory`
+pub const CAP_TAGS: &str = "tags";
+/// `PopAndPaste` requests
+pub const CAP_COPY_STACK: &str = "copy-stack";
+/// `SetConfig` requests
+pub const CAP_SET_CONFIG: &str = "set-config";
+/// `SetClipboardByIdMime` requests
+pub const CAP_SINGLE_MIME: &str = "single-mime";
+/// `original` of `SetClipboardById`
+pub const CAP_ORIGINAL_PAYLOADS: &str = "original-payloads";
+/// Length-prefixed MessagePack frames after the handshake (see `wire::WireFormat`)
+pub const CAP_MSGPACK_FRAMES: &str = "msgpack-frames";
+/// `Identify` and `ListClients` requests
+pub const CAP_CLIENTS: &str = "clients";
+/// `Subscribe` requests
+pub const CAP_SUBSCRIBE: &str = "subscribe";
+/// `AddItem` requests
+pub const CAP_ADD_ITEM: &str = "add-item";
+/// `GetHistoryRange` requests
+pub const CAP_HISTORY_RANGE: &str = "history-range";
+
+/// Everything this build supports, advertised by both the frontend and the backend
+pub const CAPABILITIES: &[&str] = &[
+    CAP_HISTORY_PAGING,
+    CAP_THUMBNAILS,
+    CAP_PUSH_ITEMS_ADDED,
+    CAP_PUSH_ITEMS_REMOVED,
+    CAP_PUSH_ITEM_UPDATED,
+    CAP_PUSH_SELECTION_TAKEN,
+    CAP_PUSH_CONFIG_RELOADED,
+    CAP_ERROR_CODES,
+    CAP_MSGPACK_FRAMES,
+    CAP_STATS,
+    CAP_SEARCH,
+    CAP_TRANSFORMS,
+    CAP_CAPTURE_PAUSE,
+    CAP_SYNC,
+    CAP_PHONE,
+    CAP_SEATS,
+    CAP_MONITOR_STATUS,
+    CAP_PING,
+    CAP_VERSION,
+    CAP_LOG_LEVEL,
+    CAP_METRICS,
+    CAP_UNDO,
+    CAP_MOVE_ITEMS,
+    CAP_ITEM_DATA,
+    CAP_SORT_MODES,
+    CAP_TAGS,
+    CAP_COPY_STACK,
+    CAP_SET_CONFIG,
+    CAP_SINGLE_MIME,
+    CAP_ORIGINAL_PAYLOADS,
+    CAP_CLIENTS,
+    CAP_SUBSCRIBE,
+    CAP_PUSH_PUSHES_DROPPED,
+    CAP_ADD_ITEM,
+    CAP_HISTORY_RANGE,
+];
+
+/// How long deleted items and a cleared history can be restored with `UndoDelete`/`UndoClear`
+pub const UNDO_WINDOW_SECS: u32 = 10;
+
+/// What the other side of a connection announced in its `Hello`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerInfo {
+    pub protocol_version: u32,
+    pub capabilities: Vec<String>,
+}
+
+impl PeerInfo {
+    /// A peer that predates the handshake and only understands the basic message set
+    pub const fn legacy() -> Self {
+        Self { protocol_version: 0, capabilities: Vec::new() }
+    }
+
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+}
+
+/// This build's capabilities for a `Hello` message
+pub fn own_capabilities() -> Vec<String> {
+    CAPABILITIES.iter().map(|c| c.to_string()).collect()
+}
@@ -0,0 +1,71 @@
+//! Wire formats of the IPC socket. Newline-delimited JSON is the default (easy to script
+//! against), clients moving image data can switch to length-prefixed MessagePack frames
+//! during the `Hello` handshake.
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+/// Frames larger than this are rejected, the stream cannot be resynchronized after that
+pub const MAX_FRAME_LEN: u32 = 256 * 1024 * 1024;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WireFormat {
+    /// One JSON message per line
+    #[default]
+    Json,
+    /// MessagePack messages, each prefixed with its length as big-endian u32
+    MsgPack,
+}
+
+/// Encode a message including its framing (trailing newline or length prefix)
+pub fn encode<T: Serialize>(format: WireFormat, message: &T) -> Result<Vec<u8>, String> {
+    match format {
+        WireFormat::Json => {
+            let mut frame = serde_json::to_vec(message).map_err(|e| e.to_string())?;
+            frame.push(b'\n');
+            Ok(frame)
+        }
+        WireFormat::MsgPack => {
+            let body = rmp_serde::to_vec_named(message).map_err(|e| e.to_string())?;
+            let len = u32::try_from(body.len()).ok().filter(|len| *len <= MAX_FRAME_LEN).ok_or("Message too large")?;
+            let mut frame = Vec::with_capacity(body.len() + 4);
+            frame.extend_from_slice(&len.to_be_bytes());
+            frame.extend_from_slice(&body);
+            Ok(frame)
+        }
+    }
+}
+
+/// Decode a frame body (the line without newline, or the bytes after the length prefix)
+pub fn decode<T: DeserializeOwned>(format: WireFormat, body: &[u8]) -> Result<T, String> {
+    match format {
+        WireFormat::Json => serde_json::from_slice(body).map_err(|e| e.to_string()),
+        WireFormat::MsgPack => rmp_serde::from_slice(body).map_err(|e| e.to_string()),
+    }
+}
+
+/// Read the next frame body from an async stream, None once the other side closed the connection
+pub async fn read_frame<R: AsyncBufRead + Unpin>(reader: &mut R, format: WireFormat) -> std::io::Result<Option<Vec<u8>>> {
+    match format {
+        WireFormat::Json => {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await? == 0 {
+                return Ok(None);
+            }
+            Ok(Some(line.trim_end().as_bytes().to_vec()))
+        }
+        WireFormat::MsgPack => {
+            let len = match reader.read_u32().await {
+                Ok(len) => len,
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            if len > MAX_FRAME_LEN {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Frame of {len} bytes is too large")));
+            }
+            let mut body = vec![0; len as usize];
+            reader.read_exact(&mut body).await?;
+            Ok(Some(body))
+        }
+    }
+}
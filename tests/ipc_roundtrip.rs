@@ -0,0 +1,333 @@
+//! Requests and pushes through a real socket: the IPC server runs on a temporary socket with
+//! the in-memory clipboard, `FrontendClient`s connect to it like the overlay does
+
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use bytes::Bytes;
+use futures_core::Stream;
+use indexmap::IndexMap;
+use tokio::sync::mpsc::UnboundedReceiver;
+use cursor_clip::backend::backend_state::BackendState;
+use cursor_clip::backend::clipboard_backend::MockClipboard;
+use cursor_clip::backend::ipc_server;
+use cursor_clip::frontend::async_client::AsyncFrontendClient;
+use cursor_clip::frontend::ipc_client::{FrontendClient, SubscriptionEvent};
+use cursor_clip::shared::config::Preferences;
+use cursor_clip::shared::protocol;
+use cursor_clip::shared::wire::WireFormat;
+use cursor_clip::shared::{BackendError, BackendMessage, ErrorCode, FrontendMessage, SortMode, TextTransform};
+
+const PUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct TestBackend {
+    socket: PathBuf,
+    state: Arc<Mutex<BackendState>>,
+    clipboard: Arc<MockClipboard>,
+}
+
+impl TestBackend {
+    async fn start(name: &str) -> Self {
+        let socket = std::env::temp_dir().join(format!("cursor-clip-test-{}-{name}.sock", std::process::id()));
+        let clipboard = Arc::new(MockClipboard::default());
+        let mut state = BackendState::new();
+        state.clipboard = clipboard.clone();
+        state.config.max_captures_per_sec = 0;
+        let state = Arc::new(Mutex::new(state));
+
+        let listener = ipc_server::bind_socket(&socket).unwrap();
+        tokio::spawn(ipc_server::serve(listener, state.clone()));
+        Self { socket, state, clipboard }
+    }
+
+    fn copy(&self, content: &str) -> u64 {
+        MockClipboard::copy(&mut self.state.lock().unwrap(), text(content)).unwrap()
+    }
+
+    /// Run blocking client calls off the runtime threads serving them
+    async fn with_client<R: Send + 'static>(&self, format: WireFormat, f: impl FnOnce(&mut FrontendClient) -> R + Send + 'static) -> R {
+        let socket = self.socket.clone();
+        tokio::task::spawn_blocking(move || f(&mut FrontendClient::connect_to(&socket, format).unwrap())).await.unwrap()
+    }
+}
+
+impl Drop for TestBackend {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.socket);
+    }
+}
+
+fn text(content: &str) -> IndexMap<String, Bytes> {
+    IndexMap::from([("text/plain;charset=utf-8".to_string(), Bytes::copy_from_slice(content.as_bytes()))])
+}
+
+fn png(width: u32, height: u32) -> IndexMap<String, Bytes> {
+    let mut png = Vec::new();
+    image::RgbImage::new(width, height).write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+    IndexMap::from([("image/png".to_string(), Bytes::from(png))])
+}
+
+fn error_code(error: &(dyn std::error::Error + 'static)) -> Option<ErrorCode> {
+    error.downcast_ref::<BackendError>().map(|error| error.code)
+}
+
+/// Next push of a subscription, skipping connection changes
+async fn next_push(pushes: &mut UnboundedReceiver<SubscriptionEvent>) -> BackendMessage {
+    loop {
+        match tokio::time::timeout(PUSH_TIMEOUT, pushes.recv()).await.expect("no push in time").expect("subscription closed") {
+            SubscriptionEvent::Push(message) => return *message,
+            SubscriptionEvent::ConnectionChanged(_) => {}
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn history_requests_round_trip() {
+    let backend = TestBackend::start("history").await;
+    let first = backend.copy("first entry");
+    let second = backend.copy("second entry");
+
+    let (page, total, found, data, health) = backend
+        .with_client(WireFormat::Json, move |client| {
+            let (page, total) = client.get_history_page(0, 10, SortMode::Recent).unwrap();
+            let (found, _) = client.search_history("first", &[], None, SortMode::Recent, 0, 10).unwrap();
+            let data = client.get_item_data(first).unwrap();
+            (page, total, found, data, client.ping().unwrap())
+        })
+        .await;
+    assert_eq!(total, 2);
+    assert_eq!(page.iter().map(|item| item.item_id).collect::<Vec<_>>(), [second, first]);
+    assert_eq!(found.iter().map(|item| item.item_id).collect::<Vec<_>>(), [first]);
+    assert_eq!(data.get("text/plain;charset=utf-8").map(|data| &data[..]), Some(&b"first entry"[..]));
+    assert!(health.uptime_secs < 60);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pasting_sets_the_selection() {
+    let backend = TestBackend::start("paste").await;
+    let id = backend.copy("paste me");
+
+    let missing = backend
+        .with_client(WireFormat::MsgPack, move |client| {
+            client.set_clipboard_by_id(id).unwrap();
+            client.set_clipboard_by_id(id + 100).map_err(|e| error_code(e.as_ref()))
+        })
+        .await;
+    assert_eq!(missing, Err(Some(ErrorCode::NotFound)));
+    assert_eq!(backend.clipboard.current().map(|selection| selection.item_id), Some(id));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn editing_requests_round_trip() {
+    let backend = TestBackend::start("edit").await;
+    let first = backend.copy("one");
+    let second = backend.copy("two");
+
+    let (tags, moved, restored, after_clear, restored_clear, stats) = backend
+        .with_client(WireFormat::Json, move |client| {
+            let tags = client.add_tag(first, "work").unwrap();
+            assert_eq!(client.list_tags().unwrap(), ["work"]);
+            assert!(client.remove_tag(first, "work").unwrap().is_empty());
+            let moved = client.move_item(first, 0).unwrap();
+            client.delete_item(second).unwrap();
+            let restored = client.undo_delete(second).unwrap();
+            client.clear_history().unwrap();
+            let (_, after_clear) = client.get_history_page(0, 10, SortMode::Recent).unwrap();
+            let restored_clear = client.undo_clear().unwrap();
+            (tags, moved, restored, after_clear, restored_clear.len(), client.get_stats().unwrap())
+        })
+        .await;
+    assert_eq!(tags, ["work"]);
+    assert_eq!(moved, 0);
+    assert_eq!(restored, [second]);
+    assert_eq!(after_clear, 0);
+    assert_eq!(restored_clear, 2);
+    assert_eq!(stats.item_count, 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pushes_reach_every_subscriber() {
+    let backend = TestBackend::start("pushes").await;
+    let mut subscribers = [FrontendClient::subscribe_to(backend.socket.clone()), FrontendClient::subscribe_to(backend.socket.clone())];
+    // Subscriptions connect on their own thread, wait until the backend knows about both
+    backend.with_client(WireFormat::Json, |client| client.ping().unwrap()).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let id = backend.copy("pushed");
+    for pushes in &mut subscribers {
+        assert!(matches!(next_push(pushes).await, BackendMessage::NewItem { item } if item.item_id == id));
+    }
+
+    backend.with_client(WireFormat::Json, move |client| client.delete_item(id).unwrap()).await;
+    for pushes in &mut subscribers {
+        assert!(matches!(next_push(pushes).await, BackendMessage::ItemsRemoved { ids } if ids == [id]));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn async_client_requests_and_pushes() {
+    let backend = TestBackend::start("async").await;
+    let mut client = AsyncFrontendClient::connect_to(&backend.socket, WireFormat::MsgPack).await.unwrap();
+    let mut pushes = client.pushes().unwrap();
+    client.ping().await.unwrap();
+
+    let id = backend.copy("async entry");
+    let push = tokio::time::timeout(PUSH_TIMEOUT, std::future::poll_fn(|cx| Pin::new(&mut pushes).poll_next(cx))).await.expect("no push in time");
+    assert!(matches!(push, Some(BackendMessage::NewItem { item }) if item.item_id == id));
+
+    let (page, total) = client.get_history_page(0, 10, SortMode::Recent).await.unwrap();
+    assert_eq!((page[0].item_id, total), (id, 1));
+    client.set_clipboard_by_id(id).await.unwrap();
+    assert_eq!(backend.clipboard.current().map(|selection| selection.item_id), Some(id));
+    let missing = client.delete_item(id + 100).await.unwrap_err();
+    assert_eq!(error_code(missing.as_ref()), Some(ErrorCode::NotFound));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn callbacks_see_history_and_errors() {
+    let backend = TestBackend::start("callbacks").await;
+    let id = backend.copy("observed");
+
+    let (pages, errors) = backend
+        .with_client(WireFormat::Json, move |client| {
+            let pages = Rc::new(RefCell::new(Vec::new()));
+            let errors = Rc::new(RefCell::new(Vec::new()));
+            let seen = pages.clone();
+            client.on_history(move |items| seen.borrow_mut().push(items.iter().map(|item| item.item_id).collect::<Vec<_>>()));
+            let seen = errors.clone();
+            client.on_error(move |error| seen.borrow_mut().push(error_code(error)));
+
+            client.get_history_page(0, 10, SortMode::Recent).unwrap();
+            client.delete_item(id + 100).unwrap_err();
+            (pages.take(), errors.take())
+        })
+        .await;
+    assert_eq!(pages, [vec![id]]);
+    assert_eq!(errors, [Some(ErrorCode::NotFound)]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn handshake_round_trips() {
+    let backend = TestBackend::start("hello").await;
+    for format in [WireFormat::Json, WireFormat::MsgPack] {
+        let (peer, version, hello) = backend
+            .with_client(format, move |client| {
+                // Asking for the format in use again keeps both sides on it
+                let hello = client.send_message(FrontendMessage::Hello {
+                    protocol_version: protocol::PROTOCOL_VERSION,
+                    capabilities: protocol::own_capabilities(),
+                    wire_format: format,
+                });
+                (client.backend().clone(), client.get_version().unwrap(), hello.unwrap())
+            })
+            .await;
+        assert_eq!(peer.protocol_version, protocol::PROTOCOL_VERSION);
+        assert!(peer.supports(protocol::CAP_THUMBNAILS));
+        assert_eq!(version, (env!("CARGO_PKG_VERSION").to_string(), protocol::PROTOCOL_VERSION));
+        assert!(matches!(hello, BackendMessage::Hello { protocol_version, wire_format, .. } if protocol_version == protocol::PROTOCOL_VERSION && wire_format == format));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn thumbnails_fit_the_requested_size() {
+    let backend = TestBackend::start("thumbnail").await;
+    let image = MockClipboard::copy(&mut backend.state.lock().unwrap(), png(64, 32)).unwrap();
+    let text = backend.copy("not an image");
+
+    let (thumbnail, not_image, missing) = backend
+        .with_client(WireFormat::MsgPack, move |client| {
+            let thumbnail = client.get_thumbnail(image, 16).unwrap();
+            let not_image = client.get_thumbnail(text, 16).map_err(|e| error_code(e.as_ref()));
+            let missing = client.get_thumbnail(image + 100, 16).map_err(|e| error_code(e.as_ref()));
+            (thumbnail, not_image, missing)
+        })
+        .await;
+    let thumbnail = image::load_from_memory(&thumbnail).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (16, 8));
+    assert_eq!(not_image.unwrap_err(), Some(ErrorCode::InvalidRequest));
+    assert_eq!(missing.unwrap_err(), Some(ErrorCode::NotFound));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn transforms_copy_a_new_item() {
+    let backend = TestBackend::start("transform").await;
+    let id = backend.copy("Mixed Case");
+
+    let failed = backend
+        .with_client(WireFormat::Json, move |client| {
+            client.transform_item(id, TextTransform::Uppercase).unwrap();
+            client.transform_item(id, TextTransform::Base64Decode).map_err(|e| error_code(e.as_ref()))
+        })
+        .await;
+    assert_eq!(failed.unwrap_err(), Some(ErrorCode::InvalidRequest));
+    let state = backend.state.lock().unwrap();
+    let newest = state.history.first().unwrap();
+    assert_eq!(backend.clipboard.current().map(|selection| selection.item_id), Some(newest.item_id));
+    assert_ne!(newest.item_id, id);
+    assert_eq!(newest.mime_data.get("text/plain;charset=utf-8").map(|data| &data[..]), Some(&b"MIXED CASE"[..]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn paused_capture_records_nothing() {
+    let backend = TestBackend::start("pause").await;
+
+    let (paused, reported) = backend.with_client(WireFormat::Json, |client| (client.set_capture_paused(true).unwrap(), client.capture_paused().unwrap())).await;
+    assert!(paused && reported);
+    assert_eq!(MockClipboard::copy(&mut backend.state.lock().unwrap(), text("while paused")), None);
+
+    let (paused, reported) = backend.with_client(WireFormat::MsgPack, |client| (client.set_capture_paused(false).unwrap(), client.capture_paused().unwrap())).await;
+    assert!(!paused && !reported);
+    backend.copy("after resuming");
+    assert_eq!(backend.state.lock().unwrap().history.len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn preferences_are_applied_and_saved() {
+    let config_dir = std::env::temp_dir().join(format!("cursor-clip-test-{}-config", std::process::id()));
+    let backend = TestBackend::start("config").await;
+    backend.state.lock().unwrap().config_path = config_dir.join("config.toml");
+    let mut preferences = Preferences::of(&backend.state.lock().unwrap().config);
+    preferences.max_history_items = 42;
+
+    let config = backend.with_client(WireFormat::Json, move |client| client.set_config(preferences).unwrap()).await;
+    assert_eq!(config.max_history_items, 42);
+    assert_eq!(backend.state.lock().unwrap().config.max_history_items, 42);
+    let saved = std::fs::read_to_string(config_dir.join("config.toml")).unwrap();
+    assert!(saved.contains("max_history_items = 42"), "{saved}");
+    std::fs::remove_dir_all(&config_dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn copy_stack_pastes_in_copy_order() {
+    let backend = TestBackend::start("stack").await;
+    backend.copy("a");
+    let b = backend.copy("b");
+    let c = backend.copy("c");
+
+    let (first, second, empty) = backend
+        .with_client(WireFormat::MsgPack, |client| {
+            let first = client.pop_and_paste(Some(2)).unwrap();
+            let second = client.pop_and_paste(None).unwrap();
+            let empty = client.pop_and_paste(None).map_err(|e| error_code(e.as_ref()));
+            (first, second, empty)
+        })
+        .await;
+    assert_eq!((first.0.item_id, first.1), (b, 1));
+    assert_eq!((second.0.item_id, second.1), (c, 0));
+    assert_eq!(empty.unwrap_err(), Some(ErrorCode::NotFound));
+    assert_eq!(backend.clipboard.current().map(|selection| selection.item_id), Some(c));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn history_ranges_are_newest_first() {
+    let backend = TestBackend::start("range").await;
+    let first = backend.copy("earlier");
+    let second = backend.copy("later");
+
+    let (all, none) = backend.with_client(WireFormat::Json, |client| (client.get_history_range(0, u64::MAX).unwrap(), client.get_history_range(0, 1).unwrap())).await;
+    assert_eq!(all.iter().map(|item| item.item_id).collect::<Vec<_>>(), [second, first]);
+    assert!(none.is_empty());
+}
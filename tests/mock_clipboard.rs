@@ -0,0 +1,64 @@
+//! History logic against the in-memory clipboard, without a compositor
+
+use std::sync::Arc;
+use bytes::Bytes;
+use indexmap::IndexMap;
+use cursor_clip::backend::backend_state::BackendState;
+use cursor_clip::backend::clipboard_backend::{MockClipboard, MockSelection};
+
+fn state_with_mock() -> (BackendState, Arc<MockClipboard>) {
+    let mut state = BackendState::new();
+    let clipboard = Arc::new(MockClipboard::default());
+    state.clipboard = clipboard.clone();
+    (state, clipboard)
+}
+
+fn text(content: &str) -> IndexMap<String, Bytes> {
+    IndexMap::from([
+        ("text/plain;charset=utf-8".to_string(), Bytes::copy_from_slice(content.as_bytes())),
+        ("text/html".to_string(), Bytes::from(format!("<b>{content}</b>"))),
+    ])
+}
+
+#[test]
+fn pasting_serves_every_payload_of_the_item() {
+    let (mut state, clipboard) = state_with_mock();
+    let first = MockClipboard::copy(&mut state, text("first")).unwrap();
+    MockClipboard::copy(&mut state, text("second")).unwrap();
+
+    state.set_clipboard_by_id(first).unwrap();
+    assert_eq!(
+        clipboard.current(),
+        Some(MockSelection { item_id: first, mime_types: vec!["text/plain;charset=utf-8".to_string(), "text/html".to_string()] })
+    );
+}
+
+#[test]
+fn pasting_a_single_mime_type_offers_only_that_one() {
+    let (mut state, clipboard) = state_with_mock();
+    let id = MockClipboard::copy(&mut state, text("formatted")).unwrap();
+
+    state.copy_item_mime(id, "text/html").unwrap();
+    assert_eq!(clipboard.current().unwrap().mime_types, ["text/html"]);
+    assert!(state.copy_item_mime(id, "image/png").is_err());
+    assert_eq!(clipboard.selections().len(), 1);
+}
+
+#[test]
+fn copies_are_ignored_while_paused_or_repeated() {
+    let (mut state, _clipboard) = state_with_mock();
+    state.set_capture_paused(true);
+    assert_eq!(MockClipboard::copy(&mut state, text("secret")), None);
+
+    state.set_capture_paused(false);
+    assert!(MockClipboard::copy(&mut state, text("again")).is_some());
+    assert_eq!(MockClipboard::copy(&mut state, text("again")), None);
+    assert_eq!(state.history.len(), 1);
+}
+
+#[test]
+fn unknown_items_are_not_pasted() {
+    let (mut state, clipboard) = state_with_mock();
+    assert!(state.set_clipboard_by_id(42).is_err());
+    assert!(clipboard.selections().is_empty());
+}
@@ -0,0 +1,73 @@
+//! Stress test of the backend state: thousands of synthetic selections interleaved with the
+//! queries clients send, checking capture latency, the size of the history and how much the
+//! resident memory of the process grows. Runs without a compositor, like the daemon's
+//! simulation mode.
+
+use std::time::{Duration, Instant};
+use cursor_clip::backend::backend_state::BackendState;
+use cursor_clip::backend::simulation::SyntheticSelections;
+use cursor_clip::shared::SortMode;
+
+const SELECTIONS: usize = 5_000;
+
+/// Generous enough for unoptimized builds on a busy CI machine
+const MAX_P99_CAPTURE: Duration = Duration::from_millis(50);
+
+/// The default history keeps 100 items, none of the synthetic ones is larger than ~1 MiB
+const MAX_HISTORY_BYTES: u64 = 100 << 20;
+
+/// Growth of the resident set over the run: the history, what the allocator keeps of evicted
+/// items and the other test running alongside (a few MiB in practice)
+const MAX_RSS_GROWTH: u64 = 64 << 20;
+
+/// Resident set size of this process from `/proc/self/statm` (in pages there)
+fn resident_bytes() -> u64 {
+    let statm = std::fs::read_to_string("/proc/self/statm").expect("/proc/self/statm is readable");
+    let pages: u64 = statm.split_whitespace().nth(1).and_then(|pages| pages.parse().ok()).expect("statm has a resident field");
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    pages * page_size as u64
+}
+
+#[test]
+fn sustained_captures_stay_fast_and_bounded() {
+    let mut state = BackendState::new();
+    let mut latencies = Vec::with_capacity(SELECTIONS);
+    let rss_before = resident_bytes();
+
+    for (i, selection) in SyntheticSelections::new(0x5eed, 200).take(SELECTIONS).enumerate() {
+        let started = Instant::now();
+        let id = state.add_clipboard_item_from_mime_map(selection, Vec::new(), None);
+        latencies.push(started.elapsed());
+        assert!(id.is_some(), "selection {i} was not recorded");
+
+        // What an open overlay asks for while copies come in
+        if i % 10 == 0 {
+            let (page, total) = state.get_history_page(0, 20, SortMode::Recent);
+            assert!(!page.is_empty() && total as usize <= state.config.max_history_items as usize);
+            state.search_history("fn", &[], None, SortMode::Recent, 0, 20);
+            state.history_stats();
+        }
+    }
+
+    latencies.sort();
+    let p99 = latencies[latencies.len() * 99 / 100];
+    assert!(p99 <= MAX_P99_CAPTURE, "p99 capture latency {p99:?} over {MAX_P99_CAPTURE:?}");
+
+    let stats = state.history_stats();
+    assert_eq!(stats.item_count, state.config.max_history_items);
+    assert!(stats.total_bytes <= MAX_HISTORY_BYTES, "history holds {} bytes", stats.total_bytes);
+    let rss_growth = resident_bytes().saturating_sub(rss_before);
+    assert!(rss_growth <= MAX_RSS_GROWTH, "resident memory grew by {rss_growth} bytes, over {MAX_RSS_GROWTH}");
+}
+
+#[test]
+fn repeated_content_does_not_grow_the_history() {
+    let mut state = BackendState::new();
+    let selections: Vec<_> = SyntheticSelections::new(7, 0).take(10).collect();
+    for _ in 0..500 {
+        for selection in &selections {
+            state.add_clipboard_item_from_mime_map(selection.clone(), Vec::new(), None);
+        }
+    }
+    assert_eq!(state.history_stats().item_count, 10);
+}